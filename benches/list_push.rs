@@ -0,0 +1,43 @@
+//! Building a list with `xs = xs.push(i)` in a loop, then setting each of
+//! its elements with `xs[i] = v`.
+//!
+//! Run with `cargo bench --bench list_push`. Each row doubles the number of
+//! operations; the time per operation stays flat because the list is changed
+//! in place rather than copied every time.
+
+use std::time::{Duration, Instant};
+use the_carrion_language::{evaluator, lexer, parser};
+
+fn run(source: &str) -> Duration {
+    let mut lexer = lexer::Lexer::new(source.to_owned(), "<bench>".into());
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+    let start = Instant::now();
+    evaluator::eval(&program).expect("benchmark script failed");
+    start.elapsed()
+}
+
+fn report(count: usize, what: &str, source: &str) {
+    // Best of three, to smooth over noise from the rest of the machine.
+    let best = (0..3).map(|_| run(source)).min().unwrap();
+    println!(
+        "{:>7} {:<8} {:>9.2} ms  {:>7.1} ns/op",
+        count,
+        what,
+        best.as_secs_f64() * 1e3,
+        best.as_nanos() as f64 / count as f64
+    );
+}
+
+fn main() {
+    for pushes in [25_000, 50_000, 100_000, 200_000] {
+        let source = format!("xs = []\nfor i in 0..{}:\n    xs = xs.push(i)\nlen(xs)", pushes);
+        report(pushes, "pushes", &source);
+    }
+    for stores in [25_000, 50_000, 100_000, 200_000] {
+        let source = format!("xs = range({}).to_list()\nfor i in 0..{}:\n    xs[i] = 1\nlen(xs)", stores, stores);
+        report(stores, "stores", &source);
+    }
+}
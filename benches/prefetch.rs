@@ -0,0 +1,68 @@
+//! Parsing a project's modules one after another, against prefetching them
+//! on worker threads.
+//!
+//! Run with `cargo bench --bench prefetch`. Each row doubles the number of
+//! modules the script imports. Prefetching parses them across the available
+//! cores and decodes the results on the main thread, so it should pull ahead
+//! as the project grows; on a single core it parses in place.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use the_carrion_language::evaluator::modules;
+use the_carrion_language::{lexer, parser};
+
+/// A module of `spells` small spell definitions.
+fn module(spells: usize) -> String {
+    (0..spells)
+        .map(|i| format!("spell f{}(a, b):\n    if a > b:\n        return [a, b, \"{}\"]\n    return {{\"sum\": a + b}}\n", i, i))
+        .collect()
+}
+
+fn parse(source: String, file: &Path) -> parser::Parser {
+    let mut parser = parser::Parser::new(lexer::Lexer::new(source, file.into()).scan_tokens());
+    parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+    parser
+}
+
+fn sequential(dir: &Path, count: usize) -> Duration {
+    let start = Instant::now();
+    for i in 0..count {
+        let file = dir.join(format!("m{}.crl", i));
+        parse(fs::read_to_string(&file).unwrap(), &file);
+    }
+    start.elapsed()
+}
+
+fn prefetched(dir: &Path, count: usize) -> Duration {
+    let imports: String = (0..count).map(|i| format!("import \"{}/m{}\"\n", dir.display(), i)).collect();
+    let mut parser = parser::Parser::new(lexer::Lexer::new(imports, "<bench>".into()).scan_tokens());
+    let program = parser.parse_program();
+
+    let start = Instant::now();
+    assert_eq!(modules::prefetch(&program), count);
+    start.elapsed()
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("carrion-prefetch-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = module(500);
+    for i in 0..64 {
+        fs::write(dir.join(format!("m{}.crl", i)), &source).unwrap();
+    }
+
+    for count in [8, 16, 32, 64] {
+        // Best of three, to smooth over noise from the rest of the machine.
+        let one_by_one = (0..3).map(|_| sequential(&dir, count)).min().unwrap();
+        let ahead = (0..3).map(|_| prefetched(&dir, count)).min().unwrap();
+        println!(
+            "{:>3} modules  sequential {:>8.2} ms  prefetch {:>8.2} ms",
+            count,
+            one_by_one.as_secs_f64() * 1e3,
+            ahead.as_secs_f64() * 1e3
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,36 @@
+//! Evaluating the same string literal over and over, for literals of
+//! growing length.
+//!
+//! Run with `cargo bench --bench string_literals`. Each row makes the
+//! literal a hundred times longer; the time per evaluation stays flat
+//! because the value shares the literal's text instead of copying it.
+
+use std::time::{Duration, Instant};
+use the_carrion_language::{evaluator, lexer, parser};
+
+const EVALUATIONS: usize = 100_000;
+
+fn run(source: &str) -> Duration {
+    let mut lexer = lexer::Lexer::new(source.to_owned(), "<bench>".into());
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+    let start = Instant::now();
+    evaluator::eval(&program).expect("benchmark script failed");
+    start.elapsed()
+}
+
+fn main() {
+    for length in [10, 1_000, 100_000] {
+        let source = format!("for i in 0..{}:\n    s = \"{}\"\nlen(s)", EVALUATIONS, "a".repeat(length));
+        // Best of three, to smooth over noise from the rest of the machine.
+        let best = (0..3).map(|_| run(&source)).min().unwrap();
+        println!(
+            "{:>7} chars {:>9.2} ms  {:>7.1} ns/op",
+            length,
+            best.as_secs_f64() * 1e3,
+            best.as_nanos() as f64 / EVALUATIONS as f64
+        );
+    }
+}
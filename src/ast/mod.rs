@@ -1,3 +1,10 @@
+use std::fmt;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::object::Str;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Identifier(pub String);
 
@@ -5,10 +12,14 @@ pub struct Identifier(pub String);
 pub enum Statement {
     Expression(Expression),
     FunctionDefinition(FunctionDefinition),
+    Grimoire(GrimoireDefinition),
     Return(ReturnStatement),
     If(IfStatement),
     While(WhileStatement),
     For(ForStatement),
+    Together(TogetherStatement),
+    Match(MatchStatement),
+    Import(ImportStatement),
     Assignment(Assignment),
     CompoundAssignment(CompoundAssignment),
 }
@@ -18,9 +29,13 @@ pub enum Expression {
     Identifier(Identifier),
     IntegerLiteral(i64),
     FloatLiteral(f64),
-    StringLiteral(String),
+    /// Held as a `Str` so each evaluation shares the text instead of copying it.
+    StringLiteral(Str),
     BooleanLiteral(bool),
+    NoneLiteral,
     List(Vec<Expression>),
+    Tuple(Vec<Expression>),
+    Set(Vec<Expression>),
     Dict {
         pairs: Vec<(Expression, Expression)>,
     },
@@ -28,17 +43,92 @@ pub enum Expression {
     Infix(InfixExpression),
     Postfix(PostfixExpression),
     Index(IndexExpression),
+    Member(MemberExpression),
     Call(CallExpression),
+    Function(FunctionLiteral),
     Unpack(UnpackExpression),
+    Conditional(ConditionalExpression),
+    Maybe(MaybeExpression),
+    Propagate(PropagateExpression),
+    /// `super` in `super.name`, a spell of the enclosing grimoire's parent.
+    Super,
+}
+
+pub type BlockStatement = Vec<SpannedStatement>;
+
+/// Where a statement starts in its source file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
 }
 
-pub type BlockStatement = Vec<Statement>;
+/// A statement and the [`Span`] it was parsed from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedStatement {
+    pub statement: Statement,
+    pub span: Span,
+}
+
+impl Deref for SpannedStatement {
+    type Target = Statement;
+
+    fn deref(&self) -> &Statement {
+        &self.statement
+    }
+}
+
+/// A statement without a source location, such as one built by the evaluator.
+impl From<Statement> for SpannedStatement {
+    fn from(statement: Statement) -> Self {
+        SpannedStatement {
+            statement,
+            span: Span::default(),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct FunctionDefinition {
     pub name: Identifier,
-    pub parameters: Vec<Identifier>,
-    pub body: BlockStatement,
+    /// Shared with every spell object made from this definition, so
+    /// evaluating it again doesn't copy the tree.
+    pub parameters: Rc<[Identifier]>,
+    /// `*name`: a list of the arguments after the named parameters.
+    pub rest: Option<Identifier>,
+    /// `**name`: a dict of the keyword arguments no parameter takes.
+    pub keywords: Option<Identifier>,
+    pub body: Rc<[SpannedStatement]>,
+    /// `pure spell`: calls may not do I/O or change objects made outside them.
+    pub pure: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GrimoireDefinition {
+    pub name: Identifier,
+    /// The grimoire in `grim Child(Parent):`.
+    pub parent: Option<Expression>,
+    pub methods: Vec<FunctionDefinition>,
+    /// Declared `arcane grim`, so it can't be instantiated itself.
+    pub arcane: bool,
+    /// The `arcanespell`s, which children must define.
+    pub arcane_spells: Vec<Identifier>,
+    /// `static spell`s, called on the grimoire without a `self`.
+    pub static_spells: Vec<FunctionDefinition>,
+    /// `property spell name():` spells, run when `instance.name` is read.
+    pub getters: Vec<FunctionDefinition>,
+    /// `property spell name(value):` spells, run when `instance.name` is
+    /// assigned.
+    pub setters: Vec<FunctionDefinition>,
+    /// `name = value` lines, attributes of the grimoire itself.
+    pub attributes: Vec<(Identifier, Expression)>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -60,13 +150,61 @@ pub struct WhileStatement {
     pub body: BlockStatement,
 }
 
+/// `together:` — a block that waits for the tasks `spawn` starts in it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TogetherStatement {
+    pub body: BlockStatement,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ForStatement {
-    pub target: Identifier,
+    /// One name, or several (`for k, v in pairs:`) that each item is unpacked into.
+    pub targets: Vec<Identifier>,
     pub iter: Box<Expression>,
     pub body: BlockStatement,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchStatement {
+    pub subject: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: BlockStatement,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    /// A literal value compared for equality, e.g. `case 3:`, `case "red":`
+    /// or `case (0, 0):`.
+    Literal(Expression),
+    /// Values from the first literal up to, but not including, the second,
+    /// in the order `<` uses, e.g. `case 1..10:` or `case (1, 0)..(2, 0):`.
+    Range(Expression, Expression),
+    /// A bare name that matches anything and binds it, e.g. `case other:`.
+    Binding(Identifier),
+    /// `case _:` matches anything without binding.
+    Wildcard,
+}
+
+/// `import "path/to/file"` or `import name`, optionally followed by `as alias`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportStatement {
+    pub source: ImportSource,
+    pub alias: Option<Identifier>,
+    /// The file containing the import; relative imports resolve against its directory.
+    pub origin: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ImportSource {
+    Path(String),
+    Name(Identifier),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Assignment {
     pub targets: Vec<Expression>,
@@ -79,6 +217,8 @@ pub enum Operator {
     Minus,
     Multiply,
     Divide,
+    Mod,
+    Exponent,
     Equal,
     NotEqual,
     LessThan,
@@ -88,6 +228,14 @@ pub enum Operator {
     And,
     Or,
     Not,
+    BitAnd,     // &
+    BitOr,      // |
+    BitXor,     // ^
+    BitNot,     // ~
+    LeftShift,  // <<
+    RightShift, // >>
+    In,         // membership
+    Range,      // ..
     Increment,  // ++
     Decrement,  // --
     PlusAssign, // +=
@@ -114,17 +262,73 @@ pub struct PostfixExpression {
     pub operator: Operator,
 }
 
+/// `maybe obj.field.method()`: the chain of attribute, index and call links
+/// in `value` evaluates to `None` as soon as a link is missing or lands on
+/// `None`, instead of failing.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MaybeExpression {
+    pub value: Box<Expression>,
+}
+
+/// `value?`: the value inside `ok(...)`, or, for `err(...)`, a return of
+/// that `err` from the enclosing spell.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PropagateExpression {
+    pub value: Box<Expression>,
+}
+
+/// `consequence if condition else alternative`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConditionalExpression {
+    pub condition: Box<Expression>,
+    pub consequence: Box<Expression>,
+    pub alternative: Box<Expression>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct IndexExpression {
     pub object: Box<Expression>,
     pub index: Box<Expression>,
 }
+/// An anonymous spell whose body is a single expression: `spell(x, y): x + y`.
+/// The parser stores that expression as a `return` statement, so the body
+/// can be shared with the spell objects the literal evaluates to.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionLiteral {
+    pub parameters: Rc<[Identifier]>,
+    pub rest: Option<Identifier>,
+    pub keywords: Option<Identifier>,
+    pub body: Rc<[SpannedStatement]>,
+}
+
+/// Attribute or method access: `object.property`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MemberExpression {
+    pub object: Box<Expression>,
+    pub property: Identifier,
+    /// Written `object?.property`, which is `none` when the object is.
+    pub optional: bool,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct CallExpression {
     pub function: Box<Expression>,
     pub arguments: Vec<Expression>,
+    /// Keyword arguments, which follow the positional ones.
+    pub keywords: Vec<KeywordArgument>,
+}
+
+/// `name=value` in a call, or `**value` (a dict whose entries become
+/// keyword arguments) when `name` is `None`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeywordArgument {
+    pub name: Option<Identifier>,
+    pub value: Expression,
 }
 
+/// `*value` in a call's arguments: the items of a list or tuple, passed as
+/// separate arguments. As an assignment target (`first, *rest = xs`) it
+/// takes a list of the items the other targets leave.
 #[derive(Debug, PartialEq, Clone)]
 pub struct UnpackExpression {
     pub value: Box<Expression>,
@@ -132,7 +336,7 @@ pub struct UnpackExpression {
 
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct Program {
-    pub statements: Vec<Statement>,
+    pub statements: Vec<SpannedStatement>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -0,0 +1,801 @@
+//! Caching parsed scripts between runs.
+//!
+//! Lexing and parsing a large script takes longer than running many small
+//! ones, and a script run from a shell loop is parsed again every time.
+//! `carrion script.crl` therefore keeps the parsed program, and the lint
+//! warnings found while reading it, in the user's cache directory, in a file
+//! named after a hash of the script's text and everything else that changes
+//! how it parses: this interpreter's version and features, the language
+//! version and the `--int-overflow` mode. A later run of the same text loads
+//! that file instead of parsing.
+//!
+//! Entries never go stale, since different text gets a different file; the
+//! directory can be emptied at any time. An entry that can't be read is
+//! ignored and the script is parsed as usual. `--no-cache` skips the cache.
+//!
+//! Reading an entry marks it as used, and once the entries add up to more
+//! than [`MAX_SIZE`] bytes, storing one removes the least recently used
+//! until they fit again.
+
+use crate::ast::*;
+use crate::parser::overflow;
+use crate::strict::Warning;
+use crate::version;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/// Bumped whenever the layout of an entry or the tree changes.
+const FORMAT: u32 = 3;
+const MAGIC: &[u8; 4] = b"CRLC";
+
+/// How many bytes of entries the cache directory keeps.
+pub const MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// What the cache keeps for a script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub program: Program,
+    pub warnings: Vec<Warning>,
+}
+
+/// The directory entries go in: `$CARRION_CACHE_DIR`, or `carrion` in
+/// `$XDG_CACHE_HOME`, `~/.cache` or `%LOCALAPPDATA%`.
+pub fn directory() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("CARRION_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    let base = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("carrion"))
+}
+
+/// The name of the entry for `source`.
+pub fn key(source: &str) -> String {
+    let build = format!(
+        "{} format {} sqlite {} http {} plugins {}",
+        env!("CARGO_PKG_VERSION"),
+        FORMAT,
+        cfg!(feature = "sqlite"),
+        cfg!(feature = "http"),
+        cfg!(feature = "plugins"),
+    );
+    let settings = format!("{} {:?}", version::of(source), overflow::mode());
+    let mut hash = Fnv::new();
+    for part in [build.as_str(), settings.as_str(), source] {
+        hash.write(&(part.len() as u64).to_le_bytes());
+        hash.write(part.as_bytes());
+    }
+    format!("{:032x}", hash.0)
+}
+
+/// The cached entry for `source`, read as the script at `file`.
+pub fn load(source: &str, file: &Path) -> Option<Entry> {
+    let path = directory()?.join(key(source));
+    let bytes = fs::read(&path).ok()?;
+    let entry = decode(&bytes, file).ok()?;
+    // The modification time is when the entry was last used, for `prune`.
+    let _ = fs::File::options().append(true).open(&path).and_then(|f| f.set_modified(SystemTime::now()));
+    Some(entry)
+}
+
+/// Keep `entry` for later runs of `source`. Failing to write is not an
+/// error: the next run parses again.
+pub fn store(source: &str, entry: &Entry) {
+    let Some(dir) = directory() else {
+        return;
+    };
+    let path = dir.join(key(source));
+    // Write under another name first, so a run reading the entry at the
+    // same time never sees half of it.
+    let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+    let written = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&partial, encode(entry)))
+        .and_then(|_| fs::rename(&partial, &path));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    prune(&dir, MAX_SIZE);
+}
+
+/// Remove the least recently used entries in `dir` until the rest add up to
+/// at most `max_size` bytes. Only files named like entries are touched, in
+/// case the directory is shared with something else.
+pub fn prune(dir: &Path, max_size: u64) {
+    let Ok(files) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(SystemTime, u64, PathBuf)> = files
+        .filter_map(|file| file.ok())
+        .filter(|file| {
+            let name = file.file_name();
+            let name = name.to_string_lossy();
+            name.len() == 32 && name.bytes().all(|b| b.is_ascii_hexdigit())
+        })
+        .filter_map(|file| {
+            let metadata = file.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), file.path()))
+        })
+        .collect();
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort();
+    for (_, size, path) in entries {
+        if total <= max_size {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+}
+
+/// 128-bit FNV-1a, which is stable across builds, unlike std's hasher.
+struct Fnv(u128);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0x6c62272e07bb014262b821756295c58d)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u128;
+            self.0 = self.0.wrapping_mul(0x0000000001000000000000000000013b);
+        }
+    }
+}
+
+/// `entry` as bytes. Spans keep only their line and column: every span in a
+/// script is in the script's own file, which [`decode`] is given.
+pub fn encode(entry: &Entry) -> Vec<u8> {
+    let mut encoder = Encoder(MAGIC.to_vec());
+    encoder.number(FORMAT as u64);
+    encoder.number(entry.warnings.len() as u64);
+    for warning in &entry.warnings {
+        encoder.span(&warning.span);
+        encoder.text(&warning.message);
+    }
+    encoder.block(&entry.program.statements);
+    encoder.0
+}
+
+/// The entry [`encode`] made `bytes` from, with its spans in `file`.
+pub fn decode(bytes: &[u8], file: &Path) -> Result<Entry, String> {
+    if !bytes.starts_with(MAGIC) {
+        return Err("not a Carrion cache entry".to_string());
+    }
+    let mut decoder = Decoder { bytes, position: MAGIC.len(), file };
+    let format = decoder.number()?;
+    if format != FORMAT as u64 {
+        return Err(format!("cache entry format {} is not {}", format, FORMAT));
+    }
+    let warnings = decoder.list(|decoder| {
+        Ok(Warning {
+            span: decoder.span()?,
+            message: decoder.text()?,
+        })
+    })?;
+    let statements = decoder.block()?;
+    if decoder.position != bytes.len() {
+        return Err("cache entry has trailing bytes".to_string());
+    }
+    Ok(Entry {
+        program: Program { statements },
+        warnings,
+    })
+}
+
+/// The operators in the order their tags count.
+const OPERATORS: [Operator; 29] = [
+    Operator::Plus,
+    Operator::Minus,
+    Operator::Multiply,
+    Operator::Divide,
+    Operator::Mod,
+    Operator::Exponent,
+    Operator::Equal,
+    Operator::NotEqual,
+    Operator::LessThan,
+    Operator::GreaterThan,
+    Operator::LessThanEqual,
+    Operator::GreaterThanEqual,
+    Operator::And,
+    Operator::Or,
+    Operator::Not,
+    Operator::BitAnd,
+    Operator::BitOr,
+    Operator::BitXor,
+    Operator::BitNot,
+    Operator::LeftShift,
+    Operator::RightShift,
+    Operator::In,
+    Operator::Range,
+    Operator::Increment,
+    Operator::Decrement,
+    Operator::PlusAssign,
+    Operator::MinusAssgn,
+    Operator::AstriskAssign,
+    Operator::SlashAssign,
+];
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn tag(&mut self, tag: u8) {
+        self.0.push(tag);
+    }
+
+    /// LEB128, so the small numbers most of a tree is made of take a byte.
+    fn number(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn flag(&mut self, value: bool) {
+        self.tag(value as u8);
+    }
+
+    fn text(&mut self, text: &str) {
+        self.number(text.len() as u64);
+        self.0.extend_from_slice(text.as_bytes());
+    }
+
+    fn name(&mut self, name: &Identifier) {
+        self.text(&name.0);
+    }
+
+    fn optional_name(&mut self, name: &Option<Identifier>) {
+        self.flag(name.is_some());
+        if let Some(name) = name {
+            self.name(name);
+        }
+    }
+
+    fn names(&mut self, names: &[Identifier]) {
+        self.number(names.len() as u64);
+        for name in names {
+            self.name(name);
+        }
+    }
+
+    fn span(&mut self, span: &Span) {
+        self.number(span.line as u64);
+        self.number(span.column as u64);
+    }
+
+    fn operator(&mut self, operator: &Operator) {
+        let tag = OPERATORS.iter().position(|known| known == operator).expect("every operator has a tag");
+        self.tag(tag as u8);
+    }
+
+    fn block(&mut self, block: &[SpannedStatement]) {
+        self.number(block.len() as u64);
+        for statement in block {
+            self.span(&statement.span);
+            self.statement(&statement.statement);
+        }
+    }
+
+    fn expressions(&mut self, expressions: &[Expression]) {
+        self.number(expressions.len() as u64);
+        for expression in expressions {
+            self.expression(expression);
+        }
+    }
+
+    fn optional_expression(&mut self, expression: &Option<Expression>) {
+        self.flag(expression.is_some());
+        if let Some(expression) = expression {
+            self.expression(expression);
+        }
+    }
+
+    fn spell(&mut self, spell: &FunctionDefinition) {
+        self.name(&spell.name);
+        self.names(&spell.parameters);
+        self.optional_name(&spell.rest);
+        self.optional_name(&spell.keywords);
+        self.block(&spell.body);
+        self.flag(spell.pure);
+    }
+
+    fn spells(&mut self, spells: &[FunctionDefinition]) {
+        self.number(spells.len() as u64);
+        for spell in spells {
+            self.spell(spell);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Expression(expression) => {
+                self.tag(0);
+                self.expression(expression);
+            }
+            Statement::FunctionDefinition(spell) => {
+                self.tag(1);
+                self.spell(spell);
+            }
+            Statement::Grimoire(grimoire) => {
+                self.tag(2);
+                self.name(&grimoire.name);
+                self.optional_expression(&grimoire.parent);
+                self.spells(&grimoire.methods);
+                self.flag(grimoire.arcane);
+                self.names(&grimoire.arcane_spells);
+                self.spells(&grimoire.static_spells);
+                self.spells(&grimoire.getters);
+                self.spells(&grimoire.setters);
+                self.number(grimoire.attributes.len() as u64);
+                for (name, value) in &grimoire.attributes {
+                    self.name(name);
+                    self.expression(value);
+                }
+            }
+            Statement::Return(ret) => {
+                self.tag(3);
+                self.optional_expression(&ret.value);
+            }
+            Statement::If(branch) => {
+                self.tag(4);
+                self.expression(&branch.condition);
+                self.block(&branch.consequence);
+                self.number(branch.alternatives.len() as u64);
+                for (condition, block) in &branch.alternatives {
+                    self.expression(condition);
+                    self.block(block);
+                }
+                self.flag(branch.default.is_some());
+                if let Some(block) = &branch.default {
+                    self.block(block);
+                }
+            }
+            Statement::While(lp) => {
+                self.tag(5);
+                self.expression(&lp.condition);
+                self.block(&lp.body);
+            }
+            Statement::Together(together) => {
+                self.tag(11);
+                self.block(&together.body);
+            }
+            Statement::For(lp) => {
+                self.tag(6);
+                self.names(&lp.targets);
+                self.expression(&lp.iter);
+                self.block(&lp.body);
+            }
+            Statement::Match(matched) => {
+                self.tag(7);
+                self.expression(&matched.subject);
+                self.number(matched.arms.len() as u64);
+                for arm in &matched.arms {
+                    match &arm.pattern {
+                        Pattern::Literal(value) => {
+                            self.tag(0);
+                            self.expression(value);
+                        }
+                        Pattern::Range(start, end) => {
+                            self.tag(1);
+                            self.expression(start);
+                            self.expression(end);
+                        }
+                        Pattern::Binding(name) => {
+                            self.tag(2);
+                            self.name(name);
+                        }
+                        Pattern::Wildcard => self.tag(3),
+                    }
+                    self.block(&arm.body);
+                }
+            }
+            // The import's origin is the script itself, like its spans.
+            Statement::Import(import) => {
+                self.tag(8);
+                match &import.source {
+                    ImportSource::Path(path) => {
+                        self.tag(0);
+                        self.text(path);
+                    }
+                    ImportSource::Name(name) => {
+                        self.tag(1);
+                        self.name(name);
+                    }
+                }
+                self.optional_name(&import.alias);
+            }
+            Statement::Assignment(assignment) => {
+                self.tag(9);
+                self.expressions(&assignment.targets);
+                self.expression(&assignment.value);
+            }
+            Statement::CompoundAssignment(assignment) => {
+                self.tag(10);
+                self.expression(&assignment.target);
+                self.operator(&assignment.operator);
+                self.expression(&assignment.value);
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(name) => {
+                self.tag(0);
+                self.name(name);
+            }
+            Expression::IntegerLiteral(value) => {
+                self.tag(1);
+                self.0.extend_from_slice(&value.to_le_bytes());
+            }
+            Expression::FloatLiteral(value) => {
+                self.tag(2);
+                self.0.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+            Expression::StringLiteral(text) => {
+                self.tag(3);
+                self.text(text);
+            }
+            Expression::BooleanLiteral(value) => {
+                self.tag(4);
+                self.flag(*value);
+            }
+            Expression::NoneLiteral => self.tag(5),
+            Expression::List(items) => {
+                self.tag(6);
+                self.expressions(items);
+            }
+            Expression::Tuple(items) => {
+                self.tag(7);
+                self.expressions(items);
+            }
+            Expression::Set(items) => {
+                self.tag(8);
+                self.expressions(items);
+            }
+            Expression::Dict { pairs } => {
+                self.tag(9);
+                self.number(pairs.len() as u64);
+                for (key, value) in pairs {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::Prefix(prefix) => {
+                self.tag(10);
+                self.operator(&prefix.operator);
+                self.expression(&prefix.right);
+            }
+            Expression::Infix(infix) => {
+                self.tag(11);
+                self.expression(&infix.left);
+                self.operator(&infix.operator);
+                self.expression(&infix.right);
+            }
+            Expression::Postfix(postfix) => {
+                self.tag(12);
+                self.expression(&postfix.left);
+                self.operator(&postfix.operator);
+            }
+            Expression::Index(index) => {
+                self.tag(13);
+                self.expression(&index.object);
+                self.expression(&index.index);
+            }
+            Expression::Member(member) => {
+                self.tag(14);
+                self.expression(&member.object);
+                self.name(&member.property);
+                self.flag(member.optional);
+            }
+            Expression::Call(call) => {
+                self.tag(15);
+                self.expression(&call.function);
+                self.expressions(&call.arguments);
+                self.number(call.keywords.len() as u64);
+                for keyword in &call.keywords {
+                    self.optional_name(&keyword.name);
+                    self.expression(&keyword.value);
+                }
+            }
+            Expression::Function(spell) => {
+                self.tag(16);
+                self.names(&spell.parameters);
+                self.optional_name(&spell.rest);
+                self.optional_name(&spell.keywords);
+                self.block(&spell.body);
+            }
+            Expression::Unpack(unpack) => {
+                self.tag(17);
+                self.expression(&unpack.value);
+            }
+            Expression::Conditional(conditional) => {
+                self.tag(18);
+                self.expression(&conditional.condition);
+                self.expression(&conditional.consequence);
+                self.expression(&conditional.alternative);
+            }
+            Expression::Maybe(maybe) => {
+                self.tag(19);
+                self.expression(&maybe.value);
+            }
+            Expression::Propagate(propagate) => {
+                self.tag(20);
+                self.expression(&propagate.value);
+            }
+            Expression::Super => self.tag(21),
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    file: &'a Path,
+}
+
+fn truncated() -> String {
+    "cache entry is truncated".to_string()
+}
+
+fn unknown(what: &str, tag: u8) -> String {
+    format!("cache entry has unknown {} tag {}", what, tag)
+}
+
+impl Decoder<'_> {
+    fn tag(&mut self) -> Result<u8, String> {
+        let tag = *self.bytes.get(self.position).ok_or_else(truncated)?;
+        self.position += 1;
+        Ok(tag)
+    }
+
+    fn number(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.tag()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("cache entry has a number that is too long".to_string())
+    }
+
+    fn flag(&mut self) -> Result<bool, String> {
+        match self.tag()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(unknown("flag", tag)),
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let end = self.position.checked_add(N).ok_or_else(truncated)?;
+        let bytes = self.bytes.get(self.position..end).ok_or_else(truncated)?;
+        self.position = end;
+        Ok(bytes.try_into().expect("slice has N bytes"))
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        let length = usize::try_from(self.number()?).map_err(|_| truncated())?;
+        let end = self.position.checked_add(length).ok_or_else(truncated)?;
+        let bytes = self.bytes.get(self.position..end).ok_or_else(truncated)?;
+        self.position = end;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "cache entry has text that isn't UTF-8".to_string())
+    }
+
+    /// A count followed by that many of what `item` reads. The count isn't
+    /// trusted to reserve space, since every item takes at least a byte.
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let count = self.number()?;
+        if count > (self.bytes.len() - self.position) as u64 {
+            return Err(truncated());
+        }
+        (0..count).map(|_| item(self)).collect()
+    }
+
+    fn name(&mut self) -> Result<Identifier, String> {
+        self.text().map(Identifier)
+    }
+
+    fn optional_name(&mut self) -> Result<Option<Identifier>, String> {
+        if self.flag()? { self.name().map(Some) } else { Ok(None) }
+    }
+
+    fn names(&mut self) -> Result<Vec<Identifier>, String> {
+        self.list(Self::name)
+    }
+
+    fn span(&mut self) -> Result<Span, String> {
+        Ok(Span {
+            file: self.file.to_path_buf(),
+            line: self.number()? as usize,
+            column: self.number()? as usize,
+        })
+    }
+
+    fn operator(&mut self) -> Result<Operator, String> {
+        let tag = self.tag()?;
+        OPERATORS.get(tag as usize).cloned().ok_or_else(|| unknown("operator", tag))
+    }
+
+    fn block(&mut self) -> Result<BlockStatement, String> {
+        self.list(|decoder| {
+            let span = decoder.span()?;
+            let statement = decoder.statement()?;
+            Ok(SpannedStatement { statement, span })
+        })
+    }
+
+    fn boxed(&mut self) -> Result<Box<Expression>, String> {
+        self.expression().map(Box::new)
+    }
+
+    fn expressions(&mut self) -> Result<Vec<Expression>, String> {
+        self.list(Self::expression)
+    }
+
+    fn optional_expression(&mut self) -> Result<Option<Expression>, String> {
+        if self.flag()? { self.expression().map(Some) } else { Ok(None) }
+    }
+
+    fn spell(&mut self) -> Result<FunctionDefinition, String> {
+        Ok(FunctionDefinition {
+            name: self.name()?,
+            parameters: self.names()?.into(),
+            rest: self.optional_name()?,
+            keywords: self.optional_name()?,
+            body: self.block()?.into(),
+            pure: self.flag()?,
+        })
+    }
+
+    fn spells(&mut self) -> Result<Vec<FunctionDefinition>, String> {
+        self.list(Self::spell)
+    }
+
+    fn statement(&mut self) -> Result<Statement, String> {
+        Ok(match self.tag()? {
+            0 => Statement::Expression(self.expression()?),
+            1 => Statement::FunctionDefinition(self.spell()?),
+            2 => Statement::Grimoire(GrimoireDefinition {
+                name: self.name()?,
+                parent: self.optional_expression()?,
+                methods: self.spells()?,
+                arcane: self.flag()?,
+                arcane_spells: self.names()?,
+                static_spells: self.spells()?,
+                getters: self.spells()?,
+                setters: self.spells()?,
+                attributes: self.list(|decoder| Ok((decoder.name()?, decoder.expression()?)))?,
+            }),
+            3 => Statement::Return(ReturnStatement {
+                value: self.optional_expression()?,
+            }),
+            4 => Statement::If(IfStatement {
+                condition: self.boxed()?,
+                consequence: self.block()?,
+                alternatives: self.list(|decoder| Ok((decoder.expression()?, decoder.block()?)))?,
+                default: if self.flag()? { Some(self.block()?) } else { None },
+            }),
+            5 => Statement::While(WhileStatement {
+                condition: self.boxed()?,
+                body: self.block()?,
+            }),
+            6 => Statement::For(ForStatement {
+                targets: self.names()?,
+                iter: self.boxed()?,
+                body: self.block()?,
+            }),
+            7 => Statement::Match(MatchStatement {
+                subject: self.boxed()?,
+                arms: self.list(|decoder| {
+                    let pattern = match decoder.tag()? {
+                        0 => Pattern::Literal(decoder.expression()?),
+                        1 => Pattern::Range(decoder.expression()?, decoder.expression()?),
+                        2 => Pattern::Binding(decoder.name()?),
+                        3 => Pattern::Wildcard,
+                        tag => return Err(unknown("pattern", tag)),
+                    };
+                    Ok(MatchArm {
+                        pattern,
+                        body: decoder.block()?,
+                    })
+                })?,
+            }),
+            8 => Statement::Import(ImportStatement {
+                source: match self.tag()? {
+                    0 => ImportSource::Path(self.text()?),
+                    1 => ImportSource::Name(self.name()?),
+                    tag => return Err(unknown("import", tag)),
+                },
+                alias: self.optional_name()?,
+                origin: self.file.to_path_buf(),
+            }),
+            9 => Statement::Assignment(Assignment {
+                targets: self.expressions()?,
+                value: self.boxed()?,
+            }),
+            10 => Statement::CompoundAssignment(CompoundAssignment {
+                target: self.expression()?,
+                operator: self.operator()?,
+                value: self.boxed()?,
+            }),
+            11 => Statement::Together(TogetherStatement { body: self.block()? }),
+            tag => return Err(unknown("statement", tag)),
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expression, String> {
+        Ok(match self.tag()? {
+            0 => Expression::Identifier(self.name()?),
+            1 => Expression::IntegerLiteral(i64::from_le_bytes(self.bytes()?)),
+            2 => Expression::FloatLiteral(f64::from_bits(u64::from_le_bytes(self.bytes()?))),
+            3 => Expression::StringLiteral(self.text()?.into()),
+            4 => Expression::BooleanLiteral(self.flag()?),
+            5 => Expression::NoneLiteral,
+            6 => Expression::List(self.expressions()?),
+            7 => Expression::Tuple(self.expressions()?),
+            8 => Expression::Set(self.expressions()?),
+            9 => Expression::Dict {
+                pairs: self.list(|decoder| Ok((decoder.expression()?, decoder.expression()?)))?,
+            },
+            10 => Expression::Prefix(PrefixExpression {
+                operator: self.operator()?,
+                right: self.boxed()?,
+            }),
+            11 => Expression::Infix(InfixExpression {
+                left: self.boxed()?,
+                operator: self.operator()?,
+                right: self.boxed()?,
+            }),
+            12 => Expression::Postfix(PostfixExpression {
+                left: self.boxed()?,
+                operator: self.operator()?,
+            }),
+            13 => Expression::Index(IndexExpression {
+                object: self.boxed()?,
+                index: self.boxed()?,
+            }),
+            14 => Expression::Member(MemberExpression {
+                object: self.boxed()?,
+                property: self.name()?,
+                optional: self.flag()?,
+            }),
+            15 => Expression::Call(CallExpression {
+                function: self.boxed()?,
+                arguments: self.expressions()?,
+                keywords: self.list(|decoder| {
+                    Ok(KeywordArgument {
+                        name: decoder.optional_name()?,
+                        value: decoder.expression()?,
+                    })
+                })?,
+            }),
+            16 => Expression::Function(FunctionLiteral {
+                parameters: Rc::from(self.names()?),
+                rest: self.optional_name()?,
+                keywords: self.optional_name()?,
+                body: Rc::from(self.block()?),
+            }),
+            17 => Expression::Unpack(UnpackExpression { value: self.boxed()? }),
+            18 => Expression::Conditional(ConditionalExpression {
+                condition: self.boxed()?,
+                consequence: self.boxed()?,
+                alternative: self.boxed()?,
+            }),
+            19 => Expression::Maybe(MaybeExpression { value: self.boxed()? }),
+            20 => Expression::Propagate(PropagateExpression { value: self.boxed()? }),
+            21 => Expression::Super,
+            tag => return Err(unknown("expression", tag)),
+        })
+    }
+}
@@ -0,0 +1,161 @@
+//! Crash reports for internal errors.
+//!
+//! A panic inside the interpreter is a bug in Carrion rather than in the
+//! script. [`catch`] stops such a panic at the top level and writes a crash
+//! bundle to the temporary directory: the interpreter version and platform,
+//! the panic message and backtrace, the script's source, and its tokens and
+//! syntax tree when the run got that far. The tokens and tree are rebuilt
+//! from the source while the bundle is written, so a run that doesn't crash
+//! only pays for keeping its source.
+//!
+//! [`install`] adds the backtrace to bundles; without it they only hold the
+//! panic message.
+
+use crate::evaluator::builtins::info::VERSION;
+use crate::{lexer, parser};
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How far the script got before the crash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Read,
+    Lexed,
+    Parsed,
+}
+
+struct Script {
+    path: PathBuf,
+    source: String,
+    stage: Stage,
+}
+
+thread_local! {
+    static SCRIPT: RefCell<Option<Script>> = const { RefCell::new(None) };
+}
+
+/// The last panic the hook saw, from any thread: its message with location,
+/// and its backtrace.
+static LAST_PANIC: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Record each panic's message and backtrace for the crash bundle instead of
+/// printing them; [`catch`] reports the crash.
+pub fn install() {
+    panic::set_hook(Box::new(|info| {
+        let message = match info.location() {
+            Some(location) => format!("{} at {}", payload_message(info.payload()), location),
+            None => payload_message(info.payload()),
+        };
+        let backtrace = Backtrace::force_capture().to_string();
+        if let Ok(mut last) = LAST_PANIC.lock() {
+            *last = Some((message, backtrace));
+        }
+    }));
+}
+
+/// Remember the script being run, for a crash bundle.
+pub fn set_script(path: &Path, source: &str) {
+    SCRIPT.with(|script| {
+        *script.borrow_mut() = Some(Script {
+            path: path.to_path_buf(),
+            source: source.to_string(),
+            stage: Stage::Read,
+        })
+    });
+}
+
+/// Note that the script has been lexed or parsed.
+pub fn reached(stage: Stage) {
+    SCRIPT.with(|script| {
+        if let Some(script) = script.borrow_mut().as_mut() {
+            script.stage = stage;
+        }
+    });
+}
+
+/// Run `run`. If it panics, write a crash bundle and return the message to
+/// show the user, which says where the bundle is.
+pub fn catch<T>(run: impl FnOnce() -> T) -> Result<T, String> {
+    let payload = match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(value) => return Ok(value),
+        Err(payload) => payload,
+    };
+    let (message, backtrace) = LAST_PANIC
+        .lock()
+        .ok()
+        .and_then(|mut last| last.take())
+        .unwrap_or_else(|| (payload_message(&*payload), "(not recorded)".to_string()));
+
+    let bundle = bundle(&message, &backtrace);
+    let path = env::temp_dir().join(format!(
+        "carrion-crash-{}-{}.txt",
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+        process::id()
+    ));
+    let saved = match fs::write(&path, bundle) {
+        Ok(()) => format!("A crash report was written to {}", path.display()),
+        Err(e) => format!("The crash report could not be written to {}: {}", path.display(), e),
+    };
+    Err(format!(
+        "Internal error: {}\n{}\nThis is a bug in Carrion; please attach the report when filing an issue.",
+        message, saved
+    ))
+}
+
+/// The text of a crash bundle for a panic with `message` and `backtrace`.
+pub fn bundle(message: &str, backtrace: &str) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "Carrion crash report");
+    let _ = writeln!(text, "version: {}", VERSION);
+    let _ = writeln!(text, "platform: {} {}", env::consts::OS, env::consts::ARCH);
+    let _ = writeln!(text, "\n== panic ==\n{}", message);
+    let _ = writeln!(text, "\n== backtrace ==\n{}", backtrace.trim_end());
+
+    SCRIPT.with(|script| {
+        let script = script.borrow();
+        let Some(script) = script.as_ref() else {
+            let _ = writeln!(text, "\n== source ==\n(no script was running)");
+            return;
+        };
+        let _ = writeln!(text, "\n== source: {} ==\n{}", script.path.display(), script.source.trim_end());
+        if script.stage >= Stage::Lexed {
+            let _ = writeln!(text, "\n== tokens ==\n{}", rebuild(script, false));
+        }
+        if script.stage >= Stage::Parsed {
+            let _ = writeln!(text, "\n== syntax tree ==\n{}", rebuild(script, true));
+        }
+    });
+    text
+}
+
+/// Lex the script again and list its tokens, or parse it and show its
+/// syntax tree. Either may hit the same bug, which is noted instead.
+fn rebuild(script: &Script, parse: bool) -> String {
+    let rebuilt = panic::catch_unwind(|| {
+        let tokens = lexer::Lexer::new(script.source.clone(), script.path.clone()).scan_tokens();
+        if !parse {
+            return tokens.iter().map(|token| format!("{:?}", token)).collect::<Vec<_>>().join("\n");
+        }
+        format!("{:#?}", parser::Parser::new(tokens).parse_program())
+    });
+    rebuilt.unwrap_or_else(|payload| format!("(panicked again while rebuilding: {})", payload_message(&*payload)))
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -1,7 +1,231 @@
+//! Reporting errors to people.
+//!
+//! [`render_diagnostic`] turns a [`CarrionError`] into the text the CLI
+//! prints: the message, and when the error knows where it happened, the
+//! source line with a caret under the spot, followed by the spells it was
+//! called from. The test runner, the REPL and embedders call it too, so an
+//! error reads the same wherever it is shown; [`render_parse_errors`] does
+//! the same for what the parser reports, underlining the offending token.
+//!
+//! Messages that mention a value, such as a type mismatch, show it with
+//! [`brief`], which keeps a huge list from burying the message.
+
+use crate::ast::Span;
+use crate::object::Object;
+use crate::parser::Diagnostic;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
 pub fn error(line_number: u32, message: &str) {
-    let _ = custom_report(line_number, "", message);
+    custom_report(line_number, "", message);
 }
 
 fn custom_report(line_number: u32, where_err: &str, message: &str) {
     eprintln!("[line: {line_number}] Error: {where_err}: {message}");
 }
+
+/// Which step of running a script failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Parse,
+    Evaluation,
+}
+
+impl Stage {
+    fn label(self) -> &'static str {
+        match self {
+            Stage::Parse => "Parse Error",
+            Stage::Evaluation => "Evaluation Error",
+        }
+    }
+}
+
+/// A spell call an error passed through on its way out, and where the
+/// call was made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub spell: String,
+    pub span: Span,
+}
+
+/// An error with what is known about where it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarrionError {
+    pub stage: Stage,
+    pub message: String,
+    pub span: Option<Span>,
+    /// How many characters from the span's column the error covers; the
+    /// rendered line underlines them all.
+    pub width: usize,
+    /// The spell calls the error left, innermost first.
+    pub frames: Vec<Frame>,
+}
+
+impl CarrionError {
+    pub fn new(stage: Stage, message: impl Into<String>) -> CarrionError {
+        CarrionError {
+            stage,
+            message: message.into(),
+            span: None,
+            width: 1,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> CarrionError {
+        CarrionError::new(Stage::Parse, message)
+    }
+
+    pub fn evaluation(message: impl Into<String>) -> CarrionError {
+        CarrionError::new(Stage::Evaluation, message)
+    }
+
+    /// The same error, located at `span`.
+    pub fn at(mut self, span: Span) -> CarrionError {
+        self.span = Some(span);
+        self
+    }
+
+    /// The same error, covering `width` characters from its column.
+    pub fn spanning(mut self, width: usize) -> CarrionError {
+        self.width = width;
+        self
+    }
+
+    /// The same error, raised inside the spell calls `frames`.
+    pub fn with_frames(mut self, frames: Vec<Frame>) -> CarrionError {
+        self.frames = frames;
+        self
+    }
+}
+
+impl From<&Diagnostic> for CarrionError {
+    /// A parse error at the diagnostic's token, covering the whole token.
+    fn from(diagnostic: &Diagnostic) -> CarrionError {
+        CarrionError::parse(diagnostic.message.as_str())
+            .at(diagnostic.span())
+            .spanning(diagnostic.token.literal.chars().count())
+    }
+}
+
+/// The source text of the files errors may point into.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: HashMap<PathBuf, String>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, file: impl Into<PathBuf>, source: impl Into<String>) {
+        self.files.insert(file.into(), source.into());
+    }
+
+    /// Line `line` (counting from 1) of `file`, if the map has it.
+    pub fn line(&self, file: &Path, line: usize) -> Option<&str> {
+        self.files.get(file)?.lines().nth(line.checked_sub(1)?)
+    }
+}
+
+thread_local! {
+    static COLOR: Cell<bool> = const { Cell::new(false) };
+    static VERBOSE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether [`brief`] shows values in full, as `--verbose-errors` asks.
+pub fn set_verbose(enabled: bool) {
+    VERBOSE.with(|verbose| verbose.set(enabled));
+}
+
+/// How many characters of a value [`brief`] shows.
+const MAX_VALUE: usize = 60;
+
+/// `value` as an error message shows it: its text, or for a long value the
+/// start of it followed by its type and size, such as
+/// `[0, 1, 2, ... (List, 10000 items)`. `--verbose-errors` shows the whole
+/// value instead.
+pub fn brief(value: &Object) -> String {
+    let text = value.to_string();
+    if VERBOSE.with(Cell::get) || text.chars().count() <= MAX_VALUE {
+        return text;
+    }
+    let start: String = text.chars().take(MAX_VALUE).collect();
+    let size = match value {
+        Object::String(text) => format!(", {} characters", text.chars().count()),
+        Object::List(items) | Object::Tuple(items) => format!(", {} items", items.len()),
+        Object::Set(items) => format!(", {} items", items.len()),
+        Object::Range(range) => format!(", {} items", range.len()),
+        Object::Dict(map) => format!(", {} keys", map.len()),
+        _ => String::new(),
+    };
+    format!("{}... ({}{})", start, value.type_name(), size)
+}
+
+/// Whether [`render_diagnostic`] colors its output with ANSI escapes. Off
+/// by default; the CLI turns it on when stderr is a terminal.
+pub fn set_color(enabled: bool) {
+    COLOR.with(|color| color.set(enabled));
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+/// `error` as the CLI shows it: the message, then the source line it points
+/// at with carets under the characters it covers, then the spell calls it
+/// came through, with a call that recursion repeated shown once.
+/// Parts the error or `sources` doesn't know are left out.
+pub fn render_diagnostic(error: &CarrionError, sources: &SourceMap) -> String {
+    let color = COLOR.with(Cell::get);
+    let paint = |code: &str, text: &str| {
+        if color { format!("{}{}{}", code, text, RESET) } else { text.to_string() }
+    };
+
+    let mut out = format!("{} {}", paint(RED, &format!("{}:", error.stage.label())), error.message);
+    if let Some(span) = &error.span {
+        let _ = write!(out, "\n  {} {}", paint(BLUE, "-->"), span);
+        if let Some(line) = sources.line(&span.file, span.line) {
+            let number = span.line.to_string();
+            let gutter = " ".repeat(number.len());
+            // Keep the line's tabs in the padding so the caret lines up.
+            let padding: String = line
+                .chars()
+                .take(span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let rest = line.chars().count().saturating_sub(padding.chars().count());
+            let carets = "^".repeat(error.width.min(rest).max(1));
+            let bar = paint(BLUE, "|");
+            let _ = write!(out, "\n{} {}", gutter, bar);
+            let _ = write!(out, "\n{} {} {}", paint(BLUE, &number), bar, line);
+            let _ = write!(out, "\n{} {} {}{}", gutter, bar, padding, paint(RED, &carets));
+        }
+    }
+    // Deep recursion repeats the same call many times; show it once.
+    let mut frames = error.frames.iter().peekable();
+    while let Some(frame) = frames.next() {
+        let _ = write!(out, "\n  in spell '{}' called at {}", frame.spell, frame.span);
+        let mut repeats = 0;
+        while frames.next_if(|next| next.spell == frame.spell && next.span.to_string() == frame.span.to_string()).is_some() {
+            repeats += 1;
+        }
+        if repeats > 0 {
+            let _ = write!(out, " ({} more {})", repeats, if repeats == 1 { "time" } else { "times" });
+        }
+    }
+    out
+}
+
+/// Everything the parser reported, each rendered as [`render_diagnostic`]
+/// does, one after another.
+pub fn render_parse_errors(diagnostics: &[Diagnostic], sources: &SourceMap) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| render_diagnostic(&CarrionError::from(diagnostic), sources))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,144 @@
+//! Complex numbers: `complex(re, im)`, displayed as `a+bi`.
+//!
+//! Integers and floats combine with complex numbers as if their imaginary
+//! part were zero. Complex numbers have no ordering, so only `==` and `!=`
+//! compare them.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    fn div(self, other: Self) -> Result<Self, String> {
+        let denominator = other.re * other.re + other.im * other.im;
+        if denominator == 0.0 {
+            return Err("Division by zero".to_string());
+        }
+        Ok(Complex {
+            re: (self.re * other.re + self.im * other.im) / denominator,
+            im: (self.im * other.re - self.re * other.im) / denominator,
+        })
+    }
+}
+
+impl std::fmt::Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.im.is_sign_negative() { '-' } else { '+' };
+        write!(f, "{}{}{}i", self.re, sign, self.im.abs())
+    }
+}
+
+impl NativeObject for Complex {
+    fn type_name(&self) -> &'static str {
+        "complex"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "real" => Some(builtin_complex_real),
+            "imag" => Some(builtin_complex_imag),
+            "conjugate" => Some(builtin_complex_conjugate),
+            "abs" => Some(builtin_complex_abs),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        self.to_string()
+    }
+
+    fn binary_op(&self, operator: &Operator, other: &Object, reflected: bool) -> Option<Result<Object, String>> {
+        let other = match other {
+            Object::Integer(i) => Complex { re: *i as f64, im: 0.0 },
+            Object::Float(f) => Complex { re: *f, im: 0.0 },
+            Object::Native(native) => *native.downcast_ref::<Complex>()?,
+            _ => return None,
+        };
+        let (a, b) = if reflected { (other, *self) } else { (*self, other) };
+
+        let result = match operator {
+            Operator::Plus => complex_object(a.re + b.re, a.im + b.im),
+            Operator::Minus => complex_object(a.re - b.re, a.im - b.im),
+            Operator::Multiply => complex_object(a.re * b.re - a.im * b.im, a.re * b.im + a.im * b.re),
+            Operator::Divide => return Some(a.div(b).map(|c| complex_object(c.re, c.im))),
+            Operator::Equal => Object::Boolean(a == b),
+            Operator::NotEqual => Object::Boolean(a != b),
+            _ => return None,
+        };
+        Some(Ok(result))
+    }
+}
+
+/// `complex(re, im)` — the imaginary part defaults to 0.
+pub fn builtin_complex(args: Vec<Object>) -> Result<Object, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1 or 2",
+            args.len()
+        ));
+    }
+    let re = real_arg(&args[0])?;
+    let im = match args.get(1) {
+        Some(arg) => real_arg(arg)?,
+        None => 0.0,
+    };
+    Ok(complex_object(re, im))
+}
+
+pub fn builtin_complex_real(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Float(complex_receiver(&args, "real")?.re))
+}
+
+pub fn builtin_complex_imag(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Float(complex_receiver(&args, "imag")?.im))
+}
+
+pub fn builtin_complex_conjugate(args: Vec<Object>) -> Result<Object, String> {
+    let value = complex_receiver(&args, "conjugate")?;
+    Ok(complex_object(value.re, -value.im))
+}
+
+pub fn builtin_complex_abs(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Float(complex_receiver(&args, "abs")?.abs()))
+}
+
+fn real_arg(arg: &Object) -> Result<f64, String> {
+    match arg {
+        Object::Integer(i) => Ok(*i as f64),
+        Object::Float(f) => Ok(*f),
+        other => Err(format!("complex expects numeric parts, got {}", brief(other))),
+    }
+}
+
+fn complex_receiver(args: &[Object], method: &str) -> Result<Complex, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len().saturating_sub(1)
+        ));
+    }
+    match &args[0] {
+        Object::Native(native) => native.downcast_ref::<Complex>().copied(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a complex number", method))
+}
+
+fn complex_object(re: f64, im: f64) -> Object {
+    Object::Native(NativeHandle::new(Complex { re, im }))
+}
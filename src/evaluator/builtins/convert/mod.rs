@@ -0,0 +1,63 @@
+//! `str`, `int` and `float`: converting values to text and numbers.
+//!
+//! Numbers print the same way everywhere, in the locale-independent form of
+//! [`format_float`], so `int(str(n)) == n` and `float(str(x)) == x` hold for
+//! every integer and float.
+//!
+//! [`format_float`]: crate::object::format_float
+
+use crate::evaluator::overloading;
+use crate::object::Object;
+
+/// `str(value)` — `value` as text, the way `print` shows it, reporting an
+/// error from an instance's `__str__` spell.
+pub fn builtin_str(args: Vec<Object>) -> Result<Object, String> {
+    match single(&args)? {
+        Object::String(s) => Ok(Object::String(s.clone())),
+        value if let Some(text) = overloading::to_text(value) => Ok(Object::String(text?.into())),
+        other => Ok(Object::String(other.to_string().into())),
+    }
+}
+
+/// `int(value)` — an integer from a number, boolean or string of digits.
+/// Floats are truncated toward zero.
+pub fn builtin_int(args: Vec<Object>) -> Result<Object, String> {
+    match single(&args)? {
+        Object::Integer(i) => Ok(Object::Integer(*i)),
+        Object::Boolean(b) => Ok(Object::Integer(i64::from(*b))),
+        // `as` saturates, so check the range first; 2^63 itself is out of range.
+        Object::Float(f) if f.is_finite() && *f >= i64::MIN as f64 && *f < i64::MAX as f64 => {
+            Ok(Object::Integer(f.trunc() as i64))
+        }
+        Object::Float(f) => Err(format!("int cannot convert {} to an integer", Object::Float(*f))),
+        Object::String(s) => s
+            .trim()
+            .parse::<i64>()
+            .map(Object::Integer)
+            .map_err(|_| format!("int cannot parse '{}' as an integer", s)),
+        other => Err(format!("int expects a number or string, got {}", other.type_name())),
+    }
+}
+
+/// `float(value)` — a float from a number, boolean or numeric string such as
+/// `"2.5"`, `"1e300"`, `"inf"` or `"NaN"`.
+pub fn builtin_float(args: Vec<Object>) -> Result<Object, String> {
+    match single(&args)? {
+        Object::Float(f) => Ok(Object::Float(*f)),
+        Object::Integer(i) => Ok(Object::Float(*i as f64)),
+        Object::Boolean(b) => Ok(Object::Float(f64::from(u8::from(*b)))),
+        Object::String(s) => s
+            .trim()
+            .parse::<f64>()
+            .map(Object::Float)
+            .map_err(|_| format!("float cannot parse '{}' as a number", s)),
+        other => Err(format!("float expects a number or string, got {}", other.type_name())),
+    }
+}
+
+fn single(args: &[Object]) -> Result<&Object, String> {
+    match args {
+        [value] => Ok(value),
+        _ => Err(format!("Wrong number of arguments. got={}, want=1", args.len())),
+    }
+}
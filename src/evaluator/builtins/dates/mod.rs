@@ -0,0 +1,556 @@
+//! Dates, times and durations.
+//!
+//! Date-times are UTC instants with millisecond precision, written and read
+//! as ISO-8601 (`2024-03-10T09:30:00Z`). Durations come from `days(2)`,
+//! `minutes(5)` and friends. Adding a duration to a date-time moves it,
+//! subtracting two date-times gives the duration between them, and both
+//! kinds compare with `<`, `==` and the other comparison operators.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MS_PER_SECOND: i64 = 1_000;
+const MS_PER_MINUTE: i64 = 60 * MS_PER_SECOND;
+const MS_PER_HOUR: i64 = 60 * MS_PER_MINUTE;
+const MS_PER_DAY: i64 = 24 * MS_PER_HOUR;
+
+/// The years `date(...)` accepts.
+const MIN_YEAR: i64 = -9999;
+const MAX_YEAR: i64 = 9999;
+
+/// A UTC instant, in milliseconds since 1970-01-01T00:00:00Z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    millis: i64,
+}
+
+/// A signed span of time in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration {
+    millis: i64,
+}
+
+impl DateTime {
+    fn from_parts(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32, milli: u32) -> Result<Self, String> {
+        // Keeps the calendar arithmetic in `days_from_civil` from overflowing.
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+            return Err(format!("Year {} is out of range; dates run from {} to {}", year, MIN_YEAR, MAX_YEAR));
+        }
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(format!("Invalid date: {:04}-{:02}-{:02}", year, month, day));
+        }
+        if hour > 23 || minute > 59 || second > 59 || milli > 999 {
+            return Err(format!("Invalid time: {:02}:{:02}:{:02}", hour, minute, second));
+        }
+        let millis = days_from_civil(year, month, day)
+            .checked_mul(MS_PER_DAY)
+            .and_then(|ms| {
+                ms.checked_add(
+                    hour as i64 * MS_PER_HOUR
+                        + minute as i64 * MS_PER_MINUTE
+                        + second as i64 * MS_PER_SECOND
+                        + milli as i64,
+                )
+            })
+            .ok_or_else(out_of_range)?;
+        Ok(DateTime { millis })
+    }
+
+    /// Parse `YYYY-MM-DD`, optionally followed by `THH:MM[:SS[.fff]]` and a
+    /// `Z` or `±HH:MM` offset. Times without an offset are taken as UTC.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (date, time) = match text.find(['T', ' ']) {
+            Some(split) => (&text[..split], Some(&text[split + 1..])),
+            None => (text, None),
+        };
+
+        let mut date_parts = date.splitn(3, '-');
+        let year = fixed_digits(date_parts.next()?, 4)?;
+        let month = fixed_digits(date_parts.next()?, 2)?;
+        let day = fixed_digits(date_parts.next()?, 2)?;
+
+        let Some(time) = time else {
+            return DateTime::from_parts(year, month as u32, day as u32, 0, 0, 0, 0).ok();
+        };
+        let (clock, offset_minutes) = split_offset(time)?;
+        let (clock, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+        let mut clock_parts = clock.split(':');
+        let hour = fixed_digits(clock_parts.next()?, 2)?;
+        let minute = fixed_digits(clock_parts.next()?, 2)?;
+        let second = match clock_parts.next() {
+            Some(s) => fixed_digits(s, 2)?,
+            None => 0,
+        };
+        if clock_parts.next().is_some() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        // Only milliseconds are kept; extra fractional digits are truncated.
+        let milli = format!("{:0<3}", &fraction[..fraction.len().min(3)]).parse().ok()?;
+
+        let local = DateTime::from_parts(year, month as u32, day as u32, hour as u32, minute as u32, second as u32, milli).ok()?;
+        local.shift(-offset_minutes * MS_PER_MINUTE).ok()
+    }
+
+    fn shift(self, millis: i64) -> Result<Self, String> {
+        self.millis
+            .checked_add(millis)
+            .map(|millis| DateTime { millis })
+            .ok_or_else(out_of_range)
+    }
+
+    /// (year, month, day, hour, minute, second, millisecond) in UTC.
+    fn parts(self) -> (i64, u32, u32, u32, u32, u32, u32) {
+        let (year, month, day) = civil_from_days(self.millis.div_euclid(MS_PER_DAY));
+        let ms = self.millis.rem_euclid(MS_PER_DAY);
+        (
+            year,
+            month,
+            day,
+            (ms / MS_PER_HOUR) as u32,
+            (ms % MS_PER_HOUR / MS_PER_MINUTE) as u32,
+            (ms % MS_PER_MINUTE / MS_PER_SECOND) as u32,
+            (ms % MS_PER_SECOND) as u32,
+        )
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day, hour, minute, second, milli) = self.parts();
+        write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, hour, minute, second)?;
+        if milli != 0 {
+            write!(f, ".{:03}", milli)?;
+        }
+        write!(f, "Z")
+    }
+}
+
+impl std::fmt::Display for Duration {
+    /// ISO-8601 duration, e.g. `P2DT3H30M` or `PT0.5S`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.millis < 0 {
+            write!(f, "-")?;
+        }
+        let ms = self.millis.unsigned_abs();
+        let (days, ms) = (ms / MS_PER_DAY as u64, ms % MS_PER_DAY as u64);
+        let (hours, ms) = (ms / MS_PER_HOUR as u64, ms % MS_PER_HOUR as u64);
+        let (minutes, ms) = (ms / MS_PER_MINUTE as u64, ms % MS_PER_MINUTE as u64);
+
+        write!(f, "P")?;
+        if days > 0 {
+            write!(f, "{}D", days)?;
+        }
+        if hours == 0 && minutes == 0 && ms == 0 {
+            return if days == 0 { write!(f, "T0S") } else { Ok(()) };
+        }
+        write!(f, "T")?;
+        if hours > 0 {
+            write!(f, "{}H", hours)?;
+        }
+        if minutes > 0 {
+            write!(f, "{}M", minutes)?;
+        }
+        if ms > 0 {
+            let (seconds, milli) = (ms / 1000, ms % 1000);
+            if milli == 0 {
+                write!(f, "{}S", seconds)?;
+            } else {
+                let fraction = format!("{:03}", milli);
+                write!(f, "{}.{}S", seconds, fraction.trim_end_matches('0'))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NativeObject for DateTime {
+    fn type_name(&self) -> &'static str {
+        "datetime"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "year" => Some(builtin_datetime_year),
+            "month" => Some(builtin_datetime_month),
+            "day" => Some(builtin_datetime_day),
+            "hour" => Some(builtin_datetime_hour),
+            "minute" => Some(builtin_datetime_minute),
+            "second" => Some(builtin_datetime_second),
+            "weekday" => Some(builtin_datetime_weekday),
+            "timestamp" => Some(builtin_datetime_timestamp),
+            "iso" => Some(builtin_datetime_iso),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        self.to_string()
+    }
+
+    fn binary_op(&self, operator: &Operator, other: &Object, reflected: bool) -> Option<Result<Object, String>> {
+        let Object::Native(native) = other else {
+            return None;
+        };
+        if let Some(duration) = native.downcast_ref::<Duration>() {
+            return match (operator, reflected) {
+                (Operator::Plus, _) => Some(self.shift(duration.millis).map(datetime_object)),
+                (Operator::Minus, false) => Some(
+                    duration
+                        .millis
+                        .checked_neg()
+                        .ok_or_else(out_of_range)
+                        .and_then(|ms| self.shift(ms))
+                        .map(datetime_object),
+                ),
+                _ => None,
+            };
+        }
+
+        let other = *native.downcast_ref::<DateTime>()?;
+        let (left, right) = if reflected { (other, *self) } else { (*self, other) };
+        match operator {
+            Operator::Minus => Some(
+                left.millis
+                    .checked_sub(right.millis)
+                    .map(|millis| duration_object(Duration { millis }))
+                    .ok_or_else(out_of_range),
+            ),
+            _ => compare(operator, left, right),
+        }
+    }
+}
+
+impl NativeObject for Duration {
+    fn type_name(&self) -> &'static str {
+        "duration"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "total_seconds" => Some(builtin_duration_total_seconds),
+            "total_ms" => Some(builtin_duration_total_ms),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        self.to_string()
+    }
+
+    fn binary_op(&self, operator: &Operator, other: &Object, reflected: bool) -> Option<Result<Object, String>> {
+        let factor = match other {
+            Object::Integer(i) => Some(*i as f64),
+            Object::Float(f) => Some(*f),
+            _ => None,
+        };
+        if let Some(factor) = factor {
+            return match (operator, reflected) {
+                (Operator::Multiply, _) => Some(scaled(self.millis as f64 * factor)),
+                (Operator::Divide, false) if factor == 0.0 => Some(Err("Division by zero".to_string())),
+                (Operator::Divide, false) => Some(scaled(self.millis as f64 / factor)),
+                _ => None,
+            };
+        }
+
+        let Object::Native(native) = other else {
+            return None;
+        };
+        if let Some(datetime) = native.downcast_ref::<DateTime>() {
+            // `duration + date` reads oddly but is as valid as `date + duration`.
+            return match operator {
+                Operator::Plus => Some(datetime.shift(self.millis).map(datetime_object)),
+                _ => None,
+            };
+        }
+
+        let other = *native.downcast_ref::<Duration>()?;
+        let (left, right) = if reflected { (other, *self) } else { (*self, other) };
+        let millis = match operator {
+            Operator::Plus => left.millis.checked_add(right.millis),
+            Operator::Minus => left.millis.checked_sub(right.millis),
+            Operator::Divide if right.millis == 0 => return Some(Err("Division by zero".to_string())),
+            Operator::Divide => return Some(Ok(Object::Float(left.millis as f64 / right.millis as f64))),
+            _ => return compare(operator, left, right),
+        };
+        Some(
+            millis
+                .map(|millis| duration_object(Duration { millis }))
+                .ok_or_else(out_of_range),
+        )
+    }
+}
+
+/// `now()` — the current UTC date-time.
+pub fn builtin_now(args: Vec<Object>) -> Result<Object, String> {
+    if !args.is_empty() {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len()
+        ));
+    }
+    let millis = crate::evaluator::trace::read("time", || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_millis() as i64)
+            .map_err(|e| format!("System clock is before 1970: {}", e))
+    })??;
+    Ok(datetime_object(DateTime { millis }))
+}
+
+/// `date(year, month, day, hour, minute, second)` — the time parts are optional.
+pub fn builtin_date(args: Vec<Object>) -> Result<Object, String> {
+    if !(3..=6).contains(&args.len()) {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=3 to 6",
+            args.len()
+        ));
+    }
+    let mut parts = [0i64; 6];
+    for (part, arg) in parts.iter_mut().zip(&args) {
+        *part = match arg {
+            Object::Integer(i) => *i,
+            other => return Err(format!("date expects integer parts, got {}", brief(other))),
+        };
+    }
+    let [year, month, day, hour, minute, second] = parts;
+    let small = |value: i64| u32::try_from(value).unwrap_or(u32::MAX);
+    DateTime::from_parts(year, small(month), small(day), small(hour), small(minute), small(second), 0)
+        .map(datetime_object)
+}
+
+/// `parse_date(text)` — read an ISO-8601 date or date-time.
+pub fn builtin_parse_date(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+    match &args[0] {
+        Object::String(text) => DateTime::parse(text)
+            .map(datetime_object)
+            .ok_or_else(|| format!("Invalid ISO-8601 date: '{}'", text)),
+        other => Err(format!("parse_date expects a string, got {}", brief(other))),
+    }
+}
+
+pub fn builtin_milliseconds(args: Vec<Object>) -> Result<Object, String> {
+    duration_from(args, 1)
+}
+
+pub fn builtin_seconds(args: Vec<Object>) -> Result<Object, String> {
+    duration_from(args, MS_PER_SECOND)
+}
+
+pub fn builtin_minutes(args: Vec<Object>) -> Result<Object, String> {
+    duration_from(args, MS_PER_MINUTE)
+}
+
+pub fn builtin_hours(args: Vec<Object>) -> Result<Object, String> {
+    duration_from(args, MS_PER_HOUR)
+}
+
+pub fn builtin_days(args: Vec<Object>) -> Result<Object, String> {
+    duration_from(args, MS_PER_DAY)
+}
+
+pub fn builtin_weeks(args: Vec<Object>) -> Result<Object, String> {
+    duration_from(args, 7 * MS_PER_DAY)
+}
+
+pub fn builtin_datetime_year(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(datetime_receiver(&args, "year")?.parts().0))
+}
+
+pub fn builtin_datetime_month(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(datetime_receiver(&args, "month")?.parts().1 as i64))
+}
+
+pub fn builtin_datetime_day(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(datetime_receiver(&args, "day")?.parts().2 as i64))
+}
+
+pub fn builtin_datetime_hour(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(datetime_receiver(&args, "hour")?.parts().3 as i64))
+}
+
+pub fn builtin_datetime_minute(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(datetime_receiver(&args, "minute")?.parts().4 as i64))
+}
+
+pub fn builtin_datetime_second(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(datetime_receiver(&args, "second")?.parts().5 as i64))
+}
+
+/// `d.weekday()` — ISO weekday number, 1 for Monday through 7 for Sunday.
+pub fn builtin_datetime_weekday(args: Vec<Object>) -> Result<Object, String> {
+    let days = datetime_receiver(&args, "weekday")?.millis.div_euclid(MS_PER_DAY);
+    // 1970-01-01 was a Thursday.
+    Ok(Object::Integer((days + 3).rem_euclid(7) + 1))
+}
+
+/// `d.timestamp()` — seconds since the Unix epoch.
+pub fn builtin_datetime_timestamp(args: Vec<Object>) -> Result<Object, String> {
+    let millis = datetime_receiver(&args, "timestamp")?.millis;
+    Ok(Object::Float(millis as f64 / MS_PER_SECOND as f64))
+}
+
+pub fn builtin_datetime_iso(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::String(datetime_receiver(&args, "iso")?.to_string().into()))
+}
+
+pub fn builtin_duration_total_seconds(args: Vec<Object>) -> Result<Object, String> {
+    let millis = duration_receiver(&args, "total_seconds")?.millis;
+    Ok(Object::Float(millis as f64 / MS_PER_SECOND as f64))
+}
+
+pub fn builtin_duration_total_ms(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Integer(duration_receiver(&args, "total_ms")?.millis))
+}
+
+/// Convert days since 1970-01-01 into a proleptic Gregorian (year, month, day).
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The inverse of [`civil_from_days`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parse exactly `width` ASCII digits.
+fn fixed_digits(text: &str, width: usize) -> Option<i64> {
+    if text.len() == width && text.chars().all(|c| c.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Split a trailing `Z` or `±HH:MM` off a time, returning the offset in minutes.
+fn split_offset(time: &str) -> Option<(&str, i64)> {
+    if let Some(clock) = time.strip_suffix('Z') {
+        return Some((clock, 0));
+    }
+    match time.rfind(['+', '-']) {
+        Some(at) => {
+            let (clock, offset) = time.split_at(at);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let hours = fixed_digits(hours, 2).filter(|hours| *hours <= 23)?;
+            let minutes = fixed_digits(minutes, 2).filter(|minutes| *minutes <= 59)?;
+            Some((clock, sign * (hours * 60 + minutes)))
+        }
+        None => Some((time, 0)),
+    }
+}
+
+fn compare<T: Ord>(operator: &Operator, left: T, right: T) -> Option<Result<Object, String>> {
+    let result = match operator {
+        Operator::Equal => left == right,
+        Operator::NotEqual => left != right,
+        Operator::LessThan => left < right,
+        Operator::GreaterThan => left > right,
+        Operator::LessThanEqual => left <= right,
+        Operator::GreaterThanEqual => left >= right,
+        _ => return None,
+    };
+    Some(Ok(Object::Boolean(result)))
+}
+
+fn duration_from(args: Vec<Object>, unit_ms: i64) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+    match &args[0] {
+        Object::Integer(n) => n
+            .checked_mul(unit_ms)
+            .map(|millis| duration_object(Duration { millis }))
+            .ok_or_else(out_of_range),
+        Object::Float(f) => scaled(f * unit_ms as f64),
+        other => Err(format!("Duration amount must be a number, got {}", brief(other))),
+    }
+}
+
+/// A duration from a float millisecond count, rounded to the nearest millisecond.
+fn scaled(millis: f64) -> Result<Object, String> {
+    if !millis.is_finite() || millis.abs() >= i64::MAX as f64 {
+        return Err(out_of_range());
+    }
+    Ok(duration_object(Duration {
+        millis: millis.round() as i64,
+    }))
+}
+
+fn datetime_receiver(args: &[Object], method: &str) -> Result<DateTime, String> {
+    receiver::<DateTime>(args, method, "datetime")
+}
+
+fn duration_receiver(args: &[Object], method: &str) -> Result<Duration, String> {
+    receiver::<Duration>(args, method, "duration")
+}
+
+fn receiver<T: Copy + 'static>(args: &[Object], method: &str, kind: &str) -> Result<T, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len().saturating_sub(1)
+        ));
+    }
+    match &args[0] {
+        Object::Native(native) => native.downcast_ref::<T>().copied(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a {}", method, kind))
+}
+
+fn datetime_object(value: DateTime) -> Object {
+    Object::Native(NativeHandle::new(value))
+}
+
+fn duration_object(value: Duration) -> Object {
+    Object::Native(NativeHandle::new(value))
+}
+
+fn out_of_range() -> String {
+    "Date or duration out of range".to_string()
+}
@@ -0,0 +1,329 @@
+//! Exact fixed-point decimals for money and other base-10 quantities.
+//!
+//! `decimal("3.50")` keeps every digit it was given: `+`, `-` and `*` are
+//! exact, and `/` rounds half-to-even once the result needs more than
+//! `DIVISION_SCALE` fractional digits. Decimals mix with integers but not
+//! with floats, since that would bring back the binary rounding errors the
+//! type exists to avoid.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cmp::Ordering;
+
+/// Fractional digits kept by division that doesn't terminate sooner.
+const DIVISION_SCALE: u32 = 18;
+/// The most fractional digits a decimal can carry.
+const MAX_SCALE: u32 = 28;
+
+/// A value of `units / 10^scale`, e.g. `3.50` is 350 units at scale 2.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    HalfEven,
+    HalfUp,
+    Down,
+    Up,
+    Floor,
+    Ceiling,
+}
+
+impl Rounding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "half_even" => Some(Self::HalfEven),
+            "half_up" => Some(Self::HalfUp),
+            "down" => Some(Self::Down),
+            "up" => Some(Self::Up),
+            "floor" => Some(Self::Floor),
+            "ceiling" => Some(Self::Ceiling),
+            _ => None,
+        }
+    }
+}
+
+impl Decimal {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() && fraction.is_empty()
+            || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+            || fraction.len() > MAX_SCALE as usize
+        {
+            return None;
+        }
+
+        let mut units: i128 = 0;
+        for c in whole.chars().chain(fraction.chars()) {
+            units = units.checked_mul(10)?.checked_add(c.to_digit(10)? as i128)?;
+        }
+        Some(Decimal {
+            units: if negative { -units } else { units },
+            scale: fraction.len() as u32,
+        })
+    }
+
+    fn from_integer(value: i64) -> Self {
+        Decimal {
+            units: value as i128,
+            scale: 0,
+        }
+    }
+
+    /// Re-express at `scale` fractional digits, rounding if digits are dropped.
+    pub fn rescale(self, scale: u32, mode: Rounding) -> Result<Self, String> {
+        let units = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.units,
+            Ordering::Greater => self
+                .units
+                .checked_mul(pow10(scale - self.scale)?)
+                .ok_or_else(overflow)?,
+            Ordering::Less => divide_rounded(self.units, pow10(self.scale - scale)?, mode),
+        };
+        Ok(Decimal { units, scale })
+    }
+
+    /// Both operands' units at their common (larger) scale.
+    fn aligned(self, other: Self) -> Result<(i128, i128, u32), String> {
+        let scale = self.scale.max(other.scale);
+        let a = self.rescale(scale, Rounding::HalfEven)?;
+        let b = other.rescale(scale, Rounding::HalfEven)?;
+        Ok((a.units, b.units, scale))
+    }
+
+    fn add(self, other: Self) -> Result<Self, String> {
+        let (a, b, scale) = self.aligned(other)?;
+        let units = a.checked_add(b).ok_or_else(overflow)?;
+        Ok(Decimal { units, scale })
+    }
+
+    fn sub(self, other: Self) -> Result<Self, String> {
+        let (a, b, scale) = self.aligned(other)?;
+        let units = a.checked_sub(b).ok_or_else(overflow)?;
+        Ok(Decimal { units, scale })
+    }
+
+    fn mul(self, other: Self) -> Result<Self, String> {
+        let units = self.units.checked_mul(other.units).ok_or_else(overflow)?;
+        let product = Decimal {
+            units,
+            scale: self.scale + other.scale,
+        };
+        if product.scale > MAX_SCALE {
+            product.rescale(MAX_SCALE, Rounding::HalfEven)
+        } else {
+            Ok(product)
+        }
+    }
+
+    fn div(self, other: Self) -> Result<Self, String> {
+        if other.units == 0 {
+            return Err("Division by zero".to_string());
+        }
+        let scale = DIVISION_SCALE.max(self.scale);
+        let numerator = self
+            .units
+            .checked_mul(pow10(scale + other.scale - self.scale)?)
+            .ok_or_else(overflow)?;
+        let (numerator, denominator) = if other.units < 0 {
+            (-numerator, -other.units)
+        } else {
+            (numerator, other.units)
+        };
+        let mut quotient = Decimal {
+            units: divide_rounded(numerator, denominator, Rounding::HalfEven),
+            scale,
+        };
+
+        // Drop padding zeros, but keep the scale a pencil-and-paper division
+        // would give, so 10.00 / 2 is 5.00.
+        let natural_scale = self.scale.saturating_sub(other.scale);
+        while quotient.scale > natural_scale && quotient.units % 10 == 0 {
+            quotient.units /= 10;
+            quotient.scale -= 1;
+        }
+        Ok(quotient)
+    }
+
+    fn compare(self, other: Self) -> Result<Ordering, String> {
+        let (a, b, _) = self.aligned(other)?;
+        Ok(a.cmp(&b))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.units.unsigned_abs().to_string();
+        let sign = if self.units < 0 { "-" } else { "" };
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+impl NativeObject for Decimal {
+    fn type_name(&self) -> &'static str {
+        "decimal"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "round" => Some(builtin_decimal_round),
+            "to_float" => Some(builtin_decimal_to_float),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        self.to_string()
+    }
+
+    fn binary_op(&self, operator: &Operator, other: &Object, reflected: bool) -> Option<Result<Object, String>> {
+        let other = match other {
+            Object::Integer(i) => Decimal::from_integer(*i),
+            Object::Native(native) => *native.downcast_ref::<Decimal>()?,
+            _ => return None,
+        };
+        let (left, right) = if reflected { (other, *self) } else { (*self, other) };
+
+        let result = match operator {
+            Operator::Plus => left.add(right).map(decimal_object),
+            Operator::Minus => left.sub(right).map(decimal_object),
+            Operator::Multiply => left.mul(right).map(decimal_object),
+            Operator::Divide => left.div(right).map(decimal_object),
+            Operator::Equal => left.compare(right).map(|o| Object::Boolean(o.is_eq())),
+            Operator::NotEqual => left.compare(right).map(|o| Object::Boolean(o.is_ne())),
+            Operator::LessThan => left.compare(right).map(|o| Object::Boolean(o.is_lt())),
+            Operator::GreaterThan => left.compare(right).map(|o| Object::Boolean(o.is_gt())),
+            Operator::LessThanEqual => left.compare(right).map(|o| Object::Boolean(o.is_le())),
+            Operator::GreaterThanEqual => left.compare(right).map(|o| Object::Boolean(o.is_ge())),
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+/// `decimal(value)` — an exact decimal from a string like `"3.50"`, an integer,
+/// or a float (taken at its printed value, so `decimal(0.1)` is exactly 0.1).
+pub fn builtin_decimal(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    let value = match &args[0] {
+        Object::String(text) => {
+            Decimal::parse(text).ok_or_else(|| format!("Invalid decimal literal: '{}'", text))?
+        }
+        Object::Integer(i) => Decimal::from_integer(*i),
+        Object::Float(f) if f.is_finite() => Decimal::parse(&f.to_string())
+            .ok_or_else(|| format!("Cannot represent {} as a decimal", f))?,
+        Object::Native(native) if native.downcast_ref::<Decimal>().is_some() => {
+            return Ok(args[0].clone());
+        }
+        other => return Err(format!("decimal expects a string or number, got {}", brief(other))),
+    };
+    Ok(decimal_object(value))
+}
+
+/// `d.round(places, mode)` — round to `places` fractional digits. `mode` is one
+/// of `"half_even"` (the default), `"half_up"`, `"down"`, `"up"`, `"floor"`
+/// or `"ceiling"`.
+pub fn builtin_decimal_round(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1 or 2",
+            args.len().saturating_sub(1)
+        ));
+    }
+
+    let value = decimal_receiver(&args[0], "round")?;
+    let places = match &args[1] {
+        Object::Integer(n) if (0..=MAX_SCALE as i64).contains(n) => *n as u32,
+        other => return Err(format!("Decimal places must be between 0 and {}, got {}", MAX_SCALE, brief(other))),
+    };
+    let mode = match args.get(2) {
+        None => Rounding::HalfEven,
+        Some(Object::String(name)) => {
+            Rounding::parse(name).ok_or_else(|| format!("Unknown rounding mode '{}'", name))?
+        }
+        Some(other) => return Err(format!("Rounding mode must be a string, got {}", brief(other))),
+    };
+    value.rescale(places, mode).map(decimal_object)
+}
+
+pub fn builtin_decimal_to_float(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len().saturating_sub(1)
+        ));
+    }
+    Ok(Object::Float(decimal_receiver(&args[0], "to_float")?.to_f64()))
+}
+
+fn decimal_receiver(receiver: &Object, method: &str) -> Result<Decimal, String> {
+    match receiver {
+        Object::Native(native) => native.downcast_ref::<Decimal>().copied(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a decimal", method))
+}
+
+fn decimal_object(value: Decimal) -> Object {
+    Object::Native(NativeHandle::new(value))
+}
+
+/// `n / d` rounded to an integer by `mode`; `d` must be positive.
+fn divide_rounded(n: i128, d: i128, mode: Rounding) -> i128 {
+    let quotient = n / d;
+    let remainder = n % d;
+    if remainder == 0 {
+        return quotient;
+    }
+
+    let away = if n < 0 { quotient - 1 } else { quotient + 1 };
+    let twice = remainder.unsigned_abs() * 2;
+    let half = twice.cmp(&d.unsigned_abs());
+    let round_away = match mode {
+        Rounding::Down => false,
+        Rounding::Up => true,
+        Rounding::Floor => n < 0,
+        Rounding::Ceiling => n > 0,
+        Rounding::HalfUp => half.is_ge(),
+        Rounding::HalfEven => half.is_gt() || half.is_eq() && quotient % 2 != 0,
+    };
+    if round_away { away } else { quotient }
+}
+
+fn pow10(exponent: u32) -> Result<i128, String> {
+    10i128.checked_pow(exponent).ok_or_else(overflow)
+}
+
+fn overflow() -> String {
+    "Decimal overflow".to_string()
+}
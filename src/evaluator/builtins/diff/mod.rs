@@ -0,0 +1,138 @@
+//! Line and element diffs: `diff(a, b)` and `print_diff(a, b)`.
+//!
+//! Strings are compared line by line and lists element by element. The edit
+//! list is a longest-common-subsequence alignment, so unchanged items stay
+//! paired up and only real insertions and deletions are reported.
+
+use crate::evaluator::{purity, watchdog};
+use crate::object::{Map, Object};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    Keep,
+    Insert,
+    Delete,
+}
+
+impl Edit {
+    fn name(self) -> &'static str {
+        match self {
+            Edit::Keep => "keep",
+            Edit::Insert => "insert",
+            Edit::Delete => "delete",
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Edit::Keep => "  ",
+            Edit::Insert => "+ ",
+            Edit::Delete => "- ",
+        }
+    }
+}
+
+/// `diff(a, b)` — a list of `{"op": "keep" | "insert" | "delete", "value": item}`
+/// dicts that turns `a` into `b`.
+pub fn builtin_diff(args: Vec<Object>) -> Result<Object, String> {
+    let (old, new) = diff_args(&args, "diff")?;
+    let edits = diff(&old, &new)
+        .into_iter()
+        .map(|(edit, value)| {
+            let mut entry = Map::default();
+            entry.insert("op".to_string(), Object::String(edit.name().into()));
+            entry.insert("value".to_string(), value.clone());
+            Object::dict(entry)
+        })
+        .collect();
+    Ok(Object::list(edits))
+}
+
+/// `print_diff(a, b)` — print the diff with `+`/`-` markers; returns whether anything changed.
+pub fn builtin_print_diff(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("print")?;
+    let (old, new) = diff_args(&args, "print_diff")?;
+    let edits = diff(&old, &new);
+    for line in render(&edits) {
+        println!("{}", line);
+    }
+    watchdog::output();
+    Ok(Object::Boolean(edits.iter().any(|(edit, _)| *edit != Edit::Keep)))
+}
+
+/// Lines of text with `  `, `+ ` and `- ` prefixes, e.g. for test failures.
+pub fn render(edits: &[(Edit, &Object)]) -> Vec<String> {
+    edits
+        .iter()
+        .map(|(edit, value)| format!("{}{}", edit.prefix(), value))
+        .collect()
+}
+
+/// Compare two texts line by line and render the result.
+pub fn text_diff(old: &str, new: &str) -> Vec<String> {
+    let old = lines(old);
+    let new = lines(new);
+    render(&diff(&old, &new))
+}
+
+/// The shortest edit script from `old` to `new`.
+pub fn diff<'a>(old: &'a [Object], new: &'a [Object]) -> Vec<(Edit, &'a Object)> {
+    // Common prefixes and suffixes don't need the quadratic table.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] is the LCS length of old_mid[i..] and new_mid[j..].
+    let mut lcs = vec![vec![0usize; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits: Vec<(Edit, &Object)> = old[..prefix].iter().map(|v| (Edit::Keep, v)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            edits.push((Edit::Keep, &old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_mid.len() && (j == new_mid.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push((Edit::Delete, &old_mid[i]));
+            i += 1;
+        } else {
+            edits.push((Edit::Insert, &new_mid[j]));
+            j += 1;
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|v| (Edit::Keep, v)));
+    edits
+}
+
+fn diff_args(args: &[Object], name: &str) -> Result<(Vec<Object>, Vec<Object>), String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+    match (&args[0], &args[1]) {
+        (Object::String(a), Object::String(b)) => Ok((lines(a), lines(b))),
+        (Object::List(a), Object::List(b)) => Ok((a.to_vec(), b.to_vec())),
+        (a, b) => Err(format!("{} expects two strings or two lists, got {} and {}", name, a, b)),
+    }
+}
+
+fn lines(text: &str) -> Vec<Object> {
+    text.lines().map(|line| Object::String(line.into())).collect()
+}
@@ -0,0 +1,130 @@
+//! Temporary files and crash-safe writes.
+//!
+//! `write_file_atomic` writes to a sibling temporary file, syncs it, and then
+//! renames it over the target, so readers see either the old or the new
+//! contents and never a partially written file.
+
+use crate::error::brief;
+use crate::evaluator::purity;
+use crate::object::Object;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static UNIQUE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `temp_file()` — create a new empty file in the system temp directory and return its path.
+pub fn builtin_temp_file(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("create files")?;
+    no_args(&args)?;
+    let path = create_unique(&std::env::temp_dir(), "carrion-", ".tmp", |path| {
+        OpenOptions::new().write(true).create_new(true).open(path).map(|_| ())
+    })
+    .map_err(|e| format!("Could not create temporary file: {}", e))?;
+    Ok(Object::String(path.to_string_lossy().into_owned().into()))
+}
+
+/// `temp_dir()` — create a new empty directory in the system temp directory and return its path.
+pub fn builtin_temp_dir(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("create files")?;
+    no_args(&args)?;
+    let path = create_unique(&std::env::temp_dir(), "carrion-", "", |path| fs::create_dir(path))
+        .map_err(|e| format!("Could not create temporary directory: {}", e))?;
+    Ok(Object::String(path.to_string_lossy().into_owned().into()))
+}
+
+/// `write_file_atomic(path, contents)` — replace `path` with `contents` in one step.
+pub fn builtin_write_file_atomic(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("write files")?;
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+
+    let target = match &args[0] {
+        Object::String(path) => PathBuf::from(path),
+        other => return Err(format!("write_file_atomic expects a path string, got {}", brief(other))),
+    };
+    let contents = match &args[1] {
+        Object::String(s) => s.to_string(),
+        other => other.to_string(),
+    };
+
+    write_atomic(&target, contents.as_bytes())
+        .map_err(|e| format!("Could not write '{}': {}", target.display(), e))?;
+    Ok(Object::None)
+}
+
+fn write_atomic(target: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_string_lossy()
+        .into_owned();
+
+    // The temporary file must live in the same directory so the rename stays
+    // on one filesystem and is atomic.
+    let mut temp: Option<File> = None;
+    let temp_path = create_unique(&dir, &format!(".{}.", name), ".tmp", |path| {
+        temp = Some(OpenOptions::new().write(true).create_new(true).open(path)?);
+        Ok(())
+    })?;
+    let mut file = temp.expect("create_unique succeeded without opening the file");
+
+    let result = file
+        .write_all(contents)
+        .and_then(|_| file.sync_all())
+        .and_then(|_| fs::rename(&temp_path, target));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Try fresh names in `dir` until `create` succeeds without hitting an existing entry.
+fn create_unique(
+    dir: &Path,
+    prefix: &str,
+    suffix: &str,
+    mut create: impl FnMut(&Path) -> io::Result<()>,
+) -> io::Result<PathBuf> {
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let unique = UNIQUE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!(
+            "{}{}-{}-{:08x}{}",
+            prefix,
+            std::process::id(),
+            unique,
+            nanos,
+            suffix
+        ));
+        match create(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn no_args(args: &[Object]) -> Result<(), String> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len()
+        ))
+    }
+}
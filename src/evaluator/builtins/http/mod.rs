@@ -0,0 +1,216 @@
+//! A tiny blocking HTTP/1.1 server for scripts (enabled with the `http` feature).
+//!
+//! `serve(port, handler)` listens on 127.0.0.1 and calls `handler(request)` for
+//! every request. The request is a dict with `method`, `path`, `query`,
+//! `headers` and `body`; the handler returns either a string (sent as a
+//! `200 text/plain` body) or a dict with optional `status`, `headers` and `body`.
+//! An optional third argument limits how many requests are served before
+//! `serve` returns, which is mostly useful for tests.
+//!
+//! Request bodies over [`MAX_BODY`] bytes are answered with `413` and a
+//! malformed `Content-Length` with `400`, without calling the handler.
+
+use crate::error::brief;
+use crate::evaluator::{purity, watchdog};
+use crate::object::{Map, Object};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// The largest request body `serve` reads, in bytes.
+pub const MAX_BODY: usize = 8 * 1024 * 1024;
+
+pub fn builtin_serve(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("serve HTTP")?;
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2 or 3",
+            args.len()
+        ));
+    }
+
+    let port = match &args[0] {
+        Object::Integer(port) if (0..=65535).contains(port) => *port as u16,
+        other => return Err(format!("serve expects a port number, got {}", brief(other))),
+    };
+    let handler = args[1].clone();
+    let limit = match args.get(2) {
+        None => None,
+        Some(Object::Integer(n)) if *n >= 0 => Some(*n as usize),
+        Some(other) => return Err(format!("Request limit must be a non-negative integer, got {}", brief(other))),
+    };
+
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("Could not listen on port {}: {}", port, e))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    eprintln!("Serving on http://{}", addr);
+
+    if limit == Some(0) {
+        return Ok(Object::None);
+    }
+    let mut served = 0;
+    let mut incoming = listener.incoming();
+    // Waiting for the next request isn't a script going quiet.
+    while let Some(stream) = watchdog::waiting(|| incoming.next()) {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("serve: connection failed: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = handle_connection(stream, &handler) {
+            eprintln!("serve: {}", e);
+        }
+        served += 1;
+        if limit.is_some_and(|limit| served >= limit) {
+            break;
+        }
+    }
+    Ok(Object::None)
+}
+
+fn handle_connection(stream: TcpStream, handler: &Object) -> Result<(), String> {
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let request = match read_request(&mut reader)? {
+        Ok(request) => request,
+        Err(status) => {
+            write_response(stream, status, &[], reason_phrase(status)).map_err(|e| e.to_string())?;
+            return Err(format!("rejected request: {} {}", status, reason_phrase(status)));
+        }
+    };
+
+    let (status, headers, body) = match crate::evaluator::apply_function(handler.clone(), vec![request]) {
+        Ok(response) => response_parts(response)?,
+        Err(e) => {
+            eprintln!("serve: handler error: {}", e);
+            (500, Vec::new(), "Internal Server Error".to_string())
+        }
+    };
+    write_response(stream, status, &headers, &body).map_err(|e| e.to_string())
+}
+
+/// Read one request. The inner error is the status to refuse it with; the
+/// outer one is a connection that broke while reading.
+fn read_request(reader: &mut impl BufRead) -> Result<Result<Object, u16>, String> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or("Empty request")?.to_string();
+    let target = parts.next().ok_or("Request line has no path")?.to_string();
+
+    let mut headers = Map::default();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(
+                name.trim().to_ascii_lowercase(),
+                Object::String(value.trim().into()),
+            );
+        }
+    }
+
+    let length = match headers.get("content-length") {
+        None => 0,
+        Some(Object::String(len)) => match len.parse::<usize>() {
+            Ok(length) if length > MAX_BODY => return Ok(Err(413)),
+            Ok(length) => length,
+            Err(_) => return Ok(Err(400)),
+        },
+        Some(_) => return Ok(Err(400)),
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+
+    let (path, query_string) = target.split_once('?').unwrap_or((&target, ""));
+    let query: Map<Object> = query_string
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key.to_string(), Object::String(value.into()))
+        })
+        .collect();
+
+    let mut request = Map::default();
+    request.insert("method".to_string(), Object::String(method.into()));
+    request.insert("path".to_string(), Object::String(path.into()));
+    request.insert("query".to_string(), Object::dict(query));
+    request.insert("headers".to_string(), Object::dict(headers));
+    request.insert(
+        "body".to_string(),
+        Object::String(String::from_utf8_lossy(&body).into_owned().into()),
+    );
+    Ok(Ok(Object::dict(request)))
+}
+
+type ResponseParts = (u16, Vec<(String, String)>, String);
+
+fn response_parts(response: Object) -> Result<ResponseParts, String> {
+    match response {
+        Object::Dict(map) => {
+            let field = |name: &str| map.get(&Object::String(name.into()));
+            let status = match field("status") {
+                None => 200,
+                Some(Object::Integer(code)) if (100..=999).contains(code) => *code as u16,
+                Some(other) => return Err(format!("Invalid response status: {}", brief(other))),
+            };
+            let headers = match field("headers") {
+                None => Vec::new(),
+                Some(Object::Dict(headers)) => headers
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                Some(other) => return Err(format!("Response headers must be a dict, got {}", brief(other))),
+            };
+            let body = field("body").map(|b| b.to_string()).unwrap_or_default();
+            Ok((status, headers, body))
+        }
+        Object::None => Ok((204, Vec::new(), String::new())),
+        other => Ok((200, Vec::new(), other.to_string())),
+    }
+}
+
+fn write_response(
+    mut stream: TcpStream,
+    status: u16,
+    headers: &[(String, String)],
+    body: &str,
+) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason_phrase(status));
+    if !headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-type")) {
+        head.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
@@ -0,0 +1,75 @@
+//! Random identifiers: `uuid4()` and `random_id(len)`.
+//!
+//! Both draw from the operating system's secure random source.
+
+use crate::error::brief;
+use crate::object::Object;
+
+/// The URL-safe alphabet used by nanoid. Its 64 symbols let each random
+/// byte map to a symbol with a mask and no modulo bias.
+const ID_ALPHABET: &[u8; 64] = b"useandom-26T198340PX75pxJACKVERYMINDBUSHWOLF_GQZbfghjklqvwyzrict";
+
+const DEFAULT_ID_LENGTH: i64 = 21;
+
+/// `uuid4()` — a random RFC 4122 version 4 UUID such as
+/// `"3f2b8c1e-9d4a-4e7b-8a61-0c5d2e9f1b37"`.
+pub fn builtin_uuid4(args: Vec<Object>) -> Result<Object, String> {
+    if !args.is_empty() {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len()
+        ));
+    }
+
+    let mut bytes = [0u8; 16];
+    random_bytes(&mut bytes)?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(Object::String(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+    .into()))
+}
+
+/// `random_id(len)` — a URL-safe random string, 21 characters by default.
+pub fn builtin_random_id(args: Vec<Object>) -> Result<Object, String> {
+    let len = match args.as_slice() {
+        [] => DEFAULT_ID_LENGTH,
+        [Object::Integer(n)] if *n > 0 => *n,
+        [other] => return Err(format!("random_id expects a positive length, got {}", brief(other))),
+        _ => {
+            return Err(format!(
+                "Wrong number of arguments. got={}, want=0 or 1",
+                args.len()
+            ));
+        }
+    };
+
+    let mut bytes = vec![0u8; len as usize];
+    random_bytes(&mut bytes)?;
+    let id = bytes
+        .iter()
+        .map(|b| ID_ALPHABET[(b & 63) as usize] as char)
+        .collect();
+    Ok(Object::String(id))
+}
+
+fn random_bytes(buf: &mut [u8]) -> Result<(), String> {
+    let bytes = crate::evaluator::trace::read("random", || {
+        let mut bytes = vec![0u8; buf.len()];
+        getrandom::fill(&mut bytes).map(|_| bytes).map_err(|e| e.to_string())
+    })?;
+    let bytes = bytes.map_err(|e| format!("Could not read random bytes: {}", e))?;
+    if bytes.len() != buf.len() {
+        return Err("Replay diverged: the trace holds a different number of random bytes".to_string());
+    }
+    buf.copy_from_slice(&bytes);
+    Ok(())
+}
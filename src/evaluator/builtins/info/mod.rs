@@ -0,0 +1,38 @@
+//! `VERSION` and `carrion_info()`: which interpreter a script is running on,
+//! so scripts and test suites can check for a feature before using it.
+
+use crate::object::{Map, Object};
+
+/// The interpreter's version, bound as `VERSION`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Optional features and whether this build has them.
+const FEATURES: &[(&str, bool)] = &[
+    ("fast-hash", cfg!(feature = "fast-hash")),
+    ("sqlite", cfg!(feature = "sqlite")),
+    ("http", cfg!(feature = "http")),
+];
+
+/// `carrion_info()` — a dict with the interpreter `version`, the `platform`
+/// (`"linux"`, `"macos"`, `"windows"`, ...), the CPU `arch` and the list of
+/// enabled `features`.
+pub fn builtin_carrion_info(args: Vec<Object>) -> Result<Object, String> {
+    if !args.is_empty() {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len()
+        ));
+    }
+
+    let features = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| Object::String((*name).into()))
+        .collect();
+    let mut info = Map::default();
+    info.insert("version".to_string(), Object::String(VERSION.into()));
+    info.insert("platform".to_string(), Object::String(std::env::consts::OS.into()));
+    info.insert("arch".to_string(), Object::String(std::env::consts::ARCH.into()));
+    info.insert("features".to_string(), Object::list(features));
+    Ok(Object::dict(info))
+}
@@ -0,0 +1,251 @@
+//! Leveled, structured logging for Carrion scripts (`log.info(...)` etc).
+//!
+//! The active level and output format are process-wide so the CLI can set
+//! them once (`--log-level`, `--log-json`, or the `CARRION_LOG_LEVEL` /
+//! `CARRION_LOG_FORMAT` environment variables) before a script runs.
+
+use super::dates::civil_from_days;
+use crate::error::brief;
+use crate::evaluator::{purity, watchdog};
+use crate::object::{format_float, Builtin, BuiltinFunction, KeywordBuiltinFunction, Object};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug = 0,
+    Info = 1,
+    Warn = 2,
+    Error = 3,
+}
+
+impl Level {
+    pub fn parse(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "debug" => Some(Level::Debug),
+            "info" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+
+    fn from_u8(value: u8) -> Level {
+        match value {
+            0 => Level::Debug,
+            1 => Level::Info,
+            2 => Level::Warn,
+            _ => Level::Error,
+        }
+    }
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static LOG_JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_level(level: Level) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    Level::from_u8(LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn set_json(enabled: bool) {
+    LOG_JSON.store(enabled, Ordering::Relaxed);
+}
+
+/// Apply `CARRION_LOG_LEVEL` and `CARRION_LOG_FORMAT=json` if they are set.
+/// Command-line flags are applied afterwards and take precedence.
+pub fn init_from_env() {
+    if let Ok(name) = std::env::var("CARRION_LOG_LEVEL") {
+        match Level::parse(&name) {
+            Some(level) => set_level(level),
+            None => eprintln!("Ignoring unknown CARRION_LOG_LEVEL '{}'", name),
+        }
+    }
+    if let Ok(format) = std::env::var("CARRION_LOG_FORMAT") {
+        set_json(format.eq_ignore_ascii_case("json"));
+    }
+}
+
+/// The `log` namespace object bound in every global environment.
+pub fn namespace() -> Object {
+    let entries: [(&str, BuiltinFunction, KeywordBuiltinFunction); 4] = [
+        ("debug", builtin_log_debug, log_debug_with_keywords),
+        ("info", builtin_log_info, log_info_with_keywords),
+        ("warn", builtin_log_warn, log_warn_with_keywords),
+        ("error", builtin_log_error, log_error_with_keywords),
+    ];
+    Object::dict(entries.iter().map(|(name, func, keywords)| {
+        let builtin = Builtin {
+            func: *func,
+            keywords: Some(*keywords),
+        };
+        (name.to_string(), Object::Builtin(builtin))
+    }))
+}
+
+pub fn builtin_log_debug(args: Vec<Object>) -> Result<Object, String> {
+    emit(Level::Debug, args, Vec::new())
+}
+
+pub fn builtin_log_info(args: Vec<Object>) -> Result<Object, String> {
+    emit(Level::Info, args, Vec::new())
+}
+
+pub fn builtin_log_warn(args: Vec<Object>) -> Result<Object, String> {
+    emit(Level::Warn, args, Vec::new())
+}
+
+pub fn builtin_log_error(args: Vec<Object>) -> Result<Object, String> {
+    emit(Level::Error, args, Vec::new())
+}
+
+// `log.info("saved", user="odin")`: keyword arguments are fields too.
+fn log_debug_with_keywords(args: Vec<Object>, keywords: Vec<(String, Object)>) -> Result<Object, String> {
+    emit(Level::Debug, args, keywords)
+}
+
+fn log_info_with_keywords(args: Vec<Object>, keywords: Vec<(String, Object)>) -> Result<Object, String> {
+    emit(Level::Info, args, keywords)
+}
+
+fn log_warn_with_keywords(args: Vec<Object>, keywords: Vec<(String, Object)>) -> Result<Object, String> {
+    emit(Level::Warn, args, keywords)
+}
+
+fn log_error_with_keywords(args: Vec<Object>, keywords: Vec<(String, Object)>) -> Result<Object, String> {
+    emit(Level::Error, args, keywords)
+}
+
+/// Log `args[0]` at `level` with the fields of the dict `args[1]`, if
+/// given, and the `keywords`, which win over dict fields of the same name.
+fn emit(level: Level, args: Vec<Object>, keywords: Vec<(String, Object)>) -> Result<Object, String> {
+    purity::check("log")?;
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1 or 2",
+            args.len()
+        ));
+    }
+
+    let mut fields: Vec<(String, Object)> = match args.get(1) {
+        Some(Object::Dict(map)) => map.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+        Some(other) => return Err(format!("Log fields must be a dictionary, got {}", brief(other))),
+        None => Vec::new(),
+    };
+    for (key, value) in keywords {
+        fields.retain(|(field, _)| *field != key);
+        fields.push((key, value));
+    }
+
+    if level < self::level() {
+        return Ok(Object::None);
+    }
+
+    // Dicts are unordered; sort so log lines are stable and greppable.
+    fields.sort_by(|a, b| a.0.cmp(&b.0));
+    eprintln!("{}", format_record(level, &args[0].to_string(), &fields));
+    watchdog::output();
+    Ok(Object::None)
+}
+
+fn format_record(level: Level, message: &str, fields: &[(String, Object)]) -> String {
+    let timestamp = format_timestamp(SystemTime::now());
+    if LOG_JSON.load(Ordering::Relaxed) {
+        let mut parts = vec![
+            format!("\"ts\":{}", json_string(&timestamp)),
+            format!("\"level\":{}", json_string(level.name())),
+            format!("\"msg\":{}", json_string(message)),
+        ];
+        for (key, value) in fields {
+            parts.push(format!("{}:{}", json_string(key), json_value(value)));
+        }
+        format!("{{{}}}", parts.join(","))
+    } else {
+        let mut line = format!(
+            "{} {:<5} {}",
+            timestamp,
+            level.name().to_ascii_uppercase(),
+            message
+        );
+        for (key, value) in fields {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+        line
+    }
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with millisecond precision.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_value(value: &Object) -> String {
+    match value {
+        Object::Integer(i) => i.to_string(),
+        Object::Float(f) if f.is_finite() => format_float(*f),
+        Object::Boolean(b) => b.to_string(),
+        Object::None => "null".to_string(),
+        Object::List(items) | Object::Tuple(items) => {
+            let parts: Vec<String> = items.iter().map(json_value).collect();
+            format!("[{}]", parts.join(","))
+        }
+        Object::Set(items) => {
+            let parts: Vec<String> = items.iter().map(json_value).collect();
+            format!("[{}]", parts.join(","))
+        }
+        Object::Dict(map) => {
+            // JSON keys are strings, so other keys are written as their text.
+            let mut entries: Vec<(String, &Object)> = map.iter().map(|(k, v)| (k.to_string(), v)).collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            let parts: Vec<String> = entries
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", json_string(&k), json_value(v)))
+                .collect();
+            format!("{{{}}}", parts.join(","))
+        }
+        other => json_string(&other.to_string()),
+    }
+}
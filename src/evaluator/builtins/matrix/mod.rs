@@ -0,0 +1,285 @@
+//! Dense 2D numeric matrices: `matrix([[1, 2], [3, 4]])`.
+//!
+//! Elements are stored as one flat row-major buffer of floats, so `get` is a
+//! constant-time lookup instead of indexing through nested lists.
+//! `+` and `-` work element-wise between matrices of the same shape, `*` and
+//! `/` scale by a number, and `matmul` is the matrix product.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    fn at(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    fn transpose(&self) -> Matrix {
+        let mut data = Vec::with_capacity(self.data.len());
+        for col in 0..self.cols {
+            for row in 0..self.rows {
+                data.push(self.at(row, col));
+            }
+        }
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            data,
+        }
+    }
+
+    fn matmul(&self, other: &Matrix) -> Result<Matrix, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "Cannot multiply a {}x{} matrix by a {}x{} matrix",
+                self.rows, self.cols, other.rows, other.cols
+            ));
+        }
+        let mut data = vec![0.0; self.rows * other.cols];
+        for row in 0..self.rows {
+            for k in 0..self.cols {
+                let left = self.at(row, k);
+                for col in 0..other.cols {
+                    data[row * other.cols + col] += left * other.at(k, col);
+                }
+            }
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data,
+        })
+    }
+
+    fn zip_with(&self, other: &Matrix, op: impl Fn(f64, f64) -> f64) -> Result<Matrix, String> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(format!(
+                "Matrix shapes differ: {}x{} and {}x{}",
+                self.rows, self.cols, other.rows, other.cols
+            ));
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().zip(&other.data).map(|(a, b)| op(*a, *b)).collect(),
+        })
+    }
+
+    fn scaled(&self, op: impl Fn(f64) -> f64) -> Matrix {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|x| op(*x)).collect(),
+        }
+    }
+
+    fn to_list(&self) -> Object {
+        Object::list(
+            self.data
+                .chunks(self.cols.max(1))
+                .take(self.rows)
+                .map(|row| Object::list(row.iter().map(|x| Object::Float(*x)).collect()))
+                .collect(),
+        )
+    }
+}
+
+impl NativeObject for Matrix {
+    fn type_name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "get" => Some(builtin_matrix_get),
+            "shape" => Some(builtin_matrix_shape),
+            "map" => Some(builtin_matrix_map),
+            "transpose" => Some(builtin_matrix_transpose),
+            "matmul" => Some(builtin_matrix_matmul),
+            "to_list" => Some(builtin_matrix_to_list),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// Entries show as compactly as possible: `matrix([[1, 2.5]])`.
+    fn display(&self) -> String {
+        let rows: Vec<String> = self
+            .data
+            .chunks(self.cols.max(1))
+            .take(self.rows)
+            .map(|row| format!("[{}]", row.iter().map(f64::to_string).collect::<Vec<_>>().join(", ")))
+            .collect();
+        format!("matrix([{}])", rows.join(", "))
+    }
+
+    fn binary_op(&self, operator: &Operator, other: &Object, reflected: bool) -> Option<Result<Object, String>> {
+        let result = match (operator, other) {
+            (Operator::Plus, Object::Native(native)) => {
+                self.zip_with(native.downcast_ref::<Matrix>()?, |a, b| a + b)
+            }
+            (Operator::Minus, Object::Native(native)) => {
+                let other = native.downcast_ref::<Matrix>()?;
+                if reflected {
+                    other.zip_with(self, |a, b| a - b)
+                } else {
+                    self.zip_with(other, |a, b| a - b)
+                }
+            }
+            (Operator::Equal, Object::Native(native)) => {
+                return Some(Ok(Object::Boolean(native.downcast_ref::<Matrix>()? == self)));
+            }
+            (Operator::NotEqual, Object::Native(native)) => {
+                return Some(Ok(Object::Boolean(native.downcast_ref::<Matrix>()? != self)));
+            }
+            (Operator::Multiply, scalar) => {
+                let factor = number(scalar)?;
+                Ok(self.scaled(|x| x * factor))
+            }
+            (Operator::Divide, scalar) if !reflected => {
+                let divisor = number(scalar)?;
+                Ok(self.scaled(|x| x / divisor))
+            }
+            _ => return None,
+        };
+        Some(result.map(matrix_object))
+    }
+}
+
+/// `matrix(rows)` — build a matrix from a list of equally long lists of numbers.
+pub fn builtin_matrix(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    let Object::List(rows) = &args[0] else {
+        return Err(format!("matrix expects a list of rows, got {}", brief(&args[0])));
+    };
+    let mut data = Vec::new();
+    let mut cols = None;
+    for row in rows.iter() {
+        let Object::List(items) = row else {
+            return Err(format!("matrix rows must be lists, got {}", brief(row)));
+        };
+        if *cols.get_or_insert(items.len()) != items.len() {
+            return Err("matrix rows must all have the same length".to_string());
+        }
+        for item in items.iter() {
+            data.push(number(item).ok_or_else(|| format!("matrix elements must be numbers, got {}", brief(item)))?);
+        }
+    }
+
+    Ok(matrix_object(Matrix {
+        rows: rows.len(),
+        cols: cols.unwrap_or(0),
+        data,
+    }))
+}
+
+/// `m.get(row, col)` — the element at a zero-based position.
+pub fn builtin_matrix_get(args: Vec<Object>) -> Result<Object, String> {
+    let (matrix, rest) = matrix_receiver(&args, 2, "get")?;
+    let (row, col) = match rest {
+        [Object::Integer(row), Object::Integer(col)] => (*row, *col),
+        _ => return Err("get expects integer row and column indexes".to_string()),
+    };
+    if row < 0 || col < 0 || row as usize >= matrix.rows || col as usize >= matrix.cols {
+        return Err(format!(
+            "Index ({}, {}) out of bounds for a {}x{} matrix",
+            row, col, matrix.rows, matrix.cols
+        ));
+    }
+    Ok(Object::Float(matrix.at(row as usize, col as usize)))
+}
+
+/// `m.shape()` — `[rows, cols]`.
+pub fn builtin_matrix_shape(args: Vec<Object>) -> Result<Object, String> {
+    let (matrix, _) = matrix_receiver(&args, 0, "shape")?;
+    Ok(Object::list(vec![
+        Object::Integer(matrix.rows as i64),
+        Object::Integer(matrix.cols as i64),
+    ]))
+}
+
+/// `m.map(spell)` — a new matrix with `spell(x)` applied to every element.
+pub fn builtin_matrix_map(args: Vec<Object>) -> Result<Object, String> {
+    let (matrix, rest) = matrix_receiver(&args, 1, "map")?;
+    let mut data = Vec::with_capacity(matrix.data.len());
+    for x in &matrix.data {
+        let value = crate::evaluator::apply_function(rest[0].clone(), vec![Object::Float(*x)])?;
+        data.push(number(&value).ok_or_else(|| format!("map spell must return a number, got {}", brief(&value)))?);
+    }
+    Ok(matrix_object(Matrix {
+        rows: matrix.rows,
+        cols: matrix.cols,
+        data,
+    }))
+}
+
+pub fn builtin_matrix_transpose(args: Vec<Object>) -> Result<Object, String> {
+    let (matrix, _) = matrix_receiver(&args, 0, "transpose")?;
+    Ok(matrix_object(matrix.transpose()))
+}
+
+pub fn builtin_matrix_matmul(args: Vec<Object>) -> Result<Object, String> {
+    let (matrix, rest) = matrix_receiver(&args, 1, "matmul")?;
+    let other = match &rest[0] {
+        Object::Native(native) => native.downcast_ref::<Matrix>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("matmul expects a matrix, got {}", brief(&rest[0])))?;
+    matrix.matmul(other).map(matrix_object)
+}
+
+/// `m.to_list()` — the rows as nested lists.
+pub fn builtin_matrix_to_list(args: Vec<Object>) -> Result<Object, String> {
+    let (matrix, _) = matrix_receiver(&args, 0, "to_list")?;
+    Ok(matrix.to_list())
+}
+
+/// Split method arguments into the receiving matrix and exactly `want` more.
+fn matrix_receiver<'a>(
+    args: &'a [Object],
+    want: usize,
+    method: &str,
+) -> Result<(&'a Matrix, &'a [Object]), String> {
+    if args.len() != want + 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want={}",
+            args.len().saturating_sub(1),
+            want
+        ));
+    }
+    let matrix = match &args[0] {
+        Object::Native(native) => native.downcast_ref::<Matrix>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a matrix", method))?;
+    Ok((matrix, &args[1..]))
+}
+
+fn number(value: &Object) -> Option<f64> {
+    match value {
+        Object::Integer(i) => Some(*i as f64),
+        Object::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn matrix_object(matrix: Matrix) -> Object {
+    Object::Native(NativeHandle::new(matrix))
+}
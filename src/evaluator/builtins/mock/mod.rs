@@ -0,0 +1,187 @@
+//! Test doubles: `mock(value)` and `spy(spell)`.
+//!
+//! Both are callable objects that record the arguments of every call in
+//! `m.calls`. A mock answers with programmed values; a spy forwards each call
+//! to the wrapped spell and returns its result.
+
+use crate::error::brief;
+use crate::evaluator::{apply_function, purity};
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+#[derive(Debug)]
+pub struct Mock {
+    /// The spell a spy forwards to; `None` for a plain mock.
+    target: Option<Object>,
+    state: RefCell<MockState>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    calls: Vec<Vec<Object>>,
+    /// Values from `returns_each`, used up one call at a time.
+    queued: VecDeque<Object>,
+    /// Returned once the queue is empty.
+    value: Object,
+}
+
+impl MockState {
+    fn new(value: Object) -> Self {
+        MockState {
+            calls: Vec::new(),
+            queued: VecDeque::new(),
+            value,
+        }
+    }
+}
+
+impl NativeObject for Mock {
+    fn type_name(&self) -> &'static str {
+        if self.target.is_some() {
+            "spy"
+        } else {
+            "mock"
+        }
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "called_with" => Some(builtin_mock_called_with),
+            "returns" => Some(builtin_mock_returns),
+            "returns_each" => Some(builtin_mock_returns_each),
+            "reset" => Some(builtin_mock_reset),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn attribute(&self, name: &str) -> Option<Object> {
+        let state = self.state.borrow();
+        match name {
+            "calls" => Some(Object::list(
+                state.calls.iter().cloned().map(Object::list).collect(),
+            )),
+            "call_count" => Some(Object::Integer(state.calls.len() as i64)),
+            _ => None,
+        }
+    }
+
+    fn call(&self, args: Vec<Object>) -> Option<Result<Object, String>> {
+        // Recording the call changes the mock, which a pure spell may not do.
+        if let Err(e) = purity::check(&format!("call a {}, which records its calls", self.type_name())) {
+            return Some(Err(e));
+        }
+        let queued = {
+            let mut state = self.state.borrow_mut();
+            state.calls.push(args.clone());
+            state.queued.pop_front()
+        };
+        // The borrow is released first: a spied spell may call the spy again.
+        Some(match (queued, &self.target) {
+            (Some(value), _) => Ok(value),
+            (None, Some(target)) => apply_function(target.clone(), args),
+            (None, None) => Ok(self.state.borrow().value.clone()),
+        })
+    }
+}
+
+/// `mock(value)` — a callable that records its calls and returns `value`
+/// (`None` by default).
+pub fn builtin_mock(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() > 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0 or 1",
+            args.len()
+        ));
+    }
+    let value = args.into_iter().next().unwrap_or(Object::None);
+    Ok(Object::Native(NativeHandle::new(Mock {
+        target: None,
+        state: RefCell::new(MockState::new(value)),
+    })))
+}
+
+/// `spy(spell)` — record every call and pass it on to `spell`.
+pub fn builtin_spy(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+    let target = match &args[0] {
+        spell @ (Object::Function(_) | Object::Builtin(_) | Object::BoundMethod(_)) => spell.clone(),
+        other => return Err(format!("spy expects a spell, got {}", brief(other))),
+    };
+    Ok(Object::Native(NativeHandle::new(Mock {
+        target: Some(target),
+        state: RefCell::new(MockState::new(Object::None)),
+    })))
+}
+
+/// `m.called_with(args...)` — whether any recorded call had exactly these arguments.
+pub fn builtin_mock_called_with(args: Vec<Object>) -> Result<Object, String> {
+    let mock = mock_receiver(&args, "called_with")?;
+    let expected = &args[1..];
+    let found = mock.state.borrow().calls.iter().any(|call| call == expected);
+    Ok(Object::Boolean(found))
+}
+
+/// `m.returns(value)` — return `value` from now on; clears queued values.
+pub fn builtin_mock_returns(args: Vec<Object>) -> Result<Object, String> {
+    let mock = mock_receiver(&args, "returns")?;
+    check_count(&args, 1)?;
+    purity::check("change a mock")?;
+    let mut state = mock.state.borrow_mut();
+    state.queued.clear();
+    state.value = args[1].clone();
+    Ok(args[0].clone())
+}
+
+/// `m.returns_each([a, b])` — answer the next calls with `a`, then `b`, then
+/// fall back to the regular return value (or the spied spell).
+pub fn builtin_mock_returns_each(args: Vec<Object>) -> Result<Object, String> {
+    let mock = mock_receiver(&args, "returns_each")?;
+    check_count(&args, 1)?;
+    purity::check("change a mock")?;
+    match &args[1] {
+        Object::List(values) => mock.state.borrow_mut().queued.extend(values.iter().cloned()),
+        other => return Err(format!("returns_each expects a list, got {}", brief(other))),
+    }
+    Ok(args[0].clone())
+}
+
+/// `m.reset()` — forget recorded calls and queued values.
+pub fn builtin_mock_reset(args: Vec<Object>) -> Result<Object, String> {
+    let mock = mock_receiver(&args, "reset")?;
+    check_count(&args, 0)?;
+    purity::check("change a mock")?;
+    let mut state = mock.state.borrow_mut();
+    state.calls.clear();
+    state.queued.clear();
+    Ok(Object::None)
+}
+
+fn check_count(args: &[Object], want: usize) -> Result<(), String> {
+    if args.len() != want + 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want={}",
+            args.len() - 1,
+            want
+        ));
+    }
+    Ok(())
+}
+
+fn mock_receiver<'a>(args: &'a [Object], method: &str) -> Result<&'a Mock, String> {
+    match args.first() {
+        Some(Object::Native(native)) => native.downcast_ref::<Mock>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a mock", method))
+}
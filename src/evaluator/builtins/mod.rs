@@ -1,11 +1,253 @@
-use crate::object::Object;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod complex;
+pub mod convert;
+pub mod dates;
+pub mod diff;
+pub mod decimal;
+pub mod files;
+pub mod ids;
+pub mod info;
+pub mod log;
+pub mod matrix;
+pub mod mock;
+pub mod ordering;
+pub mod outcome;
+pub mod path;
+pub mod pure;
+pub mod ranges;
+pub mod rational;
+pub mod sets;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod tasks;
+pub mod text;
+pub mod time;
+pub mod validate;
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::evaluator::{iteration, purity, watchdog};
+use crate::object::{BuiltinFunction, Object, Str};
+use std::rc::Rc;
+
+/// Every builtin bound by name in a fresh global environment.
+pub const BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("print", builtin_print),
+    ("len", builtin_length),
+    ("enumerate", builtin_enumerate),
+    ("sum", builtin_sum),
+    ("sorted", ordering::builtin_sorted),
+    ("set", sets::builtin_set),
+    ("range", ranges::builtin_range),
+    ("push", builtin_push),
+    ("pop", builtin_pop),
+    ("keys", builtin_keys),
+    ("values", builtin_values),
+    ("on_interrupt", builtin_on_interrupt),
+    ("breakpoint", builtin_breakpoint),
+    ("glob", path::builtin_glob),
+    ("basename", path::builtin_basename),
+    ("dirname", path::builtin_dirname),
+    ("extension", path::builtin_extension),
+    ("absolute", path::builtin_absolute),
+    ("path_join", path::builtin_path_join),
+    ("temp_file", files::builtin_temp_file),
+    ("temp_dir", files::builtin_temp_dir),
+    ("write_file_atomic", files::builtin_write_file_atomic),
+    ("abs", builtin_abs),
+    ("str", convert::builtin_str),
+    ("int", convert::builtin_int),
+    ("float", convert::builtin_float),
+    ("complex", complex::builtin_complex),
+    ("decimal", decimal::builtin_decimal),
+    ("frac", rational::builtin_frac),
+    ("matrix", matrix::builtin_matrix),
+    ("now", dates::builtin_now),
+    ("date", dates::builtin_date),
+    ("parse_date", dates::builtin_parse_date),
+    ("milliseconds", dates::builtin_milliseconds),
+    ("seconds", dates::builtin_seconds),
+    ("minutes", dates::builtin_minutes),
+    ("hours", dates::builtin_hours),
+    ("days", dates::builtin_days),
+    ("weeks", dates::builtin_weeks),
+    ("repr", builtin_repr),
+    ("template", text::builtin_template),
+    ("chars", text::builtin_chars),
+    ("bytes", text::builtin_bytes),
+    ("graphemes", text::builtin_graphemes),
+    ("diff", diff::builtin_diff),
+    ("print_diff", diff::builtin_print_diff),
+    ("check_snapshot", snapshot::builtin_check_snapshot),
+    ("mock", mock::builtin_mock),
+    ("ok", outcome::builtin_ok),
+    ("err", outcome::builtin_err),
+    ("spy", mock::builtin_spy),
+    ("uuid4", ids::builtin_uuid4),
+    ("random_id", ids::builtin_random_id),
+    ("stopwatch", time::builtin_stopwatch),
+    ("time_it", time::builtin_time_it),
+    ("memoize", pure::builtin_memoize),
+    ("map_pure", pure::builtin_map_pure),
+    ("spawn", tasks::builtin_spawn),
+    ("carrion_info", info::builtin_carrion_info),
+    ("validate", validate::builtin_validate),
+    #[cfg(feature = "sqlite")]
+    ("sqlite_open", sqlite::builtin_sqlite_open),
+    #[cfg(feature = "http")]
+    ("serve", http::builtin_serve),
+];
+
+/// Builtins reachable as methods on values of builtin types, e.g. `xs.push(4)`.
+/// The receiver is passed as the first argument.
+pub fn type_method(receiver: &Object, name: &str) -> Option<BuiltinFunction> {
+    let func: BuiltinFunction = match (receiver, name) {
+        (Object::Native(native), name) => return native.0.method(name),
+        (
+            Object::String(_) | Object::List(_) | Object::Tuple(_) | Object::Set(_) | Object::Range(_) | Object::Dict(_),
+            "len",
+        ) => builtin_length,
+        (
+            Object::String(_) | Object::List(_) | Object::Tuple(_) | Object::Set(_) | Object::Range(_) | Object::Dict(_),
+            "contains",
+        ) => builtin_contains,
+        (Object::String(_), "upper") => builtin_upper,
+        (Object::String(_), "lower") => builtin_lower,
+        (Object::String(_), "strip") => builtin_strip,
+        (Object::String(_), "split") => builtin_split,
+        (Object::List(_), "push") => builtin_push,
+        (Object::List(_), "pop") => builtin_pop,
+        (Object::Set(_), "add") => sets::builtin_set_add,
+        (Object::Set(_), "remove") => sets::builtin_set_remove,
+        (Object::Range(_), "to_list") => ranges::builtin_range_to_list,
+        (Object::Dict(_), "keys") => builtin_keys,
+        (Object::Dict(_), "values") => builtin_values,
+        (Object::Dict(_), "get") => builtin_get,
+        _ => return None,
+    };
+    Some(func)
+}
 
 pub fn builtin_print(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("print")?;
     let parts: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
     println!("{}", parts.join(" "));
+    watchdog::output();
+    Ok(Object::None)
+}
+
+/// `breakpoint()` — pause the script in a small REPL when run from the CLI.
+pub fn builtin_breakpoint(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("pause at a breakpoint")?;
+    if !args.is_empty() {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len()
+        ));
+    }
+    crate::evaluator::debugger::request();
     Ok(Object::None)
 }
 
+/// `on_interrupt(spell)` — run `spell()` when Ctrl+C stops the script.
+pub fn builtin_on_interrupt(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("register an interrupt handler")?;
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    match &args[0] {
+        Object::Function(_) | Object::Builtin(_) | Object::BoundMethod(_) => {
+            crate::evaluator::interrupt::set_handler(args[0].clone())?;
+            Ok(Object::None)
+        }
+        other => Err(format!("on_interrupt expects a spell, got {}", brief(other))),
+    }
+}
+
+/// `abs(x)` — magnitude of a number; native numbers provide an `abs` method.
+pub fn builtin_abs(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    match &args[0] {
+        Object::Integer(i) => i
+            .checked_abs()
+            .map(Object::Integer)
+            .ok_or_else(|| "Integer overflow in abs".to_string()),
+        Object::Float(f) => Ok(Object::Float(f.abs())),
+        Object::Native(native) => match native.0.method("abs") {
+            Some(method) => method(args),
+            None => Err(format!("abs is not supported for {}", brief(&args[0]))),
+        },
+        other => Err(format!("abs is not supported for {}", brief(other))),
+    }
+}
+
+/// `repr(value)` — an unambiguous, stable rendering: strings are quoted and
+/// dict keys are sorted.
+pub fn builtin_repr(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+    Ok(Object::String(repr(&args[0]).into()))
+}
+
+pub fn repr(object: &Object) -> String {
+    match object {
+        Object::String(s) => {
+            let mut out = String::with_capacity(s.len() + 2);
+            out.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\t' => out.push_str("\\t"),
+                    '\r' => out.push_str("\\r"),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+            out
+        }
+        Object::List(items) => {
+            let parts: Vec<String> = items.iter().map(repr).collect();
+            format!("[{}]", parts.join(", "))
+        }
+        Object::Tuple(items) => {
+            let parts: Vec<String> = items.iter().map(repr).collect();
+            match parts.as_slice() {
+                [single] => format!("({},)", single),
+                _ => format!("({})", parts.join(", ")),
+            }
+        }
+        Object::Set(items) if !items.is_empty() => {
+            let parts: Vec<String> = items.iter().map(repr).collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+        Object::Dict(map) => {
+            let mut entries: Vec<(String, &Object)> = map.iter().map(|(k, v)| (repr(k), v)).collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            let parts: Vec<String> = entries.into_iter().map(|(k, v)| format!("{}: {}", k, repr(v))).collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
 pub fn builtin_length(args: Vec<Object>) -> Result<Object, String> {
     // 1. Check for the correct number of arguments.
     if args.len() != 1 {
@@ -20,37 +262,95 @@ pub fn builtin_length(args: Vec<Object>) -> Result<Object, String> {
             let length = s.len() as i64;
             Ok(Object::Integer(length))
         }
-        Object::List(items) => {
+        Object::List(items) | Object::Tuple(items) => {
             let length = items.len() as i64;
             Ok(Object::Integer(length))
         }
+        Object::Set(items) => Ok(Object::Integer(items.len() as i64)),
+        Object::Range(range) => Ok(Object::Integer(range.len() as i64)),
         Object::Dict(map) => {
             let length = map.len() as i64;
             Ok(Object::Integer(length))
         }
-        other => Err(format!("Object of type {} has no length.", other)),
+        other => Err(format!("Object of type {} has no length.", brief(other))),
     }
 }
 
-pub fn builtin_push(args: Vec<Object>) -> Result<Object, String> {
-    if args.len() != 2 {
+/// `enumerate(items)` — a list of `(index, item)` tuples for anything a
+/// `for` loop can walk, for `for i, x in enumerate(xs):`.
+pub fn builtin_enumerate(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
         return Err(format!(
-            "Wrong number of arguments. got={}, want=2",
+            "Wrong number of arguments. got={}, want=1",
             args.len()
         ));
     }
 
-    match &args[0] {
-        Object::List(items) => {
-            let mut new_items = items.clone();
-            new_items.push(args[1].clone());
-            Ok(Object::List(new_items))
+    let pairs = iteration::collect(&args[0])?
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| Object::tuple(vec![Object::Integer(index as i64), item]))
+        .collect();
+    Ok(Object::list(pairs))
+}
+
+/// `sum(items)` — the items added together with `+`, or 0 when there are
+/// none; `sum(items, start)` adds them to `start`, e.g. `""` for strings.
+pub fn builtin_sum(args: Vec<Object>) -> Result<Object, String> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1 or 2",
+            args.len()
+        ));
+    }
+
+    let mut items = iteration::iterate(&args[0])?;
+    let mut total = match args.get(1) {
+        Some(start) => start.clone(),
+        None => match items.next()? {
+            Some(first) => first,
+            None => return Ok(Object::Integer(0)),
+        },
+    };
+    while let Some(item) = items.next()? {
+        total = crate::evaluator::eval_infix_expression(&Operator::Plus, total, item)?;
+    }
+    Ok(total)
+}
+
+/// `push(xs, item)` — `xs` with `item` appended. The list is copied first
+/// only if another value still shares it.
+pub fn builtin_push(args: Vec<Object>) -> Result<Object, String> {
+    let [list, item] = <[Object; 2]>::try_from(args).map_err(|args| {
+        format!("Wrong number of arguments. got={}, want=2", args.len())
+    })?;
+
+    match list {
+        Object::List(mut items) => {
+            Rc::make_mut(&mut items).push(item);
+            Ok(Object::List(items))
         }
-        other => Err(format!("Cannot push to {}", other)),
+        other => Err(format!("Cannot push to {}", brief(&other))),
     }
 }
 
+/// `pop(xs)` — `xs` without its last item, copied only if shared.
 pub fn builtin_pop(args: Vec<Object>) -> Result<Object, String> {
+    let [list] = <[Object; 1]>::try_from(args).map_err(|args| {
+        format!("Wrong number of arguments. got={}, want=1", args.len())
+    })?;
+
+    match list {
+        Object::List(items) if items.is_empty() => Err("Cannot pop from empty list".to_string()),
+        Object::List(mut items) => {
+            Rc::make_mut(&mut items).pop();
+            Ok(Object::List(items))
+        }
+        other => Err(format!("Cannot pop from {}", brief(&other))),
+    }
+}
+
+pub fn builtin_keys(args: Vec<Object>) -> Result<Object, String> {
     if args.len() != 1 {
         return Err(format!(
             "Wrong number of arguments. got={}, want=1",
@@ -59,20 +359,15 @@ pub fn builtin_pop(args: Vec<Object>) -> Result<Object, String> {
     }
 
     match &args[0] {
-        Object::List(items) => {
-            if items.is_empty() {
-                Err("Cannot pop from empty list".to_string())
-            } else {
-                let mut new_items = items.clone();
-                new_items.pop();
-                Ok(Object::List(new_items))
-            }
+        Object::Dict(map) => {
+            let keys: Vec<Object> = map.keys().cloned().collect();
+            Ok(Object::list(keys))
         }
-        other => Err(format!("Cannot pop from {}", other)),
+        other => Err(format!("Cannot get keys from {}", brief(other))),
     }
 }
 
-pub fn builtin_keys(args: Vec<Object>) -> Result<Object, String> {
+pub fn builtin_values(args: Vec<Object>) -> Result<Object, String> {
     if args.len() != 1 {
         return Err(format!(
             "Wrong number of arguments. got={}, want=1",
@@ -82,28 +377,96 @@ pub fn builtin_keys(args: Vec<Object>) -> Result<Object, String> {
 
     match &args[0] {
         Object::Dict(map) => {
-            let keys: Vec<Object> = map.keys()
-                .map(|k| Object::String(k.clone()))
-                .collect();
-            Ok(Object::List(keys))
+            let values: Vec<Object> = map.values().cloned().collect();
+            Ok(Object::list(values))
         }
-        other => Err(format!("Cannot get keys from {}", other)),
+        other => Err(format!("Cannot get values from {}", brief(other))),
     }
 }
 
-pub fn builtin_values(args: Vec<Object>) -> Result<Object, String> {
-    if args.len() != 1 {
+pub fn builtin_contains(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 2 {
         return Err(format!(
-            "Wrong number of arguments. got={}, want=1",
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+
+    match (&args[0], &args[1]) {
+        (Object::String(s), Object::String(sub)) => Ok(Object::Boolean(s.contains(sub.as_str()))),
+        (Object::List(items) | Object::Tuple(items), needle) => Ok(Object::Boolean(items.contains(needle))),
+        (Object::Set(items), needle) => Ok(Object::Boolean(items.contains(needle))),
+        (Object::Range(range), needle) => Ok(Object::Boolean(matches!(needle, Object::Integer(value) if range.contains(*value)))),
+        (Object::Dict(map), key) => Ok(Object::Boolean(map.contains_key(&super::dict_key(key)?))),
+        (other, needle) => Err(format!("Cannot check whether {} contains {}", brief(other), brief(needle))),
+    }
+}
+
+pub fn builtin_get(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2 or 3",
             args.len()
         ));
     }
 
     match &args[0] {
         Object::Dict(map) => {
-            let values: Vec<Object> = map.values().cloned().collect();
-            Ok(Object::List(values))
+            let key = super::dict_key(&args[1])?;
+            let default = args.get(2).cloned().unwrap_or(Object::None);
+            Ok(map.get(&key).cloned().unwrap_or(default))
         }
-        other => Err(format!("Cannot get values from {}", other)),
+        other => Err(format!("Cannot get a key from {}", brief(other))),
+    }
+}
+
+pub fn builtin_upper(args: Vec<Object>) -> Result<Object, String> {
+    let s = string_receiver(&args, "upper")?;
+    Ok(Object::String(s.to_uppercase().into()))
+}
+
+pub fn builtin_lower(args: Vec<Object>) -> Result<Object, String> {
+    let s = string_receiver(&args, "lower")?;
+    Ok(Object::String(s.to_lowercase().into()))
+}
+
+pub fn builtin_strip(args: Vec<Object>) -> Result<Object, String> {
+    let s = string_receiver(&args, "strip")?;
+    let trimmed = s.trim();
+    Ok(Object::String(s.slice_of(trimmed).unwrap_or_else(|| trimmed.into())))
+}
+
+pub fn builtin_split(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 && args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1 or 2",
+            args.len()
+        ));
+    }
+
+    let s = match &args[0] {
+        Object::String(s) => s,
+        other => return Err(format!("Cannot split {}", brief(other))),
+    };
+    let part = |p: &str| Object::String(s.slice_of(p).unwrap_or_else(|| p.into()));
+    let parts: Vec<Object> = match args.get(1) {
+        None => s.split_whitespace().map(part).collect(),
+        Some(Object::String(sep)) if !sep.is_empty() => s.split(sep.as_str()).map(part).collect(),
+        Some(other) => return Err(format!("Invalid separator for split: '{}'", brief(other))),
+    };
+    Ok(Object::list(parts))
+}
+
+fn string_receiver<'a>(args: &'a [Object], method: &str) -> Result<&'a Str, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    match &args[0] {
+        Object::String(s) => Ok(s),
+        other => Err(format!("Cannot call {} on {}", method, brief(other))),
     }
 }
@@ -0,0 +1,113 @@
+//! Ordering values: `<` and friends on strings, lists and tuples, `sorted`,
+//! and range patterns in `match`.
+//!
+//! Lists and tuples compare lexicographically, element by element, so
+//! `(1, "b") < (2, "a")` and a list of pairs sorts by its first items, then
+//! its second. Values of different kinds, such as an integer and a string,
+//! have no order and comparing them is an error. Instances and native values
+//! order by their `<` and `>` operators.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::evaluator::{apply_function, iteration};
+use crate::object::Object;
+use std::cmp::Ordering;
+
+/// How `left` orders against `right`.
+pub fn compare(left: &Object, right: &Object) -> Result<Ordering, String> {
+    match (left, right) {
+        (Object::Integer(l), Object::Integer(r)) => Ok(l.cmp(r)),
+        (Object::Float(l), Object::Float(r)) => l
+            .partial_cmp(r)
+            .ok_or_else(|| format!("Cannot compare {} with {}", brief(left), brief(right))),
+        (Object::String(l), Object::String(r)) => Ok(l.as_str().cmp(r.as_str())),
+        (Object::Boolean(l), Object::Boolean(r)) => Ok(l.cmp(r)),
+        (Object::List(l), Object::List(r)) | (Object::Tuple(l), Object::Tuple(r)) => {
+            for (l, r) in l.iter().zip(r.iter()) {
+                match compare(l, r)? {
+                    Ordering::Equal => {}
+                    decided => return Ok(decided),
+                }
+            }
+            Ok(l.len().cmp(&r.len()))
+        }
+        (Object::Instance(_) | Object::Native(_), _) | (_, Object::Instance(_) | Object::Native(_)) => {
+            let is = |operator| {
+                crate::evaluator::eval_infix_expression(&operator, left.clone(), right.clone())
+                    .map(|result| result == Object::Boolean(true))
+            };
+            if is(Operator::LessThan)? {
+                Ok(Ordering::Less)
+            } else if is(Operator::GreaterThan)? {
+                Ok(Ordering::Greater)
+            } else {
+                Ok(Ordering::Equal)
+            }
+        }
+        _ => Err(format!(
+            "Cannot compare {} with {}: values of different types have no order",
+            left.type_name(),
+            right.type_name()
+        )),
+    }
+}
+
+/// `left operator right` for an ordering operator (`<`, `<=`, `>`, `>=`).
+pub fn binary_op(operator: &Operator, left: &Object, right: &Object) -> Option<Result<Object, String>> {
+    let holds: fn(Ordering) -> bool = match operator {
+        Operator::LessThan => Ordering::is_lt,
+        Operator::LessThanEqual => Ordering::is_le,
+        Operator::GreaterThan => Ordering::is_gt,
+        Operator::GreaterThanEqual => Ordering::is_ge,
+        _ => return None,
+    };
+    Some(compare(left, right).map(|ordering| Object::Boolean(holds(ordering))))
+}
+
+/// Whether `value` falls in the range pattern `start..end`: at least
+/// `start` and below `end`. A value that can't be compared with the bounds
+/// is outside the range.
+pub fn in_range(value: &Object, start: &Object, end: &Object) -> bool {
+    matches!(compare(start, value), Ok(Ordering::Less | Ordering::Equal))
+        && matches!(compare(value, end), Ok(Ordering::Less))
+}
+
+/// `sorted(items)` or `sorted(items, key)` — a new list of the items in
+/// ascending order, or in the order of `key(item)`. The sort is stable, so
+/// items that compare equal keep their order.
+pub fn builtin_sorted(args: Vec<Object>) -> Result<Object, String> {
+    let (items, key) = match args.as_slice() {
+        [items] => (items, None),
+        [items, key] => (items, Some(key)),
+        _ => {
+            return Err(format!(
+                "Wrong number of arguments. got={}, want=1 or 2",
+                args.len()
+            ))
+        }
+    };
+    let mut keyed = Vec::new();
+    for item in iteration::collect(items)? {
+        let sort_key = match key {
+            Some(key) => apply_function(key.clone(), vec![item.clone()])?,
+            None => item.clone(),
+        };
+        keyed.push((sort_key, item));
+    }
+
+    // `sort_by` can't fail, so keep the first error and report it afterwards.
+    let mut error = None;
+    keyed.sort_by(|(a, _), (b, _)| {
+        if error.is_some() {
+            return Ordering::Equal;
+        }
+        compare(a, b).unwrap_or_else(|e| {
+            error = Some(e);
+            Ordering::Equal
+        })
+    });
+    match error {
+        Some(e) => Err(format!("sorted: {}", e)),
+        None => Ok(Object::list(keyed.into_iter().map(|(_, item)| item).collect())),
+    }
+}
@@ -0,0 +1,204 @@
+//! Result values: `ok(value)` and `err(error)`.
+//!
+//! A spell that can fail in an expected way returns one of these instead of
+//! stopping the script, and the caller decides what to do with it:
+//! `r.is_ok()`, `r.unwrap_or(default)`, `r.map(spell)` and friends. Inside a
+//! spell, `r?` is the value of an `ok`, or returns the `err` from the spell
+//! at once, so a chain of fallible steps reads like straight-line code.
+//!
+//! `?` leaves the spell through the error channel: it records the `err` and
+//! fails with a message saying so, and the spell call that the `err` escapes
+//! from turns it back into its return value. Used outside any spell, the
+//! message is what the script fails with.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::evaluator::apply_function;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cell::RefCell;
+
+#[derive(Debug)]
+pub struct Outcome {
+    ok: bool,
+    value: Object,
+}
+
+impl NativeObject for Outcome {
+    fn type_name(&self) -> &'static str {
+        "Result"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        let method: BuiltinFunction = match name {
+            "is_ok" => builtin_result_is_ok,
+            "is_err" => builtin_result_is_err,
+            "unwrap" => builtin_result_unwrap,
+            "unwrap_or" => builtin_result_unwrap_or,
+            "map" => builtin_result_map,
+            "map_err" => builtin_result_map_err,
+            "and_then" => builtin_result_and_then,
+            _ => return None,
+        };
+        Some(method)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        format!("{}({})", if self.ok { "ok" } else { "err" }, self.value)
+    }
+
+    /// Results are values: `ok(1) == ok(1)`.
+    fn binary_op(&self, operator: &Operator, other: &Object, _reflected: bool) -> Option<Result<Object, String>> {
+        let same = match other {
+            Object::Native(native) => native
+                .downcast_ref::<Outcome>()
+                .is_some_and(|other| other.ok == self.ok && other.value == self.value),
+            _ => false,
+        };
+        match operator {
+            Operator::Equal => Some(Ok(Object::Boolean(same))),
+            Operator::NotEqual => Some(Ok(Object::Boolean(!same))),
+            _ => None,
+        }
+    }
+
+    /// `value` and `error`, `None` for the side the result isn't.
+    fn attribute(&self, name: &str) -> Option<Object> {
+        match name {
+            "value" if self.ok => Some(self.value.clone()),
+            "error" if !self.ok => Some(self.value.clone()),
+            "value" | "error" => Some(Object::None),
+            _ => None,
+        }
+    }
+}
+
+fn outcome(ok: bool, value: Object) -> Object {
+    Object::Native(NativeHandle::new(Outcome { ok, value }))
+}
+
+/// `ok(value)` — a successful result holding `value` (`None` by default).
+pub fn builtin_ok(args: Vec<Object>) -> Result<Object, String> {
+    match <[Object; 1]>::try_from(args) {
+        Ok([value]) => Ok(outcome(true, value)),
+        Err(args) if args.is_empty() => Ok(outcome(true, Object::None)),
+        Err(args) => Err(format!("Wrong number of arguments. got={}, want=0 or 1", args.len())),
+    }
+}
+
+/// `err(error)` — a failed result holding `error`, usually a message.
+pub fn builtin_err(args: Vec<Object>) -> Result<Object, String> {
+    let [error] = <[Object; 1]>::try_from(args)
+        .map_err(|args| format!("Wrong number of arguments. got={}, want=1", args.len()))?;
+    Ok(outcome(false, error))
+}
+
+/// `r.is_ok()`
+pub fn builtin_result_is_ok(args: Vec<Object>) -> Result<Object, String> {
+    let (result, _) = receiver(&args, "is_ok", 0)?;
+    Ok(Object::Boolean(result.ok))
+}
+
+/// `r.is_err()`
+pub fn builtin_result_is_err(args: Vec<Object>) -> Result<Object, String> {
+    let (result, _) = receiver(&args, "is_err", 0)?;
+    Ok(Object::Boolean(!result.ok))
+}
+
+/// `r.unwrap()` — the value of an `ok`; an `err` stops the script.
+pub fn builtin_result_unwrap(args: Vec<Object>) -> Result<Object, String> {
+    let (result, _) = receiver(&args, "unwrap", 0)?;
+    if result.ok {
+        Ok(result.value.clone())
+    } else {
+        Err(format!("Called unwrap on {}", result.display()))
+    }
+}
+
+/// `r.unwrap_or(default)` — the value of an `ok`, or `default`.
+pub fn builtin_result_unwrap_or(args: Vec<Object>) -> Result<Object, String> {
+    let (result, rest) = receiver(&args, "unwrap_or", 1)?;
+    Ok(if result.ok { result.value.clone() } else { rest[0].clone() })
+}
+
+/// `r.map(spell)` — `ok(spell(value))` for an `ok`; an `err` is returned as is.
+pub fn builtin_result_map(args: Vec<Object>) -> Result<Object, String> {
+    let (result, rest) = receiver(&args, "map", 1)?;
+    if !result.ok {
+        return Ok(args[0].clone());
+    }
+    Ok(outcome(true, apply_function(rest[0].clone(), vec![result.value.clone()])?))
+}
+
+/// `r.map_err(spell)` — `err(spell(error))` for an `err`; an `ok` is returned as is.
+pub fn builtin_result_map_err(args: Vec<Object>) -> Result<Object, String> {
+    let (result, rest) = receiver(&args, "map_err", 1)?;
+    if result.ok {
+        return Ok(args[0].clone());
+    }
+    Ok(outcome(false, apply_function(rest[0].clone(), vec![result.value.clone()])?))
+}
+
+/// `r.and_then(spell)` — for an `ok`, what `spell(value)` returns, which
+/// must itself be a result; an `err` is returned as is.
+pub fn builtin_result_and_then(args: Vec<Object>) -> Result<Object, String> {
+    let (result, rest) = receiver(&args, "and_then", 1)?;
+    if !result.ok {
+        return Ok(args[0].clone());
+    }
+    let next = apply_function(rest[0].clone(), vec![result.value.clone()])?;
+    match &next {
+        Object::Native(native) if native.downcast_ref::<Outcome>().is_some() => Ok(next),
+        other => Err(format!("and_then expects its spell to return ok(...) or err(...), got {}", brief(other))),
+    }
+}
+
+fn receiver<'a>(args: &'a [Object], method: &str, want: usize) -> Result<(&'a Outcome, &'a [Object]), String> {
+    let result = match args.first() {
+        Some(Object::Native(native)) => native.downcast_ref::<Outcome>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on ok(...) or err(...)", method))?;
+    if args.len() != want + 1 {
+        return Err(format!("Wrong number of arguments. got={}, want={}", args.len() - 1, want));
+    }
+    Ok((result, &args[1..]))
+}
+
+thread_local! {
+    /// The `err` a `?` is returning, with the message it fails with.
+    static PROPAGATING: RefCell<Option<(String, Object)>> = const { RefCell::new(None) };
+}
+
+/// `value?`: the value of an `ok`; for an `err`, an error that the enclosing
+/// spell call turns back into the `err` with [`caught`].
+pub fn propagate(value: Object) -> Result<Object, String> {
+    let ok = match &value {
+        Object::Native(native) => native.downcast_ref::<Outcome>().map(|result| result.ok.then(|| result.value.clone())),
+        _ => None,
+    };
+    match ok {
+        Some(Some(inner)) => Ok(inner),
+        Some(None) => {
+            let message = format!("'?' returned {} outside of a spell", brief(&value));
+            PROPAGATING.with(|slot| *slot.borrow_mut() = Some((message.clone(), value)));
+            Err(message)
+        }
+        None => Err(format!("'?' expects ok(...) or err(...), got {}", brief(&value))),
+    }
+}
+
+/// The `err` a `?` is returning, if `error` is the message it failed with.
+pub fn caught(error: &str) -> Option<Object> {
+    PROPAGATING.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        match slot.as_ref() {
+            Some((message, _)) if message == error => slot.take().map(|(_, value)| value),
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,224 @@
+//! Filesystem path helpers: `glob`, `basename`, `dirname`, `extension`,
+//! `absolute` and `path_join`. Paths are plain strings on the Carrion side.
+
+use crate::error::brief;
+use crate::evaluator::purity;
+use crate::object::Object;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `glob(pattern)` — every existing path matching `pattern`, sorted.
+///
+/// Supports `*` and `?` within a path component, `[abc]` / `[a-z]` character
+/// classes, and `**` for any number of nested directories. Wildcards do not
+/// match a leading `.` unless the pattern component starts with one.
+pub fn builtin_glob(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("read the file system")?;
+    let pattern = single_path_arg(&args, "glob")?;
+
+    let mut candidates = vec![if pattern.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        PathBuf::new()
+    }];
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        let mut next = Vec::new();
+        for base in &candidates {
+            if component == "**" {
+                next.push(base.clone());
+                collect_subdirectories(base, &mut next);
+            } else if !has_wildcard(component) {
+                let path = base.join(component);
+                if fs::symlink_metadata(&path).is_ok() {
+                    next.push(path);
+                }
+            } else {
+                for name in directory_entries(base) {
+                    if wildcard_match(component, &name) {
+                        next.push(base.join(name));
+                    }
+                }
+            }
+        }
+        next.sort();
+        next.dedup();
+        candidates = next;
+    }
+
+    let mut matches: Vec<String> = candidates
+        .into_iter()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    matches.sort();
+    Ok(Object::list(matches.into_iter().map(|path| Object::String(path.into())).collect()))
+}
+
+pub fn builtin_basename(args: Vec<Object>) -> Result<Object, String> {
+    let path = single_path_arg(&args, "basename")?;
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Object::String(name.into()))
+}
+
+pub fn builtin_dirname(args: Vec<Object>) -> Result<Object, String> {
+    let path = single_path_arg(&args, "dirname")?;
+    let parent = Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Object::String(parent.into()))
+}
+
+/// `extension("notes.tar.gz")` is `"gz"`; paths without one give `""`.
+pub fn builtin_extension(args: Vec<Object>) -> Result<Object, String> {
+    let path = single_path_arg(&args, "extension")?;
+    let ext = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Object::String(ext.into()))
+}
+
+pub fn builtin_absolute(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("read the working directory")?;
+    let path = single_path_arg(&args, "absolute")?;
+    let absolute = std::path::absolute(path)
+        .map_err(|e| format!("Cannot make '{}' absolute: {}", path, e))?;
+    Ok(Object::String(absolute.to_string_lossy().into_owned().into()))
+}
+
+pub fn builtin_path_join(args: Vec<Object>) -> Result<Object, String> {
+    if args.is_empty() {
+        return Err("Wrong number of arguments. got=0, want=at least 1".to_string());
+    }
+
+    let mut joined = PathBuf::new();
+    for arg in &args {
+        match arg {
+            Object::String(part) => joined.push(part),
+            other => return Err(format!("path_join expects strings, got {}", brief(other))),
+        }
+    }
+    Ok(Object::String(joined.to_string_lossy().into_owned().into()))
+}
+
+fn single_path_arg<'a>(args: &'a [Object], name: &str) -> Result<&'a str, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    match &args[0] {
+        Object::String(path) => Ok(path),
+        other => Err(format!("{} expects a path string, got {}", name, brief(other))),
+    }
+}
+
+fn has_wildcard(component: &str) -> bool {
+    component.contains(['*', '?', '['])
+}
+
+fn directory_entries(dir: &Path) -> Vec<String> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+fn collect_subdirectories(dir: &Path, out: &mut Vec<PathBuf>) {
+    for name in directory_entries(dir) {
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = dir.join(&name);
+        // Don't follow symlinked directories; they can form cycles.
+        if fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir()) {
+            out.push(path.clone());
+            collect_subdirectories(&path, out);
+        }
+    }
+}
+
+/// Match one path component against a shell-style wildcard pattern.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_from(&pattern, &name)
+}
+
+/// Walk both strings once. On a mismatch after a `*`, retry with the star
+/// taking one more character; only the latest star needs retrying, so this
+/// stays linear per star instead of backtracking through every split.
+fn match_from(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // The pattern position after the latest `*`, and where its match ends.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if pattern.get(p) == Some(&'*') {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some(next) = match_one(pattern, p, name[n]) {
+            p = next;
+            n += 1;
+        } else if let Some((after, end)) = star {
+            star = Some((after, end + 1));
+            p = after;
+            n = end + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match the single-character pattern item at `pattern[p]` against `c`,
+/// giving the position after it.
+fn match_one(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    match pattern.get(p)? {
+        '?' => Some(p + 1),
+        '[' => match pattern[p..].iter().skip(1).position(|&c| c == ']') {
+            Some(close) => {
+                let close = p + close + 1;
+                class_contains(&pattern[p + 1..close], c).then_some(close + 1)
+            }
+            // An unterminated class is a literal '['.
+            None => (c == '[').then_some(p + 1),
+        },
+        &literal => (c == literal).then_some(p + 1),
+    }
+}
+
+fn class_contains(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                found = true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                found = true;
+            }
+            i += 1;
+        }
+    }
+    found != negated
+}
@@ -0,0 +1,144 @@
+//! Builtins that rely on `pure spell`: `memoize` and `map_pure`.
+//!
+//! A pure spell's result depends only on its arguments (see
+//! [`purity`](crate::evaluator::purity)), so `memoize` can answer a repeated
+//! call from its cache and `map_pure` could call it in any order. Both
+//! refuse other spells, whose calls may print or depend on state.
+
+use super::repr;
+use crate::error::brief;
+use crate::evaluator::{apply_function, iteration};
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A pure spell with the results of the calls made so far.
+#[derive(Debug)]
+pub struct Memo {
+    spell: Object,
+    /// Results by the `repr` of the arguments.
+    cache: RefCell<HashMap<String, Object>>,
+}
+
+impl NativeObject for Memo {
+    fn type_name(&self) -> &'static str {
+        "memoized"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "clear" => Some(builtin_memo_clear),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn attribute(&self, name: &str) -> Option<Object> {
+        match name {
+            "cache_size" => Some(Object::Integer(self.cache.borrow().len() as i64)),
+            _ => None,
+        }
+    }
+
+    fn call(&self, args: Vec<Object>) -> Option<Result<Object, String>> {
+        // Instances compare by identity, so arguments holding one aren't cached.
+        let Some(key) = cache_key(&args) else {
+            return Some(apply_function(self.spell.clone(), args));
+        };
+        if let Some(result) = self.cache.borrow().get(&key) {
+            return Some(Ok(result.clone()));
+        }
+        // The borrow is released first: a recursive spell calls the memo again.
+        let result = apply_function(self.spell.clone(), args);
+        if let Ok(value) = &result {
+            self.cache.borrow_mut().insert(key, value.clone());
+        }
+        Some(result)
+    }
+}
+
+/// `memoize(spell)` — a callable that runs the pure `spell` once per
+/// distinct set of arguments and remembers the result.
+pub fn builtin_memoize(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+    let spell = pure_spell(&args[0], "memoize")?;
+    Ok(Object::Native(NativeHandle::new(Memo {
+        spell,
+        cache: RefCell::new(HashMap::new()),
+    })))
+}
+
+/// `m.clear()` — forget the remembered results.
+pub fn builtin_memo_clear(args: Vec<Object>) -> Result<Object, String> {
+    let memo = match args.first() {
+        Some(Object::Native(native)) => native.0.as_any().downcast_ref::<Memo>(),
+        _ => None,
+    };
+    let Some(memo) = memo else {
+        return Err("clear must be called on a memoized spell".to_string());
+    };
+    memo.cache.borrow_mut().clear();
+    Ok(Object::None)
+}
+
+/// `map_pure(spell, items)` — a list of the pure `spell` applied to each
+/// item, in the items' order.
+///
+/// The calls run one after another on the script's thread: values can't be
+/// shared between threads. Since a pure spell can't observe the order of
+/// calls, spreading them over threads later wouldn't change what a script
+/// sees.
+pub fn builtin_map_pure(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+    let spell = pure_spell(&args[0], "map_pure")?;
+    let results = iteration::collect(&args[1])?
+        .into_iter()
+        .map(|item| apply_function(spell.clone(), vec![item]))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Object::list(results))
+}
+
+/// `value` if it is a pure spell or a memoized one, for the builtin `name`.
+fn pure_spell(value: &Object, name: &str) -> Result<Object, String> {
+    match value {
+        Object::Function(spell) if spell.pure => Ok(value.clone()),
+        Object::Native(native) if native.0.as_any().is::<Memo>() => Ok(value.clone()),
+        Object::Function(spell) => Err(format!(
+            "{} needs a pure spell; declare it as 'pure spell {}(...)'",
+            name, spell.name
+        )),
+        other => Err(format!("{} expects a pure spell, got {}", name, brief(other))),
+    }
+}
+
+/// The cache key for `args`, or `None` if they hold something compared by
+/// identity, such as an instance or a spell.
+fn cache_key(args: &[Object]) -> Option<String> {
+    fn is_value(object: &Object) -> bool {
+        match object {
+            Object::Integer(_) | Object::Float(_) | Object::Boolean(_) | Object::String(_) | Object::Range(_) | Object::None => true,
+            Object::List(items) | Object::Tuple(items) => items.iter().all(is_value),
+            Object::Set(items) => items.iter().all(is_value),
+            Object::Dict(map) => map.values().all(is_value),
+            _ => false,
+        }
+    }
+    if !args.iter().all(is_value) {
+        return None;
+    }
+    Some(args.iter().map(repr).collect::<Vec<_>>().join(", "))
+}
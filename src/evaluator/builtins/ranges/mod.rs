@@ -0,0 +1,51 @@
+//! Ranges: `range(stop)`, `range(start, stop, step)` and `start..stop`.
+//!
+//! A range only stores its bounds; for loops, `in`, `len` and indexing work
+//! on it without building a list, so `for i in 0..1000000` costs no memory.
+
+use crate::object::{Object, Range};
+
+/// The range from `start` up to, but not including, `stop`.
+pub fn new(start: i64, stop: i64, step: i64) -> Result<Object, String> {
+    if step == 0 {
+        return Err("range step cannot be zero".to_string());
+    }
+    Ok(Object::Range(Range { start, stop, step }))
+}
+
+/// Whether two ranges produce the same values, e.g. `0..3 == range(0, 3, 1)`.
+pub fn same_values(left: &Range, right: &Range) -> bool {
+    left.len() == right.len() && (left.len() <= 1 || left.step == right.step) && left.get(0) == right.get(0)
+}
+
+/// `range(stop)`, `range(start, stop)` or `range(start, stop, step)`.
+pub fn builtin_range(args: Vec<Object>) -> Result<Object, String> {
+    let mut bounds = Vec::with_capacity(args.len());
+    for arg in &args {
+        match arg {
+            Object::Integer(value) => bounds.push(*value),
+            other => return Err(format!("range expects integers, got {}", other.type_name())),
+        }
+    }
+    match bounds.as_slice() {
+        [stop] => new(0, *stop, 1),
+        [start, stop] => new(*start, *stop, 1),
+        [start, stop, step] => new(*start, *stop, *step),
+        _ => Err(format!(
+            "Wrong number of arguments. got={}, want=1 to 3",
+            args.len()
+        )),
+    }
+}
+
+/// `r.to_list()` — the range's values as a list.
+pub fn builtin_range_to_list(args: Vec<Object>) -> Result<Object, String> {
+    match args.as_slice() {
+        [Object::Range(range)] => Ok(Object::list(range.iter().map(Object::Integer).collect())),
+        [_] => Err("to_list must be called on a range".to_string()),
+        _ => Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len().saturating_sub(1)
+        )),
+    }
+}
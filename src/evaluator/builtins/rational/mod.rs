@@ -0,0 +1,225 @@
+//! Exact fractions: `frac(1, 3) + frac(1, 6)` is `1/2`.
+//!
+//! Fractions are always kept in lowest terms with a positive denominator.
+//! Combining one with an integer stays exact; combining one with a float
+//! gives a float.
+
+use crate::ast::Operator;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    pub fn new(numerator: i128, denominator: i128) -> Result<Self, String> {
+        if denominator == 0 {
+            return Err("Division by zero".to_string());
+        }
+        let divisor = gcd(numerator, denominator);
+        let sign = if denominator < 0 { -1 } else { 1 };
+        Ok(Rational {
+            numerator: sign * numerator / divisor,
+            denominator: sign * denominator / divisor,
+        })
+    }
+
+    fn add(self, other: Self) -> Result<Self, String> {
+        let numerator = checked(
+            self.numerator
+                .checked_mul(other.denominator)
+                .zip(other.numerator.checked_mul(self.denominator))
+                .and_then(|(a, b)| a.checked_add(b)),
+        )?;
+        Rational::new(numerator, self.common_denominator(other)?)
+    }
+
+    fn sub(self, other: Self) -> Result<Self, String> {
+        self.add(Rational {
+            numerator: -other.numerator,
+            denominator: other.denominator,
+        })
+    }
+
+    fn mul(self, other: Self) -> Result<Self, String> {
+        Rational::new(
+            checked(self.numerator.checked_mul(other.numerator))?,
+            self.common_denominator(other)?,
+        )
+    }
+
+    fn div(self, other: Self) -> Result<Self, String> {
+        if other.numerator == 0 {
+            return Err("Division by zero".to_string());
+        }
+        Rational::new(
+            checked(self.numerator.checked_mul(other.denominator))?,
+            checked(self.denominator.checked_mul(other.numerator))?,
+        )
+    }
+
+    fn compare(self, other: Self) -> Result<Ordering, String> {
+        let a = checked(self.numerator.checked_mul(other.denominator))?;
+        let b = checked(other.numerator.checked_mul(self.denominator))?;
+        Ok(a.cmp(&b))
+    }
+
+    fn common_denominator(self, other: Self) -> Result<i128, String> {
+        checked(self.denominator.checked_mul(other.denominator))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+impl NativeObject for Rational {
+    fn type_name(&self) -> &'static str {
+        "fraction"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "numerator" => Some(builtin_frac_numerator),
+            "denominator" => Some(builtin_frac_denominator),
+            "to_float" => Some(builtin_frac_to_float),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        self.to_string()
+    }
+
+    fn binary_op(&self, operator: &Operator, other: &Object, reflected: bool) -> Option<Result<Object, String>> {
+        let other = match other {
+            Object::Integer(i) => Rational {
+                numerator: *i as i128,
+                denominator: 1,
+            },
+            Object::Float(f) => {
+                let (left, right) = if reflected { (*f, self.to_f64()) } else { (self.to_f64(), *f) };
+                return float_op(operator, left, right);
+            }
+            Object::Native(native) => *native.downcast_ref::<Rational>()?,
+            _ => return None,
+        };
+        let (left, right) = if reflected { (other, *self) } else { (*self, other) };
+
+        let result = match operator {
+            Operator::Plus => left.add(right).map(rational_object),
+            Operator::Minus => left.sub(right).map(rational_object),
+            Operator::Multiply => left.mul(right).map(rational_object),
+            Operator::Divide => left.div(right).map(rational_object),
+            Operator::Equal => left.compare(right).map(|o| Object::Boolean(o.is_eq())),
+            Operator::NotEqual => left.compare(right).map(|o| Object::Boolean(o.is_ne())),
+            Operator::LessThan => left.compare(right).map(|o| Object::Boolean(o.is_lt())),
+            Operator::GreaterThan => left.compare(right).map(|o| Object::Boolean(o.is_gt())),
+            Operator::LessThanEqual => left.compare(right).map(|o| Object::Boolean(o.is_le())),
+            Operator::GreaterThanEqual => left.compare(right).map(|o| Object::Boolean(o.is_ge())),
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+/// `frac(numerator, denominator)` — an exact fraction; the denominator defaults to 1.
+pub fn builtin_frac(args: Vec<Object>) -> Result<Object, String> {
+    let (numerator, denominator) = match args.as_slice() {
+        [Object::Integer(n)] => (*n, 1),
+        [Object::Integer(n), Object::Integer(d)] => (*n, *d),
+        [_] | [_, _] => return Err("frac expects integer arguments".to_string()),
+        _ => {
+            return Err(format!(
+                "Wrong number of arguments. got={}, want=1 or 2",
+                args.len()
+            ));
+        }
+    };
+    Rational::new(numerator as i128, denominator as i128).map(rational_object)
+}
+
+pub fn builtin_frac_numerator(args: Vec<Object>) -> Result<Object, String> {
+    let value = rational_receiver(&args, "numerator")?;
+    integer_part(value.numerator)
+}
+
+pub fn builtin_frac_denominator(args: Vec<Object>) -> Result<Object, String> {
+    let value = rational_receiver(&args, "denominator")?;
+    integer_part(value.denominator)
+}
+
+pub fn builtin_frac_to_float(args: Vec<Object>) -> Result<Object, String> {
+    Ok(Object::Float(rational_receiver(&args, "to_float")?.to_f64()))
+}
+
+fn rational_receiver(args: &[Object], method: &str) -> Result<Rational, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len().saturating_sub(1)
+        ));
+    }
+    match &args[0] {
+        Object::Native(native) => native.downcast_ref::<Rational>().copied(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a fraction", method))
+}
+
+fn integer_part(value: i128) -> Result<Object, String> {
+    i64::try_from(value)
+        .map(Object::Integer)
+        .map_err(|_| "Fraction part does not fit in an integer".to_string())
+}
+
+fn rational_object(value: Rational) -> Object {
+    Object::Native(NativeHandle::new(value))
+}
+
+fn float_op(operator: &Operator, left: f64, right: f64) -> Option<Result<Object, String>> {
+    let result = match operator {
+        Operator::Plus => Object::Float(left + right),
+        Operator::Minus => Object::Float(left - right),
+        Operator::Multiply => Object::Float(left * right),
+        Operator::Divide => Object::Float(left / right),
+        Operator::Equal => Object::Boolean(left == right),
+        Operator::NotEqual => Object::Boolean(left != right),
+        Operator::LessThan => Object::Boolean(left < right),
+        Operator::GreaterThan => Object::Boolean(left > right),
+        Operator::LessThanEqual => Object::Boolean(left <= right),
+        Operator::GreaterThanEqual => Object::Boolean(left >= right),
+        _ => return None,
+    };
+    Some(Ok(result))
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+fn checked(value: Option<i128>) -> Result<i128, String> {
+    value.ok_or_else(|| "Fraction overflow".to_string())
+}
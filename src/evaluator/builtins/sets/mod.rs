@@ -0,0 +1,97 @@
+//! Sets: `{1, 2, 3}` literals, `set(items)`, and the set operators.
+//!
+//! A set is a value like a list: it keeps its elements in insertion order
+//! without duplicates, and `add`/`remove` return a new set. Elements are
+//! found through a hash index rather than a scan (see [`Set`]). Only
+//! immutable values (numbers, strings, booleans, None and tuples of those)
+//! can be elements; `0.0` and `-0.0` are the same element, like `==` says.
+
+use crate::ast::Operator;
+use crate::error::brief;
+use crate::evaluator::iteration;
+use crate::object::{Object, Set};
+use std::rc::Rc;
+
+/// Build a set from `items`, dropping duplicates.
+pub fn from_items(items: impl IntoIterator<Item = Object>) -> Result<Object, String> {
+    let mut elements = Set::default();
+    for item in items {
+        check_hashable(&item)?;
+        elements.insert(item);
+    }
+    Ok(Object::Set(Rc::new(elements)))
+}
+
+fn check_hashable(item: &Object) -> Result<(), String> {
+    if item.is_hashable() {
+        Ok(())
+    } else {
+        Err(format!("Unhashable type {} cannot be a set element", item.type_name()))
+    }
+}
+
+/// `a | b`, `a & b`, `a - b`, `a ^ b`, `a == b` and `a != b` on two sets.
+pub fn binary_op(operator: &Operator, left: &Set, right: &Set) -> Result<Object, String> {
+    let elements: Set = match operator {
+        Operator::BitOr => left.iter().chain(right.iter()).cloned().collect(),
+        Operator::BitAnd => left.iter().filter(|item| right.contains(item)).cloned().collect(),
+        Operator::Minus => left.iter().filter(|item| !right.contains(item)).cloned().collect(),
+        Operator::BitXor => left
+            .iter()
+            .filter(|item| !right.contains(item))
+            .chain(right.iter().filter(|item| !left.contains(item)))
+            .cloned()
+            .collect(),
+        Operator::Equal => return Ok(Object::Boolean(left == right)),
+        Operator::NotEqual => return Ok(Object::Boolean(left != right)),
+        _ => return Err(format!("Unknown operator for Sets: {:?}", operator)),
+    };
+    Ok(Object::Set(Rc::new(elements)))
+}
+
+/// `set()` — an empty set; `set(items)` — the distinct items of anything a
+/// `for` loop can walk.
+pub fn builtin_set(args: Vec<Object>) -> Result<Object, String> {
+    match args.as_slice() {
+        [] => Ok(Object::Set(Rc::default())),
+        [items] => from_items(iteration::collect(items)?),
+        _ => Err(format!(
+            "Wrong number of arguments. got={}, want=0 or 1",
+            args.len()
+        )),
+    }
+}
+
+/// `s.add(item)` — a copy of `s` that also contains `item`.
+pub fn builtin_set_add(args: Vec<Object>) -> Result<Object, String> {
+    let (mut elements, item) = set_receiver(args, "add")?;
+    check_hashable(&item)?;
+    if !elements.contains(&item) {
+        Rc::make_mut(&mut elements).insert(item);
+    }
+    Ok(Object::Set(elements))
+}
+
+/// `s.remove(item)` — a copy of `s` without `item`; missing items are an error.
+pub fn builtin_set_remove(args: Vec<Object>) -> Result<Object, String> {
+    let (mut elements, item) = set_receiver(args, "remove")?;
+    if !elements.contains(&item) {
+        return Err(format!("{} is not in the set", brief(&item)));
+    }
+    Rc::make_mut(&mut elements).remove(&item);
+    Ok(Object::Set(elements))
+}
+
+fn set_receiver(args: Vec<Object>, method: &str) -> Result<(Rc<Set>, Object), String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len().saturating_sub(1)
+        ));
+    }
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (Some(Object::Set(elements)), Some(item)) => Ok((elements, item)),
+        _ => Err(format!("{} must be called on a set", method)),
+    }
+}
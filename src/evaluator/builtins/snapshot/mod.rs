@@ -0,0 +1,89 @@
+//! Snapshot assertions for `carrion test`.
+//!
+//! `check_snapshot(name, value)` stores `repr(value)` in `name.snap` the
+//! first time it runs and compares against that file afterwards. When the
+//! values differ the error shows a line diff; running the tests with
+//! `--update-snapshots` rewrites the stored files instead.
+
+use super::diff::text_diff;
+use crate::evaluator::purity;
+use crate::object::Object;
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Directory used when no test file has set one.
+pub const DEFAULT_DIRECTORY: &str = "__snapshots__";
+
+static UPDATE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static DIRECTORY: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Overwrite mismatching snapshots instead of failing.
+pub fn set_update(update: bool) {
+    UPDATE.store(update, Ordering::Relaxed);
+}
+
+/// Where snapshots are read and written; `None` means `__snapshots__` in the
+/// working directory.
+pub fn set_directory(directory: Option<PathBuf>) {
+    DIRECTORY.with(|dir| *dir.borrow_mut() = directory);
+}
+
+pub fn builtin_check_snapshot(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("check snapshots")?;
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+
+    let name = match &args[0] {
+        Object::String(name) if valid_name(name) => name,
+        other => {
+            return Err(format!(
+                "Snapshot names may only use letters, digits, '-', '_' and '.', got {}",
+                other
+            ));
+        }
+    };
+    let actual = format!("{}\n", super::repr(&args[1]));
+
+    let directory = DIRECTORY
+        .with(|dir| dir.borrow().clone())
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIRECTORY));
+    let path = directory.join(format!("{}.snap", name));
+
+    match fs::read_to_string(&path) {
+        Ok(expected) if expected == actual => return Ok(Object::Boolean(true)),
+        Ok(expected) if !UPDATE.load(Ordering::Relaxed) => {
+            let diff = text_diff(&expected, &actual).join("\n");
+            return Err(format!(
+                "Snapshot '{}' does not match {} (run with --update-snapshots to accept):\n{}",
+                name,
+                path.display(),
+                diff
+            ));
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Could not read snapshot '{}': {}", path.display(), e)),
+    }
+
+    fs::create_dir_all(&directory)
+        .and_then(|_| fs::write(&path, &actual))
+        .map_err(|e| format!("Could not write snapshot '{}': {}", path.display(), e))?;
+    Ok(Object::Boolean(true))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
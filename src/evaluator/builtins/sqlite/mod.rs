@@ -0,0 +1,148 @@
+//! SQLite access for scripts (enabled with the `sqlite` cargo feature).
+//!
+//! `sqlite_open(path)` returns a connection object with two methods:
+//! `query(sql, params)` returning a list of row dicts, and
+//! `execute(sql, params)` returning the number of affected rows.
+
+use crate::error::brief;
+use crate::evaluator::purity;
+use crate::object::{BuiltinFunction, Map, NativeHandle, NativeObject, Object};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, params_from_iter};
+use std::any::Any;
+use std::fmt;
+
+pub struct SqliteConnection {
+    conn: Connection,
+    path: String,
+}
+
+impl fmt::Debug for SqliteConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteConnection")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl NativeObject for SqliteConnection {
+    fn type_name(&self) -> &'static str {
+        "sqlite connection"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "query" => Some(builtin_sqlite_query),
+            "execute" => Some(builtin_sqlite_execute),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub fn builtin_sqlite_open(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("use a database")?;
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1",
+            args.len()
+        ));
+    }
+
+    let path = match &args[0] {
+        Object::String(path) => path.to_string(),
+        other => return Err(format!("sqlite_open expects a path string, got {}", brief(other))),
+    };
+    let conn = Connection::open(&path)
+        .map_err(|e| format!("Could not open database '{}': {}", path, e))?;
+    Ok(Object::Native(NativeHandle::new(SqliteConnection { conn, path })))
+}
+
+pub fn builtin_sqlite_query(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("use a database")?;
+    let (connection, sql, params) = statement_args(&args, "query")?;
+    let mut stmt = connection
+        .conn
+        .prepare(sql)
+        .map_err(|e| format!("SQL error: {}", e))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+    let mut rows = stmt
+        .query(params_from_iter(params))
+        .map_err(|e| format!("SQL error: {}", e))?;
+    let mut result = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| format!("SQL error: {}", e))? {
+        let mut record = Map::default();
+        for (i, column) in columns.iter().enumerate() {
+            let value = row.get_ref(i).map_err(|e| format!("SQL error: {}", e))?;
+            record.insert(column.clone(), from_sql(value));
+        }
+        result.push(Object::dict(record));
+    }
+    Ok(Object::list(result))
+}
+
+pub fn builtin_sqlite_execute(args: Vec<Object>) -> Result<Object, String> {
+    purity::check("use a database")?;
+    let (connection, sql, params) = statement_args(&args, "execute")?;
+    let changed = connection
+        .conn
+        .execute(sql, params_from_iter(params))
+        .map_err(|e| format!("SQL error: {}", e))?;
+    Ok(Object::Integer(changed as i64))
+}
+
+/// Unpack `(connection, sql[, params])` for the connection methods.
+fn statement_args<'a>(
+    args: &'a [Object],
+    method: &str,
+) -> Result<(&'a SqliteConnection, &'a str, Vec<Value>), String> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=1 or 2",
+            args.len().saturating_sub(1)
+        ));
+    }
+
+    let connection = match &args[0] {
+        Object::Native(native) => native.downcast_ref::<SqliteConnection>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a sqlite connection", method))?;
+    let sql = match &args[1] {
+        Object::String(sql) => sql.as_str(),
+        other => return Err(format!("SQL must be a string, got {}", brief(other))),
+    };
+    let params = match args.get(2) {
+        None => Vec::new(),
+        Some(Object::List(items)) => items.iter().map(to_sql).collect::<Result<_, _>>()?,
+        Some(other) => return Err(format!("SQL parameters must be a list, got {}", brief(other))),
+    };
+    Ok((connection, sql, params))
+}
+
+fn to_sql(value: &Object) -> Result<Value, String> {
+    match value {
+        Object::Integer(i) => Ok(Value::Integer(*i)),
+        Object::Float(f) => Ok(Value::Real(*f)),
+        Object::Boolean(b) => Ok(Value::Integer(*b as i64)),
+        Object::String(s) => Ok(Value::Text(s.to_string())),
+        Object::None => Ok(Value::Null),
+        other => Err(format!("Cannot bind {} as a SQL parameter", brief(other))),
+    }
+}
+
+fn from_sql(value: ValueRef<'_>) -> Object {
+    match value {
+        ValueRef::Null => Object::None,
+        ValueRef::Integer(i) => Object::Integer(i),
+        ValueRef::Real(f) => Object::Float(f),
+        ValueRef::Text(bytes) => Object::String(String::from_utf8_lossy(bytes).into_owned().into()),
+        ValueRef::Blob(bytes) => {
+            Object::list(bytes.iter().map(|b| Object::Integer(*b as i64)).collect())
+        }
+    }
+}
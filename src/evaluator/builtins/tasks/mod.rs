@@ -0,0 +1,152 @@
+//! Structured tasks: `together:` blocks and `spawn(spell, args...)`.
+//!
+//! `spawn` can only be called inside a `together:` block, and the block
+//! doesn't finish until every task spawned in it has, so no task outlives
+//! the code that started it. The first task to fail ends the block with its
+//! error and cancels the tasks that haven't run yet; a failure in the block
+//! itself cancels them all.
+//!
+//! Values belong to one thread, so for now a block runs its tasks one after
+//! another, in the order they were spawned, once its own statements are
+//! done. A task can spawn more tasks; they join the same block.
+
+use crate::error::brief;
+use crate::evaluator::apply_function;
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug)]
+enum State {
+    Waiting { spell: Object, args: Vec<Object> },
+    Running,
+    Done(Object),
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub struct Task {
+    state: RefCell<State>,
+}
+
+impl NativeObject for Task {
+    fn type_name(&self) -> &'static str {
+        "task"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "result" => Some(builtin_task_result),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn attribute(&self, name: &str) -> Option<Object> {
+        match name {
+            "done" => Some(Object::Boolean(matches!(*self.state.borrow(), State::Done(_)))),
+            _ => None,
+        }
+    }
+}
+
+impl Task {
+    /// Run the task if it is still waiting.
+    fn run(&self) -> Result<(), String> {
+        let waiting = std::mem::replace(&mut *self.state.borrow_mut(), State::Running);
+        let State::Waiting { spell, args } = waiting else {
+            *self.state.borrow_mut() = waiting;
+            return Ok(());
+        };
+        // The borrow is released first: the task may look at itself.
+        let (state, result) = match apply_function(spell, args) {
+            Ok(value) => (State::Done(value), Ok(())),
+            Err(e) => (State::Failed(e.clone()), Err(e)),
+        };
+        *self.state.borrow_mut() = state;
+        result
+    }
+
+    fn cancel(&self) {
+        let mut state = self.state.borrow_mut();
+        if matches!(*state, State::Waiting { .. }) {
+            *state = State::Cancelled;
+        }
+    }
+}
+
+thread_local! {
+    /// The tasks of each `together:` block being run, innermost last.
+    static SCOPES: RefCell<Vec<Vec<Rc<Task>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `body`, a `together:` block, then the tasks spawned in it.
+pub fn together(body: impl FnOnce() -> Result<Object, String>) -> Result<Object, String> {
+    SCOPES.with(|scopes| scopes.borrow_mut().push(Vec::new()));
+    let result = body().and_then(|value| run_tasks().map(|_| value));
+    let tasks = SCOPES.with(|scopes| scopes.borrow_mut().pop()).unwrap_or_default();
+    for task in &tasks {
+        task.cancel();
+    }
+    result
+}
+
+/// Run the innermost block's tasks in order, including any they spawn.
+fn run_tasks() -> Result<(), String> {
+    let mut next = 0;
+    loop {
+        let task = SCOPES.with(|scopes| scopes.borrow().last().and_then(|tasks| tasks.get(next).cloned()));
+        let Some(task) = task else {
+            return Ok(());
+        };
+        task.run()?;
+        next += 1;
+    }
+}
+
+/// `spawn(spell, args...)` — a task that calls `spell(args...)` before the
+/// enclosing `together:` block ends.
+pub fn builtin_spawn(args: Vec<Object>) -> Result<Object, String> {
+    let mut args = args.into_iter();
+    let spell = match args.next() {
+        Some(spell @ (Object::Function(_) | Object::Builtin(_) | Object::BoundMethod(_) | Object::Native(_))) => spell,
+        Some(other) => return Err(format!("spawn expects a spell, got {}", brief(&other))),
+        None => return Err("Wrong number of arguments. got=0, want at least 1".to_string()),
+    };
+    let task = Rc::new(Task {
+        state: RefCell::new(State::Waiting {
+            spell,
+            args: args.collect(),
+        }),
+    });
+    SCOPES.with(|scopes| match scopes.borrow_mut().last_mut() {
+        Some(tasks) => {
+            tasks.push(Rc::clone(&task));
+            Ok(Object::Native(NativeHandle(task)))
+        }
+        None => Err("spawn must be called inside a 'together:' block, which waits for the task".to_string()),
+    })
+}
+
+/// `task.result()` — what the task's spell returned, once the block is over.
+pub fn builtin_task_result(args: Vec<Object>) -> Result<Object, String> {
+    let task = match args.first() {
+        Some(Object::Native(native)) => native.downcast_ref::<Task>(),
+        _ => None,
+    };
+    let Some(task) = task else {
+        return Err("result must be called on a task".to_string());
+    };
+    match &*task.state.borrow() {
+        State::Done(value) => Ok(value.clone()),
+        State::Waiting { .. } => Err("The task hasn't run yet; read its result after its 'together:' block".to_string()),
+        State::Running => Err("The task is still running".to_string()),
+        State::Failed(e) => Err(format!("The task failed: {}", e)),
+        State::Cancelled => Err("The task was cancelled because another part of its 'together:' block failed".to_string()),
+    }
+}
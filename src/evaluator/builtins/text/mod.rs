@@ -0,0 +1,110 @@
+//! Text formatting helpers, and the units a string can be taken apart in.
+
+use crate::error::brief;
+use crate::object::{DictMap, Object, Str};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// `template(text, values)` — replace each `{name}` in `text` with
+/// `values["name"]`. `{{` and `}}` produce literal braces. Every placeholder
+/// missing from `values` is reported in a single error.
+pub fn builtin_template(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+
+    let text = match &args[0] {
+        Object::String(text) => text,
+        other => return Err(format!("template expects a string, got {}", brief(other))),
+    };
+    let values = match &args[1] {
+        Object::Dict(values) => values,
+        other => return Err(format!("template values must be a dict, got {}", brief(other))),
+    };
+
+    render(text, values).map(|text| Object::String(text.into()))
+}
+
+fn render(text: &str, values: &DictMap) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut missing: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => {
+                            return Err(format!("Unclosed placeholder '{{{}' in template", name));
+                        }
+                        Some(c) => name.push(c),
+                    }
+                }
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err("Empty placeholder '{}' in template".to_string());
+                }
+                match values.get(&Object::String(name.into())) {
+                    Some(value) => out.push_str(&value.to_string()),
+                    None if !missing.iter().any(|m| m == name) => missing.push(name.to_string()),
+                    None => {}
+                }
+            }
+            '}' => return Err("Unmatched '}' in template; write '}}' for a literal brace".to_string()),
+            c => out.push(c),
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(out)
+    } else {
+        Err(format!("Missing template values: {}", missing.join(", ")))
+    }
+}
+
+/// `chars(text)` — the characters (Unicode code points) of `text`, each as a
+/// one-character string; what `for ch in text:` walks over.
+pub fn builtin_chars(args: Vec<Object>) -> Result<Object, String> {
+    let text = text_argument("chars", &args)?;
+    Ok(Object::list(text.chars_shared().map(Object::String).collect()))
+}
+
+/// `bytes(text)` — the UTF-8 encoding of `text`, as integers from 0 to 255.
+pub fn builtin_bytes(args: Vec<Object>) -> Result<Object, String> {
+    let text = text_argument("bytes", &args)?;
+    Ok(Object::list(text.as_str().bytes().map(|byte| Object::Integer(byte.into())).collect()))
+}
+
+/// `graphemes(text)` — what a reader sees as single characters, each as a
+/// string: an `e` followed by a combining accent, or a flag made of two
+/// code points, is one grapheme.
+pub fn builtin_graphemes(args: Vec<Object>) -> Result<Object, String> {
+    let text = text_argument("graphemes", &args)?;
+    Ok(Object::list(
+        text.as_str()
+            .graphemes(true)
+            .map(|grapheme| Object::String(text.slice_of(grapheme).unwrap_or_else(|| grapheme.into())))
+            .collect(),
+    ))
+}
+
+fn text_argument<'a>(name: &str, args: &'a [Object]) -> Result<&'a Str, String> {
+    match args {
+        [Object::String(text)] => Ok(text),
+        [other] => Err(format!("{} expects a string, got {}", name, other.type_name())),
+        _ => Err(format!("Wrong number of arguments. got={}, want=1", args.len())),
+    }
+}
@@ -0,0 +1,119 @@
+//! In-script timing: `stopwatch()` and `time_it(spell, iterations)`.
+//!
+//! Durations are reported as float milliseconds so sub-millisecond work is
+//! still measurable.
+
+use crate::error::brief;
+use crate::object::{BuiltinFunction, Map, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct Stopwatch {
+    started: Cell<Instant>,
+}
+
+impl NativeObject for Stopwatch {
+    fn type_name(&self) -> &'static str {
+        "stopwatch"
+    }
+
+    fn method(&self, name: &str) -> Option<BuiltinFunction> {
+        match name {
+            "elapsed_ms" => Some(builtin_stopwatch_elapsed_ms),
+            "restart" => Some(builtin_stopwatch_restart),
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// `stopwatch()` — a running stopwatch with `elapsed_ms()` and `restart()`.
+pub fn builtin_stopwatch(args: Vec<Object>) -> Result<Object, String> {
+    if !args.is_empty() {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len()
+        ));
+    }
+    Ok(Object::Native(NativeHandle::new(Stopwatch {
+        started: Cell::new(Instant::now()),
+    })))
+}
+
+pub fn builtin_stopwatch_elapsed_ms(args: Vec<Object>) -> Result<Object, String> {
+    let stopwatch = stopwatch_receiver(&args, "elapsed_ms")?;
+    Ok(Object::Float(millis(traced(stopwatch.started.get().elapsed())?)))
+}
+
+/// `sw.restart()` — reset to zero and return the time elapsed before the reset.
+pub fn builtin_stopwatch_restart(args: Vec<Object>) -> Result<Object, String> {
+    let stopwatch = stopwatch_receiver(&args, "restart")?;
+    let now = Instant::now();
+    let elapsed = traced(now - stopwatch.started.replace(now))?;
+    Ok(Object::Float(millis(elapsed)))
+}
+
+/// `time_it(spell, iterations)` — call `spell()` repeatedly and return a dict
+/// with `iterations`, `total_ms`, `mean_ms`, `min_ms` and `max_ms`.
+pub fn builtin_time_it(args: Vec<Object>) -> Result<Object, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=2",
+            args.len()
+        ));
+    }
+
+    let iterations = match &args[1] {
+        Object::Integer(n) if *n > 0 => *n,
+        other => return Err(format!("time_it expects a positive iteration count, got {}", brief(other))),
+    };
+
+    let mut total = Duration::ZERO;
+    let mut min = Duration::MAX;
+    let mut max = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        crate::evaluator::apply_function(args[0].clone(), Vec::new())?;
+        let elapsed = traced(start.elapsed())?;
+        total += elapsed;
+        min = min.min(elapsed);
+        max = max.max(elapsed);
+    }
+
+    let mut stats = Map::default();
+    stats.insert("iterations".to_string(), Object::Integer(iterations));
+    stats.insert("total_ms".to_string(), Object::Float(millis(total)));
+    stats.insert("mean_ms".to_string(), Object::Float(millis(total) / iterations as f64));
+    stats.insert("min_ms".to_string(), Object::Float(millis(min)));
+    stats.insert("max_ms".to_string(), Object::Float(millis(max)));
+    Ok(Object::dict(stats))
+}
+
+/// A measured duration, taken from the trace when replaying.
+fn traced(elapsed: Duration) -> Result<Duration, String> {
+    let nanos = crate::evaluator::trace::read("elapsed", || elapsed.as_nanos() as i64)?;
+    Ok(Duration::from_nanos(nanos.max(0) as u64))
+}
+
+fn stopwatch_receiver<'a>(args: &'a [Object], method: &str) -> Result<&'a Stopwatch, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "Wrong number of arguments. got={}, want=0",
+            args.len().saturating_sub(1)
+        ));
+    }
+    match &args[0] {
+        Object::Native(native) => native.downcast_ref::<Stopwatch>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} must be called on a stopwatch", method))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,134 @@
+//! `validate(data, schema)`: check dict data against a schema dict.
+//!
+//! The schema maps each key to a rule:
+//!
+//! - a type name such as `"Integer"`, `"String"` or `"Dict"`, or several
+//!   joined with `|` (`"String|None"`); `"Number"` is an integer or float
+//!   and `"Any"` accepts everything;
+//! - a dict, which is itself a schema for a nested dict;
+//! - a one-item list such as `["String"]`, for a list whose items all follow
+//!   that rule.
+//!
+//! Keys are required unless they end in `?`. Keys the schema doesn't mention
+//! are allowed. Problems with the data come back as a list of messages, empty
+//! when the data is valid; a malformed schema is an error.
+
+use crate::error::brief;
+use crate::object::{DictMap, Object};
+
+/// Names a rule can use besides `Any` and `Number`.
+const TYPE_NAMES: &[&str] = &[
+    "Integer", "Float", "Boolean", "String", "List", "Tuple", "Set", "Range", "Dict", "None", "Spell",
+    "Builtin", "Grimoire", "Instance", "Module",
+];
+
+/// `validate(data, schema)` — a list of messages describing how `data`
+/// breaks `schema`, in schema key order.
+pub fn builtin_validate(args: Vec<Object>) -> Result<Object, String> {
+    let [data, schema] = <[Object; 2]>::try_from(args).map_err(|args| {
+        format!("Wrong number of arguments. got={}, want=2", args.len())
+    })?;
+    if !matches!(schema, Object::Dict(_)) {
+        return Err(format!("validate expects a schema dict, got {}", schema));
+    }
+
+    check_rule(&schema, "")?;
+
+    let mut violations = Vec::new();
+    check(&data, &schema, "", &mut violations);
+    Ok(Object::list(violations.into_iter().map(|message| Object::String(message.into())).collect()))
+}
+
+/// Reject a malformed schema before any data is checked against it.
+fn check_rule(rule: &Object, path: &str) -> Result<(), String> {
+    match rule {
+        Object::String(types) => types.split('|').map(str::trim).try_for_each(|name| {
+            if name == "Any" || name == "Number" || TYPE_NAMES.contains(&name) {
+                Ok(())
+            } else {
+                Err(format!("validate: unknown type '{}' in the rule for {}", name, describe(path)))
+            }
+        }),
+        Object::Dict(schema) => schema.iter().try_for_each(|(key, rule)| match key {
+            Object::String(key) => check_rule(rule, &child_path(path, key.trim_end_matches('?'))),
+            other => Err(format!("validate: schema keys must be strings, got {} in the rule for {}", brief(other), describe(path))),
+        }),
+        Object::List(rules) if rules.len() == 1 => check_rule(&rules[0], &format!("{}[]", path)),
+        other => Err(format!(
+            "validate: the rule for {} must be a type name, a dict or a one-item list, got {}",
+            describe(path),
+            other
+        )),
+    }
+}
+
+fn check(value: &Object, rule: &Object, path: &str, violations: &mut Vec<String>) {
+    match (rule, value) {
+        (Object::String(types), _) => {
+            if !matches_type(value, types) {
+                violations.push(wrong_type(path, types, value));
+            }
+        }
+        (Object::Dict(schema), Object::Dict(map)) => check_dict(map, schema, path, violations),
+        (Object::List(rules), Object::List(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                check(item, &rules[0], &format!("{}[{}]", path, index), violations);
+            }
+        }
+        (Object::Dict(_), _) => violations.push(wrong_type(path, "Dict", value)),
+        _ => violations.push(wrong_type(path, "List", value)),
+    }
+}
+
+fn check_dict(map: &DictMap, schema: &DictMap, path: &str, violations: &mut Vec<String>) {
+    // `check_rule` has made sure every schema key is a string.
+    let mut rules: Vec<(&str, &Object)> = schema
+        .iter()
+        .filter_map(|(key, rule)| match key {
+            Object::String(key) => Some((key.as_str(), rule)),
+            _ => None,
+        })
+        .collect();
+    rules.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, rule) in rules {
+        let (name, optional) = match key.strip_suffix('?') {
+            Some(name) => (name, true),
+            None => (key, false),
+        };
+        let child = child_path(path, name);
+        match map.get(&Object::String(name.into())) {
+            Some(value) => check(value, rule, &child, violations),
+            None if optional => {}
+            None => violations.push(format!("missing required key '{}'", child)),
+        }
+    }
+}
+
+/// Whether `value` is one of the `|`-separated types in `types`.
+fn matches_type(value: &Object, types: &str) -> bool {
+    types.split('|').map(str::trim).any(|name| match name {
+        "Any" => true,
+        "Number" => matches!(value, Object::Integer(_) | Object::Float(_)),
+        name => value.type_name() == name,
+    })
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn wrong_type(path: &str, expected: &str, value: &Object) -> String {
+    format!("{} should be {}, got {}", describe(path), expected, value.type_name())
+}
+
+fn describe(path: &str) -> String {
+    if path.is_empty() {
+        "the data".to_string()
+    } else {
+        format!("'{}'", path)
+    }
+}
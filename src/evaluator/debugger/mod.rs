@@ -0,0 +1,83 @@
+//! `breakpoint()`: pause a script and inspect it from a small REPL.
+//!
+//! `breakpoint()` only marks the pause as pending; the statement loop then
+//! opens the session once the current statement finishes, in the scope that
+//! statement ran in. Input comes from a reader the CLI installs with
+//! [`set_reader`]; without one (for example when Carrion is embedded),
+//! `breakpoint()` does nothing.
+
+use super::environment::Environment;
+use super::watchdog;
+use crate::object::Object;
+use crate::error::{self, CarrionError, SourceMap};
+use crate::{lexer, parser};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Reads one line of input after showing the prompt; `None` ends the session.
+pub type LineReader = Box<dyn FnMut(&str) -> Option<String>>;
+
+pub const PROMPT: &str = "(break) ";
+
+thread_local! {
+    static READER: RefCell<Option<LineReader>> = RefCell::new(None);
+    static PENDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Enable `breakpoint()` on this thread, reading commands from `reader`.
+pub fn set_reader(reader: LineReader) {
+    READER.with(|slot| *slot.borrow_mut() = Some(reader));
+}
+
+/// Pause after the current statement, if a reader is installed.
+pub fn request() {
+    if READER.with(|slot| slot.borrow().is_some()) {
+        PENDING.with(|pending| pending.set(true));
+    }
+}
+
+/// Run a pending session in `env`. Called after every statement.
+pub fn check(env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+    if !PENDING.with(|pending| pending.replace(false)) {
+        return Ok(());
+    }
+    // Take the reader so breakpoints hit while evaluating input are ignored.
+    let Some(mut reader) = READER.with(|slot| slot.borrow_mut().take()) else {
+        return Ok(());
+    };
+    let result = session(env, &mut reader);
+    READER.with(|slot| *slot.borrow_mut() = Some(reader));
+    result
+}
+
+/// Evaluate lines in `env` until `:continue` or the end of input.
+/// Expressions can read and assign any variable visible from `env`.
+fn session(env: &Rc<RefCell<Environment>>, reader: &mut LineReader) -> Result<(), String> {
+    println!("Paused at breakpoint. Type :continue to resume, :locals to list variables, :abort to stop.");
+    while let Some(line) = watchdog::waiting(|| reader(PROMPT)) {
+        match line.trim() {
+            "" => {}
+            ":continue" | ":c" => break,
+            ":abort" => return Err("Aborted at breakpoint".to_string()),
+            ":locals" => println!("{}", env.borrow().local_names().join(", ")),
+            command if command.starts_with(':') => {
+                println!("Unknown command '{}'. Available: :continue, :locals, :abort", command);
+            }
+            source => {
+                let mut lexer = lexer::Lexer::new(source.to_owned(), "<breakpoint>".into());
+                let mut parser = parser::Parser::new(lexer.scan_tokens());
+                let program = parser.parse_program();
+                if !parser.errors().is_empty() {
+                    eprintln!("Parsing Error(s): {}", parser::diagnostic::join(parser.errors(), "; "));
+                    continue;
+                }
+                match super::eval_with_env(&program, env) {
+                    Ok(Object::None) => {}
+                    Ok(value) => println!("{}", value),
+                    Err(e) => eprintln!("{}", error::render_diagnostic(&CarrionError::evaluation(e), &SourceMap::new())),
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,59 +1,116 @@
-use crate::object::Object;
-use std::collections::HashMap;
+use crate::object::{Builtin, Map, Object};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+thread_local! {
+    /// The builtins, built once per thread. Global scopes fall back to them
+    /// for names they don't define instead of each holding a copy.
+    static BUILTINS: Map<Object> = {
+        let mut builtins: Map<Object> = super::builtins::BUILTINS
+            .iter()
+            .map(|(name, func)| (name.to_string(), Object::Builtin(Builtin::new(*func))))
+            .collect();
+        builtins.insert("log".to_string(), super::builtins::log::namespace());
+        builtins.insert("VERSION".to_string(), Object::String(super::builtins::info::VERSION.into()));
+        // `builtins.len` reaches `len` even where a script has reassigned it.
+        let namespace = Object::dict(builtins.clone());
+        builtins.insert(NAMESPACE.to_string(), namespace);
+        builtins
+    };
+}
+
+/// The name of the dict of builtins, which can't be assigned to.
+pub const NAMESPACE: &str = "builtins";
+
+/// Whether `name` is a builtin, such as `len`, `log` or `builtins`.
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.with(|builtins| builtins.contains_key(name))
+}
+
+/// Fail if `name` can't be assigned to: `builtins`, so that it always
+/// reaches the builtins.
+pub fn check_assignable(name: &str) -> Result<(), String> {
+    if name == NAMESPACE {
+        return Err(cannot_assign_namespace());
+    }
+    Ok(())
+}
+
+/// Why `builtins` can't be assigned to; strict mode warns with it too.
+pub fn cannot_assign_namespace() -> String {
+    format!("'{}' can't be assigned to; it always holds the builtins", NAMESPACE)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Environment {
-    store: HashMap<String, Object>,
+    store: Map<Object>,
+    outer: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
+    /// A global scope: empty, with the builtins behind it.
     pub fn new() -> Self {
-        let mut store = HashMap::new();
-        // --- PRE-LOAD BUILT-IN FUNCTIONS ---
-        store.insert(
-            "print".to_string(),
-            Object::Builtin(crate::object::Builtin {
-                func: super::builtins::builtin_print,
-            }), // Assuming builtins.rs
-        );
-        store.insert(
-            "len".to_string(),
-            Object::Builtin(crate::object::Builtin {
-                func: super::builtins::builtin_length,
-            }),
-        );
-        store.insert(
-            "push".to_string(),
-            Object::Builtin(crate::object::Builtin {
-                func: super::builtins::builtin_push,
-            }),
-        );
-        store.insert(
-            "pop".to_string(),
-            Object::Builtin(crate::object::Builtin {
-                func: super::builtins::builtin_pop,
-            }),
-        );
-        store.insert(
-            "keys".to_string(),
-            Object::Builtin(crate::object::Builtin {
-                func: super::builtins::builtin_keys,
-            }),
-        );
-        store.insert(
-            "values".to_string(),
-            Object::Builtin(crate::object::Builtin {
-                func: super::builtins::builtin_values,
-            }),
-        );
-
-        Self { store }
-    }
-
-    pub fn get(&self, name: &str) -> Option<&Object> {
-        self.store.get(name)
-    }
-
-    pub fn set(&mut self, name: String, val: Object) {
-        self.store.insert(name, val);
+        Self {
+            store: Map::default(),
+            outer: None,
+        }
+    }
+
+    /// Create a child scope (e.g. a spell call frame) that falls back to
+    /// `outer` for any name it does not define itself.
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            store: Map::default(),
+            outer: Some(outer),
+        }
+    }
+
+    /// How many scopes are chained here, counting this one and the global scope.
+    pub fn depth(&self) -> usize {
+        1 + self.outer.as_ref().map_or(0, |outer| outer.borrow().depth())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match (self.store.get(name), &self.outer) {
+            (Some(val), _) => Some(val.clone()),
+            (None, Some(outer)) => outer.borrow().get(name),
+            (None, None) => BUILTINS.with(|builtins| builtins.get(name).cloned()),
+        }
+    }
+
+    /// Look up `name` in this scope only, ignoring enclosing scopes.
+    pub fn get_local(&self, name: &str) -> Option<Object> {
+        self.store.get(name).cloned()
+    }
+
+    /// Move the value of a local binding out, leaving `None` in its place.
+    pub fn take_local(&mut self, name: &str) -> Option<Object> {
+        self.store
+            .get_mut(name)
+            .map(|slot| std::mem::replace(slot, Object::None))
+    }
+
+    /// Names bound directly in this scope, sorted.
+    pub fn local_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.store.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Bind `name` in this scope. Rebinding a name that is already local
+    /// reuses its key, so loops like `i += 1` don't allocate.
+    pub fn set<N: AsRef<str> + Into<String>>(&mut self, name: N, val: Object) {
+        match self.store.get_mut(name.as_ref()) {
+            Some(slot) => *slot = val,
+            None => {
+                self.store.insert(name.into(), val);
+            }
+        }
     }
 }
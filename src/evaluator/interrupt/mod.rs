@@ -0,0 +1,60 @@
+//! Cooperative cancellation for running scripts.
+//!
+//! Ctrl+C only sets a flag; the evaluator polls it between statements with
+//! [`check`], runs the spell registered through `on_interrupt`, and then
+//! unwinds with an "Interrupted" error. A second Ctrl+C while the first is
+//! still pending (for example inside a blocking builtin) exits immediately.
+
+use crate::object::Object;
+use std::cell::RefCell;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const INTERRUPTED: &str = "Interrupted";
+
+static PENDING: AtomicBool = AtomicBool::new(false);
+static INSTALL: Once = Once::new();
+
+thread_local! {
+    static HANDLER: RefCell<Option<Object>> = const { RefCell::new(None) };
+}
+
+/// Mark the running script as interrupted, as Ctrl+C does.
+pub fn request() {
+    PENDING.store(true, Ordering::SeqCst);
+}
+
+/// Register `handler` to run on Ctrl+C, installing the signal handler on first use.
+pub fn set_handler(handler: Object) -> Result<(), String> {
+    let mut installed = Ok(());
+    INSTALL.call_once(|| {
+        installed = ctrlc::set_handler(|| {
+            if PENDING.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+        })
+        .map_err(|e| format!("Could not install interrupt handler: {}", e));
+    });
+    installed?;
+    HANDLER.with(|h| *h.borrow_mut() = Some(handler));
+    Ok(())
+}
+
+/// Fail with [`INTERRUPTED`] if an interrupt is pending, after running the cleanup handler.
+///
+/// Only threads that registered a handler consume the flag; without one,
+/// Ctrl+C keeps its default behaviour of ending the process.
+pub fn check() -> Result<(), String> {
+    if !PENDING.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let Some(handler) = HANDLER.with(|h| h.borrow().clone()) else {
+        return Ok(());
+    };
+    PENDING.store(false, Ordering::SeqCst);
+
+    if let Err(e) = super::apply_function(handler, Vec::new()) {
+        eprintln!("Interrupt handler failed: {}", e);
+    }
+    Err(INTERRUPTED.to_string())
+}
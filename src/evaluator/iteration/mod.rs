@@ -0,0 +1,111 @@
+//! The iteration protocol: what `for` loops and builtins like `sum` walk over.
+//!
+//! [`iterate`] turns a value into a [`CarrionIterator`]: the elements of a
+//! list, tuple, set or range, the characters of a string, the keys of a
+//! dict, whatever a native object's [`NativeObject::items`] returns, or the
+//! values a grimoire instance produces. An instance is iterable when its
+//! grimoire defines `__iter__`, which returns the value to iterate (often
+//! `self`), or `__next__`, which returns the next item each time it is
+//! called and `None` once there are no more. Everything that consumes items
+//! goes through here, so a new iterable type needs only an arm in
+//! [`iterate`].
+//!
+//! [`NativeObject::items`]: crate::object::NativeObject::items
+
+use crate::error::brief;
+use crate::object::{DictMap, Object};
+use std::rc::Rc;
+
+/// A source of items. Unlike [`Iterator`], getting the next item can fail,
+/// because it may run a spell.
+pub trait CarrionIterator {
+    /// The next item, or `None` once the iterator is exhausted.
+    fn next(&mut self) -> Result<Option<Object>, String>;
+}
+
+impl<I: Iterator<Item = Object>> CarrionIterator for I {
+    fn next(&mut self) -> Result<Option<Object>, String> {
+        Ok(Iterator::next(self))
+    }
+}
+
+/// The items of an iterable value. It owns what it walks, so the loop body
+/// can reassign the variable that held the value.
+pub type Items = Box<dyn CarrionIterator>;
+
+pub fn iterate(value: &Object) -> Result<Items, String> {
+    match value {
+        Object::List(elements) | Object::Tuple(elements) => {
+            let elements = Rc::clone(elements);
+            Ok(Box::new((0..elements.len()).map(move |index| elements[index].clone())))
+        }
+        Object::Set(elements) => {
+            let elements = Rc::clone(elements);
+            Ok(Box::new((0..elements.len()).map(move |index| elements[index].clone())))
+        }
+        Object::Range(range) => Ok(Box::new(range.iter().map(Object::Integer))),
+        Object::String(s) => Ok(Box::new(s.chars_shared().collect::<Vec<_>>().into_iter().map(Object::String))),
+        Object::Dict(map) => Ok(Box::new(
+            map.keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )),
+        Object::Native(native) if let Some(items) = native.0.items() => Ok(Box::new(items.into_iter())),
+        Object::Instance(instance) => {
+            let (has_iter, has_next) = {
+                let grimoire = &instance.borrow().grimoire;
+                (grimoire.method("__iter__").is_some(), grimoire.method("__next__").is_some())
+            };
+            if has_iter {
+                let produced = call_method(value, "__iter__")?;
+                if produced != *value {
+                    return iterate(&produced);
+                }
+            }
+            if has_next {
+                return Ok(Box::new(Stepper(value.clone())));
+            }
+            Err(format!(
+                "Object is not iterable: {} (its grimoire defines no __next__ spell)",
+                brief(value)
+            ))
+        }
+        other => Err(format!("Object is not iterable: {}", brief(other))),
+    }
+}
+
+/// `(key, value)` tuples of a dict, for `for key, value in dict:`.
+pub fn pairs(map: &DictMap) -> Items {
+    Box::new(
+        map.iter()
+            .map(|(key, value)| Object::tuple(vec![key.clone(), value.clone()]))
+            .collect::<Vec<_>>()
+            .into_iter(),
+    )
+}
+
+/// Every item of `value`, in order.
+pub fn collect(value: &Object) -> Result<Vec<Object>, String> {
+    let mut items = iterate(value)?;
+    let mut collected = Vec::new();
+    while let Some(item) = items.next()? {
+        collected.push(item);
+    }
+    Ok(collected)
+}
+
+/// An instance whose `__next__` spell yields the items.
+struct Stepper(Object);
+
+impl CarrionIterator for Stepper {
+    fn next(&mut self) -> Result<Option<Object>, String> {
+        let item = call_method(&self.0, "__next__")?;
+        Ok((item != Object::None).then_some(item))
+    }
+}
+
+fn call_method(receiver: &Object, name: &str) -> Result<Object, String> {
+    let method = super::eval_member_expression(receiver.clone(), name)?;
+    super::apply_function(method, Vec::new())
+}
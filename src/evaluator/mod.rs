@@ -1,23 +1,70 @@
 pub mod builtins;
+pub mod debugger;
 pub mod environment;
+pub mod interrupt;
+pub mod iteration;
+pub mod modules;
+pub mod overloading;
+pub mod purity;
+pub mod slow;
+pub mod snippets;
+pub mod stack;
+pub mod stats;
+pub mod trace;
+pub mod watchdog;
 
-use crate::ast::{Expression, Identifier, Operator, Program, Statement, Assignment, CompoundAssignment, IfStatement, WhileStatement, ForStatement, BlockStatement};
-use crate::object::{Builtin, BuiltinFunction, Function, Object};
+use crate::ast::{Assignment, CallExpression, Expression, FunctionDefinition, Identifier, IndexExpression, KeywordArgument, Operator, Program, Statement, IfStatement, MemberExpression, SpannedStatement, WhileStatement, ForStatement, MatchStatement, Pattern};
+use crate::error::brief;
+use crate::object::{BoundMethod, Builtin, BuiltinFunction, Function, Grimoire, Instance, Object};
+use crate::strict;
+use std::collections::HashMap;
 use environment::Environment;
+pub use snippets::eval_string;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The binding that holds a grimoire's parent for `super`. `super` is a
+/// keyword, so no script can assign or read it directly.
+const SUPER: &str = "super";
+
+/// The name error traces give a spell literal.
+const ANONYMOUS: &str = "<anonymous>";
 
 pub fn eval(program: &Program) -> Result<Object, String> {
-    let mut env = Environment::new();
-    eval_program(program, &mut env)
+    let env = Rc::new(RefCell::new(Environment::new()));
+    eval_program(program, &env)
 }
 
-pub fn eval_with_env(program: &Program, env: &mut Environment) -> Result<Object, String> {
+pub fn eval_with_env(program: &Program, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
     eval_program(program, env)
 }
 
-fn eval_program(program: &Program, env: &mut Environment) -> Result<Object, String> {
+/// Call the `main` spell a script defines, the way running it from the CLI
+/// does once its top level has run: with `args` as a list of strings, or
+/// with nothing if `main` takes no parameters. `None` when there is no
+/// `main` spell, as in a file meant only to be imported.
+pub fn call_main(env: &Rc<RefCell<Environment>>, args: &[String]) -> Result<Option<Object>, String> {
+    let Some(main @ Object::Function(_)) = env.borrow().get_local("main") else {
+        return Ok(None);
+    };
+    let takes_args = matches!(&main, Object::Function(f) if !f.parameters.is_empty() || f.rest.is_some());
+    let args = if takes_args {
+        vec![Object::list(args.iter().map(|arg| Object::String(arg.as_str().into())).collect())]
+    } else {
+        Vec::new()
+    };
+    apply_function(main, args).map(Some)
+}
+
+fn eval_program(program: &Program, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    if stats::is_enabled() {
+        stats::env_depth(env.borrow().depth());
+    }
     let mut result = Object::None;
     for statement in &program.statements {
-        let value = eval_statement(statement, env)?;
+        let value = slow::time(&statement.span, || eval_statement(statement, env))
+            .inspect_err(|e| stack::statement_failed(e, &statement.span))?;
+        debugger::check(env)?;
 
         if let Object::ReturnValue(return_val) = value {
             return Ok(*return_val);
@@ -27,7 +74,9 @@ fn eval_program(program: &Program, env: &mut Environment) -> Result<Object, Stri
     Ok(result)
 }
 
-fn eval_statement(statement: &Statement, env: &mut Environment) -> Result<Object, String> {
+fn eval_statement(statement: &Statement, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    interrupt::check()?;
+    stats::statement();
     match statement {
         Statement::Expression(expr_stmt) => eval_expression(expr_stmt, env),
         Statement::Return(ret_stmt) => {
@@ -38,128 +87,182 @@ fn eval_statement(statement: &Statement, env: &mut Environment) -> Result<Object
             Ok(Object::ReturnValue(Box::new(value)))
         }
         Statement::Assignment(assignment) => {
+            if let Some(result) = eval_list_update(assignment, env) {
+                return result;
+            }
+
             // Evaluate the right-hand side value
             let value = eval_expression(&assignment.value, env)?;
             
             // Handle single assignment
-            if assignment.targets.len() == 1 {
-                if let Expression::Identifier(ident) = &assignment.targets[0] {
-                    env.set(ident.0.clone(), value.clone());
-                    Ok(value)
-                } else {
-                    Err("Assignment target must be an identifier".to_string())
-                }
-            } else {
-                // Handle multiple assignment (unpacking)
-                match &value {
-                    Object::List(values) => {
-                        if values.len() != assignment.targets.len() {
-                            return Err(format!(
-                                "Assignment count mismatch: {} targets but {} values",
-                                assignment.targets.len(),
-                                values.len()
-                            ));
-                        }
-                        
-                        for (i, target) in assignment.targets.iter().enumerate() {
-                            if let Expression::Identifier(ident) = target {
-                                env.set(ident.0.clone(), values[i].clone());
-                            } else {
-                                return Err("Assignment target must be an identifier".to_string());
-                            }
-                        }
-                        Ok(value)
-                    }
-                    _ => {
-                        // If it's not a list, assign the same value to all targets
-                        for target in &assignment.targets {
-                            if let Expression::Identifier(ident) = target {
-                                env.set(ident.0.clone(), value.clone());
-                            } else {
-                                return Err("Assignment target must be an identifier".to_string());
-                            }
-                        }
-                        Ok(value)
-                    }
-                }
+            match assignment.targets.as_slice() {
+                [target] if !matches!(target, Expression::Unpack(_)) => assign(target, value.clone(), env)?,
+                targets => assign_unpacked(targets, &value, env, 1)?,
             }
+            Ok(value)
         }
         Statement::CompoundAssignment(compound_assignment) => {
-            // Get the current value of the target
-            if let Expression::Identifier(ident) = &compound_assignment.target {
-                let current_value = env.get(&ident.0)
-                    .ok_or_else(|| format!("Undefined variable: {}", ident.0))?
-                    .clone();
-                    
-                // Evaluate the right-hand side
+            let (_, new_value) = update_target(&compound_assignment.target, env, "Compound assignment", |current| {
                 let rhs_value = eval_expression(&compound_assignment.value, env)?;
-                
-                // Perform the compound operation
-                let new_value = eval_infix_expression(
-                    &compound_assignment.operator,
-                    current_value,
-                    rhs_value
-                )?;
-                
-                // Set the new value
-                env.set(ident.0.clone(), new_value.clone());
-                Ok(new_value)
-            } else {
-                Err("Compound assignment target must be an identifier".to_string())
-            }
+                eval_infix_expression(&compound_assignment.operator, current, rhs_value)
+            })?;
+            Ok(new_value)
         }
         Statement::If(if_stmt) => eval_if_statement(if_stmt, env),
         Statement::While(while_stmt) => eval_while_statement(while_stmt, env),
         Statement::For(for_stmt) => eval_for_statement(for_stmt, env),
-        _ => Err(format!(
-            "Evaluation for this statement type is not yet implemented: {:?}",
-            statement
-        )),
+        Statement::Together(together) => builtins::tasks::together(|| eval_block_statement(&together.body, env)),
+        Statement::Match(match_stmt) => eval_match_statement(match_stmt, env),
+        Statement::Import(import) => modules::eval_import(import, env),
+        Statement::FunctionDefinition(func_def) => {
+            let function = Object::Function(Function {
+                name: func_def.name.0.as_str().into(),
+                parameters: Rc::clone(&func_def.parameters),
+                rest: func_def.rest.clone(),
+                keywords: func_def.keywords.clone(),
+                body: Rc::clone(&func_def.body),
+                env: Rc::clone(env),
+                pure: func_def.pure,
+            });
+            environment::check_assignable(&func_def.name.0)?;
+            env.borrow_mut().set(func_def.name.0.clone(), function);
+            Ok(Object::None)
+        }
+        Statement::Grimoire(grim_def) => {
+            let parent = match &grim_def.parent {
+                Some(expr) => match eval_expression(expr, env)? {
+                    Object::Grimoire(parent) => Some(parent),
+                    other => {
+                        return Err(format!(
+                            "Grimoire {} can only inherit from a grimoire, got {}",
+                            grim_def.name.0,
+                            brief(&other)
+                        ))
+                    }
+                },
+                None => None,
+            };
+            // The spells of a grimoire with a parent close over a scope that
+            // holds the parent, which is where `super` finds it.
+            let scope = match &parent {
+                Some(parent) => {
+                    let mut scope = Environment::new_enclosed(Rc::clone(env));
+                    scope.set(SUPER, Object::Grimoire(Rc::clone(parent)));
+                    Rc::new(RefCell::new(scope))
+                }
+                None => Rc::clone(env),
+            };
+            let spells = |definitions: &[FunctionDefinition]| -> HashMap<String, Function> {
+                definitions
+                    .iter()
+                    .map(|method| {
+                        let function = Function {
+                            name: format!("{}.{}", grim_def.name.0, method.name.0).into(),
+                            parameters: Rc::clone(&method.parameters),
+                            rest: method.rest.clone(),
+                            keywords: method.keywords.clone(),
+                            body: Rc::clone(&method.body),
+                            env: Rc::clone(&scope),
+                            pure: method.pure,
+                        };
+                        (method.name.0.clone(), function)
+                    })
+                    .collect()
+            };
+            let mut attributes = crate::object::Map::default();
+            for (name, value) in &grim_def.attributes {
+                attributes.insert(name.0.clone(), eval_expression(value, env)?);
+            }
+            let grimoire = Object::Grimoire(Rc::new(Grimoire {
+                name: grim_def.name.0.clone(),
+                methods: spells(&grim_def.methods),
+                parent,
+                arcane: grim_def.arcane,
+                arcane_spells: grim_def.arcane_spells.iter().map(|spell| spell.0.clone()).collect(),
+                static_spells: spells(&grim_def.static_spells),
+                getters: spells(&grim_def.getters),
+                setters: spells(&grim_def.setters),
+                attributes: RefCell::new(attributes),
+            }));
+            environment::check_assignable(&grim_def.name.0)?;
+            env.borrow_mut().set(grim_def.name.0.clone(), grimoire);
+            Ok(Object::None)
+        }
     }
 }
 
-fn eval_expression(expression: &Expression, env: &mut Environment) -> Result<Object, String> {
+fn eval_expression(expression: &Expression, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
     match expression {
         Expression::Identifier(ident) => eval_identifier(ident, env),
         Expression::IntegerLiteral(val) => Ok(Object::Integer(*val)),
         Expression::FloatLiteral(val) => Ok(Object::Float(*val)),
         Expression::BooleanLiteral(val) => Ok(Object::Boolean(*val)),
         Expression::StringLiteral(val) => Ok(Object::String(val.clone())),
+        Expression::NoneLiteral => Ok(Object::None),
 
+        Expression::Prefix(prefix_expr) if matches!(prefix_expr.operator, Operator::Increment | Operator::Decrement) => {
+            eval_step(&prefix_expr.operator, &prefix_expr.right, true, env)
+        }
+        Expression::Postfix(postfix_expr) => eval_step(&postfix_expr.operator, &postfix_expr.left, false, env),
         Expression::Prefix(prefix_expr) => {
             let right = eval_expression(&prefix_expr.right, env)?;
             eval_prefix_expression(&prefix_expr.operator, right)
         }
         Expression::Infix(infix_expr) => {
             let left = eval_expression(&infix_expr.left, env)?;
+            // `and`/`or` stop at the first operand that decides the result
+            // and return that operand itself, e.g. `name or "anonymous"`.
+            match infix_expr.operator {
+                Operator::And if !is_truthy(left.clone()) => return Ok(left),
+                Operator::Or if is_truthy(left.clone()) => return Ok(left),
+                Operator::And | Operator::Or => return eval_expression(&infix_expr.right, env),
+                _ => {}
+            }
             let right = eval_expression(&infix_expr.right, env)?;
             eval_infix_expression(&infix_expr.operator, left, right)
         }
         Expression::Call(call_expr) => {
-            let function_obj = eval_expression(&call_expr.function, env)?;
-            let mut args = Vec::new();
-            for arg_expr in &call_expr.arguments {
-                args.push(eval_expression(arg_expr, env)?);
-            }
-            apply_function(function_obj, args)
+            let function_obj = match &*call_expr.function {
+                // `object?.spell(...)` is `none`, without evaluating the
+                // arguments, when the object is.
+                Expression::Member(member_expr) if member_expr.optional => {
+                    let object = eval_expression(&member_expr.object, env)?;
+                    if matches!(object, Object::None) {
+                        return Ok(Object::None);
+                    }
+                    eval_member_expression(object, &member_expr.property.0)?
+                }
+                function => eval_expression(function, env)?,
+            };
+            eval_call(call_expr, function_obj, env)
         }
         Expression::List(elements) => {
             let mut list_objects = Vec::new();
             for elem in elements {
                 list_objects.push(eval_expression(elem, env)?);
             }
-            Ok(Object::List(list_objects))
+            Ok(Object::list(list_objects))
+        }
+        Expression::Tuple(elements) => {
+            let mut items = Vec::new();
+            for elem in elements {
+                items.push(eval_expression(elem, env)?);
+            }
+            Ok(Object::tuple(items))
+        }
+        Expression::Set(elements) => {
+            let mut items = Vec::new();
+            for elem in elements {
+                items.push(eval_expression(elem, env)?);
+            }
+            builtins::sets::from_items(items)
         }
         Expression::Dict { pairs } => {
-            let mut dict_map = std::collections::HashMap::new();
+            let mut dict_map = crate::object::DictMap::default();
             for (key_expr, value_expr) in pairs {
-                let key_obj = eval_expression(key_expr, env)?;
-                let key_str = match key_obj {
-                    Object::String(s) => s,
-                    _ => key_obj.to_string(),
-                };
+                let key = dict_key(&eval_expression(key_expr, env)?)?;
                 let value_obj = eval_expression(value_expr, env)?;
-                dict_map.insert(key_str, value_obj);
+                dict_map.insert(key, value_obj);
             }
             Ok(Object::Dict(dict_map))
         }
@@ -168,6 +271,35 @@ fn eval_expression(expression: &Expression, env: &mut Environment) -> Result<Obj
             let index = eval_expression(&index_expr.index, env)?;
             eval_index_expression(object, index)
         }
+        Expression::Member(member_expr) if matches!(*member_expr.object, Expression::Super) => {
+            eval_super_member(&member_expr.property.0, env)
+        }
+        Expression::Member(member_expr) => {
+            let object = eval_expression(&member_expr.object, env)?;
+            if member_expr.optional && matches!(object, Object::None) {
+                return Ok(Object::None);
+            }
+            eval_member_expression(object, &member_expr.property.0)
+        }
+        Expression::Super => Err("'super' must name a spell, as in super.init(...)".to_string()),
+        Expression::Maybe(maybe) => Ok(eval_maybe(&maybe.value, env)?.unwrap_or(Object::None)),
+        Expression::Propagate(propagate) => builtins::outcome::propagate(eval_expression(&propagate.value, env)?),
+        Expression::Conditional(conditional) => {
+            if condition(eval_expression(&conditional.condition, env)?)? {
+                eval_expression(&conditional.consequence, env)
+            } else {
+                eval_expression(&conditional.alternative, env)
+            }
+        }
+        Expression::Function(literal) => Ok(Object::Function(Function {
+            name: ANONYMOUS.into(),
+            parameters: Rc::clone(&literal.parameters),
+            rest: literal.rest.clone(),
+            keywords: literal.keywords.clone(),
+            body: Rc::clone(&literal.body),
+            env: Rc::clone(env),
+            pure: false,
+        })),
         _ => Err(format!(
             "Evaluation for this expression type is not yet implemented: {:?}",
             expression
@@ -175,25 +307,300 @@ fn eval_expression(expression: &Expression, env: &mut Environment) -> Result<Obj
     }
 }
 
-fn apply_function(func: Object, args: Vec<Object>) -> Result<Object, String> {
+/// Call `function_obj`, which `call_expr` names, with the call's arguments.
+fn eval_call(call_expr: &CallExpression, function_obj: Object, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let args = eval_arguments(&call_expr.arguments, env)?;
+    let keywords = eval_keyword_arguments(&call_expr.keywords, env)?;
+    if stats::is_enabled() {
+        count_builtin_call(&call_expr.function, &function_obj);
+    }
+    apply_function_with_keywords(function_obj, args, keywords)
+}
+
+/// Evaluate a `maybe` chain. `None` means a link was missing: an attribute
+/// or index that isn't there, or a link applied to `None`. Errors raised by
+/// the spells the chain calls, or by its arguments, still propagate.
+fn eval_maybe(expression: &Expression, env: &Rc<RefCell<Environment>>) -> Result<Option<Object>, String> {
+    let present = |value: Object| (value != Object::None).then_some(value);
+    match expression {
+        Expression::Member(member_expr) => match eval_maybe(&member_expr.object, env)? {
+            Some(object) => Ok(eval_member_expression(object, &member_expr.property.0).ok().and_then(present)),
+            None => Ok(None),
+        },
+        Expression::Index(index_expr) => match eval_maybe(&index_expr.object, env)? {
+            Some(object) => {
+                let index = eval_expression(&index_expr.index, env)?;
+                Ok(eval_index_expression(object, index).ok().and_then(present))
+            }
+            None => Ok(None),
+        },
+        Expression::Call(call_expr) => match eval_maybe(&call_expr.function, env)? {
+            Some(function_obj) => eval_call(call_expr, function_obj, env).map(present),
+            None => Ok(None),
+        },
+        other => eval_expression(other, env).map(present),
+    }
+}
+
+/// Evaluate a call's arguments, spreading `*items` into separate ones.
+fn eval_arguments(arguments: &[Expression], env: &Rc<RefCell<Environment>>) -> Result<Vec<Object>, String> {
+    let mut args = Vec::with_capacity(arguments.len());
+    for argument in arguments {
+        match argument {
+            Expression::Unpack(unpack) => match eval_expression(&unpack.value, env)? {
+                Object::List(items) | Object::Tuple(items) => args.extend(items.iter().cloned()),
+                other => {
+                    return Err(format!(
+                        "Only a list or tuple can be spread into arguments, got {}",
+                        other.type_name()
+                    ))
+                }
+            },
+            _ => args.push(eval_expression(argument, env)?),
+        }
+    }
+    Ok(args)
+}
+
+/// Evaluate a call's keyword arguments in order, spreading `**dict` into
+/// one per entry. A name may only be given once.
+fn eval_keyword_arguments(
+    keywords: &[KeywordArgument],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Vec<(String, Object)>, String> {
+    let mut values: Vec<(String, Object)> = Vec::with_capacity(keywords.len());
+    for keyword in keywords {
+        let value = eval_expression(&keyword.value, env)?;
+        let entries = match (&keyword.name, value) {
+            (Some(name), value) => vec![(name.0.clone(), value)],
+            (None, Object::Dict(map)) => {
+                let mut entries = Vec::with_capacity(map.len());
+                for (key, value) in map {
+                    match key {
+                        Object::String(name) => entries.push((name.to_string(), value)),
+                        other => return Err(format!("Keyword argument names must be strings, got {}", brief(&other))),
+                    }
+                }
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                entries
+            }
+            (None, other) => {
+                return Err(format!(
+                    "Only a dict can be spread into keyword arguments, got {}",
+                    other.type_name()
+                ))
+            }
+        };
+        for (name, value) in entries {
+            if values.iter().any(|(seen, _)| *seen == name) {
+                return Err(format!("Keyword argument '{}' given more than once", name));
+            }
+            values.push((name, value));
+        }
+    }
+    Ok(values)
+}
+
+/// Call a spell, builtin, grimoire or bound method with already-evaluated arguments.
+pub fn apply_function(func: Object, args: Vec<Object>) -> Result<Object, String> {
+    apply_function_with_keywords(func, args, Vec::new())
+}
+
+/// [`apply_function`] with keyword arguments, which spells (including
+/// methods and grimoires' `init`) and a few builtins, such as `log.info`,
+/// accept.
+pub fn apply_function_with_keywords(
+    func: Object,
+    args: Vec<Object>,
+    keywords: Vec<(String, Object)>,
+) -> Result<Object, String> {
+    let takes_keywords = match &func {
+        Object::Function(_) | Object::Grimoire(_) => true,
+        Object::Builtin(builtin) => builtin.keywords.is_some(),
+        Object::BoundMethod(bound) => matches!(bound.method, Object::Function(_)),
+        _ => false,
+    };
+    if !takes_keywords && let Some((name, _)) = keywords.first() {
+        return Err(format!("{} takes no keyword arguments, got '{}'", brief(&func), name));
+    }
     match func {
-        Object::Builtin(builtin) => (builtin.func)(args),
-        Object::Function(_user_func) => {
-            Err("User-defined function calls not yet implemented.".to_string())
+        Object::Builtin(builtin) => match builtin.keywords {
+            Some(with_keywords) if !keywords.is_empty() => with_keywords(args, keywords),
+            _ => (builtin.func)(args),
+        },
+        Object::Function(user_func) => call_function(&user_func, args, keywords, None),
+        Object::Grimoire(grimoire) => instantiate(grimoire, args, keywords),
+        Object::BoundMethod(bound) => match &bound.method {
+            Object::Function(method) => call_function(method, args, keywords, Some(bound.receiver.clone())),
+            Object::Builtin(builtin) => {
+                let mut full_args = Vec::with_capacity(args.len() + 1);
+                full_args.push(bound.receiver.clone());
+                full_args.extend(args);
+                (builtin.func)(full_args)
+            }
+            other => Err(format!("Not a function: {}", brief(other))),
+        },
+        Object::Native(native) => native
+            .0
+            .call(args)
+            .unwrap_or_else(|| Err(format!("Not a function: {}", native.0.display()))),
+        _ => Err(format!("Not a function: {}", brief(&func))),
+    }
+}
+
+/// Count a call to a builtin under the name it was called by: the
+/// identifier, or the member name for a method such as `name.upper()`.
+fn count_builtin_call(callee: &Expression, function: &Object) {
+    let is_builtin = match function {
+        Object::Builtin(_) => true,
+        Object::BoundMethod(bound) => matches!(bound.method, Object::Builtin(_)),
+        _ => false,
+    };
+    match callee {
+        Expression::Identifier(name) if is_builtin => stats::builtin_call(&name.0),
+        Expression::Member(member) if is_builtin => stats::builtin_call(&member.property.0),
+        _ => {}
+    }
+}
+
+/// Run a user-defined spell. `receiver` is bound to `self` for method calls.
+/// Positional arguments fill parameters first; keyword arguments fill the
+/// rest by name.
+fn call_function(
+    function: &Function,
+    args: Vec<Object>,
+    mut keywords: Vec<(String, Object)>,
+    receiver: Option<Object>,
+) -> Result<Object, String> {
+    match &function.rest {
+        None if args.len() > function.parameters.len() => {
+            return Err(format!(
+                "Wrong number of arguments. got={}, want={}",
+                args.len(),
+                function.parameters.len()
+            ));
+        }
+        _ if !keywords.is_empty() => {}
+        None if args.len() != function.parameters.len() => {
+            return Err(format!(
+                "Wrong number of arguments. got={}, want={}",
+                args.len(),
+                function.parameters.len()
+            ));
+        }
+        Some(_) if args.len() < function.parameters.len() => {
+            return Err(format!(
+                "Wrong number of arguments. got={}, want at least {}",
+                args.len(),
+                function.parameters.len()
+            ));
+        }
+        _ => {}
+    }
+
+    // Each call gets a fresh scope chained to the defining environment,
+    // so the spell keeps seeing the variables it closed over.
+    let call_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(
+        &function.env,
+    ))));
+    if stats::is_enabled() {
+        stats::env_depth(call_env.borrow().depth());
+    }
+    if let Some(receiver) = receiver {
+        call_env.borrow_mut().set("self".to_string(), receiver);
+    }
+    let mut args = args.into_iter();
+    for param in function.parameters.iter() {
+        let keyword = keywords
+            .iter()
+            .position(|(name, _)| *name == param.0)
+            .map(|i| keywords.remove(i).1);
+        let value = match (args.next(), keyword) {
+            (Some(_), Some(_)) => return Err(format!("Argument '{}' given both by position and by name", param.0)),
+            (Some(value), None) | (None, Some(value)) => value,
+            (None, None) => return Err(format!("Missing argument '{}'", param.0)),
+        };
+        call_env.borrow_mut().set(param.0.clone(), value);
+    }
+    if let Some(rest) = &function.rest {
+        call_env.borrow_mut().set(rest.0.clone(), Object::list(args.collect()));
+    }
+    match &function.keywords {
+        Some(name) => {
+            call_env.borrow_mut().set(name.0.clone(), Object::dict(keywords));
+        }
+        None => {
+            if let Some((name, _)) = keywords.first() {
+                return Err(format!("Unexpected keyword argument '{}'", name));
+            }
         }
-        _ => Err(format!("Not a function: {}", func)),
+    }
+
+    let result = if function.pure {
+        purity::run(&function.name, || eval_block_statement(&function.body, &call_env))
+    } else {
+        eval_block_statement(&function.body, &call_env)
+    };
+    match result {
+        Ok(Object::ReturnValue(value)) => Ok(*value),
+        Ok(_) => Ok(Object::None),
+        // An `err` returned early by `?` becomes the spell's result.
+        Err(e) => builtins::outcome::caught(&e).ok_or_else(|| {
+            stack::spell_failed(&e, &function.name);
+            e
+        }),
     }
 }
 
-fn eval_identifier(ident: &Identifier, env: &Environment) -> Result<Object, String> {
-    if let Some(val) = env.get(&ident.0) {
-        Ok(val.clone())
+/// Calling a grimoire creates an instance and runs its `init` spell, if any.
+fn instantiate(grimoire: Rc<Grimoire>, args: Vec<Object>, keywords: Vec<(String, Object)>) -> Result<Object, String> {
+    if grimoire.arcane {
+        return Err(format!(
+            "Cannot create an instance of arcane grimoire {}; create one of a grimoire that inherits from it",
+            grimoire.name
+        ));
+    }
+    let missing = grimoire.missing_overrides();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Cannot create an instance of grimoire {}; it must override the arcane spells: {}",
+            grimoire.name,
+            missing.join(", ")
+        ));
+    }
+    let instance = Rc::new(RefCell::new(Instance::new(Rc::clone(&grimoire))));
+    purity::made(&instance);
+    let instance = Object::Instance(instance);
+    match grimoire.method("init") {
+        Some(init) => {
+            call_function(init, args, keywords, Some(instance.clone()))?;
+        }
+        None if !args.is_empty() || !keywords.is_empty() => {
+            return Err(format!(
+                "Grimoire {} takes no arguments (it defines no init), got {}",
+                grimoire.name,
+                args.len() + keywords.len()
+            ));
+        }
+        None => {}
+    }
+    Ok(instance)
+}
+
+fn eval_identifier(ident: &Identifier, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    if let Some(val) = env.borrow().get(&ident.0) {
+        Ok(val)
     } else {
         Err(format!("Identifier not found: {}", ident.0))
     }
 }
 
 fn eval_prefix_expression(operator: &Operator, right: Object) -> Result<Object, String> {
+    if *operator == Operator::Minus
+        && let Some(result) = overloading::call_spell(&right, "__neg__", Vec::new())
+    {
+        return result;
+    }
     match operator {
         Operator::Not => Ok(Object::Boolean(!is_truthy(right))),
         Operator::Minus => {
@@ -202,9 +609,13 @@ fn eval_prefix_expression(operator: &Operator, right: Object) -> Result<Object,
             } else if let Object::Float(val) = right {
                 Ok(Object::Float(-val))
             } else {
-                Err(format!("Unknown operator: -{}", right))
+                Err(format!("Unknown operator: -{}", brief(&right)))
             }
         }
+        Operator::BitNot => match right {
+            Object::Integer(val) => Ok(Object::Integer(!val)),
+            _ => Err(format!("Unknown operator: ~{}", brief(&right))),
+        },
         _ => Err(format!("Unknown prefix operator: {:?}", operator)),
     }
 }
@@ -214,28 +625,76 @@ fn eval_infix_expression(
     left: Object,
     right: Object,
 ) -> Result<Object, String> {
+    if (matches!(left, Object::Instance(_)) || matches!(right, Object::Instance(_)))
+        && let Some(result) = overloading::binary_op(operator, &left, &right)
+    {
+        return result;
+    }
+    if *operator == Operator::In {
+        return eval_membership(&left, &right);
+    }
     match (&left, &right) {
         (Object::Integer(l), Object::Integer(r)) => eval_integer_infix_operator(operator, *l, *r),
+        (Object::Set(l), Object::Set(r)) => builtins::sets::binary_op(operator, l, r),
         (Object::Float(l), Object::Float(r)) => eval_float_infix_operator(operator, *l, *r),
-        (Object::String(l), Object::String(r)) => {
-            if *operator == Operator::Plus {
-                Ok(Object::String(format!("{}{}", l, r)))
-            } else {
-                Err(format!("Unknown operator for Strings: {:?}", operator))
-            }
-        }
+        (Object::String(l), Object::String(r)) => match operator {
+            Operator::Plus => Ok(Object::String(format!("{}{}", l, r).into())),
+            Operator::Equal => Ok(Object::Boolean(l.as_str() == r.as_str())),
+            Operator::NotEqual => Ok(Object::Boolean(l.as_str() != r.as_str())),
+            _ => builtins::ordering::binary_op(operator, &left, &right)
+                .unwrap_or_else(|| Err(format!("Unknown operator for Strings: {:?}", operator))),
+        },
+        (Object::Tuple(l), Object::Tuple(r)) => match operator {
+            Operator::Equal => Ok(Object::Boolean(l == r)),
+            Operator::NotEqual => Ok(Object::Boolean(l != r)),
+            _ => builtins::ordering::binary_op(operator, &left, &right)
+                .unwrap_or_else(|| Err(format!("Unknown operator for Tuples: {:?}", operator))),
+        },
+        (Object::List(l), Object::List(r)) => match operator {
+            Operator::Equal => Ok(Object::Boolean(l == r)),
+            Operator::NotEqual => Ok(Object::Boolean(l != r)),
+            _ => builtins::ordering::binary_op(operator, &left, &right)
+                .unwrap_or_else(|| Err(format!("Unknown operator for Lists: {:?}", operator))),
+        },
+        (Object::Range(l), Object::Range(r)) => match operator {
+            Operator::Equal => Ok(Object::Boolean(builtins::ranges::same_values(l, r))),
+            Operator::NotEqual => Ok(Object::Boolean(!builtins::ranges::same_values(l, r))),
+            _ => Err(format!("Unknown operator for Ranges: {:?}", operator)),
+        },
         (Object::Boolean(l), Object::Boolean(r)) => match operator {
             Operator::Equal => Ok(Object::Boolean(l == r)),
             Operator::NotEqual => Ok(Object::Boolean(l != r)),
             _ => Err(format!("Unknown operator for Booleans: {:?}", operator)),
         },
+        (Object::Native(native), other) if let Some(result) = native.0.binary_op(operator, other, false) => {
+            result
+        }
+        (other, Object::Native(native)) if let Some(result) = native.0.binary_op(operator, other, true) => {
+            result
+        }
         _ => Err(format!(
             "Type mismatch: cannot apply operator {:?} to {} and {}",
-            operator, left, right
+            operator,
+            brief(&left),
+            brief(&right)
         )),
     }
 }
 
+/// `item in container`: an element of a list, tuple or set, a substring of
+/// a string, or a key of a dict.
+fn eval_membership(item: &Object, container: &Object) -> Result<Object, String> {
+    let found = match (container, item) {
+        (Object::List(items) | Object::Tuple(items), item) => items.contains(item),
+        (Object::Set(items), item) => items.contains(item),
+        (Object::Range(range), item) => matches!(item, Object::Integer(value) if range.contains(*value)),
+        (Object::String(text), Object::String(part)) => text.contains(part.as_str()),
+        (Object::Dict(map), key) => map.contains_key(&dict_key(key)?),
+        (container, item) => return Err(format!("Cannot check whether {} is in {}", brief(item), brief(container))),
+    };
+    Ok(Object::Boolean(found))
+}
+
 fn eval_integer_infix_operator(
     operator: &Operator,
     left: i64,
@@ -246,6 +705,43 @@ fn eval_integer_infix_operator(
         Operator::Minus => Ok(Object::Integer(left - right)),
         Operator::Multiply => Ok(Object::Integer(left * right)),
         Operator::Divide => Ok(Object::Integer(left / right)),
+        Operator::Range => builtins::ranges::new(left, right, 1),
+        Operator::Mod => {
+            if right == 0 {
+                return Err("Modulo by zero".to_string());
+            }
+            // The result takes the sign of the divisor, so `-7 % 3 == 2`.
+            let rem = left.wrapping_rem(right);
+            let floored = if rem != 0 && (rem < 0) != (right < 0) { rem + right } else { rem };
+            Ok(Object::Integer(floored))
+        }
+        // A negative exponent gives a fraction, so the result is a float.
+        Operator::Exponent if right < 0 && left == 0 => {
+            Err(format!("Cannot raise 0 to a negative power ({})", right))
+        }
+        Operator::Exponent if right < 0 => Ok(Object::Float((left as f64).powf(right as f64))),
+        Operator::Exponent => u32::try_from(right)
+            .ok()
+            .and_then(|exponent| left.checked_pow(exponent))
+            .map(Object::Integer)
+            .ok_or_else(|| format!("Integer overflow: {} ** {}", left, right)),
+        Operator::BitAnd => Ok(Object::Integer(left & right)),
+        Operator::BitOr => Ok(Object::Integer(left | right)),
+        Operator::BitXor => Ok(Object::Integer(left ^ right)),
+        Operator::LeftShift | Operator::RightShift if right < 0 => {
+            Err(format!("Negative shift count: {}", right))
+        }
+        Operator::LeftShift => {
+            // Shifting out any significant bit would change the value.
+            let shifted = left.checked_shl(right.min(64) as u32).unwrap_or(0);
+            if shifted >> right.min(63) == left {
+                Ok(Object::Integer(shifted))
+            } else {
+                Err(format!("Integer overflow: {} << {}", left, right))
+            }
+        }
+        // Arithmetic shift: large counts leave 0, or -1 for negative numbers.
+        Operator::RightShift => Ok(Object::Integer(left >> right.min(63))),
         Operator::Equal => Ok(Object::Boolean(left == right)),
         Operator::NotEqual => Ok(Object::Boolean(left != right)),
         Operator::LessThan => Ok(Object::Boolean(left < right)),
@@ -262,6 +758,18 @@ fn eval_float_infix_operator(operator: &Operator, left: f64, right: f64) -> Resu
         Operator::Minus => Ok(Object::Float(left - right)),
         Operator::Multiply => Ok(Object::Float(left * right)),
         Operator::Divide => Ok(Object::Float(left / right)),
+        Operator::Mod => {
+            if right == 0.0 {
+                return Err("Modulo by zero".to_string());
+            }
+            let rem = left % right;
+            let floored = if rem != 0.0 && (rem < 0.0) != (right < 0.0) { rem + right } else { rem };
+            Ok(Object::Float(floored))
+        }
+        Operator::Exponent if left == 0.0 && right < 0.0 => {
+            Err(format!("Cannot raise 0 to a negative power ({})", right))
+        }
+        Operator::Exponent => Ok(Object::Float(left.powf(right))),
         Operator::Equal => Ok(Object::Boolean(left == right)),
         Operator::NotEqual => Ok(Object::Boolean(left != right)),
         Operator::LessThan => Ok(Object::Boolean(left < right)),
@@ -280,50 +788,490 @@ fn is_truthy(object: Object) -> bool {
     }
 }
 
+/// Whether the condition of an `if`, `otherwise`, `while` or conditional
+/// expression holds. Strict mode rejects conditions other than booleans and
+/// `None`, since `0`, `""` and `[]` would count as true.
+fn condition(value: Object) -> Result<bool, String> {
+    if !matches!(value, Object::Boolean(_) | Object::None) {
+        strict::coercion(|| {
+            format!("condition is {} {}, not a boolean; compare it explicitly", value.type_name(), brief(&value))
+        })?;
+    }
+    Ok(is_truthy(value))
+}
+
+/// `key` as a dict key, which must be hashable. Keys keep their type, so
+/// `1` and `"1"` are different keys.
+fn dict_key(key: &Object) -> Result<Object, String> {
+    if key.is_hashable() {
+        Ok(key.clone())
+    } else {
+        Err(format!("Unhashable type {} cannot be a dict key", key.type_name()))
+    }
+}
+
 fn eval_index_expression(object: Object, index: Object) -> Result<Object, String> {
+    if let Some(result) = overloading::call_spell(&object, "__index__", vec![index.clone()]) {
+        return result;
+    }
     match (&object, &index) {
-        (Object::List(elements), Object::Integer(idx)) => {
-            let idx = *idx as usize;
-            if idx < elements.len() {
-                Ok(elements[idx].clone())
-            } else {
-                Err(format!("Index out of bounds: {} (list length: {})", idx, elements.len()))
-            }
-        }
+        (Object::List(elements), Object::Integer(idx)) => match usize::try_from(*idx).ok().and_then(|i| elements.get(i)) {
+            Some(element) => Ok(element.clone()),
+            None => Err(format!("Index out of bounds: {} (list length: {})", idx, elements.len())),
+        },
+        (Object::Range(range), Object::Integer(idx)) => match usize::try_from(*idx).ok().and_then(|i| range.get(i)) {
+            Some(value) => Ok(Object::Integer(value)),
+            None => Err(format!("Index out of bounds: {} (range length: {})", idx, range.len())),
+        },
+        (Object::Tuple(elements), Object::Integer(idx)) => match usize::try_from(*idx).ok().and_then(|i| elements.get(i)) {
+            Some(element) => Ok(element.clone()),
+            None => Err(format!("Index out of bounds: {} (tuple length: {})", idx, elements.len())),
+        },
         (Object::Dict(map), key) => {
-            let key_str = match key {
-                Object::String(s) => s.clone(),
-                _ => key.to_string(),
-            };
-            if let Some(value) = map.get(&key_str) {
+            if let Some(value) = map.get(&dict_key(key)?) {
                 Ok(value.clone())
             } else {
                 Ok(Object::None)
             }
         }
-        (Object::String(s), Object::Integer(idx)) => {
-            let idx = *idx as usize;
-            let chars: Vec<char> = s.chars().collect();
-            if idx < chars.len() {
-                Ok(Object::String(chars[idx].to_string()))
-            } else {
-                Err(format!("Index out of bounds: {} (string length: {})", idx, chars.len()))
+        (Object::String(s), Object::Integer(idx)) => match usize::try_from(*idx).ok().and_then(|i| s.char_at(i)) {
+            Some(ch) => Ok(Object::String(ch)),
+            None => Err(format!(
+                "Index out of bounds: {} (string length: {})",
+                idx,
+                s.chars().count()
+            )),
+        },
+        _ => Err(format!("Index operation not supported for {} with index {}", brief(&object), brief(&index))),
+    }
+}
+
+/// Replace the value of `target` (a name, attribute or index) with what
+/// `update` makes of it, returning the old and new values. The parts of the
+/// target are evaluated once, so `make().count += 1` and `xs[i()] += 1` have
+/// no repeated side effects. `what` names the operation in errors.
+fn update_target(
+    target: &Expression,
+    env: &Rc<RefCell<Environment>>,
+    what: &str,
+    update: impl FnOnce(Object) -> Result<Object, String>,
+) -> Result<(Object, Object), String> {
+    match target {
+        Expression::Identifier(ident) => {
+            environment::check_assignable(&ident.0)?;
+            let current = env.borrow().get(&ident.0).ok_or_else(|| format!("Undefined variable: {}", ident.0))?;
+            let new_value = update(current.clone())?;
+            env.borrow_mut().set(&ident.0, new_value.clone());
+            Ok((current, new_value))
+        }
+        Expression::Member(member_expr) => {
+            let object = eval_expression(&member_expr.object, env)?;
+            let current = eval_member_expression(object.clone(), &member_expr.property.0)?;
+            let new_value = update(current.clone())?;
+            assign_member(object, member_expr, new_value.clone(), env)?;
+            Ok((current, new_value))
+        }
+        Expression::Index(index_expr) => {
+            let container = eval_expression(&index_expr.object, env)?;
+            let index = eval_expression(&index_expr.index, env)?;
+            let current = eval_index_expression(container, index.clone())?;
+            let new_value = update(current.clone())?;
+            assign_index(index_expr, Some(index), new_value.clone(), env)?;
+            Ok((current, new_value))
+        }
+        _ => Err(format!("{} target must be an identifier, index or attribute", what)),
+    }
+}
+
+/// `++x` and `x++` (or `--`): add one to (or subtract one from) the number
+/// in `target`. The prefix form is the new value, the postfix form the old.
+fn eval_step(operator: &Operator, target: &Expression, prefix: bool, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let (symbol, operation) = match operator {
+        Operator::Increment => ("++", Operator::Plus),
+        Operator::Decrement => ("--", Operator::Minus),
+        other => return Err(format!("Unknown step operator: {:?}", other)),
+    };
+    let (old, new) = update_target(target, env, &format!("'{}'", symbol), |current| {
+        let one = match current {
+            Object::Integer(_) | Object::Instance(_) => Object::Integer(1),
+            Object::Float(_) => Object::Float(1.0),
+            ref other => return Err(format!("'{}' needs a number, got {}", symbol, other.type_name())),
+        };
+        eval_infix_expression(&operation, current, one)
+    })?;
+    Ok(if prefix { new } else { old })
+}
+
+/// Store `value` into an assignment target. Lists and dicts are values, so
+/// `xs[i] = v` rebuilds the container and assigns it back to `xs`; nested
+/// targets like `grid[0][1] = v` work the same way, one level at a time.
+fn assign(target: &Expression, value: Object, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+    match target {
+        Expression::Identifier(ident) => {
+            environment::check_assignable(&ident.0)?;
+            env.borrow_mut().set(&ident.0, value);
+            Ok(())
+        }
+        Expression::Index(index_expr) => assign_index(index_expr, None, value, env),
+        Expression::Member(member_expr) => {
+            let object = eval_expression(&member_expr.object, env)?;
+            assign_member(object, member_expr, value, env)
+        }
+        Expression::Tuple(targets) | Expression::List(targets) => assign_unpacked(targets, &value, env, 1),
+        _ => Err("Assignment target must be an identifier, index, attribute or group of targets".to_string()),
+    }
+}
+
+/// `xs[i] = value`, with `index` the value of `i` if it was already
+/// evaluated. When the target starts from a name in this scope, as in
+/// `xs[i]` or `grid[i][j]`, the name's value is moved out of the scope while
+/// the element is stored, so a list or dict that nothing else shares is
+/// changed in place instead of copied on every assignment.
+fn assign_index(
+    index_expr: &IndexExpression,
+    index: Option<Object>,
+    value: Object,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), String> {
+    let mut indices = vec![&*index_expr.index];
+    let mut root = &*index_expr.object;
+    while let Expression::Index(inner) = root {
+        indices.push(&inner.index);
+        root = &inner.object;
+    }
+    let root = match root {
+        Expression::Identifier(name) if env.borrow().get_local(&name.0).is_some() => name,
+        _ => {
+            let container = eval_expression(&index_expr.object, env)?;
+            let index = match index {
+                Some(index) => index,
+                None => eval_expression(&index_expr.index, env)?,
+            };
+            let updated = eval_index_assignment(container, index, value)?;
+            return assign(&index_expr.object, updated, env);
+        }
+    };
+
+    // Evaluated in source order, outermost index last.
+    indices.reverse();
+    let given = usize::from(index.is_some());
+    let mut path = indices[..indices.len() - given]
+        .iter()
+        .map(|index| eval_expression(index, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    path.extend(index);
+    let mut container = env.borrow_mut().take_local(&root.0).unwrap_or(Object::None);
+    let result = set_path(&mut container, &path, value);
+    env.borrow_mut().set(&root.0, container);
+    result
+}
+
+/// Store `value` in `container` at `path`, one index per level of nesting.
+fn set_path(container: &mut Object, path: &[Object], value: Object) -> Result<(), String> {
+    let Some((index, rest)) = path.split_first() else {
+        *container = value;
+        return Ok(());
+    };
+    let slot = match (container, index) {
+        (Object::List(elements), Object::Integer(idx)) => {
+            let len = elements.len();
+            match usize::try_from(*idx).ok().filter(|&i| i < len) {
+                Some(i) => &mut Rc::make_mut(elements)[i],
+                None => return Err(format!("Index out of bounds: {} (list length: {})", idx, len)),
             }
         }
-        _ => Err(format!("Index operation not supported for {} with index {}", object, index)),
+        (Object::Dict(map), key) => {
+            let key = dict_key(key)?;
+            if rest.is_empty() {
+                map.insert(key, value);
+                return Ok(());
+            }
+            match map.get_mut(&key) {
+                Some(slot) => slot,
+                // A missing key reads as `None`, which can't be indexed into.
+                None => return set_path(&mut Object::None, rest, value),
+            }
+        }
+        (Object::Tuple(_), _) => return Err("Tuples are immutable; build a new tuple instead".to_string()),
+        (object, _) => {
+            return Err(format!(
+                "Index assignment not supported for {} with index {}",
+                brief(object),
+                brief(index)
+            ))
+        }
+    };
+    set_path(slot, rest, value)
+}
+
+/// `a, b = value`, `first, *rest = value` and `(a, b), c = value`: each
+/// target takes one item of a list or tuple, a `*` target takes a list of
+/// the items the others leave, and a parenthesized or bracketed group of
+/// targets unpacks its item the same way. `level` counts that nesting from 1,
+/// for errors. At the top level, a value of another type is assigned to
+/// every target as is.
+fn assign_unpacked(
+    targets: &[Expression],
+    value: &Object,
+    env: &Rc<RefCell<Environment>>,
+    level: usize,
+) -> Result<(), String> {
+    let at_level = if level > 1 { format!(" at nesting level {}", level) } else { String::new() };
+    let star = targets.iter().position(|target| matches!(target, Expression::Unpack(_)));
+    let values = match (value, star) {
+        (Object::List(values) | Object::Tuple(values), _) => values,
+        (other, None) if level == 1 => {
+            for target in targets {
+                assign(target, other.clone(), env)?;
+            }
+            return Ok(());
+        }
+        (other, None) => {
+            return Err(format!(
+                "Cannot unpack {}{}: {} targets need a list or tuple",
+                other.type_name(),
+                at_level,
+                targets.len()
+            ))
+        }
+        (other, Some(_)) => return Err(format!("Cannot unpack {} into a '*' target{}", other.type_name(), at_level)),
+    };
+    let assign_item = |target: &Expression, item: &Object| match target {
+        Expression::Tuple(group) | Expression::List(group) => assign_unpacked(group, item, env, level + 1),
+        _ => assign(target, item.clone(), env),
+    };
+
+    let Some(star) = star else {
+        if values.len() != targets.len() {
+            return Err(format!(
+                "Assignment count mismatch{}: {} targets but {} values",
+                at_level,
+                targets.len(),
+                values.len()
+            ));
+        }
+        for (target, item) in targets.iter().zip(values.iter()) {
+            assign_item(target, item)?;
+        }
+        return Ok(());
+    };
+    if values.len() < targets.len() - 1 {
+        return Err(format!(
+            "Assignment count mismatch{}: at least {} values needed but got {}",
+            at_level,
+            targets.len() - 1,
+            values.len()
+        ));
+    }
+    let rest_end = values.len() - (targets.len() - star - 1);
+    for (target, item) in targets[..star].iter().zip(&values[..star]) {
+        assign_item(target, item)?;
+    }
+    if let Expression::Unpack(unpack) = &targets[star] {
+        assign(&unpack.value, Object::list(values[star..rest_end].to_vec()), env)?;
     }
+    for (target, item) in targets[star + 1..].iter().zip(&values[rest_end..]) {
+        assign_item(target, item)?;
+    }
+    Ok(())
 }
 
-fn eval_if_statement(if_stmt: &IfStatement, env: &mut Environment) -> Result<Object, String> {
-    let condition = eval_expression(&if_stmt.condition, env)?;
-    
-    if is_truthy(condition) {
+/// `xs = xs.push(v)`, `xs = push(xs, v)` and the same with `pop`. The old
+/// list is replaced anyway, so it is moved out of the scope before the
+/// builtin runs; unless another value shares it, the builtin then changes it
+/// in place instead of copying the whole list on every call. `None` means the
+/// statement has some other form and takes the general path.
+fn eval_list_update(assignment: &Assignment, env: &Rc<RefCell<Environment>>) -> Option<Result<Object, String>> {
+    let [Expression::Identifier(target)] = assignment.targets.as_slice() else {
+        return None;
+    };
+    let Expression::Call(call) = &*assignment.value else {
+        return None;
+    };
+    if !call.keywords.is_empty() || call.arguments.iter().any(|arg| matches!(arg, Expression::Unpack(_))) {
+        return None;
+    }
+    let is_target = |expr: &Expression| matches!(expr, Expression::Identifier(name) if name == target);
+    let (callee, args) = match (&*call.function, call.arguments.as_slice()) {
+        (Expression::Member(member), args) if is_target(&member.object) => (&member.property, args),
+        (Expression::Identifier(callee), [first, args @ ..]) if is_target(first) => (callee, args),
+        _ => return None,
+    };
+    let func: BuiltinFunction = match callee.0.as_str() {
+        "push" => builtins::builtin_push,
+        "pop" => builtins::builtin_pop,
+        _ => return None,
+    };
+    // `push(xs, v)` only counts while `push` still names the builtin.
+    if matches!(&*call.function, Expression::Identifier(_))
+        && env.borrow().get(&callee.0) != Some(Object::Builtin(Builtin::new(func)))
+    {
+        return None;
+    }
+    // Only calls that cannot fail, so the moved list is never lost.
+    let fits = |value: &Object| match (callee.0.as_str(), value) {
+        ("push", Object::List(_)) => args.len() == 1,
+        ("pop", Object::List(items)) => args.is_empty() && !items.is_empty(),
+        _ => false,
+    };
+    if !env.borrow().get_local(&target.0).is_some_and(|value| fits(&value)) {
+        return None;
+    }
+
+    let mut call_args = vec![Object::None];
+    for arg in args {
+        match eval_expression(arg, env) {
+            Ok(value) => call_args.push(value),
+            Err(e) => return Some(Err(e)),
+        }
+    }
+    let current = env.borrow_mut().take_local(&target.0).unwrap_or(Object::None);
+    if !fits(&current) {
+        // Evaluating the arguments rebound the target; leave it in place.
+        env.borrow_mut().set(&target.0, current.clone());
+    }
+    call_args[0] = current;
+    let result = func(call_args);
+    if let Ok(value) = &result {
+        env.borrow_mut().set(&target.0, value.clone());
+    }
+    Some(result)
+}
+
+/// `object.name = value`: instances are shared, so their field is set in
+/// place; dicts are rebuilt and written back like index assignment.
+fn assign_member(
+    object: Object,
+    member: &MemberExpression,
+    value: Object,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), String> {
+    let name = member.property.0.clone();
+    if member.optional {
+        return Err(format!("Cannot assign through '?.'; use '.{}' instead", name));
+    }
+    match object {
+        Object::Instance(instance) => {
+            let grimoire = Rc::clone(&instance.borrow().grimoire);
+            purity::check_change(Some(&instance), || format!("a {} instance", grimoire.name))?;
+            if let Some(setter) = grimoire.setter(&name) {
+                return call_function(setter, vec![value], Vec::new(), Some(Object::Instance(instance))).map(|_| ());
+            }
+            if grimoire.getter(&name).is_some() {
+                return Err(format!("Property '{}' of {} is read-only; it has no setter", name, grimoire.name));
+            }
+            instance.borrow_mut().fields.insert(name, value);
+            Ok(())
+        }
+        Object::Dict(mut map) => {
+            map.insert(Object::String(name.into()), value);
+            assign(&member.object, Object::Dict(map), env)
+        }
+        Object::Grimoire(grimoire) => {
+            purity::check_change(None, || format!("grimoire {}", grimoire.name))?;
+            grimoire.attributes.borrow_mut().insert(name, value);
+            Ok(())
+        }
+        other => Err(format!("Cannot set attribute '{}' on {}", name, brief(&other))),
+    }
+}
+
+fn eval_index_assignment(object: Object, index: Object, value: Object) -> Result<Object, String> {
+    match (object, &index) {
+        (Object::List(mut elements), Object::Integer(idx)) => {
+            if *idx < 0 || *idx as usize >= elements.len() {
+                return Err(format!("Index out of bounds: {} (list length: {})", idx, elements.len()));
+            }
+            Rc::make_mut(&mut elements)[*idx as usize] = value;
+            Ok(Object::List(elements))
+        }
+        (Object::Dict(mut map), key) => {
+            map.insert(dict_key(key)?, value);
+            Ok(Object::Dict(map))
+        }
+        (Object::Tuple(_), _) => Err("Tuples are immutable; build a new tuple instead".to_string()),
+        (object, _) => Err(format!("Index assignment not supported for {} with index {}", brief(&object), brief(&index))),
+    }
+}
+
+/// Resolve `object.name`: dict keys, instance fields, then spells and
+/// builtin-type methods (returned bound to `object`).
+fn eval_member_expression(object: Object, name: &str) -> Result<Object, String> {
+    let found = match &object {
+        Object::Dict(map) => map.get(&Object::String(name.into())).cloned(),
+        Object::Instance(instance) => {
+            let getter = instance.borrow().grimoire.getter(name).cloned();
+            if let Some(getter) = getter {
+                return call_function(&getter, Vec::new(), Vec::new(), Some(object.clone()));
+            }
+            let instance = instance.borrow();
+            match instance.fields.get(name) {
+                Some(value) => Some(value.clone()),
+                None => match instance.grimoire.method(name) {
+                    Some(method) => Some(Object::BoundMethod(Rc::new(BoundMethod {
+                        receiver: object.clone(),
+                        method: Object::Function(method.clone()),
+                    }))),
+                    None => grimoire_member(&instance.grimoire, name),
+                },
+            }
+        }
+        Object::Grimoire(grimoire) => grimoire_member(grimoire, name)
+            .or_else(|| grimoire.method(name).map(|method| Object::Function(method.clone()))),
+        Object::Module(module) => module.env.borrow().get_local(name),
+        Object::Native(native) => native.0.attribute(name),
+        _ => None,
+    };
+    if let Some(value) = found {
+        return Ok(value);
+    }
+
+    match builtins::type_method(&object, name) {
+        Some(func) => Ok(Object::BoundMethod(Rc::new(BoundMethod {
+            receiver: object,
+            method: Object::Builtin(Builtin::new(func)),
+        }))),
+        None => Err(format!("{} has no attribute '{}'", brief(&object), name)),
+    }
+}
+
+/// A static spell or attribute of `grimoire`, which its instances share.
+fn grimoire_member(grimoire: &Grimoire, name: &str) -> Option<Object> {
+    match grimoire.static_spell(name) {
+        Some(spell) => Some(Object::Function(spell.clone())),
+        None => grimoire.attribute(name),
+    }
+}
+
+/// `super.name` inside a spell: the parent grimoire's spell `name`, bound
+/// to the current `self`.
+fn eval_super_member(name: &str, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let (receiver, parent) = {
+        let env = env.borrow();
+        (env.get("self"), env.get(SUPER))
+    };
+    match (receiver, parent) {
+        (Some(receiver), Some(Object::Grimoire(parent))) => match parent.method(name) {
+            Some(method) => Ok(Object::BoundMethod(Rc::new(BoundMethod {
+                receiver,
+                method: Object::Function(method.clone()),
+            }))),
+            None => match parent.arcane_owner(name) {
+                Some(owner) => Err(format!("Spell '{}' of {} is arcane and has no body to call", name, owner.name)),
+                None => Err(format!("Parent grimoire {} has no spell '{}'", parent.name, name)),
+            },
+        },
+        _ => Err("'super' can only be used in the spells of a grimoire that inherits from another".to_string()),
+    }
+}
+
+fn eval_if_statement(if_stmt: &IfStatement, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    if condition(eval_expression(&if_stmt.condition, env)?)? {
         eval_block_statement(&if_stmt.consequence, env)
     } else {
         // Check otherwise clauses
         for (alt_condition, alt_consequence) in &if_stmt.alternatives {
-            let alt_cond_result = eval_expression(alt_condition, env)?;
-            if is_truthy(alt_cond_result) {
+            if condition(eval_expression(alt_condition, env)?)? {
                 return eval_block_statement(alt_consequence, env);
             }
         }
@@ -337,15 +1285,15 @@ fn eval_if_statement(if_stmt: &IfStatement, env: &mut Environment) -> Result<Obj
     }
 }
 
-fn eval_while_statement(while_stmt: &WhileStatement, env: &mut Environment) -> Result<Object, String> {
+fn eval_while_statement(while_stmt: &WhileStatement, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
     let mut result = Object::None;
     
     loop {
-        let condition = eval_expression(&while_stmt.condition, env)?;
-        if !is_truthy(condition) {
+        if !condition(eval_expression(&while_stmt.condition, env)?)? {
             break;
         }
         
+        drop(result);
         result = eval_block_statement(&while_stmt.body, env)?;
         
         // Handle return values
@@ -357,46 +1305,92 @@ fn eval_while_statement(while_stmt: &WhileStatement, env: &mut Environment) -> R
     Ok(result)
 }
 
-fn eval_for_statement(for_stmt: &ForStatement, env: &mut Environment) -> Result<Object, String> {
+fn eval_for_statement(for_stmt: &ForStatement, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
     let iterable = eval_expression(&for_stmt.iter, env)?;
+    let mut items = match &iterable {
+        Object::Dict(map) if for_stmt.targets.len() == 2 => iteration::pairs(map),
+        other => iteration::iterate(other)?,
+    };
+
     let mut result = Object::None;
-    
-    match iterable {
-        Object::List(elements) => {
-            for element in elements {
-                env.set(for_stmt.target.0.clone(), element);
-                result = eval_block_statement(&for_stmt.body, env)?;
-                
-                // Handle return values
-                if let Object::ReturnValue(_) = result {
-                    break;
-                }
-            }
+    while let Some(item) = items.next()? {
+        bind_loop_targets(&for_stmt.targets, item, env)?;
+        drop(result);
+        result = eval_block_statement(&for_stmt.body, env)?;
+
+        // Handle return values
+        if let Object::ReturnValue(_) = result {
+            break;
         }
-        Object::String(s) => {
-            for ch in s.chars() {
-                env.set(for_stmt.target.0.clone(), Object::String(ch.to_string()));
-                result = eval_block_statement(&for_stmt.body, env)?;
-                
-                // Handle return values
-                if let Object::ReturnValue(_) = result {
-                    break;
-                }
-            }
+    }
+
+    Ok(result)
+}
+
+/// Bind one item of a `for` loop: to its name, or, with several names, to
+/// each element of the item, which must be a list or tuple of that length.
+fn bind_loop_targets(targets: &[Identifier], item: Object, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+    for target in targets {
+        environment::check_assignable(&target.0)?;
+    }
+    let [target] = targets else {
+        let values = match &item {
+            Object::List(values) | Object::Tuple(values) => values,
+            other => return Err(format!("Cannot unpack {} into {} loop targets", other.type_name(), targets.len())),
+        };
+        if values.len() != targets.len() {
+            return Err(format!(
+                "Loop target count mismatch: {} targets but {} values",
+                targets.len(),
+                values.len()
+            ));
         }
-        _ => {
-            return Err(format!("Object is not iterable: {}", iterable));
+        let mut env = env.borrow_mut();
+        for (target, value) in targets.iter().zip(values.iter()) {
+            env.set(&target.0, value.clone());
+        }
+        return Ok(());
+    };
+    env.borrow_mut().set(&target.0, item);
+    Ok(())
+}
+
+/// Run the first arm whose pattern matches the subject; no match yields `None`.
+fn eval_match_statement(match_stmt: &MatchStatement, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let subject = eval_expression(&match_stmt.subject, env)?;
+
+    for arm in &match_stmt.arms {
+        let matched = match &arm.pattern {
+            Pattern::Wildcard => true,
+            Pattern::Binding(name) => {
+                env.borrow_mut().set(&name.0, subject.clone());
+                true
+            }
+            Pattern::Literal(literal) => eval_expression(literal, env)? == subject,
+            Pattern::Range(start, end) => {
+                let start = eval_expression(start, env)?;
+                let end = eval_expression(end, env)?;
+                builtins::ordering::in_range(&subject, &start, &end)
+            }
+        };
+        if matched {
+            return eval_block_statement(&arm.body, env);
         }
     }
-    
-    Ok(result)
+
+    Ok(Object::None)
 }
 
-fn eval_block_statement(block: &BlockStatement, env: &mut Environment) -> Result<Object, String> {
+fn eval_block_statement(block: &[SpannedStatement], env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
     let mut result = Object::None;
     
     for statement in block {
-        result = eval_statement(statement, env)?;
+        // Release the previous statement's value before running the next,
+        // so it doesn't keep a list shared and force `eval_list_update` to copy it.
+        drop(result);
+        result = slow::time(&statement.span, || eval_statement(statement, env))
+            .inspect_err(|e| stack::statement_failed(e, &statement.span))?;
+        debugger::check(env)?;
         
         // Handle return values
         if let Object::ReturnValue(_) = result {
@@ -0,0 +1,81 @@
+//! Module sources supplied by the embedding program.
+//!
+//! A host can register loaders that map an import, as written (`helpers` for
+//! `import helpers`, `lib/shapes` for `import "lib/shapes"`), to source
+//! text, so modules can come from a database, an archive or a virtual file
+//! system. Loaders are asked before the disk, in the order they were added;
+//! an import none of them knows is looked up in files as usual.
+//!
+//! A loaded module's path is `<loader>/` followed by the import and the
+//! source extension, which is what error messages show. Loader
+//! reads don't go through the `--record` trace.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The directory every loaded module's path starts with.
+const ROOT: &str = "<loader>";
+
+type Loader = Rc<dyn Fn(&str) -> Option<String>>;
+
+thread_local! {
+    static LOADERS: RefCell<Vec<Loader>> = const { RefCell::new(Vec::new()) };
+    /// Source returned while resolving an import, kept until it is parsed.
+    static PENDING: RefCell<HashMap<PathBuf, String>> = RefCell::new(HashMap::new());
+    /// The import each loaded module's path was made from.
+    static REQUESTS: RefCell<HashMap<PathBuf, String>> = RefCell::new(HashMap::new());
+}
+
+/// Register `loader` for imports on this thread. It returns a module's
+/// source, or `None` to let later loaders and then the disk handle it.
+pub fn add_loader(loader: impl Fn(&str) -> Option<String> + 'static) {
+    LOADERS.with(|loaders| loaders.borrow_mut().push(Rc::new(loader)));
+}
+
+/// Remove every loader registered on this thread.
+pub fn clear_loaders() {
+    LOADERS.with(|loaders| loaders.borrow_mut().clear());
+}
+
+/// The path a module imported as `request` has when a loader supplies it.
+pub fn path_for(request: &str) -> PathBuf {
+    let mut path = Path::new(ROOT).join(request);
+    if path.extension().is_none() {
+        path.set_extension(super::SOURCE_EXTENSION);
+    }
+    path
+}
+
+/// Whether `path` names a module from a loader rather than a file.
+pub fn is_loaded(path: &Path) -> bool {
+    path.starts_with(ROOT)
+}
+
+/// Ask the loaders for `request`. On success its source is kept for
+/// [`source`] and the module's path is returned.
+pub(super) fn find(request: &str) -> Option<PathBuf> {
+    let loaders = LOADERS.with(|loaders| loaders.borrow().clone());
+    let text = loaders.iter().find_map(|loader| loader(request))?;
+    let path = path_for(request);
+    PENDING.with(|pending| pending.borrow_mut().insert(path.clone(), text));
+    REQUESTS.with(|requests| requests.borrow_mut().insert(path.clone(), request.to_string()));
+    Some(path)
+}
+
+/// The source of the loaded module at `path`: what [`find`] kept, or, when
+/// the module is read again, a fresh answer from the loaders.
+pub(super) fn source(path: &Path) -> Result<String, String> {
+    if let Some(text) = PENDING.with(|pending| pending.borrow_mut().remove(path)) {
+        return Ok(text);
+    }
+    let request = REQUESTS
+        .with(|requests| requests.borrow().get(path).cloned())
+        .ok_or_else(|| "no loader supplied it".to_string())?;
+    let loaders = LOADERS.with(|loaders| loaders.borrow().clone());
+    loaders
+        .iter()
+        .find_map(|loader| loader(&request))
+        .ok_or_else(|| "no loader supplies it any more".to_string())
+}
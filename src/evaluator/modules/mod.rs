@@ -0,0 +1,253 @@
+//! Loading `import`ed files.
+//!
+//! Each file is lexed, parsed and evaluated once per thread in its own
+//! top-level scope; later imports of the same file reuse the cached module.
+//! [`prefetch`] can read and parse a script's imports before it runs, and
+//! an embedding program can supply modules itself with [`add_loader`] or,
+//! for modules written in Rust, [`add_native_module`].
+
+pub mod loader;
+pub mod plugins;
+pub mod prefetch;
+
+pub use loader::{add_loader, clear_loaders};
+pub use prefetch::prefetch;
+
+use super::environment::Environment;
+use super::trace;
+use crate::ast::{ImportSource, ImportStatement, Program};
+use crate::lexer::Lexer;
+use crate::object::{Module, Object};
+use crate::parser::{diagnostic, Parser};
+use crate::strict;
+use crate::token::Token;
+use crate::version;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// File extension added to imports that don't name one.
+pub const SOURCE_EXTENSION: &str = "crl";
+
+/// The directory every native module's path starts with.
+const NATIVE_ROOT: &str = "<native>";
+
+thread_local! {
+    static CACHE: RefCell<HashMap<PathBuf, Rc<Module>>> = RefCell::new(HashMap::new());
+    /// Modules currently being evaluated, innermost last, to report import cycles.
+    static LOADING: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Load the module named by `import` and bind it in `env`.
+pub fn eval_import(import: &ImportStatement, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let path = resolve(import)?;
+    let module = load(&path)?;
+    let binding = match &import.alias {
+        Some(alias) => alias.0.clone(),
+        None => module.name.clone(),
+    };
+    env.borrow_mut().set(binding, Object::Module(module));
+    Ok(Object::None)
+}
+
+fn resolve(import: &ImportStatement) -> Result<PathBuf, String> {
+    let request = match &import.source {
+        ImportSource::Path(path) => path,
+        ImportSource::Name(name) => &name.0,
+    };
+    if let ImportSource::Name(name) = &import.source {
+        let native = Path::new(NATIVE_ROOT).join(&name.0);
+        if CACHE.with(|cache| cache.borrow().contains_key(&native)) {
+            return Ok(native);
+        }
+    }
+    let loaded = loader::path_for(request);
+    if CACHE.with(|cache| cache.borrow().contains_key(&loaded)) {
+        return Ok(loaded);
+    }
+    if let Some(path) = loader::find(request) {
+        return Ok(path);
+    }
+
+    let base = import.origin.parent().unwrap_or(Path::new(""));
+    match &import.source {
+        ImportSource::Path(path) => {
+            let mut candidate = base.join(path);
+            if candidate.extension().is_none() {
+                candidate.set_extension(SOURCE_EXTENSION);
+            }
+            fs::canonicalize(&candidate).map_err(|_| not_found(path, &[candidate]))
+        }
+        ImportSource::Name(name) => {
+            let file = format!("{}.{}", name.0, SOURCE_EXTENSION);
+            let candidates: Vec<PathBuf> = search_path(base)?
+                .into_iter()
+                .map(|dir| dir.join(&file))
+                .collect();
+            candidates
+                .iter()
+                .find_map(|candidate| fs::canonicalize(candidate).ok())
+                .ok_or_else(|| not_found(&name.0, &candidates))
+        }
+    }
+}
+
+/// Make `members` importable as `import name`, ahead of loaders and files.
+/// Binary plugins register their spells this way.
+pub fn add_native_module(name: &str, members: Vec<(String, Object)>) {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    for (member, value) in members {
+        env.borrow_mut().set(member, value);
+    }
+    let path = Path::new(NATIVE_ROOT).join(name);
+    let module = Module {
+        name: name.to_string(),
+        path: path.clone(),
+        env,
+    };
+    CACHE.with(|cache| cache.borrow_mut().insert(path, Rc::new(module)));
+}
+
+/// Directories searched for `import name`, in order: the importing file's
+/// directory, each entry of `CARRION_PATH`, then the standard library.
+pub fn search_path(base: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut dirs = vec![base.to_path_buf()];
+    if let Some(paths) = trace::env_var("CARRION_PATH")? {
+        dirs.extend(env::split_paths(&paths).filter(|p| !p.as_os_str().is_empty()));
+    }
+    if let Some(stdlib) = stdlib_dir() {
+        dirs.push(stdlib);
+    }
+    Ok(dirs)
+}
+
+/// The `std` directory installed next to the `carrion` executable.
+fn stdlib_dir() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    Some(exe.parent()?.join("std"))
+}
+
+fn not_found(requested: &str, searched: &[PathBuf]) -> String {
+    let mut message = format!("Module not found: '{}'. Searched:", requested);
+    for path in searched {
+        message.push_str(&format!("\n    {}", path.display()));
+    }
+    message
+}
+
+fn load(path: &Path) -> Result<Rc<Module>, String> {
+    if let Some(module) = CACHE.with(|cache| cache.borrow().get(path).cloned()) {
+        return Ok(module);
+    }
+    if LOADING.with(|loading| loading.borrow().iter().any(|p| p == path)) {
+        return Err(format!("Circular import of '{}'", path.display()));
+    }
+
+    LOADING.with(|loading| loading.borrow_mut().push(path.to_path_buf()));
+    let result = evaluate_file(path);
+    LOADING.with(|loading| loading.borrow_mut().pop());
+
+    let module = Rc::new(result?);
+    CACHE.with(|cache| cache.borrow_mut().insert(path.to_path_buf(), Rc::clone(&module)));
+    Ok(module)
+}
+
+/// Re-read an imported file and evaluate it again in the module's existing
+/// scope, so every binding of the module sees the new definitions while
+/// values the file no longer assigns are kept. A file that was never
+/// imported is simply loaded.
+pub fn reload(path: &Path) -> Result<Rc<Module>, String> {
+    if path.starts_with(NATIVE_ROOT) {
+        // Native modules have no source to read again.
+        return CACHE
+            .with(|cache| cache.borrow().get(path).cloned())
+            .ok_or_else(|| not_found(&path.display().to_string(), &[]));
+    }
+    let path = if loader::is_loaded(path) {
+        path.to_path_buf()
+    } else {
+        fs::canonicalize(path).map_err(|_| not_found(&path.display().to_string(), &[path.to_path_buf()]))?
+    };
+    let Some(module) = CACHE.with(|cache| cache.borrow().get(&path).cloned()) else {
+        return load(&path);
+    };
+    run_file(&path, &module.env)?;
+    Ok(module)
+}
+
+fn evaluate_file(path: &Path) -> Result<Module, String> {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    run_file(path, &env)?;
+
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Module {
+        name,
+        path: path.to_path_buf(),
+        env,
+    })
+}
+
+fn run_file(path: &Path, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+    let program = match prefetch::take(path) {
+        Some(program) => program,
+        None => parse_file(path)?,
+    };
+
+    super::eval_program(&program, env)
+        .map(|_| ())
+        .map_err(|e| format!("Error in module '{}': {}", path.display(), e))
+}
+
+fn parse_file(path: &Path) -> Result<Program, String> {
+    let source = read_source(path)
+        .map_err(|e| format!("Could not read module '{}': {}", path.display(), e))?;
+
+    let is_strict = pragmas(path, &source)?;
+    let tokens = Lexer::new(source, path.to_path_buf()).scan_tokens();
+    parse_tokens(path, tokens, is_strict)
+}
+
+/// Check the version pragma of the module at `path`, whose text is
+/// `source`, and return whether it is checked in strict mode.
+fn pragmas(path: &Path, source: &str) -> Result<bool, String> {
+    version::pragma(source).map_err(|e| format!("Could not read module '{}': {}", path.display(), e))?;
+    Ok(strict::is_enabled() || strict::has_pragma(source))
+}
+
+/// Parse the module at `path` from its `tokens`, checking it in strict mode
+/// if `is_strict`.
+fn parse_tokens(path: &Path, tokens: Vec<Token>, is_strict: bool) -> Result<Program, String> {
+    let mut warnings = if is_strict { strict::check_tokens(&tokens) } else { Vec::new() };
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        return Err(format!(
+            "Could not parse module '{}': {}",
+            path.display(),
+            diagnostic::join(parser.errors(), "; ")
+        ));
+    }
+    if is_strict {
+        warnings.extend(strict::check_program(&program));
+    }
+    if !warnings.is_empty() {
+        let messages: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        return Err(format!("Strict mode: module '{}': {}", path.display(), messages.join("; ")));
+    }
+    Ok(program)
+}
+
+/// The text of the module at `path`, from its loader or its file.
+fn read_source(path: &Path) -> Result<String, String> {
+    if loader::is_loaded(path) {
+        loader::source(path)
+    } else {
+        trace::read_file(path)
+    }
+}
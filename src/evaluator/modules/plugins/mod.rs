@@ -0,0 +1,296 @@
+//! Native modules shipped as shared libraries.
+//!
+//! A plugin is a `cdylib` that exports
+//!
+//! ```text
+//! extern "C" fn carrion_plugin() -> *const PluginDescriptor
+//! ```
+//!
+//! describing its spells with the C-compatible types below, so it doesn't
+//! have to be built with the same compiler as `carrion`. The descriptor
+//! states the [`ABI_VERSION`] it was written against, and a plugin for any
+//! other version is refused instead of called. Values crossing the boundary
+//! are `None`, booleans, integers, floats and strings.
+//!
+//! The `carrion.toml` next to a script lists the plugins `carrion --plugins`
+//! loads before running it, each under the name scripts import it by:
+//!
+//! ```toml
+//! [plugins]
+//! image = "plugins/libcarrion_image.so"
+//! ```
+//!
+//! Relative paths are taken from the directory `carrion.toml` is in.
+//! Loading libraries needs the `plugins` feature; a loaded library stays
+//! loaded until the program exits.
+
+use crate::object::{BuiltinFunction, NativeHandle, NativeObject, Object};
+use std::any::Any;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+/// The plugin ABI this build understands.
+pub const ABI_VERSION: u32 = 1;
+
+/// The file listing the plugins to load at startup.
+pub const MANIFEST: &str = "carrion.toml";
+
+/// The symbol every plugin library exports.
+#[cfg(feature = "plugins")]
+const ENTRY_POINT: &[u8] = b"carrion_plugin";
+
+/// UTF-8 text lent across the plugin boundary.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl PluginStr {
+    /// Lend `text`; the result is only valid while `text` is.
+    pub fn new(text: &str) -> PluginStr {
+        PluginStr {
+            ptr: text.as_ptr(),
+            len: text.len(),
+        }
+    }
+
+    /// Copy the text out.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` readable bytes.
+    pub unsafe fn read(&self) -> Result<String, String> {
+        if self.len == 0 {
+            return Ok(String::new());
+        }
+        let bytes = unsafe { slice::from_raw_parts(self.ptr, self.len) };
+        String::from_utf8(bytes.to_vec()).map_err(|_| "plugin text is not valid UTF-8".to_string())
+    }
+}
+
+/// A value passed to or returned from a plugin spell.
+#[repr(C, u8)]
+#[derive(Debug, Clone, Copy)]
+pub enum PluginValue {
+    None,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(PluginStr),
+}
+
+/// A plugin spell. It reads `count` arguments from `args`, writes its result
+/// to `result` and returns `true`; on failure it writes an error message
+/// string instead and returns `false`. Strings it writes are handed back to
+/// the descriptor's `free_string` once copied; argument strings are only
+/// lent for the call.
+pub type PluginCall = extern "C" fn(args: *const PluginValue, count: usize, result: *mut PluginValue) -> bool;
+
+/// One spell a plugin offers, under the name scripts call it by.
+#[repr(C)]
+pub struct PluginFunction {
+    pub name: PluginStr,
+    pub call: PluginCall,
+}
+
+/// What a plugin's `carrion_plugin` entry point returns.
+#[repr(C)]
+pub struct PluginDescriptor {
+    /// The [`ABI_VERSION`] the plugin was written against.
+    pub abi_version: u32,
+    pub functions: *const PluginFunction,
+    pub function_count: usize,
+    /// Release a string the plugin returned from a spell.
+    pub free_string: extern "C" fn(PluginStr),
+}
+
+/// A plugin spell as a callable value.
+#[derive(Debug)]
+struct PluginSpell {
+    module: String,
+    name: String,
+    call: PluginCall,
+    free_string: extern "C" fn(PluginStr),
+}
+
+impl PluginSpell {
+    fn invoke(&self, args: Vec<Object>) -> Result<Object, String> {
+        // The plugin borrows argument strings from `args`, which outlives the call.
+        let values = args.iter().map(to_plugin).collect::<Result<Vec<_>, _>>()?;
+        let mut result = PluginValue::None;
+        let succeeded = (self.call)(values.as_ptr(), values.len(), &mut result);
+        match (succeeded, self.take_result(result)?) {
+            (true, value) => Ok(value),
+            (false, Object::String(message)) => Err(format!("{}.{}: {}", self.module, self.name, message)),
+            (false, _) => Err(format!("{}.{} failed", self.module, self.name)),
+        }
+    }
+
+    /// Convert what a spell wrote to `result`, releasing any string in it.
+    fn take_result(&self, value: PluginValue) -> Result<Object, String> {
+        Ok(match value {
+            PluginValue::None => Object::None,
+            PluginValue::Boolean(b) => Object::Boolean(b),
+            PluginValue::Integer(i) => Object::Integer(i),
+            PluginValue::Float(f) => Object::Float(f),
+            PluginValue::String(text) => {
+                // SAFETY: the plugin handed over a string it owns until `free_string`.
+                let copied = unsafe { text.read() };
+                (self.free_string)(text);
+                Object::String(copied.map_err(|e| format!("{}.{}: {}", self.module, self.name, e))?.into())
+            }
+        })
+    }
+}
+
+impl NativeObject for PluginSpell {
+    fn type_name(&self) -> &'static str {
+        "Builtin"
+    }
+
+    fn method(&self, _name: &str) -> Option<BuiltinFunction> {
+        None
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn display(&self) -> String {
+        format!("<plugin spell {}.{}>", self.module, self.name)
+    }
+
+    fn call(&self, args: Vec<Object>) -> Option<Result<Object, String>> {
+        Some(self.invoke(args))
+    }
+}
+
+fn to_plugin(value: &Object) -> Result<PluginValue, String> {
+    Ok(match value {
+        Object::None => PluginValue::None,
+        Object::Boolean(b) => PluginValue::Boolean(*b),
+        Object::Integer(i) => PluginValue::Integer(*i),
+        Object::Float(f) => PluginValue::Float(*f),
+        Object::String(s) => PluginValue::String(PluginStr::new(s.as_str())),
+        other => {
+            return Err(format!(
+                "Plugin spells take None, booleans, integers, floats and strings, got {}",
+                other.type_name()
+            ))
+        }
+    })
+}
+
+/// Make the spells `descriptor` describes importable as `import name`, and
+/// return how many there are.
+///
+/// # Safety
+///
+/// `descriptor` must be null or point to a descriptor whose function table,
+/// names and spells stay valid for the rest of the program.
+pub unsafe fn register(name: &str, descriptor: *const PluginDescriptor) -> Result<usize, String> {
+    let descriptor =
+        unsafe { descriptor.as_ref() }.ok_or_else(|| format!("Plugin '{}' returned no descriptor", name))?;
+    if descriptor.abi_version != ABI_VERSION {
+        return Err(format!(
+            "Plugin '{}' was built for plugin ABI version {}, but this carrion supports version {}",
+            name, descriptor.abi_version, ABI_VERSION
+        ));
+    }
+    let functions = match descriptor.function_count {
+        0 => &[][..],
+        count => unsafe { slice::from_raw_parts(descriptor.functions, count) },
+    };
+
+    let mut members = Vec::new();
+    for function in functions {
+        let spell = unsafe { function.name.read() }
+            .map_err(|_| format!("Plugin '{}' has a spell name that is not valid UTF-8", name))?;
+        let value = Object::Native(NativeHandle::new(PluginSpell {
+            module: name.to_string(),
+            name: spell.clone(),
+            call: function.call,
+            free_string: descriptor.free_string,
+        }));
+        members.push((spell, value));
+    }
+    let count = members.len();
+    super::add_native_module(name, members);
+    Ok(count)
+}
+
+/// Load the plugin library at `path` and register it as `import name`.
+#[cfg(feature = "plugins")]
+pub fn load(name: &str, path: &Path) -> Result<usize, String> {
+    let failed = |e: libloading::Error| format!("Could not load plugin '{}' from {}: {}", name, path.display(), e);
+    // SAFETY: loading a library runs its initializers, and the entry point
+    // is trusted to have the documented signature; plugins listed in
+    // carrion.toml are trusted like the interpreter itself.
+    let library = unsafe { libloading::Library::new(path) }.map_err(failed)?;
+    let descriptor = unsafe {
+        let entry = library
+            .get::<extern "C" fn() -> *const PluginDescriptor>(ENTRY_POINT)
+            .map_err(failed)?;
+        entry()
+    };
+    let count = unsafe { register(name, descriptor) }?;
+    // The registered spells point into the library.
+    std::mem::forget(library);
+    Ok(count)
+}
+
+/// Without the `plugins` feature no library can be loaded.
+#[cfg(not(feature = "plugins"))]
+pub fn load(name: &str, path: &Path) -> Result<usize, String> {
+    Err(format!(
+        "Cannot load plugin '{}' from {}: this carrion was built without the `plugins` feature",
+        name,
+        path.display()
+    ))
+}
+
+/// Load every plugin the manifest at `path` lists, and return how many.
+pub fn load_manifest(path: &Path) -> Result<usize, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let plugins = parse_manifest(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    for (name, library) in &plugins {
+        load(name, &base.join(library))?;
+    }
+    Ok(plugins.len())
+}
+
+/// The `[plugins]` table of a manifest: `name = "path"` pairs, in order.
+/// Blank lines, `#` comments and other tables are skipped.
+pub fn parse_manifest(text: &str) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut plugins = Vec::new();
+    let mut in_plugins = false;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(table) = line.strip_prefix('[') {
+            in_plugins = table.trim_end_matches(']').trim() == "plugins";
+            continue;
+        }
+        if !in_plugins {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected 'name = \"path\"'", number + 1));
+        };
+        let path = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.split_once('"'))
+            .filter(|(_, rest)| rest.trim().is_empty() || rest.trim().starts_with('#'))
+            .map(|(path, _)| path)
+            .ok_or_else(|| format!("line {}: the path of plugin '{}' must be a quoted string", number + 1, name.trim()))?;
+        plugins.push((name.trim().to_string(), PathBuf::from(path)));
+    }
+    Ok(plugins)
+}
@@ -0,0 +1,133 @@
+//! Reading imported files ahead of evaluation.
+//!
+//! Before a script runs, [`prefetch`] follows its top-level imports, and
+//! theirs, one breadth-first level at a time, lexing and parsing each
+//! level's files on worker threads. Reads stay on the calling thread, since
+//! they go through the (per-thread) trace. Parsed spells share their bodies
+//! through `Rc`, which can't cross threads, so a worker hands each program
+//! back encoded the way the [`cache`] stores it, and the calling thread
+//! decodes it. Results are collected in the order the imports appear, so
+//! they never depend on which thread finished first. When the import itself
+//! runs, it takes the parsed program from here instead of reading the file
+//! again.
+
+use crate::ast::{Program, Statement};
+use crate::cache;
+use crate::lexer::Lexer;
+use crate::parser::overflow;
+use crate::version;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+thread_local! {
+    static PARSED: RefCell<HashMap<PathBuf, Program>> = RefCell::new(HashMap::new());
+}
+
+/// Read, lex and parse every module `program` imports, directly or through
+/// other modules, and return how many were parsed. Imports that can't be
+/// resolved, read or parsed, that ask for an unknown version, or that strict
+/// mode rejects, are skipped; evaluating them reports the error.
+pub fn prefetch(program: &Program) -> usize {
+    let mut seen = HashSet::new();
+    let mut level = imports_of(program, &mut seen);
+    let mut parsed = 0;
+    while !level.is_empty() {
+        // Pragmas are read on this thread, which holds the strict setting.
+        let mut sources = Vec::new();
+        for path in level {
+            let Ok(source) = super::read_source(&path) else {
+                continue;
+            };
+            let Ok(is_strict) = super::pragmas(&path, &source) else {
+                continue;
+            };
+            sources.push(Source { path, text: source, is_strict });
+        }
+
+        level = Vec::new();
+        for (path, program) in parse_all(sources) {
+            let Some(program) = program else {
+                continue;
+            };
+            level.extend(imports_of(&program, &mut seen));
+            PARSED.with(|cache| cache.borrow_mut().insert(path, program));
+            parsed += 1;
+        }
+    }
+    parsed
+}
+
+/// The program prefetched for `path`, if any. Each is handed out once, so
+/// a later reload reads the file again.
+pub(super) fn take(path: &Path) -> Option<Program> {
+    PARSED.with(|cache| cache.borrow_mut().remove(path))
+}
+
+/// Files imported by `program`'s top-level statements that aren't in `seen`.
+fn imports_of(program: &Program, seen: &mut HashSet<PathBuf>) -> Vec<PathBuf> {
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| match &statement.statement {
+            Statement::Import(import) => super::resolve(import).ok(),
+            _ => None,
+        })
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// A module read for prefetching, and whether strict mode checks it.
+struct Source {
+    path: PathBuf,
+    text: String,
+    is_strict: bool,
+}
+
+/// Lex and parse `sources` spread over the available cores, keeping their
+/// order. A program is `None` if it didn't parse.
+fn parse_all(sources: Vec<Source>) -> Vec<(PathBuf, Option<Program>)> {
+    // Workers parse with this thread's settings, not their own defaults.
+    let settings = (version::default_version(), overflow::mode());
+    let parse = move |Source { path, text, is_strict }: Source| {
+        version::set_default(settings.0);
+        overflow::set_mode(settings.1);
+        let tokens = Lexer::new(text, path.clone()).scan_tokens();
+        let program = super::parse_tokens(&path, tokens, is_strict).ok();
+        (path, program)
+    };
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(sources.len());
+    if workers <= 1 {
+        return sources.into_iter().map(parse).collect();
+    }
+
+    let encode = |program: Program| cache::encode(&cache::Entry { program, warnings: Vec::new() });
+    let batch_size = sources.len().div_ceil(workers);
+    let mut sources = sources.into_iter();
+    thread::scope(|scope| {
+        let mut batches = Vec::new();
+        loop {
+            let batch: Vec<_> = sources.by_ref().take(batch_size).collect();
+            if batch.is_empty() {
+                break;
+            }
+            batches.push(scope.spawn(move || {
+                batch
+                    .into_iter()
+                    .map(parse)
+                    .map(|(path, program)| (path, program.map(encode)))
+                    .collect::<Vec<_>>()
+            }));
+        }
+        batches
+            .into_iter()
+            .flat_map(|batch| batch.join().unwrap_or_else(|payload| panic::resume_unwind(payload)))
+            .map(|(path, bytes)| {
+                let program = bytes.and_then(|bytes| cache::decode(&bytes, &path).ok()).map(|entry| entry.program);
+                (path, program)
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,86 @@
+//! Operators on grimoire instances, through dunder spells.
+//!
+//! A grimoire takes part in an operator by defining the spell for it:
+//! `__add__(other)` for `+`, `__eq__(other)` for `==`, `__neg__()` for
+//! unary `-`, `__contains__(item)` for `in`, `__index__(key)` for `x[key]`
+//! and `__str__()` for how `print` and `str` show an instance. When only the
+//! right operand is an instance, `a + b` calls `b.__radd__(a)`, and a
+//! comparison calls the mirrored spell, so `1 < b` is `b.__gt__(1)`. `!=`
+//! falls back to the opposite of `__eq__`.
+
+use super::call_function;
+use crate::ast::Operator;
+use crate::object::Object;
+
+/// The spell for `operator` with the instance on the left, and the one to
+/// call on an instance on the right.
+fn spell_names(operator: &Operator) -> Option<(&'static str, &'static str)> {
+    let names = match operator {
+        Operator::Plus => ("__add__", "__radd__"),
+        Operator::Minus => ("__sub__", "__rsub__"),
+        Operator::Multiply => ("__mul__", "__rmul__"),
+        Operator::Divide => ("__div__", "__rdiv__"),
+        Operator::Mod => ("__mod__", "__rmod__"),
+        Operator::Exponent => ("__pow__", "__rpow__"),
+        Operator::BitAnd => ("__and__", "__rand__"),
+        Operator::BitOr => ("__or__", "__ror__"),
+        Operator::BitXor => ("__xor__", "__rxor__"),
+        Operator::LeftShift => ("__lshift__", "__rlshift__"),
+        Operator::RightShift => ("__rshift__", "__rrshift__"),
+        Operator::Equal => ("__eq__", "__eq__"),
+        Operator::NotEqual => ("__ne__", "__ne__"),
+        Operator::LessThan => ("__lt__", "__gt__"),
+        Operator::GreaterThan => ("__gt__", "__lt__"),
+        Operator::LessThanEqual => ("__le__", "__ge__"),
+        Operator::GreaterThanEqual => ("__ge__", "__le__"),
+        _ => return None,
+    };
+    Some(names)
+}
+
+/// Call `receiver.name(args)` if `receiver` is an instance whose grimoire
+/// defines `name`; `None` otherwise.
+pub fn call_spell(receiver: &Object, name: &str, args: Vec<Object>) -> Option<Result<Object, String>> {
+    let Object::Instance(instance) = receiver else {
+        return None;
+    };
+    let grimoire = instance.borrow().grimoire.clone();
+    let spell = grimoire.method(name)?;
+    Some(call_function(spell, args, Vec::new(), Some(receiver.clone())))
+}
+
+/// `left operator right` through the operands' dunder spells, or `None` when
+/// neither operand is an instance that defines one.
+pub fn binary_op(operator: &Operator, left: &Object, right: &Object) -> Option<Result<Object, String>> {
+    if *operator == Operator::In {
+        return call_spell(right, "__contains__", vec![left.clone()])
+            .map(|found| found.map(|found| Object::Boolean(super::is_truthy(found))));
+    }
+    let (name, reflected) = spell_names(operator)?;
+    if let Some(result) = call_spell(left, name, vec![right.clone()]) {
+        return Some(result);
+    }
+    if *operator == Operator::NotEqual
+        && let Some(equal) = call_spell(left, "__eq__", vec![right.clone()])
+    {
+        return Some(equal.map(|equal| Object::Boolean(!super::is_truthy(equal))));
+    }
+    if let Some(result) = call_spell(right, reflected, vec![left.clone()]) {
+        return Some(result);
+    }
+    if *operator == Operator::NotEqual {
+        return call_spell(right, "__eq__", vec![left.clone()])
+            .map(|equal| equal.map(|equal| Object::Boolean(!super::is_truthy(equal))));
+    }
+    None
+}
+
+/// The text `__str__` gives for `value`, or `None` if it isn't an instance
+/// with a `__str__` spell.
+pub fn to_text(value: &Object) -> Option<Result<String, String>> {
+    let text = call_spell(value, "__str__", Vec::new())?;
+    Some(text.and_then(|text| match text {
+        Object::String(s) => Ok(s.to_string()),
+        other => Err(format!("__str__ must return a string, got {}", other.type_name())),
+    }))
+}
@@ -0,0 +1,78 @@
+//! `pure spell`: spells that promise not to touch the world.
+//!
+//! While a pure spell runs, and everything it calls, builtins that print,
+//! write files or read the outside world (the clock, random bytes,
+//! environment variables) fail through [`check`], and so does changing an
+//! instance or grimoire that existed before the outermost pure call began
+//! ([`check_change`]). Instances the call makes itself are its own to fill
+//! in. That makes a pure spell's result depend only on its arguments, which
+//! is what `memoize` and `map_pure` rely on.
+
+use crate::object::Instance;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct Running {
+    /// The pure spells being run, innermost last.
+    spells: Vec<Rc<str>>,
+    /// The addresses of instances made since the outermost one began.
+    made: HashSet<usize>,
+}
+
+thread_local! {
+    static RUNNING: RefCell<Running> = RefCell::default();
+}
+
+/// Run `call`, the body of the pure spell `spell`, under the checks.
+pub fn run<T>(spell: &Rc<str>, call: impl FnOnce() -> T) -> T {
+    RUNNING.with(|running| running.borrow_mut().spells.push(Rc::clone(spell)));
+    let result = call();
+    RUNNING.with(|running| {
+        let mut running = running.borrow_mut();
+        running.spells.pop();
+        if running.spells.is_empty() {
+            running.made.clear();
+        }
+    });
+    result
+}
+
+/// Fail if a pure spell is running, as it may not `action`, e.g. `"print"`.
+pub fn check(action: &str) -> Result<(), String> {
+    RUNNING.with(|running| match running.borrow().spells.last() {
+        Some(spell) => Err(format!("Pure spell '{}' can't {}", spell, action)),
+        None => Ok(()),
+    })
+}
+
+/// Note that `instance` was just made, so a running pure spell may change it.
+pub fn made(instance: &Rc<RefCell<Instance>>) {
+    RUNNING.with(|running| {
+        let mut running = running.borrow_mut();
+        if !running.spells.is_empty() {
+            running.made.insert(Rc::as_ptr(instance) as usize);
+        }
+    });
+}
+
+/// Fail if a pure spell is running and `instance`, which `describe` names,
+/// existed before it began. `None` stands for something no pure spell may
+/// change, such as a grimoire.
+pub fn check_change(instance: Option<&Rc<RefCell<Instance>>>, describe: impl FnOnce() -> String) -> Result<(), String> {
+    RUNNING.with(|running| {
+        let running = running.borrow();
+        let Some(spell) = running.spells.last() else {
+            return Ok(());
+        };
+        match instance {
+            Some(instance) if running.made.contains(&(Rc::as_ptr(instance) as usize)) => Ok(()),
+            _ => Err(format!(
+                "Pure spell '{}' can't change {}, which was made outside it",
+                spell,
+                describe()
+            )),
+        }
+    })
+}
@@ -0,0 +1,55 @@
+//! `--slow-statements ms`: report statements that take longer than a budget.
+//!
+//! Every statement is timed on its own, so a slow loop is reported along with
+//! the slow statements inside it, innermost first. Reports go to the reporter
+//! installed with [`enable`]; while none is installed statements are not
+//! timed at all.
+
+use crate::ast::Span;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+/// Receives each statement over budget with how long it took.
+pub type Reporter = Box<dyn FnMut(&Span, Duration)>;
+
+struct Budget {
+    threshold: Duration,
+    report: Reporter,
+}
+
+thread_local! {
+    static BUDGET: RefCell<Option<Budget>> = const { RefCell::new(None) };
+}
+
+/// Report statements on this thread that run longer than `threshold`.
+pub fn enable(threshold: Duration, report: Reporter) {
+    BUDGET.with(|budget| *budget.borrow_mut() = Some(Budget { threshold, report }));
+}
+
+/// Stop timing statements.
+pub fn disable() {
+    BUDGET.with(|budget| *budget.borrow_mut() = None);
+}
+
+/// Run the statement at `span`, reporting it if it goes over budget.
+pub fn time<T>(span: &Span, run: impl FnOnce() -> T) -> T {
+    if BUDGET.with(|budget| budget.borrow().is_none()) {
+        return run();
+    }
+    let start = Instant::now();
+    let result = run();
+    let elapsed = start.elapsed();
+    BUDGET.with(|budget| {
+        if let Some(budget) = budget.borrow_mut().as_mut()
+            && elapsed > budget.threshold
+        {
+            (budget.report)(span, elapsed);
+        }
+    });
+    result
+}
+
+/// The line printed for a slow statement, e.g. `Slow statement at main.crl:3:1 took 12.5 ms`.
+pub fn describe(span: &Span, elapsed: Duration) -> String {
+    format!("Slow statement at {} took {:.1} ms", span, elapsed.as_secs_f64() * 1000.0)
+}
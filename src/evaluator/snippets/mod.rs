@@ -0,0 +1,58 @@
+//! Running source that doesn't come from a file.
+//!
+//! Code from `-e`, a REPL entry or an embedder's [`eval_string`] call gets a
+//! virtual source name in angle brackets, such as `<-e>` or `<repl:3>`, and
+//! its line numbers count from the start of that snippet. Error messages and
+//! slow-statement reports then point into the snippet instead of at a file
+//! that doesn't exist.
+
+use super::environment::Environment;
+use crate::ast::Program;
+use crate::error::{self, SourceMap};
+use crate::object::Object;
+use crate::parser::Diagnostic;
+use crate::{lexer, parser};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// The virtual path of the snippet called `name`: `<name>`, unless `name`
+/// is already in angle brackets.
+pub fn source_name(name: &str) -> PathBuf {
+    if name.starts_with('<') && name.ends_with('>') {
+        PathBuf::from(name)
+    } else {
+        PathBuf::from(format!("<{}>", name))
+    }
+}
+
+/// Parse `source` as the snippet `name`.
+pub fn parse(source: &str, name: &str) -> Result<Program, String> {
+    parse_diagnostics(source, name)
+        .map_err(|errors| format!("Parsing Error(s):\n\t{}", parser::diagnostic::join(&errors, "\n\t")))
+}
+
+/// Parse `source` as the snippet `name`, keeping what the parser reported
+/// as [`Diagnostic`]s.
+pub fn parse_diagnostics(source: &str, name: &str) -> Result<Program, Vec<Diagnostic>> {
+    let mut lexer = lexer::Lexer::new(source.to_string(), source_name(name));
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        return Err(parser.errors().to_vec());
+    }
+    Ok(program)
+}
+
+/// Run `source` in `env` as the snippet `name`, e.g.
+/// `eval_string("total(items)", "checkout-rule", &env)`. Errors point at
+/// `<checkout-rule>:line:column`; runtime errors also show the failing line
+/// and the spell calls they came through.
+pub fn eval_string(source: &str, name: &str, env: &Rc<RefCell<Environment>>) -> Result<Object, String> {
+    let program = parse(source, name)?;
+    super::eval_with_env(&program, env).map_err(|e| {
+        let mut sources = SourceMap::new();
+        sources.add(source_name(name), source);
+        error::render_diagnostic(&super::stack::error(e), &sources)
+    })
+}
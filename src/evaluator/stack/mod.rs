@@ -0,0 +1,78 @@
+//! Where an error happened, and the spell calls it came through.
+//!
+//! Errors are plain strings, so their location travels beside them: as an
+//! error leaves a statement, [`statement_failed`] notes the statement's span
+//! the first time, and as it leaves a spell, [`spell_failed`] notes the
+//! spell so that the statement it unwinds into next becomes the call site
+//! of a [`Frame`]. [`error`] then turns the string that reached the top
+//! level into a [`CarrionError`] with that location and those frames.
+//!
+//! Each record belongs to the error message it was made for; an error with
+//! a different message starts a new one, so an error a script recovered
+//! from (`maybe`, `?`) can't lend its location to a later one.
+
+use crate::ast::Span;
+use crate::error::{CarrionError, Frame};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+struct Unwinding {
+    message: String,
+    span: Span,
+    /// The calls left so far, innermost first.
+    frames: Vec<Frame>,
+    /// The spell the error just left, waiting for the statement that called it.
+    spell: Option<Rc<str>>,
+}
+
+thread_local! {
+    static UNWINDING: RefCell<Option<Unwinding>> = const { RefCell::new(None) };
+}
+
+/// Note that the error `message` is leaving the statement at `span`.
+pub fn statement_failed(message: &str, span: &Span) {
+    UNWINDING.with(|unwinding| {
+        let mut unwinding = unwinding.borrow_mut();
+        match unwinding.as_mut() {
+            Some(current) if current.message == message => {
+                if let Some(spell) = current.spell.take() {
+                    current.frames.push(Frame {
+                        spell: spell.to_string(),
+                        span: span.clone(),
+                    });
+                }
+            }
+            _ => {
+                *unwinding = Some(Unwinding {
+                    message: message.to_string(),
+                    span: span.clone(),
+                    frames: Vec::new(),
+                    spell: None,
+                });
+            }
+        }
+    });
+}
+
+/// Note that the error `message` is leaving the spell called `spell`.
+pub fn spell_failed(message: &str, spell: &Rc<str>) {
+    UNWINDING.with(|unwinding| {
+        if let Some(current) = unwinding.borrow_mut().as_mut()
+            && current.message == message
+        {
+            current.spell = Some(Rc::clone(spell));
+        }
+    });
+}
+
+/// `message`, an error that reached the top level, with where it happened
+/// and the calls it left, if they were recorded.
+pub fn error(message: String) -> CarrionError {
+    let recorded = UNWINDING.with(|unwinding| unwinding.borrow_mut().take());
+    match recorded {
+        Some(recorded) if recorded.message == message => {
+            CarrionError::evaluation(message).at(recorded.span).with_frames(recorded.frames)
+        }
+        _ => CarrionError::evaluation(message),
+    }
+}
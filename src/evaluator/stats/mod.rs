@@ -0,0 +1,74 @@
+//! Usage counters for embedders.
+//!
+//! After [`enable`], the evaluator counts the statements it runs, the deepest
+//! scope chain a spell call creates, and how often each builtin is called.
+//! [`take`] hands the counts back as an [`InterpreterStats`] and starts
+//! counting afresh. Nothing leaves the process: the counts are only there for
+//! the embedder to read. While counting is off the evaluator skips it.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// What the evaluator did since counting started.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterpreterStats {
+    /// Statements evaluated, including those inside loops and spells.
+    pub statements: u64,
+    /// The most scopes chained together, counting the global scope.
+    pub peak_env_depth: usize,
+    /// Calls per builtin, under the name they were called by
+    /// (`len`, or `upper` for `name.upper()`).
+    pub builtin_calls: BTreeMap<String, u64>,
+}
+
+thread_local! {
+    static STATS: RefCell<Option<InterpreterStats>> = const { RefCell::new(None) };
+}
+
+/// Start counting on this thread, from zero.
+pub fn enable() {
+    STATS.with(|stats| *stats.borrow_mut() = Some(InterpreterStats::default()));
+}
+
+/// Stop counting and drop the counts.
+pub fn disable() {
+    STATS.with(|stats| *stats.borrow_mut() = None);
+}
+
+pub fn is_enabled() -> bool {
+    STATS.with(|stats| stats.borrow().is_some())
+}
+
+/// The counts so far, without resetting them.
+pub fn snapshot() -> Option<InterpreterStats> {
+    STATS.with(|stats| stats.borrow().clone())
+}
+
+/// The counts so far, resetting them to zero; `None` while counting is off.
+pub fn take() -> Option<InterpreterStats> {
+    STATS.with(|stats| stats.borrow_mut().as_mut().map(std::mem::take))
+}
+
+pub(crate) fn statement() {
+    STATS.with(|stats| {
+        if let Some(stats) = stats.borrow_mut().as_mut() {
+            stats.statements += 1;
+        }
+    });
+}
+
+pub(crate) fn env_depth(depth: usize) {
+    STATS.with(|stats| {
+        if let Some(stats) = stats.borrow_mut().as_mut() {
+            stats.peak_env_depth = stats.peak_env_depth.max(depth);
+        }
+    });
+}
+
+pub(crate) fn builtin_call(name: &str) {
+    STATS.with(|stats| {
+        if let Some(stats) = stats.borrow_mut().as_mut() {
+            *stats.builtin_calls.entry(name.to_string()).or_default() += 1;
+        }
+    });
+}
@@ -0,0 +1,217 @@
+//! Recording and replaying a script's outside inputs.
+//!
+//! With `--record trace.bin`, every nondeterministic read — the command
+//! line, environment variables, random bytes, clock reads and source files —
+//! goes through [`read`] and is appended to the trace. `--replay trace.bin`
+//! answers the same reads from the file, in order, so a failing run can be
+//! repeated exactly. A replay that asks for something else than the trace
+//! holds stops with a "Replay diverged" error.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8] = b"CRLTRACE\x01";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Event {
+    kind: String,
+    payload: Vec<u8>,
+}
+
+enum Mode {
+    Live,
+    Record { path: PathBuf, events: Vec<Event> },
+    Replay(VecDeque<Event>),
+}
+
+thread_local! {
+    static MODE: RefCell<Mode> = const { RefCell::new(Mode::Live) };
+}
+
+/// A value that can be stored in a trace.
+pub trait Traced: Sized {
+    fn encode(&self) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Record every read on this thread; [`finish`] writes them to `path`.
+pub fn start_recording(path: &Path) {
+    MODE.with(|mode| {
+        *mode.borrow_mut() = Mode::Record {
+            path: path.to_path_buf(),
+            events: Vec::new(),
+        }
+    });
+}
+
+/// Answer reads on this thread from the trace stored at `path`.
+pub fn start_replay(path: &Path) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Could not read trace '{}': {}", path.display(), e))?;
+    let events = parse(&bytes).ok_or_else(|| format!("'{}' is not a Carrion trace", path.display()))?;
+    MODE.with(|mode| *mode.borrow_mut() = Mode::Replay(events.into()));
+    Ok(())
+}
+
+/// Write the recorded trace, if recording, and go back to live reads.
+pub fn finish() -> Result<(), String> {
+    let mode = MODE.with(|mode| std::mem::replace(&mut *mode.borrow_mut(), Mode::Live));
+    if let Mode::Record { path, events } = mode {
+        let mut bytes = MAGIC.to_vec();
+        for event in &events {
+            put_bytes(&mut bytes, event.kind.as_bytes());
+            put_bytes(&mut bytes, &event.payload);
+        }
+        fs::write(&path, bytes).map_err(|e| format!("Could not write trace '{}': {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Read a value through the trace: `live` supplies it unless replaying.
+/// `kind` names the read, e.g. `"time"` or `"env:HOME"`. Pure spells can't read.
+pub fn read<T: Traced>(kind: &str, live: impl FnOnce() -> T) -> Result<T, String> {
+    super::purity::check(&format!("read {} from outside the program", kind))?;
+    let replayed = MODE.with(|mode| match &mut *mode.borrow_mut() {
+        Mode::Replay(events) => Some(events.pop_front()),
+        _ => None,
+    });
+    match replayed {
+        Some(Some(event)) if event.kind == kind => T::decode(&event.payload)
+            .ok_or_else(|| format!("Replay diverged: the trace holds a malformed {} value", kind)),
+        Some(Some(event)) => Err(format!(
+            "Replay diverged: the script read {} but the trace has {}",
+            kind, event.kind
+        )),
+        Some(None) => Err(format!("Replay diverged: the script read {} after the trace ended", kind)),
+        None => {
+            let value = live();
+            MODE.with(|mode| {
+                if let Mode::Record { events, .. } = &mut *mode.borrow_mut() {
+                    events.push(Event {
+                        kind: kind.to_string(),
+                        payload: value.encode(),
+                    });
+                }
+            });
+            Ok(value)
+        }
+    }
+}
+
+/// Read a source file through the trace.
+pub fn read_file(path: &Path) -> Result<String, String> {
+    read(&format!("file:{}", path.display()), || {
+        fs::read_to_string(path).map_err(|e| e.to_string())
+    })?
+}
+
+/// Read an environment variable through the trace.
+pub fn env_var(name: &str) -> Result<Option<String>, String> {
+    read(&format!("env:{}", name), || std::env::var(name).ok())
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn take_bytes<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+    let value = bytes.get(4..4 + len)?;
+    *bytes = &bytes[4 + len..];
+    Some(value)
+}
+
+fn parse(bytes: &[u8]) -> Option<Vec<Event>> {
+    let mut rest = bytes.strip_prefix(MAGIC)?;
+    let mut events = Vec::new();
+    while !rest.is_empty() {
+        let kind = String::from_utf8(take_bytes(&mut rest)?.to_vec()).ok()?;
+        let payload = take_bytes(&mut rest)?.to_vec();
+        events.push(Event { kind, payload });
+    }
+    Some(events)
+}
+
+impl Traced for Vec<u8> {
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl Traced for i64 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(i64::from_le_bytes(bytes.try_into().ok()?))
+    }
+}
+
+impl Traced for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl Traced for Option<String> {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Some(value) => [&[1], value.as_bytes()].concat(),
+            None => vec![0],
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first()? {
+            (1, value) => Some(Some(String::decode(value)?)),
+            (0, []) => Some(None),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Traced> Traced for Result<T, String> {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Ok(value) => [&[0], value.encode().as_slice()].concat(),
+            Err(error) => [&[1], error.as_bytes()].concat(),
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first()? {
+            (0, value) => Some(Ok(T::decode(value)?)),
+            (1, error) => Some(Err(String::decode(error)?)),
+            _ => None,
+        }
+    }
+}
+
+impl Traced for Vec<String> {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for item in self {
+            put_bytes(&mut out, item.as_bytes());
+        }
+        out
+    }
+
+    fn decode(mut bytes: &[u8]) -> Option<Self> {
+        let mut items = Vec::new();
+        while !bytes.is_empty() {
+            items.push(String::decode(take_bytes(&mut bytes)?)?);
+        }
+        Some(items)
+    }
+}
@@ -0,0 +1,70 @@
+//! A hint for scripts that have gone quiet, such as one stuck in a loop.
+//!
+//! Once [`start`] is called, a background thread watches how long it has
+//! been since the script last printed anything. When the quiet stretch
+//! reaches the limit it prints [`HINT`] to stderr, once per stretch; Ctrl+C
+//! then interrupts the script through the usual [`interrupt`] path. Builtins
+//! that print call [`output`], and builtins that wait on purpose (a
+//! breakpoint prompt, a server between requests) run inside [`waiting`], so
+//! neither sets it off.
+//!
+//! [`interrupt`]: super::interrupt
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const HINT: &str = "script still running; press Ctrl+C to interrupt, use --no-watchdog to silence";
+
+/// How long a script may stay quiet before the hint when none is configured.
+pub const DEFAULT_LIMIT: Duration = Duration::from_secs(30);
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+/// Milliseconds after [`STARTED`] at which the script last printed or waited.
+static LAST_OUTPUT: AtomicU64 = AtomicU64::new(0);
+static HINTED: AtomicBool = AtomicBool::new(false);
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// Print the hint whenever the script goes `limit` without output. Later
+/// calls do nothing.
+pub fn start(limit: Duration) {
+    if STARTED.set(Instant::now()).is_err() {
+        return;
+    }
+    thread::spawn(move || {
+        loop {
+            let quiet = now().saturating_sub(Duration::from_millis(LAST_OUTPUT.load(Ordering::Relaxed)));
+            if quiet < limit {
+                thread::sleep(limit - quiet);
+                continue;
+            }
+            if WAITING.load(Ordering::Relaxed) == 0 && !HINTED.swap(true, Ordering::Relaxed) {
+                eprintln!("{}", HINT);
+            }
+            thread::sleep(limit.min(Duration::from_millis(100)));
+        }
+    });
+}
+
+/// Note that the script just printed something.
+pub fn output() {
+    if STARTED.get().is_some() {
+        LAST_OUTPUT.store(now().as_millis() as u64, Ordering::Relaxed);
+        HINTED.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Run `wait`, which blocks on purpose, without the watchdog counting it as
+/// quiet time.
+pub fn waiting<T>(wait: impl FnOnce() -> T) -> T {
+    WAITING.fetch_add(1, Ordering::Relaxed);
+    let result = wait();
+    WAITING.fetch_sub(1, Ordering::Relaxed);
+    output();
+    result
+}
+
+fn now() -> Duration {
+    STARTED.get().map_or(Duration::ZERO, Instant::elapsed)
+}
@@ -0,0 +1,145 @@
+//! `carrion examples`: run the example programs and check what they print.
+//!
+//! Every `.crl` file directly inside the examples directory (`examples/` by
+//! default) is an example, and the `.out` file next to it records the output
+//! it should print. Plain `carrion examples` runs them all and shows their
+//! output; `--verify` compares each run with its recording and fails on any
+//! difference; `--update` records the current output instead. Examples run
+//! as separate `carrion` processes from their own directory, so one that
+//! fails can't disturb the others.
+
+use crate::evaluator::builtins::diff::text_diff;
+use crate::evaluator::modules::SOURCE_EXTENSION;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where examples live unless another directory is given.
+pub const DEFAULT_DIRECTORY: &str = "examples";
+
+/// Extension of the files that record each example's output.
+pub const OUTPUT_EXTENSION: &str = "out";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Run each example and show what it printed.
+    Show,
+    /// Compare each example's output with its recording.
+    Verify,
+    /// Record each example's output.
+    Update,
+}
+
+/// The outcome of running one example: `output` is what it printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExampleResult {
+    pub file: PathBuf,
+    pub output: String,
+    pub outcome: Result<(), String>,
+}
+
+/// Run the examples in `dir` with this executable, printing a report.
+/// Returns `true` when every example ran (and, with `--verify`, matched).
+pub fn run(dir: &Path, mode: Mode) -> bool {
+    let carrion = match std::env::current_exe() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error: cannot find the carrion executable: {}", e);
+            return false;
+        }
+    };
+    let results = run_examples(dir, &carrion, mode);
+    if results.is_empty() {
+        println!("No examples found in {}", dir.display());
+        return true;
+    }
+
+    for result in &results {
+        match (&result.outcome, mode) {
+            (Ok(()), Mode::Show) => print!("== {} ==\n{}", result.file.display(), result.output),
+            (Ok(()), Mode::Verify) => println!("PASS {}", result.file.display()),
+            (Ok(()), Mode::Update) => println!("RECORDED {}", output_path(&result.file).display()),
+            (Err(e), _) => println!("FAIL {}\n{}", result.file.display(), indent(e)),
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+    println!("\n{} passed, {} failed", results.len() - failed, failed);
+    failed == 0
+}
+
+/// Run every example in `dir` with the interpreter at `carrion`.
+pub fn run_examples(dir: &Path, carrion: &Path, mode: Mode) -> Vec<ExampleResult> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files.into_iter().map(|file| run_example(&file, carrion, mode)).collect()
+}
+
+/// The file that records the output of the example at `file`.
+pub fn output_path(file: &Path) -> PathBuf {
+    file.with_extension(OUTPUT_EXTENSION)
+}
+
+fn run_example(file: &Path, carrion: &Path, mode: Mode) -> ExampleResult {
+    let (output, ran) = execute(file, carrion);
+    let outcome = ran.and_then(|()| match mode {
+        Mode::Show => Ok(()),
+        Mode::Verify => verify(file, &output),
+        Mode::Update => fs::write(output_path(file), &output)
+            .map_err(|e| format!("Could not write {}: {}", output_path(file).display(), e)),
+    });
+    ExampleResult {
+        file: file.to_path_buf(),
+        output,
+        outcome,
+    }
+}
+
+/// Run the example and return its standard output, and whether it finished
+/// without errors.
+fn execute(file: &Path, carrion: &Path) -> (String, Result<(), String>) {
+    let (dir, name) = match (file.parent(), file.file_name()) {
+        (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => (dir, name),
+        _ => (Path::new("."), file.as_os_str()),
+    };
+    let output = match Command::new(carrion).arg(name).current_dir(dir).output() {
+        Ok(output) => output,
+        Err(e) => return (String::new(), Err(format!("Could not run {}: {}", carrion.display(), e))),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+    if output.status.success() {
+        return (stdout, Ok(()));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    (stdout, Err(format!("Exited with {}:\n{}", output.status, stderr.trim_end())))
+}
+
+fn verify(file: &Path, output: &str) -> Result<(), String> {
+    let path = output_path(file);
+    let expected = fs::read_to_string(&path).map_err(|_| {
+        format!(
+            "No recorded output at {} (run `carrion examples --update` to record it)",
+            path.display()
+        )
+    })?;
+    if expected.replace("\r\n", "\n") == output {
+        return Ok(());
+    }
+    Err(format!(
+        "Output differs from {} (- recorded, + actual):\n{}",
+        path.display(),
+        text_diff(&expected, output).join("\n")
+    ))
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("    {}", line)).collect::<Vec<_>>().join("\n")
+}
@@ -1,6 +1,7 @@
 //! lexer.rs  ― Carrion language
 
 use crate::token::{KEYWORDS, Token, TokenType};
+use crate::version::{self, Version};
 use std::path::PathBuf;
 
 /// Scans a UTF-8 source file into a stream of `Token`s.
@@ -17,12 +18,15 @@ pub struct Lexer {
     at_line_start: bool,
     pending_dedents: usize,
     max_nesting_depth: usize,
+    /// The language version the source asks for.
+    version: Version,
 }
 
 impl Lexer {
     /// Create a new lexer for the given source string and filename.
     pub fn new(source: String, file: PathBuf) -> Self {
         Self {
+            version: version::of(&source),
             source,
             tokens: Vec::new(),
             start: 0,
@@ -55,7 +59,7 @@ impl Lexer {
             "",
             self.file.clone(),
             self.line,
-            self.current,
+            self.column(self.current),
         ));
 
         std::mem::take(&mut self.tokens)
@@ -96,6 +100,12 @@ impl Lexer {
 
     // ─── TOKEN EMISSION HELPERS ───────────────────────────────────────────────
 
+    /// 1-based column of the byte offset `pos` within its line.
+    fn column(&self, pos: usize) -> usize {
+        let line_start = self.source[..pos].rfind('\n').map_or(0, |i| i + 1);
+        self.source[line_start..pos].chars().count() + 1
+    }
+
     fn add_simple(&mut self, kind: TokenType) {
         let text = &self.source[self.start..self.current];
         self.tokens.push(Token::new(
@@ -103,7 +113,7 @@ impl Lexer {
             text,
             self.file.clone(),
             self.line,
-            self.start,
+            self.column(self.start),
         ));
     }
 
@@ -113,7 +123,7 @@ impl Lexer {
             value.to_string(),
             self.file.clone(),
             self.line,
-            self.start,
+            self.column(self.start),
         ));
     }
 
@@ -161,10 +171,33 @@ impl Lexer {
             '{' => self.add_simple(TokenType::LeftBrace),
             '}' => self.add_simple(TokenType::RightBrace),
             '@' => self.add_simple(TokenType::At),
-            '%' => self.add_simple(TokenType::Mod),
+            '%' => {
+                let kind = if self.match_char('=') {
+                    TokenType::ModAssign
+                } else {
+                    TokenType::Mod
+                };
+                self.add_simple(kind);
+            }
             ',' => self.add_simple(TokenType::Comma),
             ':' => self.add_simple(TokenType::Colon),
-            '.' => self.add_simple(TokenType::Dot),
+            ';' => self.add_simple(TokenType::Semicolon),
+            '.' => {
+                let kind = if self.match_char('.') {
+                    TokenType::DotDot
+                } else {
+                    TokenType::Dot
+                };
+                self.add_simple(kind);
+            }
+            '?' => {
+                let kind = if self.match_char('.') {
+                    TokenType::QuestionDot
+                } else {
+                    TokenType::Question
+                };
+                self.add_simple(kind);
+            }
             '|' => self.add_simple(TokenType::Pipe),
             '~' => self.add_simple(TokenType::Tilde),
             '^' => self.add_simple(TokenType::Xor),
@@ -185,6 +218,13 @@ impl Lexer {
                     self.add_simple(TokenType::Slash);
                 }
             }
+            // `_name` and `__next__` are names; a lone `_` or `__` is its own token.
+            '_' if self.peek().is_some_and(char::is_alphanumeric)
+                || (self.peek() == Some('_')
+                    && self.peek_next().is_some_and(|c| c.is_alphanumeric() || c == '_')) =>
+            {
+                self.identifier()
+            }
             '_' => {
                 let kind = if self.match_char('_') {
                     TokenType::DoubleUnderscore
@@ -205,7 +245,11 @@ impl Lexer {
                 let kind = if self.match_char('=') {
                     TokenType::AsteriskAssign
                 } else if self.match_char('*') {
-                    TokenType::Exponent
+                    if self.match_char('=') {
+                        TokenType::ExponentAssign
+                    } else {
+                        TokenType::Exponent
+                    }
                 } else {
                     TokenType::Asterisk
                 };
@@ -267,9 +311,26 @@ impl Lexer {
             // whitespace / newlines -------------------------------------------
             ' ' | '\r' | '\t' => {}
             '\n' => {
+                // The newline belongs to the line it ends.
+                self.add_simple(TokenType::Newline);
                 self.line += 1;
                 self.at_line_start = true;
-                self.add_simple(TokenType::Newline);
+            }
+            // `\` at the end of a line joins the next line to it: no Newline
+            // token, and the next line's indentation is just whitespace.
+            '\\' => {
+                while matches!(self.peek(), Some(' ' | '\t' | '\r')) {
+                    self.advance();
+                }
+                if self.match_char('\n') {
+                    self.line += 1;
+                } else {
+                    eprintln!(
+                        "[Line {}, Col {}] '\\' only continues a line at its end, skipping.",
+                        self.line,
+                        self.column(self.start)
+                    );
+                }
             }
 
             // literals ---------------------------------------------------------
@@ -280,7 +341,7 @@ impl Lexer {
             // catch-all --------------------------------------------------------
             _ => eprintln!(
                 "[Line {}, Col {}] Unexpected '{}', skipping.",
-                self.line, self.start, c
+                self.line, self.column(self.start), c
             ),
         }
     }
@@ -308,12 +369,10 @@ impl Lexer {
     }
 
     fn number(&mut self) {
-        while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-            self.advance();
-        }
+        self.digits();
 
         // fractional?
-        let is_float = self.peek() == Some('.')
+        let mut is_float = self.peek() == Some('.')
             && self
                 .peek_next()
                 .map(|c| c.is_ascii_digit())
@@ -321,8 +380,20 @@ impl Lexer {
 
         if is_float {
             self.advance(); // consume '.'
-            while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-                self.advance();
+            self.digits();
+        }
+
+        // exponent, e.g. 1e9 or 2.5e-3
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let signed = matches!(self.peek_next(), Some('+' | '-'));
+            let mut rest = self.source[self.current..].chars().skip(if signed { 2 } else { 1 });
+            if rest.next().is_some_and(|c| c.is_ascii_digit()) {
+                self.advance(); // consume 'e'
+                if signed {
+                    self.advance();
+                }
+                self.digits();
+                is_float = true;
             }
         }
 
@@ -336,6 +407,21 @@ impl Lexer {
         self.add_literal(kind, lexeme);
     }
 
+    /// Consume a run of digits, allowing single `_` separators between them.
+    fn digits(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => {
+                    self.advance();
+                }
+                Some('_') if self.peek_next().is_some_and(|c| c.is_ascii_digit()) => {
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+    }
+
     fn identifier(&mut self) {
         while self
             .peek()
@@ -346,7 +432,13 @@ impl Lexer {
         }
 
         let text = &self.source[self.start..self.current];
-        let key = text.to_ascii_lowercase(); // lexer is case-insensitive
+        // Before 0.2 keywords are case-insensitive; from 0.2 only the
+        // values `True`, `False` and `None` are capitalised.
+        let key = if !self.version.case_sensitive_keywords() || matches!(text, "True" | "False" | "None") {
+            text.to_ascii_lowercase()
+        } else {
+            text.to_string()
+        };
         let kind = KEYWORDS
             .get(key.as_str())
             .copied()
@@ -358,7 +450,7 @@ impl Lexer {
     fn block_comment(&mut self) {
         let start_pos = self.current;
         
-        while !(self.peek() == Some('*') && self.peek_next() == Some('/')) && !self.is_at_end() {
+        while !(self.is_at_end() || self.peek() == Some('*') && self.peek_next() == Some('/')) {
             if self.peek() == Some('\n') {
                 self.line += 1;
             }
@@ -485,11 +577,4 @@ impl Lexer {
         
         Ok(())
     }
-
-    /// Legacy indentation handler for backward compatibility
-    fn handle_indentation(&mut self) {
-        if let Err(e) = self.handle_indentation_safe() {
-            eprintln!("Indentation error at line {}: {}", self.line, e);
-        }
-    }
 }
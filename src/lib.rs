@@ -1,9 +1,16 @@
 // This file makes your modules available to external crates, like your tests.
 pub mod ast;
+pub mod cache;
+pub mod crash;
 pub mod error;
 pub mod evaluator;
+pub mod examples;
 pub mod lexer;
 pub mod object;
 pub mod parser;
 pub mod repl;
+pub mod strict;
+pub mod test_runner;
 pub mod token;
+pub mod version;
+pub mod watch;
@@ -1,70 +1,343 @@
+use std::cell::RefCell;
 use std::env;
-use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::rc::Rc;
+use std::time::Duration;
+use rustyline::DefaultEditor;
+use the_carrion_language::evaluator::builtins::{log, snapshot};
+use the_carrion_language::evaluator::environment::Environment;
+use the_carrion_language::evaluator::{debugger, slow, trace, watchdog};
+use the_carrion_language::error::{self, SourceMap};
+use the_carrion_language::{cache, crash, evaluator, examples, lexer, object, parser, repl, strict, test_runner, version, watch};
 
-mod ast;
-mod error;
-mod evaluator;
-mod lexer;
-mod object;
-mod parser;
-mod repl;
-mod token;
+const USAGE: &str = "Usage: carrion [--log-level LEVEL] [--log-json] [--slow-statements MS]
+               [--int-overflow error|float] [--strict] [--no-cache] [--verbose-errors] [--plugins]
+               [--watchdog SECONDS | --no-watchdog]
+               [--record TRACE | --replay TRACE] [file_path [args...] | -e CODE]
+       carrion test [--update-snapshots] [--doc] [paths...]
+       carrion examples [--verify | --update] [dir]
+       carrion watch [--full-restart] file_path";
+
+/// The first words that name a subcommand rather than a script.
+const SUBCOMMANDS: [&str; 3] = ["test", "examples", "watch"];
 
 fn main() {
+    // A panic is a bug in the interpreter: report it with a crash bundle.
+    crash::install();
+    if let Err(report) = crash::catch(run) {
+        eprintln!("{}", report);
+        process::exit(101);
+    }
+}
+
+fn run() {
+    log::init_from_env();
+    error::set_color(std::io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none());
+
     let mut args = env::args();
     args.next(); // Skip the program name
 
-    if let Some(file_path) = args.next() {
-        if args.next().is_some() {
-            eprintln!("Error: Expected 0 or 1 arguments (path to file), but received more.");
-            eprintln!("Usage: carrion [file_path]");
-            process::exit(1);
+    let mut positionals: Vec<String> = Vec::new();
+    let mut doc = false;
+    let mut full_restart = false;
+    let mut example_mode = examples::Mode::Show;
+    let mut recording = None;
+    let mut code = None;
+    let mut use_cache = true;
+    let mut load_plugins = false;
+    // Only someone watching the terminal needs to hear that a script is still running.
+    let mut watchdog = std::io::stderr().is_terminal().then_some(watchdog::DEFAULT_LIMIT);
+    while let Some(arg) = args.next() {
+        // Everything after a script's path is the script's own arguments.
+        if positionals.first().is_some_and(|first| !SUBCOMMANDS.contains(&first.as_str())) {
+            positionals.push(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "--log-level" => {
+                let Some(name) = args.next() else {
+                    exit_with_usage("Error: --log-level requires a value (debug, info, warn, error).");
+                };
+                match log::Level::parse(&name) {
+                    Some(level) => log::set_level(level),
+                    None => exit_with_usage(&format!("Error: Unknown log level '{}'.", name)),
+                }
+            }
+            "--log-json" => log::set_json(true),
+            "--slow-statements" => {
+                let threshold = args
+                    .next()
+                    .and_then(|ms| ms.parse::<f64>().ok())
+                    .and_then(|ms| Duration::try_from_secs_f64(ms / 1000.0).ok());
+                let Some(threshold) = threshold else {
+                    exit_with_usage("Error: --slow-statements requires a threshold in milliseconds.");
+                };
+                slow::enable(
+                    threshold,
+                    Box::new(|span, elapsed| eprintln!("{}", slow::describe(span, elapsed))),
+                );
+            }
+            "--int-overflow" => {
+                let Some(name) = args.next() else {
+                    exit_with_usage("Error: --int-overflow requires a value (error, float).");
+                };
+                match parser::IntegerOverflow::parse(&name) {
+                    Some(mode) => parser::overflow::set_mode(mode),
+                    None => exit_with_usage(&format!("Error: Unknown --int-overflow mode '{}'.", name)),
+                }
+            }
+            "--watchdog" => {
+                let limit = args
+                    .next()
+                    .and_then(|secs| secs.parse::<f64>().ok())
+                    .filter(|secs| *secs > 0.0)
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+                let Some(limit) = limit else {
+                    exit_with_usage("Error: --watchdog requires a number of seconds.");
+                };
+                watchdog = Some(limit);
+            }
+            "--no-watchdog" => watchdog = None,
+            "--strict" => strict::set_enabled(true),
+            "--no-cache" => use_cache = false,
+            "--plugins" => load_plugins = true,
+            "--verbose-errors" => error::set_verbose(true),
+            "--update-snapshots" => snapshot::set_update(true),
+            "--doc" => doc = true,
+            "--full-restart" => full_restart = true,
+            "--verify" => example_mode = examples::Mode::Verify,
+            "--update" => example_mode = examples::Mode::Update,
+            "--record" | "--replay" if recording.is_some() => {
+                exit_with_usage("Error: --record and --replay can only be given once.");
+            }
+            "--record" | "--replay" => {
+                let Some(path) = args.next() else {
+                    exit_with_usage(&format!("Error: {} requires a trace file path.", arg));
+                };
+                recording = Some((arg == "--record", PathBuf::from(path)));
+            }
+            "-e" if code.is_some() => exit_with_usage("Error: -e can only be given once."),
+            "-e" => {
+                let Some(source) = args.next() else {
+                    exit_with_usage("Error: -e requires the code to run.");
+                };
+                code = Some(source);
+            }
+            flag if flag.starts_with("--") => {
+                exit_with_usage(&format!("Error: Unknown option '{}'.", flag));
+            }
+            _ => positionals.push(arg),
+        }
+    }
+
+    // `breakpoint()` reads its commands from the terminal.
+    let mut editor: Option<DefaultEditor> = None;
+    debugger::set_reader(Box::new(move |prompt| {
+        if editor.is_none() {
+            editor = DefaultEditor::new().ok();
+        }
+        editor.as_mut()?.readline(prompt).ok()
+    }));
+
+    if positionals.first().is_some_and(|command| command == "test") {
+        let mut paths: Vec<PathBuf> = positionals[1..].iter().map(PathBuf::from).collect();
+        if paths.is_empty() {
+            paths.push(PathBuf::from("."));
+        }
+        let passed = test_runner::run(&paths, doc);
+        process::exit(if passed { 0 } else { 1 });
+    }
+
+    if positionals.first().is_some_and(|command| command == "examples") {
+        let dir = match positionals.as_slice() {
+            [_] => PathBuf::from(examples::DEFAULT_DIRECTORY),
+            [_, dir] => PathBuf::from(dir),
+            _ => exit_with_usage("Error: carrion examples expects at most one directory."),
+        };
+        let passed = examples::run(&dir, example_mode);
+        process::exit(if passed { 0 } else { 1 });
+    }
+
+    if positionals.first().is_some_and(|command| command == "watch") {
+        let [_, path] = positionals.as_slice() else {
+            exit_with_usage("Error: carrion watch expects exactly one file path.");
+        };
+        watch::run(&PathBuf::from(path), full_restart);
+    }
+
+    // A replay runs the recorded file with the recorded arguments.
+    if let Some((record, path)) = &recording {
+        if *record {
+            trace::start_recording(path);
+        } else if let Err(e) = trace::start_replay(path) {
+            exit_with_usage(&format!("Error: {}", e));
         }
-        let path = PathBuf::from(file_path);
-        if let Err(e) = run_file(&path) {
+        let live = positionals;
+        positionals = trace::read("argv", || live).unwrap_or_else(|e| exit_with_usage(&format!("Error: {}", e)));
+    }
+
+    if load_plugins && (code.is_some() || positionals.is_empty()) {
+        exit_with_usage("Error: --plugins only applies when running a file.");
+    }
+
+    if let Some(limit) = watchdog
+        && (code.is_some() || !positionals.is_empty())
+    {
+        watchdog::start(limit);
+    }
+
+    if let Some(code) = code {
+        if !positionals.is_empty() {
+            exit_with_usage("Error: -e cannot be combined with a file path.");
+        }
+        run_source(code, &evaluator::snippets::source_name("-e"), &[], false);
+    } else if !positionals.is_empty() {
+        let path = PathBuf::from(positionals.remove(0));
+        load_manifest(&path, load_plugins);
+        if let Err(e) = run_file(&path, &positionals, use_cache) {
             eprintln!("Error running file: {}", e);
+            finish_trace();
             process::exit(1);
         }
     } else {
         println!("Welcome to the Carrion REPL!");
         repl::run_repl();
     }
+    finish_trace();
+}
+
+/// Apply the `carrion.toml` beside the script at `file_path`, if there is
+/// one: its language version covers files without a version pragma, and with
+/// `--plugins` the native modules it lists load before the script runs.
+fn load_manifest(file_path: &Path, plugins: bool) {
+    let manifest = file_path.parent().unwrap_or(Path::new("")).join(evaluator::modules::plugins::MANIFEST);
+    if !manifest.is_file() {
+        return;
+    }
+    let loaded = version::load_manifest(&manifest)
+        .and_then(|_| if plugins { evaluator::modules::plugins::load_manifest(&manifest).map(|_| ()) } else { Ok(()) });
+    if let Err(e) = loaded {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Write the `--record` trace, if any.
+fn finish_trace() {
+    if let Err(e) = trace::finish() {
+        eprintln!("Error: {}", e);
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(1);
 }
 
-fn run_file(file_path: &PathBuf) -> io::Result<()> {
-    let source = fs::read_to_string(file_path)?;
+fn run_file(file_path: &Path, args: &[String], use_cache: bool) -> Result<(), String> {
+    let source = trace::read_file(file_path)?;
+    run_source(source, file_path, args, use_cache);
+    Ok(())
+}
 
+/// The program in `source` and what strict mode finds in it. Exits if it
+/// doesn't parse, showing the errors against `sources`.
+fn parse_source(source: &str, file_path: &Path, sources: &SourceMap) -> cache::Entry {
     // 1. Lexing
-    let mut lexer = lexer::Lexer::new(source, file_path.clone());
+    let mut lexer = lexer::Lexer::new(source.to_string(), file_path.to_path_buf());
     let tokens = lexer.scan_tokens();
+    crash::reached(crash::Stage::Lexed);
+    let mut warnings = strict::check_tokens(&tokens);
 
     // 2. Parsing
     let mut parser = parser::Parser::new(tokens);
     let program = parser.parse_program();
 
     if !parser.errors().is_empty() {
-        eprintln!("Encountered parsing errors:");
-        for err in parser.errors() {
-            eprintln!("\t{}", err);
-        }
-        return Ok(()); // Don't proceed to evaluation if parsing fails
+        eprintln!("{}", error::render_parse_errors(parser.errors(), sources));
+        // Don't proceed to evaluation if parsing fails
+        finish_trace();
+        process::exit(1);
     }
 
-    // 3. Evaluation
-    match evaluator::eval(&program) {
-        Ok(evaluated) => {
-            // Only print if the final result isn't 'None'
-            if evaluated != object::Object::None {
-                println!("{}", evaluated);
+    warnings.extend(strict::check_program(&program));
+    warnings.sort_by_key(|warning| (warning.span.line, warning.span.column));
+    cache::Entry { program, warnings }
+}
+
+/// Run a script's source, then its `main` spell with `args`, if it has one;
+/// `file_path` names it in error messages, and may be a snippet's virtual
+/// name such as `<-e>`. Exits on errors, or with the status `main` returns.
+fn run_source(source: String, file_path: &Path, args: &[String], use_cache: bool) {
+    crash::set_script(file_path, &source);
+    let mut sources = SourceMap::new();
+    sources.add(file_path, source.as_str());
+    if let Err(e) = version::pragma(&source) {
+        eprintln!("Error: {}: {}", file_path.display(), e);
+        finish_trace();
+        process::exit(1);
+    }
+    if strict::has_pragma(&source) {
+        strict::set_enabled(true);
+    }
+
+    // 1-2. Lexing and parsing, unless an earlier run cached the result
+    let cached = if use_cache { cache::load(&source, file_path) } else { None };
+    let cache::Entry { program, warnings } = match cached {
+        Some(entry) => entry,
+        None => {
+            let entry = parse_source(&source, file_path, &sources);
+            if use_cache {
+                cache::store(&source, &entry);
             }
+            entry
         }
-        Err(e) => {
-            eprintln!("Evaluation Error: {}", e);
+    };
+    crash::reached(crash::Stage::Parsed);
+
+    // Suspicious code is an error in strict mode, and a warning otherwise.
+    if strict::is_enabled() && !warnings.is_empty() {
+        eprintln!("Strict mode errors:");
+        for warning in &warnings {
+            eprintln!("\t{}", warning);
         }
+        finish_trace();
+        process::exit(1);
+    }
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
     }
 
-    Ok(())
+    // 3. Read and parse imported modules ahead of evaluation
+    evaluator::modules::prefetch(&program);
+
+    // 4. Evaluation, then `main`
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let evaluated = evaluator::eval_with_env(&program, &env).and_then(|evaluated| {
+        // Only print if the final result isn't 'None'
+        if evaluated != object::Object::None {
+            println!("{}", evaluated);
+        }
+        evaluator::call_main(&env, args)
+    });
+    match evaluated {
+        Ok(Some(object::Object::Integer(status))) => {
+            finish_trace();
+            process::exit(status as i32);
+        }
+        Ok(_) => {}
+        Err(e) if e == evaluator::interrupt::INTERRUPTED => {
+            eprintln!("{}", e);
+            finish_trace();
+            process::exit(130);
+        }
+        Err(e) => {
+            eprintln!("{}", error::render_diagnostic(&evaluator::stack::error(e), &sources));
+            finish_trace();
+            process::exit(1);
+        }
+    }
 }
@@ -1,12 +1,50 @@
-use crate::ast::{BlockStatement, Identifier};
+use crate::ast::{Identifier, Operator, SpannedStatement};
+use crate::evaluator::environment::Environment;
+use std::any::Any;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+mod set;
+mod string;
+pub use set::Set;
+pub use string::Str;
 
 pub type BuiltinFunction = fn(Vec<Object>) -> Result<Object, String>;
+/// A builtin called with keyword arguments: the positional arguments, then
+/// the keywords as `(name, value)` pairs in the order they were written.
+pub type KeywordBuiltinFunction = fn(Vec<Object>, Vec<(String, Object)>) -> Result<Object, String>;
+
+/// The hasher behind [`Map`] and [`DictMap`]. With the default `fast-hash`
+/// feature it is FxHash, which is much quicker for short names; without it,
+/// std's DoS-resistant SipHash.
+#[cfg(feature = "fast-hash")]
+pub type Hasher = rustc_hash::FxBuildHasher;
+#[cfg(not(feature = "fast-hash"))]
+pub type Hasher = std::collections::hash_map::RandomState;
+
+/// String-keyed map behind instance fields and scopes.
+pub type Map<V> = HashMap<String, V, Hasher>;
+
+/// The entries of a dict. Keys keep their type, so `1` and `"1"` are
+/// different keys.
+pub type DictMap = HashMap<Object, Object, Hasher>;
 
 #[derive(Clone)]
 pub struct Builtin {
     pub func: BuiltinFunction,
+    /// What runs instead of `func` when a call passes keyword arguments,
+    /// for the few builtins that take them.
+    pub keywords: Option<KeywordBuiltinFunction>,
+}
+
+impl Builtin {
+    /// A builtin that takes no keyword arguments.
+    pub fn new(func: BuiltinFunction) -> Builtin {
+        Builtin { func, keywords: None }
+    }
 }
 
 impl PartialEq for Builtin {
@@ -26,41 +64,456 @@ pub enum Object {
     Integer(i64),
     Float(f64),
     Boolean(bool),
-    String(String),
-    List(Vec<Object>),
-    Dict(HashMap<String, Object>),
+    String(Str),
+    /// Lists, tuples and sets share their elements when copied; builtins
+    /// that change one copy it first only if it is still shared.
+    List(Rc<Vec<Object>>),
+    /// An immutable, fixed-size sequence: `(1, 2)`.
+    Tuple(Rc<Vec<Object>>),
+    /// Distinct elements in insertion order: `{1, 2}`.
+    Set(Rc<Set>),
+    /// Integers from `start` up to `stop`, produced on demand: `0..10`.
+    Range(Range),
+    Dict(DictMap),
     ReturnValue(Box<Object>),
     Function(Function),
     Error(String),
     Builtin(Builtin),
+    Grimoire(Rc<Grimoire>),
+    Instance(Rc<RefCell<Instance>>),
+    BoundMethod(Rc<BoundMethod>),
+    Native(NativeHandle),
+    Module(Rc<Module>),
     None,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// `range(start, stop, step)`: `start`, `start + step`, ... stopping before
+/// `stop`. `step` is never zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub stop: i64,
+    pub step: i64,
+}
+
+impl Range {
+    pub fn len(&self) -> usize {
+        let (start, stop, step) = (self.start as i128, self.stop as i128, self.step as i128);
+        let span = if step > 0 { stop - start } else { start - stop };
+        if span <= 0 {
+            0
+        } else {
+            ((span - 1) / step.abs() + 1) as usize
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `index`th value, if the range has that many.
+    pub fn get(&self, index: usize) -> Option<i64> {
+        (index < self.len()).then(|| (self.start as i128 + index as i128 * self.step as i128) as i64)
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let offset = value as i128 - self.start as i128;
+        let index = offset / self.step as i128;
+        offset % self.step as i128 == 0 && index >= 0 && index < self.len() as i128
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + use<> {
+        let range = *self;
+        (0..range.len()).map(move |index| (range.start as i128 + index as i128 * range.step as i128) as i64)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.step == 1 {
+            write!(f, "range({}, {})", self.start, self.stop)
+        } else {
+            write!(f, "range({}, {}, {})", self.start, self.stop, self.step)
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Function {
-    pub parameters: Vec<Identifier>,
-    pub body: BlockStatement,
-    // Environment will be stored externally to avoid circular dependency
+    /// What error traces call the spell: its name, `Grimoire.method` for a
+    /// method, or `<anonymous>` for a spell literal.
+    pub name: Rc<str>,
+    pub parameters: Rc<[Identifier]>,
+    /// Collects the arguments beyond `parameters` into a list.
+    pub rest: Option<Identifier>,
+    /// Collects keyword arguments that name no parameter into a dict.
+    pub keywords: Option<Identifier>,
+    pub body: Rc<[SpannedStatement]>,
+    /// The scope the spell was defined in; calls run in a child of it.
+    pub env: Rc<RefCell<Environment>>,
+    /// Declared with `pure spell`; calls run under [`purity`](crate::evaluator::purity) checks.
+    pub pure: bool,
+}
+
+// The captured environment usually contains the function itself, so equality
+// and debug output must not recurse into it.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.parameters == other.parameters
+            && self.rest == other.rest
+            && self.keywords == other.keywords
+            && self.body == other.body
+            && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("parameters", &self.parameters)
+            .field("rest", &self.rest)
+            .field("keywords", &self.keywords)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A grimoire (class): its name, the spells it defines, and the grimoire it
+/// inherits the rest of its spells from. An arcane grimoire can't be
+/// instantiated, and its arcane spells are left for children to define.
+/// Static spells and attributes belong to the grimoire itself; instances
+/// and child grimoires read them through it.
+#[derive(Debug, PartialEq)]
+pub struct Grimoire {
+    pub name: String,
+    pub methods: HashMap<String, Function>,
+    pub parent: Option<Rc<Grimoire>>,
+    pub arcane: bool,
+    pub arcane_spells: Vec<String>,
+    pub static_spells: HashMap<String, Function>,
+    /// Property spells run to read an instance's attribute.
+    pub getters: HashMap<String, Function>,
+    /// Property spells run to assign an instance's attribute.
+    pub setters: HashMap<String, Function>,
+    /// Shared by every instance; `Grimoire.name = value` changes them all.
+    pub attributes: RefCell<Map<Object>>,
+}
+
+impl Grimoire {
+    /// The spell `name`, from this grimoire or the nearest ancestor defining
+    /// it. An arcane spell has no definition, so it hides any ancestor's.
+    pub fn method(&self, name: &str) -> Option<&Function> {
+        match (self.methods.get(name), &self.parent) {
+            (Some(method), _) => Some(method),
+            _ if self.arcane_spells.iter().any(|spell| spell == name) => None,
+            (None, Some(parent)) => parent.method(name),
+            (None, None) => None,
+        }
+    }
+
+    /// The static spell `name`, from this grimoire or the nearest ancestor
+    /// defining it.
+    pub fn static_spell(&self, name: &str) -> Option<&Function> {
+        match (self.static_spells.get(name), &self.parent) {
+            (Some(spell), _) => Some(spell),
+            (None, Some(parent)) => parent.static_spell(name),
+            (None, None) => None,
+        }
+    }
+
+    /// The spell reading the property `name`, from this grimoire or the
+    /// nearest ancestor defining it.
+    pub fn getter(&self, name: &str) -> Option<&Function> {
+        match (self.getters.get(name), &self.parent) {
+            (Some(getter), _) => Some(getter),
+            (None, Some(parent)) => parent.getter(name),
+            (None, None) => None,
+        }
+    }
+
+    /// The spell assigning the property `name`, from this grimoire or the
+    /// nearest ancestor defining it.
+    pub fn setter(&self, name: &str) -> Option<&Function> {
+        match (self.setters.get(name), &self.parent) {
+            (Some(setter), _) => Some(setter),
+            (None, Some(parent)) => parent.setter(name),
+            (None, None) => None,
+        }
+    }
+
+    /// The attribute `name`, from this grimoire or the nearest ancestor
+    /// defining it.
+    pub fn attribute(&self, name: &str) -> Option<Object> {
+        match (self.attributes.borrow().get(name), &self.parent) {
+            (Some(value), _) => Some(value.clone()),
+            (None, Some(parent)) => parent.attribute(name),
+            (None, None) => None,
+        }
+    }
+
+    /// The grimoire declaring `name` as an arcane spell, when that's what a
+    /// lookup of `name` finds.
+    pub fn arcane_owner(&self, name: &str) -> Option<&Grimoire> {
+        if self.methods.contains_key(name) {
+            None
+        } else if self.arcane_spells.iter().any(|spell| spell == name) {
+            Some(self)
+        } else {
+            self.parent.as_ref().and_then(|parent| parent.arcane_owner(name))
+        }
+    }
+
+    /// Arcane spells, from this grimoire or its ancestors, that nothing
+    /// defines, in name order.
+    pub fn missing_overrides(&self) -> Vec<String> {
+        let mut missing: Vec<String> = self
+            .arcane_names()
+            .into_iter()
+            .filter(|name| self.arcane_owner(name).is_some())
+            .collect();
+        missing.sort();
+        missing.dedup();
+        missing
+    }
+
+    fn arcane_names(&self) -> Vec<String> {
+        let mut names = self.arcane_spells.clone();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.arcane_names());
+        }
+        names
+    }
+
+    /// The names of every spell instances respond to, inherited ones included.
+    pub fn method_names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.methods.keys().collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.method_names().into_iter().filter(|name| !self.methods.contains_key(*name)));
+        }
+        names
+    }
+}
+
+/// An object created by calling a grimoire, holding its own field values.
+pub struct Instance {
+    pub grimoire: Rc<Grimoire>,
+    pub fields: Map<Object>,
+}
+
+impl Instance {
+    pub fn new(grimoire: Rc<Grimoire>) -> Self {
+        Self {
+            grimoire,
+            fields: Map::default(),
+        }
+    }
+}
+
+// Instances compare by identity; fields may refer back to the instance itself.
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for Instance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut fields: Vec<&String> = self.fields.keys().collect();
+        fields.sort();
+        f.debug_struct("Instance")
+            .field("grimoire", &self.grimoire.name)
+            .field("fields", &fields)
+            .finish()
+    }
+}
+
+/// A spell or builtin looked up through `receiver.name`, remembering its receiver.
+///
+/// Spells get the receiver bound to `self`; builtins get it as their first argument.
+#[derive(Debug, PartialEq)]
+pub struct BoundMethod {
+    pub receiver: Object,
+    pub method: Object,
+}
+
+/// An imported file; its top-level bindings are reached as `module.name`.
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub env: Rc<RefCell<Environment>>,
+}
+
+// Each file is loaded once, so a module is only ever equal to itself.
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Module")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host-side state exposed to scripts, such as database connections.
+///
+/// Methods are ordinary builtins that receive the object as their first
+/// argument and recover the concrete type through `as_any`.
+pub trait NativeObject: Debug {
+    fn type_name(&self) -> &'static str;
+    fn method(&self, name: &str) -> Option<BuiltinFunction>;
+    fn as_any(&self) -> &dyn Any;
+
+    /// How the object prints; resources just show their type.
+    fn display(&self) -> String {
+        format!("<{}>", self.type_name())
+    }
+
+    /// Apply a binary operator with this object on the left, or on the right
+    /// when `reflected` is set. `None` means the combination is unsupported.
+    fn binary_op(
+        &self,
+        _operator: &Operator,
+        _other: &Object,
+        _reflected: bool,
+    ) -> Option<Result<Object, String>> {
+        None
+    }
+
+    /// A data attribute read with `object.name`, checked before methods.
+    fn attribute(&self, _name: &str) -> Option<Object> {
+        None
+    }
+
+    /// Call the object like a spell. `None` means it is not callable.
+    fn call(&self, _args: Vec<Object>) -> Option<Result<Object, String>> {
+        None
+    }
+
+    /// The items a `for` loop visits. `None` means it is not iterable.
+    fn items(&self) -> Option<Vec<Object>> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NativeHandle(pub Rc<dyn NativeObject>);
+
+impl NativeHandle {
+    pub fn new<T: NativeObject + 'static>(object: T) -> Self {
+        NativeHandle(Rc::new(object))
+    }
+
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref::<T>()
+    }
 }
 
+// Native objects wrap external resources, so equality means "same handle".
+impl PartialEq for NativeHandle {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(Rc::as_ptr(&self.0), Rc::as_ptr(&other.0))
+    }
+}
+
+/// Hashes agree with `==` for the values [`Object::is_hashable`] accepts;
+/// other values hash by their type alone.
 impl std::hash::Hash for Object {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
         match self {
             Object::Integer(i) => i.hash(state),
+            // `0.0 == -0.0`, so both hash as `0.0`.
+            Object::Float(f) => (if *f == 0.0 { 0.0 } else { *f }).to_bits().hash(state),
             Object::Boolean(b) => b.hash(state),
             Object::String(s) => s.hash(state),
-
-            _ => "UNHASHABLE".hash(state),
+            Object::Tuple(items) => items.hash(state),
+            _ => {}
         }
     }
 }
 impl Eq for Object {}
 
+impl Object {
+    pub fn list(items: Vec<Object>) -> Object {
+        Object::List(Rc::new(items))
+    }
+
+    pub fn tuple(items: Vec<Object>) -> Object {
+        Object::Tuple(Rc::new(items))
+    }
+
+    /// A dict with string keys, as builtins return.
+    pub fn dict(entries: impl IntoIterator<Item = (String, Object)>) -> Object {
+        Object::Dict(entries.into_iter().map(|(key, value)| (Object::String(key.into()), value)).collect())
+    }
+
+    /// Whether the value can be a dict key or set element: numbers, strings,
+    /// booleans, None and tuples of those, which never change.
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Object::Integer(_) | Object::Float(_) | Object::Boolean(_) | Object::String(_) | Object::None => true,
+            Object::Tuple(items) => items.iter().all(Object::is_hashable),
+            _ => false,
+        }
+    }
+
+    /// The name of the value's type as shown to users, e.g. `Integer` or `Spell`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "Integer",
+            Object::Float(_) => "Float",
+            Object::Boolean(_) => "Boolean",
+            Object::String(_) => "String",
+            Object::List(_) => "List",
+            Object::Tuple(_) => "Tuple",
+            Object::Set(_) => "Set",
+            Object::Range(_) => "Range",
+            Object::Dict(_) => "Dict",
+            Object::ReturnValue(_) => "ReturnValue",
+            Object::Function(_) => "Spell",
+            Object::Error(_) => "Error",
+            Object::Builtin(_) => "Builtin",
+            Object::Grimoire(_) => "Grimoire",
+            Object::Instance(_) => "Instance",
+            Object::BoundMethod(_) => "BoundMethod",
+            Object::Native(native) => native.0.type_name(),
+            Object::Module(_) => "Module",
+            Object::None => "None",
+        }
+    }
+}
+
+/// The text of a float: the shortest digits that read back as exactly the
+/// same value, with `.0` on whole numbers so the text still reads as a float
+/// (`2.0`), and an exponent for very large or small magnitudes (`1e300`,
+/// `2.5e-7`). It never depends on the locale, and `float()` parses every
+/// result back, including `NaN`, `inf` and `-inf`.
+pub fn format_float(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let magnitude = value.abs();
+    if magnitude != 0.0 && !(1e-4..1e16).contains(&magnitude) {
+        return format!("{:e}", value);
+    }
+    let text = value.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        text + ".0"
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Object::Integer(val) => write!(f, "{}", val),
-            Object::Float(val) => write!(f, "{}", val),
+            Object::Float(val) => write!(f, "{}", format_float(*val)),
             Object::Boolean(val) => {
                 if *val {
                     write!(f, "True")
@@ -73,6 +526,19 @@ impl fmt::Display for Object {
                 let parts: Vec<String> = items.iter().map(|i| i.to_string()).collect();
                 write!(f, "[{}]", parts.join(", "))
             }
+            Object::Tuple(items) => {
+                let parts: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                match parts.as_slice() {
+                    [single] => write!(f, "({},)", single),
+                    _ => write!(f, "({})", parts.join(", ")),
+                }
+            }
+            Object::Set(items) if items.is_empty() => write!(f, "set()"),
+            Object::Set(items) => {
+                let parts: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                write!(f, "{{{}}}", parts.join(", "))
+            }
+            Object::Range(range) => write!(f, "{}", range),
             Object::Dict(map) => {
                 let parts: Vec<String> = map.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
                 write!(f, "{{{}}}", parts.join(", "))
@@ -80,6 +546,15 @@ impl fmt::Display for Object {
             Object::ReturnValue(val) => write!(f, "{}", val),
             Object::Function(_) => write!(f, "[Function]"),
             Object::Builtin(_) => write!(f, "[Builtin Function]"),
+            Object::Grimoire(grimoire) => write!(f, "<grimoire {}>", grimoire.name),
+            // `str` reports a failing `__str__`; here it falls back to the default.
+            Object::Instance(instance) => match crate::evaluator::overloading::to_text(self) {
+                Some(Ok(text)) => write!(f, "{}", text),
+                _ => write!(f, "<{} instance>", instance.borrow().grimoire.name),
+            },
+            Object::BoundMethod(_) => write!(f, "[Bound Method]"),
+            Object::Native(native) => write!(f, "{}", native.0.display()),
+            Object::Module(module) => write!(f, "<module {}>", module.name),
             Object::Error(msg) => write!(f, "Error: {}", msg),
             Object::None => write!(f, "None"),
         }
@@ -0,0 +1,82 @@
+//! `Set`, the elements behind `Object::Set`.
+//!
+//! Elements keep the order they were first added in, which is the order
+//! sets print and loop in. Beside them a hash index maps each element to its
+//! place, so `in`, `add` and the set operators look elements up instead of
+//! scanning. Only values [`Object::is_hashable`] accepts belong in a set;
+//! callers check that before inserting.
+
+use super::{Hasher, Object};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Deref;
+
+#[derive(Clone, Default)]
+pub struct Set {
+    items: Vec<Object>,
+    /// Where each element is in `items`.
+    positions: HashMap<Object, usize, Hasher>,
+}
+
+impl Set {
+    /// Add `item` at the end unless an equal element is already there.
+    /// Returns whether it was added.
+    pub fn insert(&mut self, item: Object) -> bool {
+        if self.positions.contains_key(&item) {
+            return false;
+        }
+        self.positions.insert(item.clone(), self.items.len());
+        self.items.push(item);
+        true
+    }
+
+    pub fn contains(&self, item: &Object) -> bool {
+        self.positions.contains_key(item)
+    }
+
+    /// Take `item` out, keeping the other elements in order. Returns whether
+    /// it was there.
+    pub fn remove(&mut self, item: &Object) -> bool {
+        let Some(position) = self.positions.remove(item) else {
+            return false;
+        };
+        self.items.remove(position);
+        for later in self.positions.values_mut().filter(|p| **p > position) {
+            *later -= 1;
+        }
+        true
+    }
+}
+
+/// The elements in order, as a slice.
+impl Deref for Set {
+    type Target = [Object];
+
+    fn deref(&self) -> &[Object] {
+        &self.items
+    }
+}
+
+/// A set of the distinct `items`, each of which must be hashable.
+impl FromIterator<Object> for Set {
+    fn from_iter<I: IntoIterator<Item = Object>>(items: I) -> Self {
+        let mut set = Set::default();
+        for item in items {
+            set.insert(item);
+        }
+        set
+    }
+}
+
+/// Sets are equal when they hold the same elements, in any order.
+impl PartialEq for Set {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|item| other.contains(item))
+    }
+}
+
+impl Debug for Set {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.items.iter()).finish()
+    }
+}
@@ -0,0 +1,179 @@
+//! `Str`, the text behind `Object::String`.
+//!
+//! A `Str` is a shared buffer plus the byte range of it the value covers.
+//! Cloning, indexing and slicing only make a new handle on the same buffer;
+//! operations that change text (`+`, `upper`, ...) build a fresh string, so
+//! one value never changes another.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Range};
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub struct Str {
+    buf: Rc<str>,
+    start: usize,
+    end: usize,
+}
+
+impl Str {
+    pub fn as_str(&self) -> &str {
+        &self.buf[self.start..self.end]
+    }
+
+    /// The part of this string at `range`, a byte range relative to it, or
+    /// `None` when the range is out of bounds or splits a character. Shares
+    /// the buffer.
+    pub fn slice(&self, range: Range<usize>) -> Option<Str> {
+        self.as_str().get(range.clone())?;
+        Some(self.share(range))
+    }
+
+    /// The handle for `part` when it points into this string (as the results
+    /// of `split`, `trim` and friends do), or `None` when it lies elsewhere.
+    pub fn slice_of(&self, part: &str) -> Option<Str> {
+        let offset = (part.as_ptr() as usize).checked_sub(self.as_str().as_ptr() as usize)?;
+        self.slice(offset..offset.checked_add(part.len())?)
+    }
+
+    /// A handle on `range`, which the caller knows is valid.
+    fn share(&self, range: Range<usize>) -> Str {
+        Str {
+            buf: Rc::clone(&self.buf),
+            start: self.start + range.start,
+            end: self.start + range.end,
+        }
+    }
+
+    /// The `index`th character, counting from zero.
+    pub fn char_at(&self, index: usize) -> Option<Str> {
+        let (start, ch) = self.char_indices().nth(index)?;
+        Some(self.share(start..start + ch.len_utf8()))
+    }
+
+    /// Each character as its own handle.
+    pub fn chars_shared(&self) -> impl Iterator<Item = Str> + '_ {
+        self.char_indices()
+            .map(|(start, ch)| self.share(start..start + ch.len_utf8()))
+    }
+}
+
+impl Deref for Str {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Str {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Str {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<OsStr> for Str {
+    fn as_ref(&self) -> &OsStr {
+        self.as_str().as_ref()
+    }
+}
+
+impl AsRef<Path> for Str {
+    fn as_ref(&self) -> &Path {
+        self.as_str().as_ref()
+    }
+}
+
+impl FromIterator<char> for Str {
+    fn from_iter<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        String::from_iter(chars).into()
+    }
+}
+
+impl From<String> for Str {
+    fn from(text: String) -> Self {
+        let end = text.len();
+        Str {
+            buf: text.into(),
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl From<&str> for Str {
+    fn from(text: &str) -> Self {
+        Str {
+            buf: text.into(),
+            start: 0,
+            end: text.len(),
+        }
+    }
+}
+
+impl From<Str> for String {
+    fn from(text: Str) -> Self {
+        text.as_str().to_owned()
+    }
+}
+
+impl PartialEq for Str {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Str {}
+
+impl Hash for Str {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for Str {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Str {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialEq<str> for Str {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Str {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Str {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Str {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,68 @@
+//! What the parser reports when it can't read something.
+//!
+//! A [`Diagnostic`] says where parsing stopped, at which token, and which
+//! tokens would have been accepted there, so the CLI, the REPL and other
+//! tools can point at the spot instead of parsing a message.
+
+use crate::ast::Span;
+use crate::token::{Token, TokenType};
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    /// The token the parser stopped at.
+    pub token: Token,
+    /// The tokens that would have been accepted instead of `token`. Empty
+    /// when the parser doesn't know, e.g. for a pattern it can't use.
+    pub expected: Vec<TokenType>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// `message` about `token`.
+    pub fn new(token: &Token, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            file: token.file_name.clone(),
+            line: token.line,
+            column: token.column,
+            token: token.clone(),
+            expected: Vec::new(),
+            message: message.into(),
+        }
+    }
+
+    /// The same diagnostic, saying `expected` would have been accepted.
+    pub fn expecting(mut self, expected: &[TokenType]) -> Diagnostic {
+        self.expected = expected.to_vec();
+        self
+    }
+
+    /// Where the diagnostic points.
+    pub fn span(&self) -> Span {
+        Span {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.message)
+    }
+}
+
+/// `token` as messages mention it, such as `Colon(:)`.
+pub fn describe(token: &Token) -> String {
+    format!("{}({})", token.token_type, token.literal)
+}
+
+/// `diagnostics` one after another, with `separator` between them.
+pub fn join(diagnostics: &[Diagnostic], separator: &str) -> String {
+    diagnostics.iter().map(Diagnostic::to_string).collect::<Vec<_>>().join(separator)
+}
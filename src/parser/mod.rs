@@ -1,18 +1,38 @@
 use crate::ast::{
     self, Assignment, BlockStatement, CallExpression, CompoundAssignment, Expression, ForStatement,
-    FunctionDefinition, Identifier, IfStatement, InfixExpression, Operator, PostfixExpression,
-    PrefixExpression, Program, ReturnStatement, Statement, UnpackExpression, WhileStatement,
+    FunctionDefinition, FunctionLiteral, GrimoireDefinition, Identifier, IfStatement, ImportSource, ImportStatement,
+    InfixExpression, MatchArm, MatchStatement, MemberExpression, Operator, Pattern, PostfixExpression, PrefixExpression, Program, ReturnStatement,
+    Span, SpannedStatement, Statement, TogetherStatement, WhileStatement,
 };
 use crate::token::{Token, TokenType};
+use std::num::IntErrorKind;
+use std::rc::Rc;
+
+pub mod diagnostic;
+pub mod overflow;
+
+pub use diagnostic::Diagnostic;
+pub use overflow::IntegerOverflow;
+
+use diagnostic::describe;
+
+/// A spell's named parameters, then its `*` and `**` ones.
+type Parameters = (Vec<Identifier>, Option<Identifier>, Option<Identifier>);
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 enum Precedence {
     Lowest,
     Assign,
+    Conditional,
     Or,
     And,
     Equality,
     Comparison,
+    Range,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Term,
     Factor,
     Exponent,
@@ -25,7 +45,7 @@ enum Precedence {
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-    errors: Vec<String>,
+    errors: Vec<Diagnostic>,
 }
 
 impl Parser {
@@ -37,7 +57,7 @@ impl Parser {
         }
     }
 
-    pub fn errors(&self) -> &[String] {
+    pub fn errors(&self) -> &[Diagnostic] {
         &self.errors
     }
 
@@ -48,12 +68,13 @@ impl Parser {
             loop_count += 1;
             
             if loop_count > 1000 {
-                self.errors.push("Parser infinite loop detected".to_string());
+                let error = self.error("Parser infinite loop detected");
+                self.errors.push(*error);
                 break;
             }
             
-            // Skip any newlines before parsing statements
-            while self.peek().token_type == TokenType::Newline {
+            // Skip any newlines and semicolons before parsing statements
+            while matches!(self.peek().token_type, TokenType::Newline | TokenType::Semicolon) {
                 self.advance();
             }
             
@@ -61,12 +82,12 @@ impl Parser {
                 break;
             }
             
-            match self.parse_statement() {
+            match self.parse_spanned_statement() {
                 Ok(stmt) => {
                     program.statements.push(stmt);
                 }
                 Err(e) => {
-                    self.errors.push(e);
+                    self.errors.push(*e);
                     // Safety: advance past the problematic token to prevent infinite loops
                     if !self.is_at_end() {
                         self.advance();
@@ -77,22 +98,274 @@ impl Parser {
         program
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, String> {
+    /// Parse a statement and record where it starts.
+    fn parse_spanned_statement(&mut self) -> Result<SpannedStatement, Box<Diagnostic>> {
+        let span = self.span();
+        let statement = self.parse_statement()?;
+        Ok(SpannedStatement { statement, span })
+    }
+
+    /// Where the next token starts.
+    fn span(&self) -> Span {
+        let start = self.peek();
+        Span {
+            file: start.file_name.clone(),
+            line: start.line,
+            column: start.column,
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
         match self.peek().token_type {
-            TokenType::Spell => self.parse_function_definition(),
+            // `spell(` starts an anonymous spell used as an expression.
+            TokenType::Spell if self.peek_next_type() != TokenType::LeftParen => {
+                self.parse_function_definition(false)
+            }
+            // `pure` is only a keyword in front of `spell`, so it stays usable as a name.
+            TokenType::Identifier if self.peek().literal == "pure" && self.peek_next_type() == TokenType::Spell => {
+                self.advance();
+                self.parse_function_definition(true)
+            }
+            TokenType::Grimoire | TokenType::Arcane => self.parse_grimoire_definition(),
             TokenType::Return => self.parse_return_statement(),
             TokenType::If => self.parse_if_statement(),
             TokenType::While => self.parse_while_statement(),
+            // Like `pure`, `together` is only a keyword where it opens a block.
+            TokenType::Identifier if self.peek().literal == "together" && self.peek_next_type() == TokenType::Colon => {
+                self.parse_together_statement()
+            }
             TokenType::For => self.parse_for_statement(),
+            TokenType::Match => self.parse_match_statement(),
+            TokenType::Import => self.parse_import_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
-    fn parse_function_definition(&mut self) -> Result<Statement, String> {
-        Err("Parsing for function definitions is not yet implemented".to_string())
+    /// `spell name(...):`, or with `pure` marking it as a pure spell.
+    fn parse_function_definition(&mut self, pure: bool) -> Result<Statement, Box<Diagnostic>> {
+        self.consume(TokenType::Spell, "Expected 'spell' keyword.")?;
+        let name_token = self.consume(TokenType::Identifier, "Expected spell name after 'spell'.")?;
+        let name = Identifier(name_token.literal.clone());
+        let spell = self.parse_function_rest(name)?;
+        Ok(Statement::FunctionDefinition(FunctionDefinition { pure, ..spell }))
     }
 
-    fn parse_if_statement(&mut self) -> Result<Statement, String> {
+    /// Parse the parameter list and body that follow a spell's name.
+    fn parse_function_rest(&mut self, name: Identifier) -> Result<FunctionDefinition, Box<Diagnostic>> {
+        let (parameters, rest, keywords) = self.parse_function_parameters()?;
+        self.consume(TokenType::Colon, "Expected ':' after spell signature.")?;
+
+        // Skip newline after colon
+        if self.peek().token_type == TokenType::Newline {
+            self.advance();
+        }
+
+        let body = self.parse_block_statement()?;
+
+        Ok(FunctionDefinition {
+            name,
+            parameters: parameters.into(),
+            rest,
+            keywords,
+            body: body.into(),
+            pure: false,
+        })
+    }
+
+    /// `grim Name:` or `arcane grim Name:`, optionally with a parent as in
+    /// `grim Name(Parent):`, followed by its spells and `name = value`
+    /// attributes.
+    fn parse_grimoire_definition(&mut self) -> Result<Statement, Box<Diagnostic>> {
+        let arcane = self.peek().token_type == TokenType::Arcane;
+        if arcane {
+            self.advance();
+        }
+        self.consume(TokenType::Grimoire, "Expected 'grim' keyword.")?;
+        let name_token = self.consume(TokenType::Identifier, "Expected grimoire name after 'grim'.")?;
+        let name = Identifier(name_token.literal.clone());
+        let parent = if self.peek().token_type == TokenType::LeftParen {
+            self.advance();
+            let parent = self.parse_expression(Precedence::Lowest)?;
+            self.consume(TokenType::RightParen, "Expected ')' after the parent grimoire.")?;
+            Some(parent)
+        } else {
+            None
+        };
+        self.consume(TokenType::Colon, "Expected ':' after grimoire name.")?;
+
+        while self.peek().token_type == TokenType::Newline {
+            self.advance();
+        }
+        self.consume(TokenType::Indent, "Expected an indented block of spells after grimoire header.")?;
+
+        let mut methods = Vec::new();
+        let mut arcane_spells = Vec::new();
+        let mut static_spells = Vec::new();
+        let (mut getters, mut setters) = (Vec::new(), Vec::new());
+        let mut attributes = Vec::new();
+        loop {
+            while self.peek().token_type == TokenType::Newline {
+                self.advance();
+            }
+            match self.peek().token_type {
+                TokenType::Dedent => {
+                    self.advance();
+                    break;
+                }
+                TokenType::Eof => break,
+                // `init(...)` may be written with or without the `spell` keyword
+                TokenType::Init => {
+                    self.advance();
+                    methods.push(self.parse_function_rest(Identifier("init".to_string()))?);
+                }
+                TokenType::Spell => {
+                    self.advance();
+                    let method_name = match self.peek().token_type {
+                        TokenType::Identifier => self.advance().literal.clone(),
+                        TokenType::Init => {
+                            self.advance();
+                            "init".to_string()
+                        }
+                        _ => {
+                            let expected = [TokenType::Identifier, TokenType::Init];
+                            return Err(self.error_expecting("Expected spell name after 'spell'.", &expected));
+                        }
+                    };
+                    methods.push(self.parse_function_rest(Identifier(method_name))?);
+                }
+                TokenType::ArcaneSpell if arcane => {
+                    self.advance();
+                    arcane_spells.push(self.parse_arcane_spell()?);
+                }
+                TokenType::Static => {
+                    self.advance();
+                    self.consume(TokenType::Spell, "Expected 'spell' after 'static'.")?;
+                    let name = self.consume(TokenType::Identifier, "Expected spell name after 'static spell'.")?;
+                    let name = Identifier(name.literal.clone());
+                    static_spells.push(self.parse_function_rest(name)?);
+                }
+                TokenType::Property => {
+                    self.advance();
+                    self.consume(TokenType::Spell, "Expected 'spell' after 'property'.")?;
+                    let name = self.consume(TokenType::Identifier, "Expected spell name after 'property spell'.")?;
+                    let name = Identifier(name.literal.clone());
+                    let spell = self.parse_function_rest(name)?;
+                    match (spell.parameters.len(), &spell.rest, &spell.keywords) {
+                        (0, None, None) => getters.push(spell),
+                        (1, None, None) => setters.push(spell),
+                        _ => {
+                            return Err(self.error(format!(
+                                "Property '{}' must take no parameters (to read it) or one (to assign it)",
+                                spell.name.0
+                            )))
+                        }
+                    }
+                }
+                TokenType::Identifier if self.peek_next_type() == TokenType::Assign => {
+                    let name = Identifier(self.advance().literal.clone());
+                    self.advance();
+                    attributes.push((name, self.parse_expression(Precedence::Lowest)?));
+                }
+                TokenType::ArcaneSpell => {
+                    return Err(self.error(format!(
+                        "arcanespell is only allowed in an arcane grimoire; declare it as 'arcane grim {}'",
+                        name.0
+                    )));
+                }
+                _ => {
+                    return Err(self.error(format!(
+                        "Expected a spell definition inside grimoire '{}', found {}",
+                        name.0,
+                        describe(self.peek())
+                    )));
+                }
+            }
+        }
+
+        Ok(Statement::Grimoire(GrimoireDefinition {
+            name,
+            parent,
+            methods,
+            arcane,
+            arcane_spells,
+            static_spells,
+            getters,
+            setters,
+            attributes,
+        }))
+    }
+
+    /// The rest of `arcanespell name(params)`: a spell children must
+    /// define. It may have a block, such as a docstring, which never runs.
+    fn parse_arcane_spell(&mut self) -> Result<Identifier, Box<Diagnostic>> {
+        let name = match self.peek().token_type {
+            TokenType::Identifier => Identifier(self.advance().literal.clone()),
+            _ => return Err(self.error_expecting("Expected spell name after 'arcanespell'.", &[TokenType::Identifier])),
+        };
+        self.parse_function_parameters()?;
+        if self.peek().token_type == TokenType::Colon {
+            self.advance();
+            self.parse_block_statement()?;
+        }
+        Ok(name)
+    }
+
+    /// `(a, b, *rest, **options)`: the named parameters, then the optional
+    /// `*` and `**` ones, in that order.
+    fn parse_function_parameters(&mut self) -> Result<Parameters, Box<Diagnostic>> {
+        self.consume(TokenType::LeftParen, "Expected '(' to open parameter list.")?;
+        let mut parameters = Vec::new();
+        let mut rest: Option<Identifier> = None;
+        let mut keywords = None;
+        // Methods receive `self` implicitly; allow it to be spelled out anyway.
+        if self.peek().token_type == TokenType::SelfKeyword {
+            self.advance();
+            if self.peek().token_type == TokenType::Comma {
+                self.advance();
+            }
+        }
+        if self.peek().token_type != TokenType::RightParen {
+            loop {
+                match self.peek().token_type {
+                    TokenType::Exponent => {
+                        self.advance();
+                        let name = self.consume(TokenType::Identifier, "Expected parameter name after '**'.")?.literal.clone();
+                        if self.peek().token_type == TokenType::Comma {
+                            return Err(self.error(format!("The '**{}' parameter must come last.", name)));
+                        }
+                        keywords = Some(Identifier(name));
+                        break;
+                    }
+                    TokenType::Asterisk if rest.is_some() => {
+                        return Err(self.error("A spell can only have one '*' parameter."));
+                    }
+                    TokenType::Asterisk => {
+                        self.advance();
+                        let name = self.consume(TokenType::Identifier, "Expected parameter name after '*'.")?.literal.clone();
+                        rest = Some(Identifier(name));
+                    }
+                    _ if rest.is_some() => {
+                        return Err(self.error(format!(
+                            "Only a '**' parameter can follow '*{}'.",
+                            rest.map(|rest| rest.0).unwrap_or_default()
+                        )));
+                    }
+                    _ => {
+                        let param = self.consume(TokenType::Identifier, "Expected parameter name.")?;
+                        parameters.push(Identifier(param.literal.clone()));
+                    }
+                }
+                if self.peek().token_type != TokenType::Comma {
+                    break;
+                }
+                self.advance(); // consume comma
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' to close parameter list.")?;
+        Ok((parameters, rest, keywords))
+    }
+
+    fn parse_if_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
         self.consume(TokenType::If, "Expected 'if' keyword.")?;
         let condition = self.parse_expression(Precedence::Lowest)?;
         self.consume(TokenType::Colon, "Expected ':' after if condition.")?;
@@ -126,16 +399,16 @@ impl Parser {
             let alt_consequence = self.parse_block_statement()?;
             alternatives.push((alt_condition, alt_consequence));
             
-            // Skip newlines and dedents between otherwise clauses (with safety limit)
+            // Skip newlines between otherwise clauses (with safety limit)
             let mut skip_count = 0;
-            while (self.peek().token_type == TokenType::Newline || self.peek().token_type == TokenType::Dedent) && skip_count < 100 {
+            while self.peek().token_type == TokenType::Newline && skip_count < 100 {
                 self.advance();
                 skip_count += 1;
             }
         }
         
         if otherwise_count >= MAX_OTHERWISE_CLAUSES {
-            return Err(format!("Too many otherwise clauses: maximum {} allowed", MAX_OTHERWISE_CLAUSES));
+            return Err(self.error(format!("Too many otherwise clauses: maximum {} allowed", MAX_OTHERWISE_CLAUSES)));
         }
         
         // Parse else clause
@@ -150,8 +423,8 @@ impl Parser {
             
             default = Some(self.parse_block_statement()?);
             
-            // Skip newlines and dedents after the else block
-            while self.peek().token_type == TokenType::Newline || self.peek().token_type == TokenType::Dedent {
+            // Skip newlines after the else block
+            while self.peek().token_type == TokenType::Newline {
                 self.advance();
             }
         }
@@ -164,7 +437,7 @@ impl Parser {
         }))
     }
 
-    fn parse_while_statement(&mut self) -> Result<Statement, String> {
+    fn parse_while_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
         self.consume(TokenType::While, "Expected 'while' keyword.")?;
         let condition = self.parse_expression(Precedence::Lowest)?;
         self.consume(TokenType::Colon, "Expected ':' after while condition.")?;
@@ -182,11 +455,28 @@ impl Parser {
         }))
     }
 
-    fn parse_for_statement(&mut self) -> Result<Statement, String> {
+    fn parse_together_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
+        self.advance();
+        self.consume(TokenType::Colon, "Expected ':' after 'together'.")?;
+        if self.peek().token_type == TokenType::Newline {
+            self.advance();
+        }
+        let body = self.parse_block_statement()?;
+        Ok(Statement::Together(TogetherStatement { body }))
+    }
+
+    fn parse_for_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
         self.consume(TokenType::For, "Expected 'for' keyword.")?;
         
-        let target_token = self.consume(TokenType::Identifier, "Expected variable name in for loop.")?;
-        let target = Identifier(target_token.literal.clone());
+        let mut targets = Vec::new();
+        loop {
+            let target_token = self.consume(TokenType::Identifier, "Expected variable name in for loop.")?;
+            targets.push(Identifier(target_token.literal.clone()));
+            if self.peek().token_type != TokenType::Comma {
+                break;
+            }
+            self.advance();
+        }
         
         self.consume(TokenType::In, "Expected 'in' keyword in for loop.")?;
         let iter = self.parse_expression(Precedence::Lowest)?;
@@ -200,17 +490,125 @@ impl Parser {
         let body = self.parse_block_statement()?;
         
         Ok(Statement::For(ForStatement {
-            target,
+            targets,
             iter: Box::new(iter),
             body,
         }))
     }
 
-    fn parse_block_statement(&mut self) -> Result<BlockStatement, String> {
+    fn parse_match_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
+        self.consume(TokenType::Match, "Expected 'match' keyword.")?;
+        let subject = self.parse_expression(Precedence::Lowest)?;
+        self.consume(TokenType::Colon, "Expected ':' after match subject.")?;
+
+        while self.peek().token_type == TokenType::Newline {
+            self.advance();
+        }
+        self.consume(TokenType::Indent, "Expected an indented block of 'case' arms after match.")?;
+
+        let mut arms = Vec::new();
+        loop {
+            while self.peek().token_type == TokenType::Newline {
+                self.advance();
+            }
+            match self.peek().token_type {
+                TokenType::Dedent => {
+                    self.advance();
+                    break;
+                }
+                TokenType::Eof => break,
+                TokenType::Case => {
+                    self.advance(); // consume 'case'
+                    let pattern = self.parse_pattern()?;
+                    self.consume(TokenType::Colon, "Expected ':' after case pattern.")?;
+
+                    // Skip newline after colon
+                    self.end_statement();
+
+                    let body = self.parse_block_statement()?;
+                    arms.push(MatchArm { pattern, body });
+                }
+                _ => {
+                    let message = format!("Expected 'case' inside match block, found {}", describe(self.peek()));
+                    return Err(self.error_expecting(message, &[TokenType::Case]));
+                }
+            }
+        }
+
+        if arms.is_empty() {
+            return Err(self.error("A match statement needs at least one 'case' arm."));
+        }
+
+        Ok(Statement::Match(MatchStatement {
+            subject: Box::new(subject),
+            arms,
+        }))
+    }
+
+    fn parse_import_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
+        let origin = self
+            .consume(TokenType::Import, "Expected 'import' keyword.")?
+            .file_name
+            .clone();
+        let token = self.advance().clone();
+        let source = match token.token_type {
+            TokenType::StringLit => ImportSource::Path(token.literal),
+            TokenType::Identifier => ImportSource::Name(Identifier(token.literal)),
+            _ => {
+                let message = format!("Expected a module name or path string after 'import', found {}", describe(&token));
+                return Err(Box::new(
+                    Diagnostic::new(&token, message).expecting(&[TokenType::StringLit, TokenType::Identifier]),
+                ));
+            }
+        };
+
+        let alias = if self.peek().token_type == TokenType::As {
+            self.advance();
+            let name = self.consume(TokenType::Identifier, "Expected a name after 'as'.")?;
+            Some(Identifier(name.literal.clone()))
+        } else {
+            None
+        };
+
+        Ok(Statement::Import(ImportStatement {
+            source,
+            alias,
+            origin,
+        }))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, Box<Diagnostic>> {
+        match self.peek().token_type {
+            TokenType::Underscore => {
+                self.advance();
+                Ok(Pattern::Wildcard)
+            }
+            TokenType::Identifier if self.peek_next_type() != TokenType::DotDot => {
+                let name = self.advance().literal.clone();
+                Ok(Pattern::Binding(Identifier(name)))
+            }
+            _ => {
+                let literal = self.parse_expression(Precedence::Lowest)?;
+                match literal {
+                    Expression::Infix(range) if range.operator == Operator::Range => {
+                        if is_literal_pattern(&range.left) && is_literal_pattern(&range.right) {
+                            Ok(Pattern::Range(*range.left, *range.right))
+                        } else {
+                            Err(self.error("Range patterns must be between two literals, as in 'case 1..10:'."))
+                        }
+                    }
+                    literal if is_literal_pattern(&literal) => Ok(Pattern::Literal(literal)),
+                    _ => Err(self.error("Case patterns must be a literal, a range of literals, a name, or '_'.")),
+                }
+            }
+        }
+    }
+
+    fn parse_block_statement(&mut self) -> Result<BlockStatement, Box<Diagnostic>> {
         self.parse_block_statement_with_limit(100) // Production limit
     }
 
-    fn parse_block_statement_with_limit(&mut self, max_statements: usize) -> Result<BlockStatement, String> {
+    fn parse_block_statement_with_limit(&mut self, max_statements: usize) -> Result<BlockStatement, Box<Diagnostic>> {
         let mut statements = Vec::new();
         
         // Skip any leading newlines
@@ -225,8 +623,8 @@ impl Parser {
             // Parse statements until we hit a DEDENT
             let mut statement_count = 0;
             while !self.is_at_end() && statement_count < max_statements {
-                // Skip newlines within the block
-                while self.peek().token_type == TokenType::Newline {
+                // Skip newlines and semicolons within the block
+                while matches!(self.peek().token_type, TokenType::Newline | TokenType::Semicolon) {
                     self.advance();
                 }
                 
@@ -237,7 +635,7 @@ impl Parser {
                     _ => {}
                 }
                 
-                match self.parse_statement() {
+                match self.parse_spanned_statement() {
                     Ok(stmt) => {
                         statements.push(stmt);
                         statement_count += 1;
@@ -247,7 +645,7 @@ impl Parser {
             }
             
             if statement_count >= max_statements {
-                return Err(format!("Block too large: maximum {} statements allowed", max_statements));
+                return Err(self.error(format!("Block too large: maximum {} statements allowed", max_statements)));
             }
             
             // Consume the DEDENT if present
@@ -255,26 +653,34 @@ impl Parser {
                 self.advance();
             }
         } else {
-            // No indentation - parse a single statement (inline style)
+            // No indentation - parse a single statement (inline style), or
+            // several joined by semicolons, which all belong to the block
             if !self.is_at_end() && 
                self.peek().token_type != TokenType::Otherwise && 
                self.peek().token_type != TokenType::Else && 
                self.peek().token_type != TokenType::Eof &&
                self.peek().token_type != TokenType::Dedent {
-                match self.parse_statement() {
+                match self.parse_spanned_statement() {
                     Ok(stmt) => statements.push(stmt),
                     Err(e) => return Err(e),
                 }
+                while self.tokens[self.current - 1].token_type == TokenType::Semicolon
+                    && !matches!(self.peek().token_type, TokenType::Newline | TokenType::Eof | TokenType::Dedent)
+                {
+                    statements.push(self.parse_spanned_statement()?);
+                }
             }
         }
         
         Ok(statements)
     }
 
-    fn parse_return_statement(&mut self) -> Result<Statement, String> {
+    fn parse_return_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
         self.consume(TokenType::Return, "Expected 'return' keyword.")?;
         let value = if self.peek().token_type == TokenType::Newline
+            || self.peek().token_type == TokenType::Semicolon
             || self.peek().token_type == TokenType::Eof
+            || self.peek().token_type == TokenType::Dedent
         {
             None
         } else {
@@ -283,22 +689,26 @@ impl Parser {
         Ok(Statement::Return(ReturnStatement { value }))
     }
 
-    fn parse_expression_statement(&mut self) -> Result<Statement, String> {
+    fn parse_expression_statement(&mut self) -> Result<Statement, Box<Diagnostic>> {
         // Check if this could be an assignment statement
         let checkpoint = self.current;
         
         // Try to parse assignment targets
         let mut targets = Vec::new();
         loop {
+            // `*rest` takes whatever the other targets leave.
+            let starred = self.peek().token_type == TokenType::Asterisk;
+            if starred {
+                self.advance();
+            }
             match self.parse_expression(Precedence::Assign) {
+                Ok(expr) if starred => targets.push(Expression::Unpack(ast::UnpackExpression { value: Box::new(expr) })),
                 Ok(expr) => targets.push(expr),
                 Err(_) => {
                     // Reset and parse as regular expression
                     self.current = checkpoint;
                     let expr = self.parse_expression(Precedence::Lowest)?;
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                    }
+                    self.end_statement();
                     return Ok(Statement::Expression(expr));
                 }
             }
@@ -311,6 +721,9 @@ impl Parser {
                 }
                 TokenType::Assign => {
                     self.advance(); // consume =
+                    if targets.iter().filter(|target| matches!(target, Expression::Unpack(_))).count() > 1 {
+                        return Err(self.error("Only one '*' target is allowed in an assignment."));
+                    }
                     
                     // Parse comma-separated values for multiple assignment
                     let mut values = Vec::new();
@@ -322,25 +735,24 @@ impl Parser {
                         self.advance(); // consume comma
                     }
                     
-                    // If only one value, use it directly; otherwise create a List
+                    // If only one value, use it directly; `a, b` makes a tuple
                     let value = if values.len() == 1 {
                         values.into_iter().next().unwrap()
                     } else {
-                        Expression::List(values)
+                        Expression::Tuple(values)
                     };
                     
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                    }
+                    self.end_statement();
                     return Ok(Statement::Assignment(Assignment {
                         targets,
                         value: Box::new(value),
                     }));
                 }
                 TokenType::PlusAssign | TokenType::MinusAssign | 
-                TokenType::AsteriskAssign | TokenType::SlashAssign => {
+                TokenType::AsteriskAssign | TokenType::SlashAssign |
+                TokenType::ModAssign | TokenType::ExponentAssign => {
                     if targets.len() != 1 {
-                        return Err("Compound assignment requires exactly one target".to_string());
+                        return Err(self.error("Compound assignment requires exactly one target"));
                     }
                     let op_token = self.advance();
                     let operator = match op_token.token_type {
@@ -348,12 +760,12 @@ impl Parser {
                         TokenType::MinusAssign => ast::Operator::Minus,
                         TokenType::AsteriskAssign => ast::Operator::Multiply,
                         TokenType::SlashAssign => ast::Operator::Divide,
+                        TokenType::ModAssign => ast::Operator::Mod,
+                        TokenType::ExponentAssign => ast::Operator::Exponent,
                         _ => unreachable!(),
                     };
                     let value = self.parse_expression(Precedence::Lowest)?;
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                    }
+                    self.end_statement();
                     return Ok(Statement::CompoundAssignment(CompoundAssignment {
                         target: targets.into_iter().next().unwrap(),
                         operator,
@@ -364,33 +776,52 @@ impl Parser {
                     // Not an assignment, reset and parse as expression
                     self.current = checkpoint;
                     let expr = self.parse_expression(Precedence::Lowest)?;
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                    }
+                    self.end_statement();
                     return Ok(Statement::Expression(expr));
                 }
             }
         }
     }
 
-    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, String> {
+    /// Consume the newline or semicolon that ends a simple statement.
+    fn end_statement(&mut self) {
+        if matches!(self.peek().token_type, TokenType::Newline | TokenType::Semicolon) {
+            self.advance();
+        }
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, Box<Diagnostic>> {
         let mut left_expr = match self.peek().token_type {
-            TokenType::Identifier => self.parse_identifier(),
+            TokenType::Identifier | TokenType::SelfKeyword => self.parse_identifier(),
             TokenType::Integer => self.parse_integer_literal(),
             TokenType::Float => self.parse_float_literal(),
             TokenType::StringLit => self.parse_string_literal(),
             TokenType::True | TokenType::False => self.parse_boolean_literal(),
+            TokenType::NoneKeyword => {
+                self.advance();
+                Ok(Expression::NoneLiteral)
+            }
             TokenType::LeftParen => self.parse_grouped_expression(),
-            TokenType::Minus | TokenType::Not | TokenType::Increment | TokenType::Decrement => {
+            TokenType::Minus
+            | TokenType::Not
+            | TokenType::Tilde
+            | TokenType::Increment
+            | TokenType::Decrement => {
                 self.parse_prefix_expression()
             }
             TokenType::LeftBracket => self.parse_list_expression(),
             TokenType::LeftBrace => self.parse_dict_expression(),
+            TokenType::Spell => self.parse_function_literal(),
+            TokenType::Maybe => self.parse_maybe_expression(),
+            TokenType::Super => {
+                self.advance();
+                Ok(Expression::Super)
+            }
 
-            _ => Err(format!(
+            _ => Err(self.error(format!(
                 "No prefix parsing function found for token: {}",
-                self.peek()
-            )),
+                describe(self.peek())
+            ))),
         }?;
 
         while precedence < self.peek_precedence() {
@@ -407,16 +838,32 @@ impl Parser {
                 | TokenType::GreaterThan
                 | TokenType::LessThanEqual
                 | TokenType::GreaterThanEqual
+                | TokenType::In
                 | TokenType::And
-                | TokenType::Or => self.parse_infix_expression(left_expr)?,
+                | TokenType::Or
+                | TokenType::Ampersand
+                | TokenType::Pipe
+                | TokenType::Xor
+                | TokenType::LeftShift
+                | TokenType::RightShift
+                | TokenType::DotDot => self.parse_infix_expression(left_expr)?,
 
                 TokenType::Increment | TokenType::Decrement => {
                     self.parse_postfix_expression(left_expr)?
                 }
 
+                TokenType::Question => {
+                    self.advance();
+                    Expression::Propagate(ast::PropagateExpression { value: Box::new(left_expr) })
+                }
+
                 TokenType::LeftParen => self.parse_call_expression(left_expr)?,
 
                 TokenType::LeftBracket => self.parse_index_expression(left_expr)?,
+
+                TokenType::Dot | TokenType::QuestionDot => self.parse_member_expression(left_expr)?,
+
+                TokenType::If => self.parse_conditional_expression(left_expr)?,
                 _ => return Ok(left_expr),
             }
         }
@@ -424,58 +871,119 @@ impl Parser {
         Ok(left_expr)
     }
 
-    fn parse_identifier(&mut self) -> Result<Expression, String> {
+    fn parse_identifier(&mut self) -> Result<Expression, Box<Diagnostic>> {
         let ident_token = self.advance();
-        Ok(Expression::Identifier(Identifier(
-            ident_token.literal.clone(),
-        )))
+        // Keywords are case-insensitive, so normalise `Self`/`SELF` to one binding name.
+        let name = if ident_token.token_type == TokenType::SelfKeyword {
+            "self".to_string()
+        } else {
+            ident_token.literal.clone()
+        };
+        Ok(Expression::Identifier(Identifier(name)))
     }
 
-    fn parse_integer_literal(&mut self) -> Result<Expression, String> {
+    fn parse_integer_literal(&mut self) -> Result<Expression, Box<Diagnostic>> {
         let int_token = self.advance();
-        match int_token.literal.parse::<i64>() {
+        let digits = int_token.literal.replace('_', "");
+        match digits.parse::<i64>() {
             Ok(value) => Ok(Expression::IntegerLiteral(value)),
-            Err(_) => Err(format!(
-                "Could not parse '{}' as an integer.",
-                int_token.literal
-            )),
+            Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow) => match overflow::mode() {
+                IntegerOverflow::Error => Err(Box::new(Diagnostic::new(int_token, overflow::error(int_token, &digits)))),
+                IntegerOverflow::Float => Ok(Expression::FloatLiteral(digits.parse().unwrap_or(f64::INFINITY))),
+            },
+            Err(_) => Err(Box::new(Diagnostic::new(
+                int_token,
+                format!("Could not parse '{}' as an integer.", int_token.literal),
+            ))),
         }
     }
-    fn parse_float_literal(&mut self) -> Result<Expression, String> {
+    fn parse_float_literal(&mut self) -> Result<Expression, Box<Diagnostic>> {
         let float_token = self.advance();
-        match float_token.literal.parse::<f64>() {
+        match float_token.literal.replace('_', "").parse::<f64>() {
             Ok(value) => Ok(Expression::FloatLiteral(value)),
-            Err(_) => Err(format!(
-                "Could not parse '{}' as a float.",
-                float_token.literal
-            )),
+            Err(_) => Err(Box::new(Diagnostic::new(
+                float_token,
+                format!("Could not parse '{}' as a float.", float_token.literal),
+            ))),
         }
     }
 
-    fn parse_string_literal(&mut self) -> Result<Expression, String> {
+    fn parse_string_literal(&mut self) -> Result<Expression, Box<Diagnostic>> {
         let str_token = self.advance();
-        Ok(Expression::StringLiteral(str_token.literal.clone()))
+        Ok(Expression::StringLiteral(str_token.literal.as_str().into()))
     }
 
-    fn parse_boolean_literal(&mut self) -> Result<Expression, String> {
+    fn parse_boolean_literal(&mut self) -> Result<Expression, Box<Diagnostic>> {
         let bool_token = self.advance();
         Ok(Expression::BooleanLiteral(
             bool_token.token_type == TokenType::True,
         ))
     }
 
-    fn parse_grouped_expression(&mut self) -> Result<Expression, String> {
+    /// `(expr)`, or a tuple: `()`, `(1,)`, `(1, 2)`.
+    fn parse_grouped_expression(&mut self) -> Result<Expression, Box<Diagnostic>> {
         self.consume(TokenType::LeftParen, "Expected '(' for grouped expression.")?;
+        if self.peek().token_type == TokenType::RightParen {
+            self.advance();
+            return Ok(Expression::Tuple(Vec::new()));
+        }
         let expr = self.parse_expression(Precedence::Lowest)?;
-        self.consume(
-            TokenType::RightParen,
-            "Expected ')' to close grouped expression.",
-        )?;
-        Ok(expr)
+        if self.peek().token_type != TokenType::Comma {
+            self.consume(
+                TokenType::RightParen,
+                "Expected ')' to close grouped expression.",
+            )?;
+            return Ok(expr);
+        }
+
+        let mut elements = vec![expr];
+        while self.peek().token_type == TokenType::Comma {
+            self.advance();
+            if self.peek().token_type == TokenType::RightParen {
+                break;
+            }
+            elements.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.consume(TokenType::RightParen, "Expected ')' to close tuple.")?;
+        Ok(Expression::Tuple(elements))
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<Expression, String> {
+    fn parse_function_literal(&mut self) -> Result<Expression, Box<Diagnostic>> {
+        self.consume(TokenType::Spell, "Expected 'spell' keyword.")?;
+        let (parameters, rest, keywords) = self.parse_function_parameters()?;
+        self.consume(TokenType::Colon, "Expected ':' after anonymous spell parameters.")?;
+        let span = self.span();
+        let body = self.parse_expression(Precedence::Lowest)?;
+        let body = SpannedStatement {
+            statement: Statement::Return(ReturnStatement { value: Some(body) }),
+            span,
+        };
+        Ok(Expression::Function(FunctionLiteral {
+            parameters: parameters.into(),
+            rest,
+            keywords,
+            body: Rc::new([body]),
+        }))
+    }
+
+    fn parse_maybe_expression(&mut self) -> Result<Expression, Box<Diagnostic>> {
+        self.consume(TokenType::Maybe, "Expected 'maybe' keyword.")?;
+        let value = self.parse_expression(Precedence::Prefix)?;
+        Ok(Expression::Maybe(ast::MaybeExpression { value: Box::new(value) }))
+    }
+
+    fn parse_prefix_expression(&mut self) -> Result<Expression, Box<Diagnostic>> {
         let prefix_token = self.advance().clone();
+        // `-9223372036854775808` is the smallest integer, though its digits
+        // alone don't fit.
+        if prefix_token.token_type == TokenType::Minus
+            && self.peek().token_type == TokenType::Integer
+            && self.peek().literal.replace('_', "") == i64::MIN.unsigned_abs().to_string()
+            && self.peek_next_type() != TokenType::Exponent
+        {
+            self.advance();
+            return Ok(Expression::IntegerLiteral(i64::MIN));
+        }
         let operator = self.map_token_to_prefix_operator(prefix_token.token_type)?;
         let right = self.parse_expression(Precedence::Prefix)?;
         Ok(Expression::Prefix(PrefixExpression {
@@ -484,11 +992,16 @@ impl Parser {
         }))
     }
 
-    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, String> {
+    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, Box<Diagnostic>> {
         let infix_token = self.advance().clone();
         let precedence = self.get_precedence(infix_token.token_type);
         let operator = self.map_token_to_infix_operator(infix_token.token_type)?;
-        let right = self.parse_expression(precedence)?;
+        // `**` is right-associative: `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+        let right = if operator == Operator::Exponent {
+            self.parse_expression(Precedence::Factor)?
+        } else {
+            self.parse_expression(precedence)?
+        };
         Ok(Expression::Infix(InfixExpression {
             left: Box::new(left),
             operator,
@@ -496,7 +1009,7 @@ impl Parser {
         }))
     }
 
-    fn parse_postfix_expression(&mut self, left: Expression) -> Result<Expression, String> {
+    fn parse_postfix_expression(&mut self, left: Expression) -> Result<Expression, Box<Diagnostic>> {
         let postfix_token = self.advance().clone();
         let operator = self.map_token_to_postfix_operator(postfix_token.token_type)?;
         Ok(Expression::Postfix(PostfixExpression {
@@ -505,12 +1018,39 @@ impl Parser {
         }))
     }
 
-    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, String> {
+    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, Box<Diagnostic>> {
         self.consume(TokenType::LeftParen, "Expected '(' for function call.")?;
         let mut arguments = Vec::new();
+        let mut keywords = Vec::new();
         if self.peek().token_type != TokenType::RightParen {
             loop {
-                arguments.push(self.parse_expression(Precedence::Lowest)?);
+                match self.peek().token_type {
+                    TokenType::Exponent => {
+                        self.advance();
+                        keywords.push(ast::KeywordArgument {
+                            name: None,
+                            value: self.parse_expression(Precedence::Lowest)?,
+                        });
+                    }
+                    TokenType::Identifier if self.peek_next_type() == TokenType::Assign => {
+                        let name = Identifier(self.advance().literal.clone());
+                        self.advance(); // consume =
+                        keywords.push(ast::KeywordArgument {
+                            name: Some(name),
+                            value: self.parse_expression(Precedence::Lowest)?,
+                        });
+                    }
+                    _ if !keywords.is_empty() => {
+                        return Err(self.error("Positional arguments must come before keyword arguments."));
+                    }
+                    TokenType::Asterisk => {
+                        self.advance();
+                        arguments.push(Expression::Unpack(ast::UnpackExpression {
+                            value: Box::new(self.parse_expression(Precedence::Lowest)?),
+                        }));
+                    }
+                    _ => arguments.push(self.parse_expression(Precedence::Lowest)?),
+                }
                 if self.peek().token_type != TokenType::Comma {
                     break;
                 }
@@ -524,10 +1064,11 @@ impl Parser {
         Ok(Expression::Call(CallExpression {
             function: Box::new(function),
             arguments,
+            keywords,
         }))
     }
 
-    fn parse_index_expression(&mut self, array: Expression) -> Result<Expression, String> {
+    fn parse_index_expression(&mut self, array: Expression) -> Result<Expression, Box<Diagnostic>> {
         self.consume(TokenType::LeftBracket, "Expected '[' for index expression.")?;
         let index = self.parse_expression(Precedence::Lowest)?;
         self.consume(
@@ -540,7 +1081,42 @@ impl Parser {
         }))
     }
 
-    fn parse_list_expression(&mut self) -> Result<Expression, String> {
+    /// `a if cond else b`. The condition binds tighter than another
+    /// conditional, and chains nest to the right:
+    /// `a if x else b if y else c` is `a if x else (b if y else c)`.
+    fn parse_conditional_expression(&mut self, consequence: Expression) -> Result<Expression, Box<Diagnostic>> {
+        self.consume(TokenType::If, "Expected 'if' in conditional expression.")?;
+        let condition = self.parse_expression(Precedence::Conditional)?;
+        self.consume(TokenType::Else, "Expected 'else' in conditional expression.")?;
+        let alternative = self.parse_expression(Precedence::Lowest)?;
+        Ok(Expression::Conditional(ast::ConditionalExpression {
+            condition: Box::new(condition),
+            consequence: Box::new(consequence),
+            alternative: Box::new(alternative),
+        }))
+    }
+
+    fn parse_member_expression(&mut self, object: Expression) -> Result<Expression, Box<Diagnostic>> {
+        let optional = self.advance().token_type == TokenType::QuestionDot;
+        let property = match self.peek().token_type {
+            TokenType::Identifier => self.advance().literal.clone(),
+            TokenType::Init => {
+                self.advance();
+                "init".to_string()
+            }
+            _ => {
+                let message = format!("Expected attribute name after '.', found {}", describe(self.peek()));
+                return Err(self.error_expecting(message, &[TokenType::Identifier]));
+            }
+        };
+        Ok(Expression::Member(MemberExpression {
+            object: Box::new(object),
+            property: Identifier(property),
+            optional,
+        }))
+    }
+
+    fn parse_list_expression(&mut self) -> Result<Expression, Box<Diagnostic>> {
         self.consume(TokenType::LeftBracket, "Expected '[' for list literal.")?;
         let mut elements = Vec::new();
         
@@ -550,7 +1126,8 @@ impl Parser {
             if self.peek().token_type == TokenType::Comma {
                 self.advance();
             } else if self.peek().token_type != TokenType::RightBracket {
-                return Err("Expected ',' or ']' in list literal.".to_string());
+                let expected = [TokenType::Comma, TokenType::RightBracket];
+                return Err(self.error_expecting("Expected ',' or ']' in list literal.", &expected));
             }
         }
         
@@ -558,12 +1135,17 @@ impl Parser {
         Ok(Expression::List(elements))
     }
 
-    fn parse_dict_expression(&mut self) -> Result<Expression, String> {
+    /// `{key: value, ...}`, or a set such as `{1, 2}` when the first item
+    /// has no `:`. `{}` is an empty dict.
+    fn parse_dict_expression(&mut self) -> Result<Expression, Box<Diagnostic>> {
         self.consume(TokenType::LeftBrace, "Expected '{' for dictionary literal.")?;
         let mut pairs = Vec::new();
         
         while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
             let key = self.parse_expression(Precedence::Lowest)?;
+            if pairs.is_empty() && self.peek().token_type != TokenType::Colon {
+                return self.parse_set_rest(key);
+            }
             self.consume(TokenType::Colon, "Expected ':' after dictionary key.")?;
             let value = self.parse_expression(Precedence::Lowest)?;
             pairs.push((key, value));
@@ -571,7 +1153,8 @@ impl Parser {
             if self.peek().token_type == TokenType::Comma {
                 self.advance();
             } else if self.peek().token_type != TokenType::RightBrace {
-                return Err("Expected ',' or '}' in dictionary literal.".to_string());
+                let expected = [TokenType::Comma, TokenType::RightBrace];
+                return Err(self.error_expecting("Expected ',' or '}' in dictionary literal.", &expected));
             }
         }
         
@@ -579,9 +1162,29 @@ impl Parser {
         Ok(Expression::Dict { pairs })
     }
 
+    /// The elements of a set literal after its first one.
+    fn parse_set_rest(&mut self, first: Expression) -> Result<Expression, Box<Diagnostic>> {
+        let mut elements = vec![first];
+        while self.peek().token_type == TokenType::Comma {
+            self.advance();
+            if self.peek().token_type == TokenType::RightBrace {
+                break;
+            }
+            elements.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.consume(TokenType::RightBrace, "Expected ',' or '}' in set literal.")?;
+        Ok(Expression::Set(elements))
+    }
+
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
     }
+    fn peek_next_type(&self) -> TokenType {
+        self.tokens
+            .get(self.current + 1)
+            .map_or(TokenType::Eof, |token| token.token_type)
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::Eof
     }
@@ -593,11 +1196,23 @@ impl Parser {
         &self.tokens[self.current - 1]
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, String> {
+    /// `message` about the token the parser is at. Boxed, since errors are
+    /// rare and `Result`s of the parse steps are returned everywhere.
+    fn error(&self, message: impl Into<String>) -> Box<Diagnostic> {
+        Box::new(Diagnostic::new(self.peek(), message))
+    }
+
+    /// [`Parser::error`], saying the tokens `expected` would have been
+    /// accepted.
+    fn error_expecting(&self, message: impl Into<String>, expected: &[TokenType]) -> Box<Diagnostic> {
+        Box::new(Diagnostic::new(self.peek(), message).expecting(expected))
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, Box<Diagnostic>> {
         if self.peek().token_type == token_type {
             Ok(self.advance())
         } else {
-            Err(message.to_string())
+            Err(self.error_expecting(message, &[token_type]))
         }
     }
 
@@ -607,20 +1222,29 @@ impl Parser {
             | TokenType::PlusAssign
             | TokenType::MinusAssign
             | TokenType::AsteriskAssign
-            | TokenType::SlashAssign => Precedence::Assign,
+            | TokenType::SlashAssign
+            | TokenType::ModAssign
+            | TokenType::ExponentAssign => Precedence::Assign,
+            TokenType::If => Precedence::Conditional,
             TokenType::Or => Precedence::Or,
             TokenType::And => Precedence::And,
             TokenType::Equality | TokenType::NotEqual => Precedence::Equality,
             TokenType::LessThan
             | TokenType::GreaterThan
             | TokenType::LessThanEqual
-            | TokenType::GreaterThanEqual => Precedence::Comparison,
+            | TokenType::GreaterThanEqual
+            | TokenType::In => Precedence::Comparison,
+            TokenType::DotDot => Precedence::Range,
+            TokenType::Pipe => Precedence::BitOr,
+            TokenType::Xor => Precedence::BitXor,
+            TokenType::Ampersand => Precedence::BitAnd,
+            TokenType::LeftShift | TokenType::RightShift => Precedence::Shift,
             TokenType::Plus | TokenType::Minus => Precedence::Term,
             TokenType::Asterisk | TokenType::Slash | TokenType::Mod => Precedence::Factor,
             TokenType::Exponent => Precedence::Exponent,
-            TokenType::LeftParen => Precedence::Call,
+            TokenType::LeftParen | TokenType::Dot | TokenType::QuestionDot => Precedence::Call,
             TokenType::LeftBracket => Precedence::Index,
-            TokenType::Increment | TokenType::Decrement => Precedence::Postfix,
+            TokenType::Increment | TokenType::Decrement | TokenType::Question => Precedence::Postfix,
             _ => Precedence::Lowest,
         }
     }
@@ -629,47 +1253,77 @@ impl Parser {
         self.get_precedence(self.peek().token_type)
     }
 
-    fn map_token_to_prefix_operator(&self, tt: TokenType) -> Result<Operator, String> {
+    fn map_token_to_prefix_operator(&self, tt: TokenType) -> Result<Operator, Box<Diagnostic>> {
         match tt {
             TokenType::Minus => Ok(Operator::Minus),
             TokenType::Not => Ok(Operator::Not),
+            TokenType::Tilde => Ok(Operator::BitNot),
             TokenType::Increment => Ok(Operator::Increment),
             TokenType::Decrement => Ok(Operator::Decrement),
-            _ => Err(format!(
+            _ => Err(self.error(format!(
                 "Cannot map token type {:?} to a prefix operator.",
                 tt
-            )),
+            ))),
         }
     }
 
-    fn map_token_to_infix_operator(&self, tt: TokenType) -> Result<Operator, String> {
+    fn map_token_to_infix_operator(&self, tt: TokenType) -> Result<Operator, Box<Diagnostic>> {
         match tt {
             TokenType::Plus => Ok(Operator::Plus),
             TokenType::Minus => Ok(Operator::Minus),
             TokenType::Asterisk => Ok(Operator::Multiply),
             TokenType::Slash => Ok(Operator::Divide),
+            TokenType::Mod => Ok(Operator::Mod),
+            TokenType::Exponent => Ok(Operator::Exponent),
             TokenType::Equality => Ok(Operator::Equal),
             TokenType::NotEqual => Ok(Operator::NotEqual),
             TokenType::LessThan => Ok(Operator::LessThan),
             TokenType::GreaterThan => Ok(Operator::GreaterThan),
             TokenType::LessThanEqual => Ok(Operator::LessThanEqual),
             TokenType::GreaterThanEqual => Ok(Operator::GreaterThanEqual),
+            TokenType::In => Ok(Operator::In),
+            TokenType::And => Ok(Operator::And),
+            TokenType::Or => Ok(Operator::Or),
+            TokenType::Ampersand => Ok(Operator::BitAnd),
+            TokenType::Pipe => Ok(Operator::BitOr),
+            TokenType::Xor => Ok(Operator::BitXor),
+            TokenType::LeftShift => Ok(Operator::LeftShift),
+            TokenType::RightShift => Ok(Operator::RightShift),
+            TokenType::DotDot => Ok(Operator::Range),
 
-            _ => Err(format!(
+            _ => Err(self.error(format!(
                 "Cannot map token type {:?} to an infix operator.",
                 tt
-            )),
+            ))),
         }
     }
 
-    fn map_token_to_postfix_operator(&self, tt: TokenType) -> Result<Operator, String> {
+    fn map_token_to_postfix_operator(&self, tt: TokenType) -> Result<Operator, Box<Diagnostic>> {
         match tt {
             TokenType::Increment => Ok(Operator::Increment),
             TokenType::Decrement => Ok(Operator::Decrement),
-            _ => Err(format!(
+            _ => Err(self.error(format!(
                 "Cannot map token type {:?} to a postfix operator.",
                 tt
-            )),
+            ))),
+        }
+    }
+}
+
+/// Whether `expression` can be a `case` pattern compared by value: a number
+/// (negative ones too), string, boolean, `none`, or a tuple of these.
+fn is_literal_pattern(expression: &Expression) -> bool {
+    match expression {
+        Expression::IntegerLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::NoneLiteral => true,
+        Expression::Prefix(prefix) => {
+            prefix.operator == Operator::Minus
+                && matches!(*prefix.right, Expression::IntegerLiteral(_) | Expression::FloatLiteral(_))
         }
+        Expression::Tuple(items) => items.iter().all(is_literal_pattern),
+        _ => false,
     }
 }
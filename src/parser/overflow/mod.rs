@@ -0,0 +1,55 @@
+//! Integer literals too large for a 64-bit integer.
+//!
+//! By default such a literal is a parse error that says where it is and what
+//! to write instead. `--int-overflow float` reads it as a float instead,
+//! which keeps the magnitude but not every digit.
+
+use crate::token::Token;
+use std::cell::Cell;
+
+/// What the parser does with an integer literal that doesn't fit in an `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerOverflow {
+    /// Report a parse error.
+    #[default]
+    Error,
+    /// Read the literal as the nearest float.
+    Float,
+}
+
+impl IntegerOverflow {
+    pub fn parse(name: &str) -> Option<IntegerOverflow> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(IntegerOverflow::Error),
+            "float" => Some(IntegerOverflow::Float),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    static MODE: Cell<IntegerOverflow> = const { Cell::new(IntegerOverflow::Error) };
+}
+
+/// Choose how parsers on this thread treat oversized integer literals.
+pub fn set_mode(mode: IntegerOverflow) {
+    MODE.with(|current| current.set(mode));
+}
+
+pub fn mode() -> IntegerOverflow {
+    MODE.with(Cell::get)
+}
+
+/// The parse error for `token`, an integer literal with the digits `digits`
+/// that is too large.
+pub fn error(token: &Token, digits: &str) -> String {
+    format!(
+        "Integer literal {} is too large; integers range from {} to {}. \
+         Write it as a float ({}.0) or run with --int-overflow float to read such literals as floats \
+         (arbitrary-size integers are not supported yet).",
+        token.literal,
+        i64::MIN,
+        i64::MAX,
+        digits
+    )
+}
@@ -0,0 +1,67 @@
+//! `~/.carrionrc`: settings for the interactive REPL.
+//!
+//! Each line is `key = value`; blank lines and lines starting with `#` are
+//! skipped. Settings:
+//!
+//! - `history_size`: how many entries the REPL history keeps (default 1000).
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplConfig {
+    pub history_size: usize,
+}
+
+impl Default for ReplConfig {
+    fn default() -> Self {
+        ReplConfig { history_size: 1000 }
+    }
+}
+
+impl ReplConfig {
+    /// Settings from `~/.carrionrc`, or the defaults when there is no such
+    /// file. A file that can't be used is reported and ignored.
+    pub fn load() -> ReplConfig {
+        let Some(path) = rc_path() else {
+            return ReplConfig::default();
+        };
+        let Ok(text) = fs::read_to_string(&path) else {
+            return ReplConfig::default();
+        };
+        ReplConfig::parse(&text).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring {}: {}", path.display(), e);
+            ReplConfig::default()
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<ReplConfig, String> {
+        let mut config = ReplConfig::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'key = value'", number + 1));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "history_size" => {
+                    config.history_size = value.parse().map_err(|_| {
+                        format!("line {}: history_size must be a whole number, got '{}'", number + 1, value)
+                    })?;
+                }
+                _ => return Err(format!("line {}: unknown setting '{}'", number + 1, key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn rc_path() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".carrionrc"))
+}
@@ -0,0 +1,102 @@
+//! `:diff-env` — what entries did to the session's variables.
+//!
+//! A [`Snapshot`] records the session's bindings at one moment. Values are
+//! shared rather than copied, which is cheap; instances, whose fields can
+//! change in place, also keep a copy of their fields, so `p.x = 5` shows up
+//! as a change to `p.x`.
+
+use crate::evaluator::environment::Environment;
+use crate::object::{Map, Object};
+use std::collections::{BTreeSet, HashMap};
+
+const MAX_TEXT: usize = 60;
+
+/// The bindings of a scope at one moment.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    bindings: HashMap<String, Binding>,
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    value: Object,
+    /// An instance's fields as they were, since the instance itself is shared.
+    fields: Option<Map<Object>>,
+}
+
+impl Snapshot {
+    /// The names bound directly in `env` and their values.
+    pub fn take(env: &Environment) -> Snapshot {
+        let bindings = env
+            .local_names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = env.get_local(&name)?;
+                let fields = match &value {
+                    Object::Instance(instance) => Some(instance.borrow().fields.clone()),
+                    _ => None,
+                };
+                Some((name, Binding { value, fields }))
+            })
+            .collect();
+        Snapshot { bindings }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+/// One line per binding that differs between `before` and `after`, sorted
+/// by name: `+ name = value` for a new one, `- name` for one that is gone,
+/// and `~ name: old -> new` for one that changed.
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let names: BTreeSet<&String> = before.bindings.keys().chain(after.bindings.keys()).collect();
+    let mut lines = Vec::new();
+    for name in names {
+        match (before.bindings.get(name), after.bindings.get(name)) {
+            (None, Some(new)) => lines.push(format!("+ {} = {}", name, summary(&new.value))),
+            (Some(_), None) => lines.push(format!("- {}", name)),
+            (Some(old), Some(new)) if old.value != new.value => {
+                lines.push(format!("~ {}: {} -> {}", name, summary(&old.value), summary(&new.value)));
+            }
+            // The same instance, whose fields may have changed.
+            (Some(old), Some(new)) => {
+                if let (Some(old), Some(new)) = (&old.fields, &new.fields) {
+                    diff_fields(&mut lines, name, old, new);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    lines
+}
+
+fn diff_fields(lines: &mut Vec<String>, name: &str, before: &Map<Object>, after: &Map<Object>) {
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    for field in fields {
+        match (before.get(field), after.get(field)) {
+            (None, Some(new)) => lines.push(format!("+ {}.{} = {}", name, field, summary(new))),
+            (Some(_), None) => lines.push(format!("- {}.{}", name, field)),
+            (Some(old), Some(new)) if old != new => {
+                lines.push(format!("~ {}.{}: {} -> {}", name, field, summary(old), summary(new)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `value` as the REPL prints it, cut off after [`MAX_TEXT`] characters.
+fn summary(value: &Object) -> String {
+    let text = value.to_string();
+    let shown: String = text.chars().take(MAX_TEXT).collect();
+    if shown.len() < text.len() {
+        format!("{}...", shown)
+    } else {
+        shown
+    }
+}
@@ -0,0 +1,180 @@
+//! `:inspect name` — a value rendered as an indented tree.
+//!
+//! Each line names a value's type and size; containers list their element
+//! types and then their items one level deeper, and instances show their
+//! grimoire, fields and spells. Deep or long values are cut off.
+
+use crate::evaluator::builtins::repr;
+use crate::object::Object;
+use std::collections::{BTreeSet, HashSet};
+use std::mem::size_of;
+use std::rc::Rc;
+
+const MAX_DEPTH: usize = 4;
+const MAX_ITEMS: usize = 20;
+const MAX_TEXT: usize = 60;
+
+/// The tree for `value`, bound to `name`, without a trailing newline.
+pub fn inspect(name: &str, value: &Object) -> String {
+    let mut lines = Vec::new();
+    let mut seen = HashSet::new();
+    render(&mut lines, name, value, 0, &mut seen);
+    lines.join("\n")
+}
+
+/// Rough heap plus inline size of `value` in bytes. Shared values such as
+/// instances are only counted once.
+pub fn estimate_size(value: &Object) -> usize {
+    fn size(value: &Object, seen: &mut HashSet<usize>) -> usize {
+        size_of::<Object>()
+            + match value {
+                Object::String(text) => text.len(),
+                Object::List(items) | Object::Tuple(items) => items.iter().map(|item| size(item, seen)).sum(),
+                Object::Set(items) => items.iter().map(|item| size(item, seen)).sum(),
+                Object::Dict(map) => map.iter().map(|(key, item)| size(key, seen) + size(item, seen)).sum(),
+                Object::Instance(instance) if seen.insert(Rc::as_ptr(instance) as usize) => instance
+                    .borrow()
+                    .fields
+                    .iter()
+                    .map(|(key, item)| size_of::<String>() + key.capacity() + size(item, seen))
+                    .sum(),
+                _ => 0,
+            }
+    }
+    size(value, &mut HashSet::new())
+}
+
+fn render(lines: &mut Vec<String>, label: &str, value: &Object, depth: usize, seen: &mut HashSet<usize>) {
+    let indent = "  ".repeat(depth);
+    let line = |text: String| format!("{}{}", indent, text);
+    let child_indent = "  ".repeat(depth + 1);
+
+    match value {
+        Object::List(items) | Object::Tuple(items) => {
+            lines.push(line(format!(
+                "{}: {}, len {}, ~{} bytes",
+                label,
+                value.type_name(),
+                items.len(),
+                estimate_size(value)
+            )));
+            render_elements(lines, items, depth, seen);
+        }
+        Object::Set(items) => {
+            lines.push(line(format!("{}: Set, len {}, ~{} bytes", label, items.len(), estimate_size(value))));
+            render_elements(lines, items, depth, seen);
+        }
+        Object::Dict(map) => {
+            lines.push(line(format!("{}: Dict, len {}, ~{} bytes", label, map.len(), estimate_size(value))));
+            let mut entries: Vec<_> = map.iter().map(|(key, item)| (repr(key), item)).collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            render_items(
+                lines,
+                entries.into_iter(),
+                map.len(),
+                true,
+                depth,
+                seen,
+            );
+        }
+        Object::Instance(instance) => {
+            let instance_ref = instance.borrow();
+            let grimoire = &instance_ref.grimoire;
+            if !seen.insert(Rc::as_ptr(instance) as usize) {
+                lines.push(line(format!("{}: Instance of {} (shown above)", label, grimoire.name)));
+                return;
+            }
+            lines.push(line(format!(
+                "{}: Instance of {}, {} fields, ~{} bytes",
+                label,
+                grimoire.name,
+                instance_ref.fields.len(),
+                estimate_size(value)
+            )));
+            let spells: BTreeSet<&String> = grimoire.method_names().into_iter().collect();
+            if !spells.is_empty() {
+                let names: Vec<&str> = spells.into_iter().map(String::as_str).collect();
+                lines.push(format!("{}spells: {}", child_indent, names.join(", ")));
+            }
+            let mut fields: Vec<_> = instance_ref.fields.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            let count = fields.len();
+            render_items(
+                lines,
+                fields.into_iter().map(|(name, item)| (format!(".{}", name), item)),
+                count,
+                false,
+                depth,
+                seen,
+            );
+        }
+        Object::String(text) => {
+            let shown: String = text.chars().take(MAX_TEXT).collect();
+            let ellipsis = if shown.len() < text.len() { "..." } else { "" };
+            lines.push(line(format!(
+                "{}: String, len {}: {:?}{}",
+                label,
+                text.chars().count(),
+                shown,
+                ellipsis
+            )));
+        }
+        Object::Function(function) => {
+            let mut params: Vec<String> = function.parameters.iter().map(|p| p.0.clone()).collect();
+            params.extend(function.rest.iter().map(|rest| format!("*{}", rest.0)));
+            params.extend(function.keywords.iter().map(|keywords| format!("**{}", keywords.0)));
+            lines.push(line(format!("{}: Spell({})", label, params.join(", "))));
+        }
+        Object::Module(module) => {
+            let names = module.env.borrow().local_names();
+            lines.push(line(format!("{}: Module {} ({})", label, module.name, module.path.display())));
+            lines.push(format!("{}names: {}", child_indent, names.join(", ")));
+        }
+        Object::None => lines.push(line(format!("{}: None", label))),
+        other => lines.push(line(format!("{}: {} {}", label, other.type_name(), other))),
+    }
+}
+
+/// Element types (for lists and dicts), then each item one level deeper.
+/// The elements of a list, tuple or set, labelled by position.
+fn render_elements(lines: &mut Vec<String>, items: &[Object], depth: usize, seen: &mut HashSet<usize>) {
+    render_items(
+        lines,
+        items.iter().enumerate().map(|(i, item)| (format!("[{}]", i), item)),
+        items.len(),
+        true,
+        depth,
+        seen,
+    );
+}
+
+fn render_items<'a>(
+    lines: &mut Vec<String>,
+    items: impl Iterator<Item = (String, &'a Object)>,
+    len: usize,
+    show_types: bool,
+    depth: usize,
+    seen: &mut HashSet<usize>,
+) {
+    let child_indent = "  ".repeat(depth + 1);
+    if len == 0 {
+        return;
+    }
+    if depth + 1 > MAX_DEPTH {
+        lines.push(format!("{}...", child_indent));
+        return;
+    }
+
+    let items: Vec<(String, &Object)> = items.collect();
+    if show_types {
+        let types: BTreeSet<&str> = items.iter().map(|(_, item)| item.type_name()).collect();
+        let types: Vec<&str> = types.into_iter().collect();
+        lines.push(format!("{}element types: {}", child_indent, types.join(", ")));
+    }
+    for (label, item) in items.iter().take(MAX_ITEMS) {
+        render(lines, label, item, depth + 1, seen);
+    }
+    if len > MAX_ITEMS {
+        lines.push(format!("{}... {} more", child_indent, len - MAX_ITEMS));
+    }
+}
@@ -1,8 +1,13 @@
-use crate::{evaluator, lexer, parser};
-use crate::evaluator::environment::Environment;
+pub mod config;
+pub mod diff;
+pub mod inspect;
+pub mod session;
+
 use indoc::indoc;
-use rustyline::DefaultEditor;
+use config::ReplConfig;
 use rustyline::error::ReadlineError;
+use rustyline::{Config, DefaultEditor};
+use session::{ReplResponse, ReplSession};
 
 const CROW_IMAGE: &str = indoc! {
     "
@@ -106,9 +111,16 @@ fn print_commands_help() {
     println!("\n=== REPL Commands ===");
     println!("  help, scry  - Enter interactive help system");
     println!("  quit, exit  - Exit the REPL");
+    println!("  :load path  - Run a script in this session");
+    println!("  :reload name - Re-read a loaded script or imported module");
+    println!("  :inspect name - Show a value's structure as a tree");
+    println!("  :partial on|off - Run the good statements of an entry with a parse error");
+    println!("  :checkpoint name - Remember the session's variables as they are now");
+    println!("  :diff-env [name] - Show variables added, removed or changed since the last entry or a checkpoint");
     println!("  Ctrl+C      - Interrupt current input");
     println!("  Ctrl+D      - Exit the REPL");
-    println!("  Up/Down     - Navigate command history\n");
+    println!("  Up/Down     - Navigate command history");
+    println!("  line ending in ':' - Start a block; an empty line ends it\n");
 }
 
 fn print_syntax_help() {
@@ -147,12 +159,13 @@ fn print_variables_help() {
 fn print_functions_help() {
     println!("\n=== Functions ===");
     println!("  Definition:");
-    println!("    let add = fn(x, y) {{ x + y }};");
-    println!("    let greet = fn(name) {{ \"Hello, \" + name }};");
+    println!("    spell add(x, y):");
+    println!("        return x + y");
     println!("\n  Calling:");
-    println!("    add(3, 4);        // returns 7");
-    println!("    greet(\"Thor\");    // returns \"Hello, Thor\"");
-    println!("\n  Functions are first-class values.\n");
+    println!("    add(3, 4)         // returns 7");
+    println!("\n  Spells are first-class values and capture the scope they");
+    println!("  were defined in, so a spell returned from another spell");
+    println!("  still sees its enclosing variables.\n");
 }
 
 fn print_control_flow_help() {
@@ -226,59 +239,55 @@ pub fn run_repl() {
     println!("{CROW_IMAGE}");
     println!("Type type 'help' or 'scry' for help and 'quit' or 'exit' to leave.\n");
 
-    // Create a new Rustyline Editor with history support
-    let mut rl = DefaultEditor::new().expect("Failed to create line editor");
+    // Create a new Rustyline Editor with history support; repeating the
+    // previous entry doesn't add it again.
+    let settings = ReplConfig::load();
+    let editor_config = Config::builder()
+        .max_history_size(settings.history_size)
+        .and_then(|builder| builder.history_ignore_dups(true))
+        .map(|builder| builder.build())
+        .unwrap_or_default();
+    let mut rl = DefaultEditor::with_config(editor_config).expect("Failed to create line editor");
 
     // Optionally load history from a file
     let history_path = ".carrion_history";
     let _ = rl.load_history(history_path);
     
-    // Create a persistent environment for the REPL session
-    let mut env = Environment::new();
+    let mut session = ReplSession::new();
+    // The lines of the entry being typed; a block is one history entry, so
+    // recalling it brings back every line.
+    let mut entry: Vec<String> = Vec::new();
 
     loop {
-        let readline = rl.readline(">>> ");
-        match readline {
+        let prompt = if session.in_block() { "... " } else { ">>> " };
+        match rl.readline(prompt) {
             Ok(line) => {
-                let input = line.trim();
-
-                // Add to history
-                rl.add_history_entry(&line).ok();
-
-                if matches!(input, "quit" | "exit") {
-                    println!("Farewell. May the All-Father bless your travels!");
-                    break;
-                }
-
-                if matches!(input, "help" | "scry") {
-                    run_help_interactive();
-                    continue;
-                }
-
-                if input.is_empty() {
-                    continue;
-                }
-
-                // --- The Full Pipeline ---
-                let mut lexer = lexer::Lexer::new(input.to_owned(), "<stdin>".into());
-                let tokens = lexer.scan_tokens();
-
-                let mut parser = parser::Parser::new(tokens);
-                let program = parser.parse_program();
-
-                if !parser.errors().is_empty() {
-                    eprintln!("Parsing Error(s):");
-                    for err in parser.errors() {
-                        eprintln!("\t{}", err);
+                entry.push(line.clone());
+                let response = session.feed_line(&line);
+                if response != ReplResponse::Continue {
+                    let text = entry.join("\n");
+                    entry.clear();
+                    if !text.trim().is_empty() {
+                        rl.add_history_entry(text.as_str()).ok();
                     }
-                    continue; // Go to next loop iteration
                 }
-
-                match evaluator::eval_with_env(&program, &mut env) {
-                    Ok(evaluated) => println!("{}", evaluated),
-                    Err(e) => eprintln!("Evaluation Error: {}", e),
+                match response {
+                    ReplResponse::Value(value) => println!("{}", value),
+                    ReplResponse::Output(output) => println!("{}", output),
+                    ReplResponse::Error(e) => eprintln!("{}", e),
+                    ReplResponse::Help => run_help_interactive(),
+                    ReplResponse::Exit(farewell) => {
+                        println!("{}", farewell);
+                        break;
+                    }
+                    ReplResponse::Continue | ReplResponse::Empty => {}
                 }
             }
+            // Ctrl-C abandons a block being typed; otherwise it leaves.
+            Err(ReadlineError::Interrupted) if session.in_block() => {
+                session.cancel();
+                entry.clear();
+            }
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
                 break;
@@ -0,0 +1,311 @@
+//! The REPL's session logic, apart from any terminal.
+//!
+//! A [`ReplSession`] takes input a line at a time with
+//! [`feed_line`](ReplSession::feed_line) and says what happened as a
+//! [`ReplResponse`]; showing prompts, printing and keeping history are left
+//! to the frontend. The terminal REPL drives one with rustyline, and a GUI,
+//! the web playground or a test can drive the same session with plain
+//! strings. Output from `print` still goes to standard output.
+
+use super::diff::Snapshot;
+use crate::error::{self, SourceMap};
+use crate::evaluator::environment::Environment;
+use crate::evaluator::{modules, snippets};
+use crate::object::Object;
+use crate::{evaluator, lexer, parser};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// What feeding a line to the session produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplResponse {
+    /// The entry was evaluated to this value.
+    Value(Object),
+    /// A `:` command ran and reported this.
+    Output(String),
+    /// The entry failed to parse or evaluate, or the command failed.
+    Error(String),
+    /// The line opened a block, or continued one; the session wants the
+    /// block's next line, and an empty line ends it.
+    Continue,
+    /// The user asked for help (`help` or `scry`).
+    Help,
+    /// The user asked to leave (`quit` or `exit`); show the farewell.
+    Exit(String),
+    /// Nothing to do, such as for a blank line.
+    Empty,
+}
+
+pub const FAREWELL: &str = "Farewell. May the All-Father bless your travels!";
+
+/// One REPL session: the variables defined so far, the scripts loaded with
+/// `:load`, and any block still being typed.
+pub struct ReplSession {
+    env: Rc<RefCell<Environment>>,
+    /// Scripts run with `:load`, by file stem, so `:reload` can find them.
+    loaded: HashMap<String, PathBuf>,
+    /// The lines of a block that hasn't been ended yet.
+    block: Option<Vec<String>>,
+    /// How many entries have been evaluated, to name the next one.
+    entries: usize,
+    /// Whether an entry that fails to parse still runs the statements
+    /// before the first bad one.
+    partial: bool,
+    /// The bindings before the last entry that ran code, for `:diff-env`.
+    previous: Snapshot,
+    /// Snapshots saved with `:checkpoint name`.
+    checkpoints: HashMap<String, Snapshot>,
+    /// The text of every entry, so errors can show the lines they point at,
+    /// including those of spells defined in earlier entries.
+    sources: SourceMap,
+}
+
+impl Default for ReplSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplSession {
+    pub fn new() -> Self {
+        ReplSession {
+            env: Rc::new(RefCell::new(Environment::new())),
+            loaded: HashMap::new(),
+            block: None,
+            entries: 0,
+            partial: false,
+            previous: Snapshot::default(),
+            checkpoints: HashMap::new(),
+            sources: SourceMap::new(),
+        }
+    }
+
+    /// Turn partial evaluation on or off, as `:partial on` and `:partial off`
+    /// do. With it on, an entry with a statement that doesn't parse, such as
+    /// a pasted block with one typo, runs the whole statements before it and
+    /// reports the errors in the rest, instead of running nothing.
+    pub fn set_partial(&mut self, partial: bool) {
+        self.partial = partial;
+    }
+
+    /// The session's global scope.
+    pub fn env(&self) -> &Rc<RefCell<Environment>> {
+        &self.env
+    }
+
+    /// Whether a block is open, so the next line continues it (the terminal
+    /// shows the `... ` prompt).
+    pub fn in_block(&self) -> bool {
+        self.block.is_some()
+    }
+
+    /// Abandon the block being typed, as Ctrl-C does.
+    pub fn cancel(&mut self) {
+        self.block = None;
+    }
+
+    /// Take one line of input. A line that opens a block (`if x:`,
+    /// `spell f():`, ...) is held, with the lines after it, until an empty
+    /// line; a block arriving whole, as from history, runs at once.
+    pub fn feed_line(&mut self, line: &str) -> ReplResponse {
+        if let Some(block) = &mut self.block {
+            if !line.trim().is_empty() {
+                block.push(line.to_string());
+                return ReplResponse::Continue;
+            }
+            let entry = block.join("\n");
+            self.block = None;
+            return self.run_entry(&entry);
+        }
+        let opens_block = line.trim_end().ends_with(':') && !line.trim_start().starts_with(':');
+        if opens_block && !line.contains('\n') {
+            self.block = Some(vec![line.to_string()]);
+            return ReplResponse::Continue;
+        }
+        self.run_entry(line)
+    }
+
+    fn run_entry(&mut self, entry: &str) -> ReplResponse {
+        let input = entry.trim();
+        match input {
+            "quit" | "exit" => return ReplResponse::Exit(FAREWELL.to_string()),
+            "help" | "scry" => return ReplResponse::Help,
+            "" => return ReplResponse::Empty,
+            _ => {}
+        }
+        if let Some(command) = input.strip_prefix(':') {
+            return match self.run_command(command) {
+                Ok(output) => ReplResponse::Output(output),
+                Err(e) => ReplResponse::Error(e),
+            };
+        }
+
+        // Each entry is its own snippet, `<repl:1>`, `<repl:2>`, ..., with
+        // line numbers counted from the entry's first line.
+        self.entries += 1;
+        self.previous = Snapshot::take(&self.env.borrow());
+        let name = format!("repl:{}", self.entries);
+        self.sources.add(snippets::source_name(&name), input);
+        let program = match snippets::parse_diagnostics(input, &name) {
+            Ok(program) => program,
+            Err(errors) => {
+                let e = error::render_parse_errors(&errors, &self.sources);
+                if self.partial {
+                    return self.run_prefix(input, &name, e);
+                }
+                return ReplResponse::Error(e);
+            }
+        };
+        match evaluator::eval_with_env(&program, &self.env) {
+            Ok(value) => ReplResponse::Value(value),
+            Err(e) => ReplResponse::Error(self.render(e)),
+        }
+    }
+
+    /// Run the longest run of whole top-level statements at the start of
+    /// `input` that parses, then report `error`, the entry's parse errors.
+    fn run_prefix(&mut self, input: &str, name: &str, error: String) -> ReplResponse {
+        let lines: Vec<&str> = input.lines().collect();
+        let runnable = (1..lines.len())
+            .rev()
+            .filter(|&end| starts_statement(&lines, end))
+            .find_map(|end| snippets::parse(&lines[..end].join("\n"), name).ok().map(|program| (end, program)));
+        let Some((end, program)) = runnable else {
+            return ReplResponse::Error(error);
+        };
+        match evaluator::eval_with_env(&program, &self.env) {
+            Ok(_) => ReplResponse::Error(format!(
+                "Ran lines 1-{} of the entry; the rest did not run.\n{}",
+                end, error
+            )),
+            Err(e) => ReplResponse::Error(self.render(e)),
+        }
+    }
+
+    /// The runtime error `e`, with where it happened and the spell calls it
+    /// came through.
+    fn render(&self, e: String) -> String {
+        error::render_diagnostic(&evaluator::stack::error(e), &self.sources)
+    }
+
+    /// Session commands: `:load path`, `:reload name`, `:inspect name`,
+    /// `:partial on|off`, `:checkpoint name` and `:diff-env [name]`.
+    fn run_command(&mut self, command: &str) -> Result<String, String> {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let argument = argument.trim();
+        if matches!(name, "load" | "reload") && !argument.is_empty() {
+            self.previous = Snapshot::take(&self.env.borrow());
+        }
+        match name {
+            "load" if !argument.is_empty() => {
+                let path = PathBuf::from(argument);
+                load_script(&path, &self.env)?;
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                let output = format!("Loaded {}", path.display());
+                self.loaded.insert(stem, path);
+                Ok(output)
+            }
+            "reload" if !argument.is_empty() => self.reload(argument),
+            "partial" if argument == "on" || argument == "off" => {
+                self.partial = argument == "on";
+                Ok(format!("Partial evaluation {}", argument))
+            }
+            "inspect" if !argument.is_empty() => {
+                let value = self
+                    .env
+                    .borrow()
+                    .get(argument)
+                    .ok_or_else(|| format!("Identifier not found: {}", argument))?;
+                Ok(super::inspect::inspect(argument, &value))
+            }
+            "checkpoint" if !argument.is_empty() => {
+                let snapshot = Snapshot::take(&self.env.borrow());
+                let output = format!("Checkpoint '{}' saved with {} bindings", argument, snapshot.len());
+                self.checkpoints.insert(argument.to_string(), snapshot);
+                Ok(output)
+            }
+            "diff-env" => self.diff_env(argument),
+            "load" => Err("Usage: :load path".to_string()),
+            "reload" => Err("Usage: :reload name".to_string()),
+            "inspect" => Err("Usage: :inspect name".to_string()),
+            "partial" => Err("Usage: :partial on|off".to_string()),
+            "checkpoint" => Err("Usage: :checkpoint name".to_string()),
+            _ => Err(format!(
+                "Unknown command ':{}'. Available: :load path, :reload name, :inspect name, :partial on|off, \
+                 :checkpoint name, :diff-env [name]",
+                name
+            )),
+        }
+    }
+
+    /// What changed in the session's bindings since the last entry, or since
+    /// the checkpoint `checkpoint` when it is given.
+    fn diff_env(&self, checkpoint: &str) -> Result<String, String> {
+        let (before, since) = if checkpoint.is_empty() {
+            (&self.previous, "the last entry".to_string())
+        } else {
+            let before = self.checkpoints.get(checkpoint).ok_or_else(|| {
+                format!("No checkpoint named '{}'; save one with :checkpoint {}", checkpoint, checkpoint)
+            })?;
+            (before, format!("checkpoint '{}'", checkpoint))
+        };
+        let lines = super::diff::diff(before, &Snapshot::take(&self.env.borrow()));
+        if lines.is_empty() {
+            Ok(format!("No bindings changed since {}", since))
+        } else {
+            Ok(lines.join("\n"))
+        }
+    }
+
+    /// Re-read a script from `:load` or a module bound in the session. Scripts
+    /// run again in the session scope; modules are re-evaluated in place, so
+    /// every binding of them sees the new definitions. Other state is kept.
+    fn reload(&self, name: &str) -> Result<String, String> {
+        if let Some(path) = self.loaded.get(name) {
+            load_script(path, &self.env)?;
+            return Ok(format!("Reloaded {}", path.display()));
+        }
+        let binding = self.env.borrow().get(name);
+        match binding {
+            Some(Object::Module(module)) => {
+                let module = modules::reload(&module.path)?;
+                Ok(format!("Reloaded module {} from {}", module.name, module.path.display()))
+            }
+            _ => Err(format!("Nothing named '{}' was loaded or imported", name)),
+        }
+    }
+}
+
+/// Whether line `index` of an entry starts a new top-level statement: it
+/// isn't indented, doesn't continue an `if` or `match`, and doesn't follow a
+/// line ending in `\`.
+fn starts_statement(lines: &[&str], index: usize) -> bool {
+    let line = lines[index];
+    let continues = ["otherwise", "else", "case"].iter().any(|keyword| {
+        line.strip_prefix(keyword)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+    });
+    !line.is_empty()
+        && !line.starts_with(char::is_whitespace)
+        && !continues
+        && !lines[index - 1].trim_end().ends_with('\\')
+}
+
+fn load_script(path: &Path, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+    let mut sources = SourceMap::new();
+    sources.add(path, source.as_str());
+    let mut lexer = lexer::Lexer::new(source, path.to_path_buf());
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        return Err(error::render_parse_errors(parser.errors(), &sources));
+    }
+    evaluator::eval_with_env(&program, env)
+        .map(|_| ())
+        .map_err(|e| error::render_diagnostic(&evaluator::stack::error(e), &sources))
+}
@@ -0,0 +1,395 @@
+//! Strict mode: treating suspicious code as an error.
+//!
+//! [`check_tokens`] and [`check_program`] look for code that runs but
+//! probably doesn't do what was meant:
+//!
+//! - reading a name that is only assigned inside an `if`, `match` arm or
+//!   loop, so it may not exist yet;
+//! - assigning to the name of a builtin such as `len` or `print`, which
+//!   hides the builtin for the rest of the scope (`builtins.len` still
+//!   reaches it);
+//! - keywords written with capitals (`If`, `SPELL`), which still work only
+//!   for backwards compatibility and are names from version 0.2 on.
+//!
+//! Normally the CLI shows these as warnings. With `--strict`, or a
+//! `# carrion: strict` line among a file's leading comments, they are errors
+//! and the file doesn't run. Strict mode also stops an implicit coercion
+//! while the script runs: a condition that isn't a boolean or `None` (`0`
+//! and `""` count as true).
+
+use crate::ast::{Expression, FunctionDefinition, ImportSource, Pattern, Program, Span, SpannedStatement, Statement};
+use crate::evaluator::environment;
+use crate::token::{lookup_identifier, Token, TokenType};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+/// The pragma that makes a file strict. It must come before any code.
+pub const PRAGMA: &str = "# carrion: strict";
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Make every file run on this thread strict, as `--strict` does.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|current| current.set(enabled));
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Whether `source` asks for strict mode with [`PRAGMA`] in the comments at
+/// its top.
+pub fn has_pragma(source: &str) -> bool {
+    source
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with('#'))
+        .any(|line| {
+            let words: String = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            words.eq_ignore_ascii_case(PRAGMA)
+        })
+}
+
+/// Something strict mode rejects, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+/// In strict mode, the error for an implicit coercion described by
+/// `message`; otherwise nothing.
+pub(crate) fn coercion(message: impl FnOnce() -> String) -> Result<(), String> {
+    if is_enabled() {
+        Err(format!("Strict mode: {}", message()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Keywords written with capitals. `True`, `False` and `None` are how the
+/// language writes those values, so they are fine.
+pub fn check_tokens(tokens: &[Token]) -> Vec<Warning> {
+    tokens
+        .iter()
+        .filter(|token| {
+            token.token_type != TokenType::Identifier
+                && token.literal.chars().any(|c| c.is_ascii_uppercase())
+                && lookup_identifier(&token.literal) == token.token_type
+                && !matches!(token.literal.as_str(), "True" | "False" | "None")
+        })
+        .map(|token| Warning {
+            span: Span {
+                file: token.file_name.clone(),
+                line: token.line,
+                column: token.column,
+            },
+            message: format!(
+                "Keyword '{}' is written with capitals, which version 0.2 doesn't allow; write '{}'",
+                token.literal,
+                token.literal.to_ascii_lowercase()
+            ),
+        })
+        .collect()
+}
+
+/// Names that may be read before they are assigned, and builtins hidden by
+/// assignments.
+pub fn check_program(program: &Program) -> Vec<Warning> {
+    let mut scope = Scope::default();
+    scope.block(&program.statements);
+    scope.warnings
+}
+
+/// What is known about the names of one scope (the file, or one spell)
+/// while walking its statements in order.
+#[derive(Default, Clone)]
+struct Scope {
+    /// Assigned on every path to the current statement.
+    assigned: HashSet<String>,
+    /// Assigned on some paths only, with the line of that assignment.
+    conditional: HashMap<String, usize>,
+    /// Names already reported, so each is reported once.
+    reported: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl Scope {
+    fn for_spell(function: &FunctionDefinition, is_method: bool) -> Scope {
+        let mut scope = Scope::default();
+        let parameters = function.parameters.iter().chain(&function.rest).chain(&function.keywords);
+        scope.assigned.extend(parameters.map(|name| name.0.clone()));
+        if is_method {
+            scope.assigned.insert("self".to_string());
+        }
+        scope
+    }
+
+    /// Walk `block`, returning whether it always ends in a `return`.
+    fn block(&mut self, block: &[SpannedStatement]) -> bool {
+        for statement in block {
+            if self.statement(statement) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn statement(&mut self, statement: &SpannedStatement) -> bool {
+        let span = &statement.span;
+        match &statement.statement {
+            Statement::Expression(expression) => self.read(expression, span),
+            Statement::Return(ret) => {
+                if let Some(value) = &ret.value {
+                    self.read(value, span);
+                }
+                return true;
+            }
+            Statement::Assignment(assignment) => {
+                self.read(&assignment.value, span);
+                for target in &assignment.targets {
+                    self.assign_target(target, span);
+                }
+            }
+            Statement::CompoundAssignment(compound) => {
+                self.read(&compound.target, span);
+                self.read(&compound.value, span);
+                self.assign_target(&compound.target, span);
+            }
+            Statement::FunctionDefinition(function) => {
+                self.define(&function.name.0, span);
+                self.spell(function, false);
+            }
+            Statement::Grimoire(grimoire) => {
+                if let Some(parent) = &grimoire.parent {
+                    self.read(parent, span);
+                }
+                for (_, value) in &grimoire.attributes {
+                    self.read(value, span);
+                }
+                self.define(&grimoire.name.0, span);
+                for method in grimoire.methods.iter().chain(&grimoire.getters).chain(&grimoire.setters) {
+                    self.spell(method, true);
+                }
+                for spell in &grimoire.static_spells {
+                    self.spell(spell, false);
+                }
+            }
+            Statement::Import(import) => {
+                let name = match (&import.alias, &import.source) {
+                    (Some(alias), _) => alias.0.clone(),
+                    (None, ImportSource::Name(name)) => name.0.clone(),
+                    (None, ImportSource::Path(path)) => {
+                        Path::new(path).file_stem().unwrap_or_default().to_string_lossy().into_owned()
+                    }
+                };
+                self.define(&name, span);
+            }
+            Statement::If(if_stmt) => {
+                self.read(&if_stmt.condition, span);
+                let mut branches = vec![&if_stmt.consequence];
+                for (condition, block) in &if_stmt.alternatives {
+                    self.read(condition, span);
+                    branches.push(block);
+                }
+                return self.branches(&branches, if_stmt.default.as_ref(), span);
+            }
+            Statement::Match(match_stmt) => {
+                self.read(&match_stmt.subject, span);
+                let exhaustive = match_stmt
+                    .arms
+                    .iter()
+                    .position(|arm| matches!(arm.pattern, Pattern::Wildcard | Pattern::Binding(_)));
+                let arms: Vec<_> = match_stmt.arms.iter().map(|arm| &arm.body).collect();
+                return match exhaustive {
+                    Some(last) => self.branches(&arms[..last], Some(arms[last]), span),
+                    None => self.branches(&arms, None, span),
+                };
+            }
+            Statement::While(while_stmt) => {
+                self.read(&while_stmt.condition, span);
+                self.loop_body(&while_stmt.body, &[], span);
+            }
+            // The block runs once, as if its statements stood on their own.
+            Statement::Together(together) => return self.block(&together.body),
+            Statement::For(for_stmt) => {
+                self.read(&for_stmt.iter, span);
+                let targets: Vec<String> = for_stmt.targets.iter().map(|target| target.0.clone()).collect();
+                self.loop_body(&for_stmt.body, &targets, span);
+            }
+        }
+        false
+    }
+
+    /// Branches of an `if` or `match`, of which exactly one runs; with no
+    /// `default`, possibly none does. Names every branch that falls through
+    /// assigns are assigned afterwards.
+    fn branches(&mut self, branches: &[&Vec<SpannedStatement>], default: Option<&Vec<SpannedStatement>>, span: &Span) -> bool {
+        let before = self.assigned.clone();
+        let mut outcomes = Vec::new();
+        for block in branches.iter().copied().chain(default) {
+            let mut branch = self.nested();
+            let returns = branch.block(block);
+            self.absorb(&mut branch);
+            outcomes.push((returns, branch.assigned));
+        }
+        if default.is_none() {
+            outcomes.push((false, before.clone()));
+        }
+        let falling: Vec<&HashSet<String>> = outcomes.iter().filter(|(returns, _)| !returns).map(|(_, names)| names).collect();
+        let Some((first, rest)) = falling.split_first() else {
+            return true;
+        };
+        let everywhere: HashSet<String> = first.iter().filter(|name| rest.iter().all(|names| names.contains(*name))).cloned().collect();
+        for (_, names) in &outcomes {
+            for name in names.difference(&everywhere) {
+                self.conditional.entry(name.clone()).or_insert(span.line);
+            }
+        }
+        self.assigned = everywhere;
+        false
+    }
+
+    /// A loop body may run any number of times, including none.
+    fn loop_body(&mut self, body: &[SpannedStatement], targets: &[String], span: &Span) {
+        let mut inner = self.nested();
+        for target in targets {
+            inner.define(target, span);
+        }
+        inner.block(body);
+        self.absorb(&mut inner);
+        for name in inner.assigned.difference(&self.assigned) {
+            self.conditional.entry(name.clone()).or_insert(span.line);
+        }
+    }
+
+    /// A copy to walk a branch or loop body with, which starts with no
+    /// warnings of its own.
+    fn nested(&self) -> Scope {
+        Scope {
+            warnings: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Take over what a branch or loop body reported.
+    fn absorb(&mut self, inner: &mut Scope) {
+        self.warnings.append(&mut inner.warnings);
+        self.reported.extend(inner.reported.iter().cloned());
+    }
+
+    fn spell(&mut self, function: &FunctionDefinition, is_method: bool) {
+        let mut scope = Scope::for_spell(function, is_method);
+        scope.block(&function.body);
+        self.warnings.append(&mut scope.warnings);
+    }
+
+    fn assign_target(&mut self, target: &Expression, span: &Span) {
+        match target {
+            Expression::Identifier(name) => self.define(&name.0, span),
+            Expression::Tuple(targets) | Expression::List(targets) => {
+                for target in targets {
+                    self.assign_target(target, span);
+                }
+            }
+            Expression::Unpack(unpack) => self.assign_target(&unpack.value, span),
+            Expression::Index(index) => {
+                self.read(&index.object, span);
+                self.read(&index.index, span);
+            }
+            Expression::Member(member) => self.read(&member.object, span),
+            other => self.read(other, span),
+        }
+    }
+
+    fn define(&mut self, name: &str, span: &Span) {
+        if environment::is_builtin(name) && self.reported.insert(format!("builtin {}", name)) {
+            let message = if name == environment::NAMESPACE {
+                environment::cannot_assign_namespace()
+            } else {
+                format!(
+                    "Assigning to '{}' hides the builtin of that name; 'builtins.{}' still reaches it",
+                    name, name
+                )
+            };
+            self.warnings.push(Warning {
+                span: span.clone(),
+                message,
+            });
+        }
+        self.assigned.insert(name.to_string());
+    }
+
+    fn read(&mut self, expression: &Expression, span: &Span) {
+        let mut names = Vec::new();
+        identifiers(expression, &mut names);
+        for name in names {
+            if self.assigned.contains(name) {
+                continue;
+            }
+            if let Some(line) = self.conditional.get(name)
+                && self.reported.insert(name.to_string())
+            {
+                self.warnings.push(Warning {
+                    span: span.clone(),
+                    message: format!(
+                        "'{}' may be used before it is assigned: it is only assigned conditionally (line {})",
+                        name, line
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// The names `expression` reads, leaving out the bodies of anonymous spells,
+/// which run later in their own scope.
+fn identifiers<'a>(expression: &'a Expression, names: &mut Vec<&'a str>) {
+    match expression {
+        Expression::Identifier(name) => names.push(&name.0),
+        Expression::List(items) | Expression::Tuple(items) | Expression::Set(items) => {
+            items.iter().for_each(|item| identifiers(item, names));
+        }
+        Expression::Dict { pairs } => {
+            for (key, value) in pairs {
+                identifiers(key, names);
+                identifiers(value, names);
+            }
+        }
+        Expression::Prefix(prefix) => identifiers(&prefix.right, names),
+        Expression::Infix(infix) => {
+            identifiers(&infix.left, names);
+            identifiers(&infix.right, names);
+        }
+        Expression::Postfix(postfix) => identifiers(&postfix.left, names),
+        Expression::Call(call) => {
+            identifiers(&call.function, names);
+            call.arguments.iter().for_each(|argument| identifiers(argument, names));
+            call.keywords.iter().for_each(|keyword| identifiers(&keyword.value, names));
+        }
+        Expression::Index(index) => {
+            identifiers(&index.object, names);
+            identifiers(&index.index, names);
+        }
+        Expression::Member(member) => identifiers(&member.object, names),
+        Expression::Conditional(conditional) => {
+            identifiers(&conditional.condition, names);
+            identifiers(&conditional.consequence, names);
+            identifiers(&conditional.alternative, names);
+        }
+        Expression::Maybe(maybe) => identifiers(&maybe.value, names),
+        Expression::Propagate(propagate) => identifiers(&propagate.value, names),
+        Expression::Unpack(unpack) => identifiers(&unpack.value, names),
+        _ => {}
+    }
+}
@@ -0,0 +1,140 @@
+//! Doctests: `>>>` examples inside docstrings, run by `carrion test --doc`.
+//!
+//! A docstring is a string literal written as the first statement of a file,
+//! spell or grimoire method. Each `>>> expr` line is evaluated and its value
+//! is compared with the lines that follow, printed the way the REPL prints
+//! it. Longer snippets continue on `... ` lines, an example with no output
+//! expects `None` (or a statement such as an assignment), and
+//! `Error: message` matches a failing example. Text written with `print` is
+//! not captured.
+
+use crate::ast::{Expression, Program, SpannedStatement, Statement};
+use crate::evaluator::{self, environment::Environment};
+use crate::object::Object;
+use crate::{lexer, parser};
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+/// The docstring of a file, spell or method, named for reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Docstring {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    pub source: String,
+    pub expected: String,
+}
+
+/// Every docstring in `program` that contains at least one example.
+pub fn docstrings(program: &Program) -> Vec<Docstring> {
+    let mut docs = Vec::new();
+    if let Some(text) = leading_string(&program.statements) {
+        docs.push(Docstring {
+            name: "<module>".to_string(),
+            text,
+        });
+    }
+    for statement in &program.statements {
+        match &statement.statement {
+            Statement::FunctionDefinition(def) => {
+                if let Some(text) = leading_string(&def.body) {
+                    docs.push(Docstring {
+                        name: def.name.0.clone(),
+                        text,
+                    });
+                }
+            }
+            Statement::Grimoire(grim) => {
+                for method in &grim.methods {
+                    if let Some(text) = leading_string(&method.body) {
+                        docs.push(Docstring {
+                            name: format!("{}.{}", grim.name.0, method.name.0),
+                            text,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    docs.retain(|doc| !examples(&doc.text).is_empty());
+    docs
+}
+
+fn leading_string(statements: &[SpannedStatement]) -> Option<String> {
+    match statements.first().map(|first| &first.statement) {
+        Some(Statement::Expression(Expression::StringLiteral(text))) => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+/// Split a docstring into examples. Expected output runs until a blank line
+/// or the next `>>>`.
+pub fn examples(doc: &str) -> Vec<Example> {
+    let mut examples: Vec<Example> = Vec::new();
+    let mut in_output = false;
+    for line in doc.lines().map(str::trim) {
+        if let Some(source) = line.strip_prefix(">>>") {
+            examples.push(Example {
+                source: source.trim_start().to_string(),
+                expected: String::new(),
+            });
+            in_output = true;
+            continue;
+        }
+        let Some(example) = examples.last_mut().filter(|_| in_output) else {
+            continue;
+        };
+        if let Some(more) = line.strip_prefix("...").filter(|_| example.expected.is_empty()) {
+            example.source.push('\n');
+            example.source.push_str(more.strip_prefix(' ').unwrap_or(more));
+        } else if line.is_empty() {
+            in_output = false;
+        } else {
+            if !example.expected.is_empty() {
+                example.expected.push('\n');
+            }
+            example.expected.push_str(line);
+        }
+    }
+    examples
+}
+
+/// Run the examples of one docstring in a scope enclosing `env`, so they
+/// can use the file's spells and share variables with each other.
+pub fn run_docstring(doc: &Docstring, env: &Rc<RefCell<Environment>>, file: &Path) -> Result<(), String> {
+    let scope = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(env))));
+    for example in examples(&doc.text) {
+        let actual = run_example(&example.source, &scope, file);
+        if actual != example.expected {
+            return Err(format!(
+                ">>> {}\nexpected:\n{}\ngot:\n{}",
+                example.source.replace('\n', "\n... "),
+                example.expected,
+                actual
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn run_example(source: &str, scope: &Rc<RefCell<Environment>>, file: &Path) -> String {
+    let mut lexer = lexer::Lexer::new(source.to_string(), file.to_path_buf());
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        return format!("Error: {}", parser::diagnostic::join(parser.errors(), "; "));
+    }
+    // Like a REPL session, statements such as assignments show no value.
+    let shows_value = matches!(program.statements.last().map(|last| &last.statement), Some(Statement::Expression(_)));
+    match evaluator::eval_with_env(&program, scope) {
+        Ok(_) if !shows_value => String::new(),
+        Ok(Object::None) => String::new(),
+        Ok(value) => value.to_string(),
+        Err(e) => format!("Error: {}", e),
+    }
+}
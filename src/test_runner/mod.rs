@@ -0,0 +1,166 @@
+//! `carrion test`: discover and run Carrion test files.
+//!
+//! Every `test_*.crl` file under the given paths is evaluated in a fresh
+//! environment, then each top-level spell whose name starts with `test_` is
+//! called with no arguments. A test passes when it returns without an error.
+//! Snapshots for a file live in `__snapshots__/` next to it.
+//!
+//! With `--doc`, every `.crl` file is also searched for docstring examples;
+//! see [`doctest`].
+
+pub mod doctest;
+
+use crate::error::{render_diagnostic, CarrionError, SourceMap};
+use crate::evaluator::builtins::snapshot;
+use crate::evaluator::environment::Environment;
+use crate::evaluator::modules::SOURCE_EXTENSION;
+use crate::object::Object;
+use crate::{evaluator, lexer, parser};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The outcome of one test: `name` is the spell, or the file itself when
+/// loading it failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub file: PathBuf,
+    pub name: String,
+    pub outcome: Result<(), String>,
+}
+
+/// Run every test under `paths` (files or directories), printing a report.
+/// `doc` adds the docstring examples of all source files. Returns `true`
+/// when all tests passed.
+pub fn run(paths: &[PathBuf], doc: bool) -> bool {
+    let results = run_tests(paths, doc);
+    for result in &results {
+        match &result.outcome {
+            Ok(()) => println!("PASS {} :: {}", result.file.display(), result.name),
+            Err(e) => println!("FAIL {} :: {}\n{}", result.file.display(), result.name, indent(e)),
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+    println!(
+        "\n{} passed, {} failed",
+        results.len() - failed,
+        failed
+    );
+    failed == 0
+}
+
+pub fn run_tests(paths: &[PathBuf], doc: bool) -> Vec<TestResult> {
+    let include = if doc { is_source_file } else { is_test_file };
+    let mut files = Vec::new();
+    for path in paths {
+        discover(path, include, &mut files);
+    }
+    files.sort();
+    files.dedup();
+    files.iter().flat_map(|file| run_file(file, doc)).collect()
+}
+
+fn discover(path: &Path, include: fn(&Path) -> bool, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let child = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with(['.', '_']);
+            if child.is_dir() && !hidden || include(&child) {
+                discover(&child, include, files);
+            }
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    }
+}
+
+fn is_source_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION)
+}
+
+fn is_test_file(path: &Path) -> bool {
+    is_source_file(path)
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("test_"))
+}
+
+fn run_file(file: &Path, doc: bool) -> Vec<TestResult> {
+    let result = |name: &str, outcome| TestResult {
+        file: file.to_path_buf(),
+        name: name.to_string(),
+        outcome,
+    };
+
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => return vec![result("<load>", Err(e.to_string()))],
+    };
+    let mut sources = SourceMap::new();
+    sources.add(file, source.as_str());
+    let render = |error: CarrionError| render_diagnostic(&error, &sources);
+
+    let mut lexer = lexer::Lexer::new(source, file.to_path_buf());
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        let errors: Vec<String> = parser.errors().iter().map(|e| render(CarrionError::parse(e.message.as_str()).at(e.span()))).collect();
+        return vec![result("<load>", Err(errors.join("\n")))];
+    }
+
+    let snapshot_dir = file
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(snapshot::DEFAULT_DIRECTORY);
+    snapshot::set_directory(Some(snapshot_dir));
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let mut results = Vec::new();
+    if let Err(e) = evaluator::eval_with_env(&program, &env) {
+        results.push(result("<load>", Err(render(evaluator::stack::error(e)))));
+    } else {
+        if is_test_file(file) {
+            let tests: Vec<(String, Object)> = env
+                .borrow()
+                .local_names()
+                .into_iter()
+                .filter(|name| name.starts_with("test_"))
+                .filter_map(|name| match env.borrow().get_local(&name) {
+                    Some(spell @ Object::Function(_)) => Some((name, spell)),
+                    _ => None,
+                })
+                .collect();
+            if tests.is_empty() {
+                // A file without test spells is a single test of its own.
+                results.push(result("<file>", Ok(())));
+            }
+            for (name, spell) in tests {
+                let outcome = evaluator::apply_function(spell, Vec::new())
+                    .map(|_| ())
+                    .map_err(|e| render(evaluator::stack::error(e)));
+                results.push(result(&name, outcome));
+            }
+        }
+        if doc {
+            for docstring in doctest::docstrings(&program) {
+                let outcome = doctest::run_docstring(&docstring, &env, file);
+                results.push(result(&format!("doc: {}", docstring.name), outcome));
+            }
+        }
+    }
+
+    snapshot::set_directory(None);
+    results
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -32,6 +32,8 @@ pub enum TokenType {
     MinusAssign,
     AsteriskAssign,
     SlashAssign,
+    ModAssign,
+    ExponentAssign,
     Increment,
     Decrement,
     Equality,
@@ -49,8 +51,12 @@ pub enum TokenType {
     // Delimiters
     Comma,
     Colon,
+    Semicolon,
     Pipe,
     Dot,
+    DotDot,
+    QuestionDot,
+    Question,
     LeftShift,
     RightShift,
     Xor,
@@ -93,6 +99,8 @@ pub enum TokenType {
     Maybe,
     Arcane,
     ArcaneSpell,
+    Static,
+    Property,
     Super,
     Fstring,
     Check,
@@ -198,6 +206,8 @@ pub static KEYWORDS: Lazy<HashMap<&'static str, TokenType>> = Lazy::new(|| {
     map.insert("as", As);
     map.insert("arcane", Arcane);
     map.insert("arcanespell", ArcaneSpell);
+    map.insert("static", Static);
+    map.insert("property", Property);
     map.insert("super", Super);
     map.insert("check", Check);
     map.insert("maybe", Maybe);
@@ -0,0 +1,130 @@
+//! Language versions, so breaking changes don't change old scripts.
+//!
+//! A file picks the version it was written for with a `# carrion: 0.2` line
+//! among its leading comments; a project can pick one for all its files with
+//! `carrion = "0.2"` at the top of `carrion.toml`. Files that pick neither
+//! get version 0.1, the behavior scripts were written against before
+//! versions existed.
+//!
+//! Each breaking change is gated on the version here, as a method of
+//! [`Version`], and the code that changes behavior asks the version of the
+//! file it is working on:
+//!
+//! - 0.2: keywords are case-sensitive. `If` and `SPELL` are names, and only
+//!   `True`, `False` and `None` are written with a capital.
+
+use std::cell::Cell;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Version {
+    pub const V0_1: Version = Version { major: 0, minor: 1 };
+    pub const V0_2: Version = Version { major: 0, minor: 2 };
+
+    /// Every version this interpreter can run, oldest first.
+    pub const SUPPORTED: [Version; 2] = [Version::V0_1, Version::V0_2];
+    pub const LATEST: Version = Version::V0_2;
+
+    /// A supported version written as `major.minor`, such as `0.2`.
+    pub fn parse(text: &str) -> Result<Version, String> {
+        let version = text.trim().split_once('.').and_then(|(major, minor)| {
+            Some(Version {
+                major: major.parse().ok()?,
+                minor: minor.parse().ok()?,
+            })
+        });
+        match version {
+            Some(version) if Version::SUPPORTED.contains(&version) => Ok(version),
+            _ => {
+                let supported: Vec<String> = Version::SUPPORTED.iter().map(Version::to_string).collect();
+                Err(format!(
+                    "Unknown Carrion version '{}'; this interpreter runs {}",
+                    text.trim(),
+                    supported.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Whether keywords must be written in lowercase (0.2 on).
+    pub fn case_sensitive_keywords(self) -> bool {
+        self >= Version::V0_2
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+thread_local! {
+    static DEFAULT: Cell<Version> = const { Cell::new(Version::V0_1) };
+}
+
+/// The version of files without a pragma, as `carrion.toml` sets it.
+pub fn set_default(version: Version) {
+    DEFAULT.with(|default| default.set(version));
+}
+
+pub fn default_version() -> Version {
+    DEFAULT.with(Cell::get)
+}
+
+/// The version `source` asks for with a `# carrion: X.Y` line in the
+/// comments at its top, if any. Other `# carrion:` lines, such as
+/// `# carrion: strict`, are not versions.
+pub fn pragma(source: &str) -> Result<Option<Version>, String> {
+    let requested = source
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.trim_start_matches('#').split_once(':')?;
+            let value = value.trim();
+            let is_version = key.trim().eq_ignore_ascii_case("carrion")
+                && value.starts_with(|c: char| c.is_ascii_digit());
+            is_version.then_some(value)
+        })
+        .next();
+    requested.map(Version::parse).transpose()
+}
+
+/// The version to read `source` with: its pragma, or the default. A pragma
+/// naming an unknown version is reported by [`pragma`]; here it gets the
+/// default.
+pub fn of(source: &str) -> Version {
+    pragma(source).ok().flatten().unwrap_or_else(default_version)
+}
+
+/// The `carrion = "X.Y"` line of a manifest, which must come before its
+/// first table.
+pub fn from_manifest(text: &str) -> Result<Option<Version>, String> {
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            break;
+        }
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "carrion"
+        {
+            return Version::parse(value.trim().trim_matches('"')).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Make the version `carrion.toml` at `path` asks for the default.
+pub fn load_manifest(path: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    if let Some(version) = from_manifest(&text).map_err(|e| format!("{}: {}", path.display(), e))? {
+        set_default(version);
+    }
+    Ok(())
+}
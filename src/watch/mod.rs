@@ -0,0 +1,143 @@
+//! `carrion watch`: re-run a script whenever its file changes.
+//!
+//! When the only difference between two versions is the parameters or body
+//! of top-level spells, just those definitions are evaluated again in the
+//! running program's environment, so its state survives the edit. Any other
+//! change restarts the program from scratch, as does every change when
+//! `full_restart` is set.
+
+use crate::ast::{Program, Statement};
+use crate::evaluator::{self, environment::Environment};
+use crate::object::Object;
+use crate::error::{self, SourceMap};
+use crate::{lexer, parser};
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the file's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// What an edit requires, see [`classify`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Unchanged,
+    /// Only these top-level spells changed; re-evaluate their definitions.
+    Spells(Vec<String>),
+    Restart,
+}
+
+/// Compare two versions of a program statement by statement.
+pub fn classify(old: &Program, new: &Program) -> Change {
+    if old.statements.len() != new.statements.len() {
+        return Change::Restart;
+    }
+    let mut spells = Vec::new();
+    for (before, after) in old.statements.iter().zip(&new.statements) {
+        match (&before.statement, &after.statement) {
+            _ if before == after => {}
+            // A spell that only moved is defined again too, so its errors
+            // point at its new lines.
+            (Statement::FunctionDefinition(a), Statement::FunctionDefinition(b)) if a.name == b.name => {
+                spells.push(b.name.0.clone());
+            }
+            // Other statements have already run; where they now sit doesn't matter.
+            (before, after) if before == after => {}
+            _ => return Change::Restart,
+        }
+    }
+    if spells.is_empty() {
+        Change::Unchanged
+    } else {
+        Change::Spells(spells)
+    }
+}
+
+/// Run `path`, then keep watching it until the process is stopped.
+pub fn run(path: &Path, full_restart: bool) -> ! {
+    let mut modified = modified_time(path);
+    let mut session = parse(path).map(|(program, sources)| start(program, &sources));
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let now = modified_time(path);
+        if now == modified {
+            continue;
+        }
+        modified = now;
+
+        let Some((program, sources)) = parse(path) else {
+            // Keep the old program running until the file parses again.
+            continue;
+        };
+        let change = match &session {
+            Some((old, _)) if !full_restart => classify(old, &program),
+            _ => Change::Restart,
+        };
+        match (change, &mut session) {
+            (Change::Unchanged, _) => {}
+            (Change::Spells(names), Some((old, env))) => {
+                println!("[watch] Reloading {}", names.join(", "));
+                for statement in &program.statements {
+                    if matches!(&statement.statement, Statement::FunctionDefinition(def) if names.contains(&def.name.0)) {
+                        let single = Program {
+                            statements: vec![statement.clone()],
+                        };
+                        if let Err(e) = evaluator::eval_with_env(&single, env) {
+                            eprintln!("{}", error::render_diagnostic(&evaluator::stack::error(e), &sources));
+                        }
+                    }
+                }
+                *old = program;
+            }
+            _ => {
+                println!("[watch] Restarting {}", path.display());
+                session = Some(start(program, &sources));
+            }
+        }
+    }
+}
+
+/// Run the program in a fresh environment, then its `main` spell. Errors
+/// show their lines from `sources`.
+fn start(program: Program, sources: &SourceMap) -> (Program, Rc<RefCell<Environment>>) {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let evaluated = evaluator::eval_with_env(&program, &env).and_then(|evaluated| {
+        if evaluated != Object::None {
+            println!("{}", evaluated);
+        }
+        evaluator::call_main(&env, &[])
+    });
+    match evaluated {
+        Ok(_) => {}
+        Err(e) => eprintln!("{}", error::render_diagnostic(&evaluator::stack::error(e), sources)),
+    }
+    (program, env)
+}
+
+/// The program in `path`, with its text for error messages.
+fn parse(path: &Path) -> Option<(Program, SourceMap)> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Could not read '{}': {}", path.display(), e);
+            return None;
+        }
+    };
+    let mut sources = SourceMap::new();
+    sources.add(path, source.as_str());
+    let mut lexer = lexer::Lexer::new(source, path.to_path_buf());
+    let mut parser = parser::Parser::new(lexer.scan_tokens());
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        eprintln!("{}", error::render_parse_errors(parser.errors(), &sources));
+        return None;
+    }
+    Some((program, sources))
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -1,4 +1,5 @@
-use the_carrion_language::{evaluator, lexer, object::Object, parser};
+use the_carrion_language::object::{Object, Str};
+use the_carrion_language::{ast, evaluator, lexer, parser};
 
 fn run_eval(input: &str) -> Result<Object, String> {
     let mut lexer = lexer::Lexer::new(input.to_owned(), "<test>".into());
@@ -7,7 +8,7 @@ fn run_eval(input: &str) -> Result<Object, String> {
     let program = parser.parse_program();
 
     if !parser.errors().is_empty() {
-        return Err(format!("Parser errors: {:?}", parser.errors()));
+        return Err(format!("Parser errors: {}", parser::diagnostic::join(parser.errors(), "; ")));
     }
 
     evaluator::eval(&program)
@@ -45,6 +46,14 @@ fn test_integer_arithmetic() {
             50,
             "Failed a complex expression",
         ),
+        ("7 % 3", 1, "Failed with modulo"),
+        ("-7 % 3", 2, "Modulo should take the sign of the divisor"),
+        ("7 % -3", -2, "Modulo should take the sign of the divisor"),
+        ("2 + 10 % 4 * 3", 8, "Modulo should bind like multiplication"),
+        ("2 ** 10", 1024, "Failed with exponent"),
+        ("2 ** 3 ** 2", 512, "Exponent should be right-associative"),
+        ("3 * 2 ** 2", 12, "Exponent should bind tighter than multiplication"),
+        ("(-2) ** 3", -8, "Failed with negative base"),
     ];
 
     for (input, expected_val, msg) in tests {
@@ -59,319 +68,3942 @@ fn test_integer_arithmetic() {
 }
 
 #[test]
-fn test_boolean_expressions() {
+fn test_bitwise_operators() {
     let tests = vec![
-        ("true", true),
-        ("false", false),
-        ("1 < 2", true),
-        ("1 > 2", false),
-        ("1 < 1", false),
-        ("1 > 1", false),
-        ("1 == 1", true),
-        ("1 != 1", false),
-        ("1 == 2", false),
-        ("1 != 2", true),
+        ("12 & 10", 8),
+        ("12 | 10", 14),
+        ("12 ^ 10", 6),
+        ("~5", -6),
+        ("~-1", 0),
+        ("1 << 4", 16),
+        ("-3 << 2", -12),
+        ("256 >> 4", 16),
+        ("-16 >> 2", -4),
+        ("-1 >> 100", -1),
+        ("5 >> 64", 0),
+        ("0 << 100", 0),
+        // Precedence follows Python: shifts above &, then ^, then |.
+        ("1 | 2 ^ 3 & 5", 3),
+        ("1 << 2 + 1", 8),
+        ("6 & 3 << 1", 6),
+        ("~2 & 7", 5),
     ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(Object::Integer(expected)), "Failed for input: {}", input);
+    }
 
-    for (input, expected_val) in tests {
-        match run_eval(input) {
-            Ok(Object::Boolean(actual)) => {
-                assert_eq!(actual, expected_val, "Input: '{}'", input);
-            }
-            Ok(other) => panic!("Expected Boolean, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", e, input),
-        }
+    assert_eq!(run_eval("1 | 2 == 3"), Ok(Object::Boolean(true)));
+    let err_tests = vec!["1 << 63", "1 << -1", "8 >> -2", "1.0 & 1.0", "~1.5", "true | false"];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
     }
 }
 
 #[test]
-fn test_assignment_statements() {
-    // Test integer assignments
-    let int_tests = vec![
-        ("x = 5\nx", 5),
-        ("y = 10\ny", 10),
-        ("a = 5 * 5\na", 25),
-        ("b = 10 + 5 * 2\nb", 20),
-        ("x = 5\nx = 10\nx", 10),
-        ("x = 5\nx = x + 5\nx", 10),
+fn test_and_or_short_circuit() {
+    let tests = vec![
+        // The deciding operand is returned as-is.
+        ("none or \"anonymous\"", Object::String("anonymous".into())),
+        ("\"Odin\" or \"anonymous\"", Object::String("Odin".into())),
+        ("none and missing", Object::None),
+        ("1 and 2", Object::Integer(2)),
+        ("false or none", Object::None),
+        // The right side is never evaluated once the left decides.
+        ("false and missing()", Object::Boolean(false)),
+        ("true or missing()", Object::Boolean(true)),
+        ("m = mock(true)\nfalse and m()\nm.call_count", Object::Integer(0)),
+        ("m = mock(true)\ntrue and m()\nm.call_count", Object::Integer(1)),
     ];
-
-    for (input, expected) in int_tests {
-        match run_eval(input) {
-            Ok(Object::Integer(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
-        }
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
     }
 
-    // Test string assignments
-    let string_tests = vec![
-        ("s = \"hello\"\ns", "hello"),
-        ("msg = \"world\"\nmsg", "world"),
-    ];
+    assert!(run_eval("true and missing()").is_err());
+    assert!(run_eval("false or missing()").is_err());
+}
 
-    for (input, expected) in string_tests {
-        match run_eval(input) {
-            Ok(Object::String(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+#[test]
+fn test_log_keyword_fields() {
+    let carrion = std::path::Path::new(env!("CARGO_BIN_EXE_carrion"));
+    let code = "log.info(\"saved\", user=\"odin\")\nlog.warn(\"retry\", {\"attempt\": 1, \"user\": \"loki\"}, user=\"odin\")";
+    let tests = vec![
+        (vec![], vec!["INFO  saved user=odin\n", "WARN  retry attempt=1 user=odin\n"]),
+        (vec!["--log-json"], vec!["\"msg\":\"saved\",\"user\":\"odin\"}", "\"msg\":\"retry\",\"attempt\":1,\"user\":\"odin\"}"]),
+    ];
+    for (flags, expected) in tests {
+        let output = std::process::Command::new(carrion).args(&flags).args(["-e", code]).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        for line in expected {
+            assert!(stderr.contains(line), "flags {:?}: expected '{}' in '{}'", flags, line, stderr);
         }
     }
 
-    // Test boolean assignments
-    let bool_tests = vec![
-        ("t = True\nt", true),
-        ("f = False\nf", false),
+    match run_eval("print(\"x\", end=\"\")") {
+        Err(e) => assert!(e.contains("takes no keyword arguments, got 'end'"), "got '{}'", e),
+        other => panic!("Expected an error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_together_blocks() {
+    let setup = "grim Log:\n    init():\n        self.items = []\n    spell add(x):\n        self.items = push(self.items, x)\n\nlog_ = Log()\nspell note(x):\n    log_.add(x)\n    return x * 2\n\nspell fail(x):\n    return x + missing\n\n";
+    let tests = vec![
+        // Tasks run once the block's own statements are done, in spawn order.
+        ("together:\n    spawn(note, 1)\n    spawn(note, 2)\n    log_.add(0)\nlog_.items", Object::list(vec![Object::Integer(0), Object::Integer(1), Object::Integer(2)])),
+        ("together:\n    t = spawn(note, 5)\nt.result()", Object::Integer(10)),
+        ("together:\n    t = spawn(note, 5)\nt.done", Object::Boolean(true)),
+        // A task's own spawns join the same block.
+        ("spell fan(n):\n    spawn(note, n)\n    spawn(note, n + 1)\ntogether:\n    spawn(fan, 1)\nlog_.items", Object::list(vec![Object::Integer(1), Object::Integer(2)])),
+        ("together:\n    together:\n        spawn(note, 1)\n    log_.add(9)\nlog_.items", Object::list(vec![Object::Integer(1), Object::Integer(9)])),
+        ("together = 3\ntogether + 1", Object::Integer(4)),
     ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(expected), "Failed for input: {}", input);
+    }
 
-    for (input, expected) in bool_tests {
-        match run_eval(input) {
-            Ok(Object::Boolean(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Boolean, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+    let errors = vec![
+        // The first failure ends the block; the tasks after it never run.
+        ("spell other(x):\n    return nowhere\ntogether:\n    spawn(fail, 1)\n    spawn(other, 2)", "Identifier not found: missing"),
+        ("together:\n    spawn(note, 1)\n    x = nowhere", "Identifier not found: nowhere"),
+        ("spawn(note, 1)", "spawn must be called inside a 'together:' block"),
+        ("spawn(3)", "spawn expects a spell, got"),
+        ("together:\n    t = spawn(note, 1)\n    t.result()", "The task hasn't run yet"),
+    ];
+    for (input, expected) in errors {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Err(e) => assert!(e.contains(expected), "For input {}: expected '{}', got '{}'", input, expected, e),
+            Ok(value) => panic!("Expected an error for {}, got {:?}", input, value),
         }
     }
+
 }
 
 #[test]
-fn test_compound_assignment_statements() {
+fn test_pure_spells() {
+    let setup = "grim Point:\n    init(x):\n        self.x = x\n\npure spell square(x):\n    return x * x\n\nspell plain(x):\n    return x\n\n";
     let tests = vec![
-        ("x = 10\nx += 5\nx", 15),
-        ("x = 20\nx -= 5\nx", 15),
-        ("x = 5\nx *= 3\nx", 15),
-        ("x = 20\nx /= 4\nx", 5),
+        ("square(4)", Object::Integer(16)),
+        ("pure = 3\npure + 1", Object::Integer(4)),
+        ("pure spell moved(x):\n    p = Point(x)\n    p.x = p.x + 1\n    return p.x\nmoved(1)", Object::Integer(2)),
+        ("pure spell fib(n):\n    if n < 2:\n        return n\n    return fast(n - 1) + fast(n - 2)\nfast = memoize(fib)\nfast(30)", Object::Integer(832040)),
+        ("fast = memoize(square)\nfast(3)\nfast(3)\nfast(4)\nfast.cache_size", Object::Integer(2)),
+        ("fast = memoize(square)\nfast(3)\nfast.clear()\nfast.cache_size", Object::Integer(0)),
+        ("map_pure(square, [1, 2, 3])", Object::list(vec![Object::Integer(1), Object::Integer(4), Object::Integer(9)])),
+        ("map_pure(memoize(square), 1..3)", Object::list(vec![Object::Integer(1), Object::Integer(4)])),
     ];
-
     for (input, expected) in tests {
-        match run_eval(input) {
-            Ok(Object::Integer(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(expected), "Failed for input: {}", input);
+    }
+
+    let errors = vec![
+        ("pure spell noisy(x):\n    print(x)\nnoisy(1)", "Pure spell 'noisy' can't print"),
+        ("pure spell noisy(x):\n    return plain(log.info(x))\nnoisy(1)", "Pure spell 'noisy' can't log"),
+        ("pure spell clock():\n    return now()\nclock()", "Pure spell 'clock' can't read time from outside the program"),
+        ("p = Point(1)\npure spell bump(q):\n    q.x = 2\nbump(p)", "Pure spell 'bump' can't change a Point instance, which was made outside it"),
+        ("pure spell rename():\n    Point.label = \"p\"\nrename()", "Pure spell 'rename' can't change grimoire Point, which was made outside it"),
+        ("m = mock(1)\npure spell ask(x):\n    return m(x)\nask(1)", "Pure spell 'ask' can't call a mock, which records its calls"),
+        ("m = mock(1)\npure spell rig():\n    m.returns(2)\nrig()", "Pure spell 'rig' can't change a mock"),
+        ("memoize(plain)", "memoize needs a pure spell; declare it as 'pure spell plain(...)'"),
+        ("map_pure(len, [[1]])", "map_pure expects a pure spell, got"),
+    ];
+    for (input, expected) in errors {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Err(e) => assert!(e.contains(expected), "For input {}: expected '{}', got '{}'", input, expected, e),
+            Ok(value) => panic!("Expected an error for {}, got {:?}", input, value),
         }
     }
+    // A failed pure call leaves nothing behind for the next program.
+    assert_eq!(run_eval("print(2)"), Ok(Object::None));
 }
 
 #[test]
-fn test_multiple_assignment() {
-    // Test multiple assignment with different values
+fn test_runtime_error_locations() {
+    use the_carrion_language::error::{render_diagnostic, SourceMap};
+    use the_carrion_language::evaluator::stack;
+
     let tests = vec![
-        ("x, y = 10, 20\nx", 10),
-        ("x, y = 10, 20\ny", 20),
-        ("a, b, c = 1, 2, 3\na", 1),
-        ("a, b, c = 1, 2, 3\nb", 2),
-        ("a, b, c = 1, 2, 3\nc", 3),
-        ("x, y = 5 * 2, 3 + 4\nx", 10),
-        ("x, y = 5 * 2, 3 + 4\ny", 7),
+        (
+            "spell half(n):\n    return n / zero\n\nspell quarter(n):\n    x = half(n)\n    return x\n\nfor i in range(3):\n    quarter(i)",
+            "Evaluation Error: Identifier not found: zero\n  --> <test>:2:5\n  |\n2 |     return n / zero\n  |     ^\n  in spell 'half' called at <test>:5:5\n  in spell 'quarter' called at <test>:9:5",
+        ),
+        (
+            "spell fact(n):\n    if n == 0:\n        return missing\n    return n * fact(n - 1)\nfact(3)",
+            "Evaluation Error: Identifier not found: missing\n  --> <test>:3:9\n  |\n3 |         return missing\n  |         ^\n  in spell 'fact' called at <test>:4:5 (2 more times)\n  in spell 'fact' called at <test>:5:1",
+        ),
+        (
+            "grim Raven:\n    spell fly():\n        return self.wings\nRaven().fly()",
+            "Evaluation Error: <Raven instance> has no attribute 'wings'\n  --> <test>:3:9\n  |\n3 |         return self.wings\n  |         ^\n  in spell 'Raven.fly' called at <test>:4:1",
+        ),
+        // The early return `?` unwound with doesn't lend its location to the next error.
+        (
+            "spell parse():\n    x = err(\"bad\")?\n    return x\nresult = parse()\nlen(5)",
+            "Evaluation Error: Object of type 5 has no length.\n  --> <test>:5:1\n  |\n5 | len(5)\n  | ^",
+        ),
     ];
 
     for (input, expected) in tests {
-        match run_eval(input) {
-            Ok(Object::Integer(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
-        }
+        let error = match run_eval(input) {
+            Err(e) => stack::error(e),
+            Ok(value) => panic!("Expected an error for {}, got {:?}", input, value),
+        };
+        let mut sources = SourceMap::new();
+        sources.add("<test>", input);
+        assert_eq!(render_diagnostic(&error, &sources), expected, "Failed for input: {}", input);
     }
+}
 
-    // Test multiple assignment with strings
-    let string_tests = vec![
-        ("s1, s2 = \"hello\", \"world\"\ns1", "hello"),
-        ("s1, s2 = \"hello\", \"world\"\ns2", "world"),
+#[test]
+fn test_parse_error_snippets() {
+    use the_carrion_language::error::{render_parse_errors, SourceMap};
+
+    let tests = vec![
+        (
+            "x = 1 + import",
+            "Parse Error: No prefix parsing function found for token: Import(import)\n  --> <test>:1:9\n  |\n1 | x = 1 + import\n  |         ^^^^^^",
+        ),
+        (
+            "total = (1 + 2",
+            "Parse Error: Expected ')' to close grouped expression.\n  --> <test>:1:15\n  |\n1 | total = (1 + 2\n  |               ^",
+        ),
     ];
 
-    for (input, expected) in string_tests {
-        match run_eval(input) {
-            Ok(Object::String(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
-        }
+    for (input, expected) in tests {
+        let mut parser = parser::Parser::new(lexer::Lexer::new(input.to_string(), "<test>".into()).scan_tokens());
+        parser.parse_program();
+        let mut sources = SourceMap::new();
+        sources.add("<test>", input);
+        assert_eq!(render_parse_errors(parser.errors(), &sources), expected, "Failed for input: {}", input);
     }
 }
 
 #[test]
-fn test_list_expressions() {
+fn test_builtins_namespace() {
     let tests = vec![
-        ("[]", Vec::<Object>::new()),
-        ("[1]", vec![Object::Integer(1)]),
-        ("[1, 2, 3]", vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)]),
-        ("[1, \"hello\", True]", vec![Object::Integer(1), Object::String("hello".to_string()), Object::Boolean(true)]),
+        ("len = 3\nbuiltins.len([1, 2])", Object::Integer(2)),
+        ("spell len(x):\n    return 0\nbuiltins.len(\"raven\")", Object::Integer(5)),
+        ("spell count(len):\n    return builtins.len([len, len])\ncount(7)", Object::Integer(2)),
+        ("builtins.VERSION == VERSION", Object::Boolean(true)),
+        ("log = None\nbuiltins.log.debug(\"quiet\")", Object::None),
     ];
 
     for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    // Nothing replaces the namespace, so it always reaches the builtins.
+    let errors = vec![
+        "builtins = 1",
+        "builtins[\"print\"] = 1",
+        "builtins[\"VERSION\"] += \"-dev\"",
+        "spell builtins():\n    return 1",
+        "for builtins in [1]:\n    x = 1",
+    ];
+    for input in errors {
         match run_eval(input) {
-            Ok(Object::List(elements)) => {
-                assert_eq!(elements, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected List, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+            Err(e) => assert!(e.contains("'builtins' can't be assigned to"), "For input {}: got '{}'", input, e),
+            Ok(value) => panic!("Expected an error for {}, got {:?}", input, value),
         }
     }
 }
 
 #[test]
-fn test_list_indexing() {
+fn test_watchdog_hint() {
+    use the_carrion_language::evaluator::watchdog::HINT;
+
+    let carrion = std::path::Path::new(env!("CARGO_BIN_EXE_carrion"));
+    let code = "print(\"start\")\ntotal = 0\nfor i in range(300000):\n    total = total + i\nprint(total)\n";
     let tests = vec![
-        ("[1, 2, 3][0]", 1),
-        ("[1, 2, 3][1]", 2),
-        ("[1, 2, 3][2]", 3),
-        ("[10, 20, 30][1]", 20),
+        (vec!["--watchdog", "0.05"], true),
+        (vec!["--watchdog", "0.05", "--no-watchdog"], false),
     ];
 
-    for (input, expected) in tests {
-        match run_eval(input) {
-            Ok(Object::Integer(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
-        }
+    for (flags, hinted) in tests {
+        let output = std::process::Command::new(carrion).args(&flags).args(["-e", code]).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "start\n44999850000\n");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(stderr.contains(HINT), hinted, "flags {:?}, stderr: {}", flags, stderr);
     }
 
-    // Test string elements
-    let string_tests = vec![
-        ("[\"hello\", \"world\"][0]", "hello"),
-        ("[\"hello\", \"world\"][1]", "world"),
-    ];
+    for limit in ["inf", "1e300", "0", "soon"] {
+        let output = std::process::Command::new(carrion).args(["--watchdog", limit, "-e", "1"]).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{:?}", output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.starts_with("Error: --watchdog requires a number of seconds."), "{}", stderr);
+    }
+}
 
-    for (input, expected) in string_tests {
-        match run_eval(input) {
-            Ok(Object::String(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
+#[test]
+fn test_long_values_in_error_messages() {
+    use the_carrion_language::error;
+
+    let digits = (0..100).map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+    let start = |text: &str| text.chars().take(60).collect::<String>();
+    let long_list = format!("[{}]", digits);
+    let error_tests = vec![
+        ("range(100).to_list() + 1".to_string(), format!("to {}... (List, 100 items) and 1", start(&long_list))),
+        ("x = 5\nx(range(100).to_list())".to_string(), "Not a function: 5".to_string()),
+        (format!("\"{}\"()", "a".repeat(200)), format!("Not a function: {}... (String, 200 characters)", "a".repeat(60))),
+        ("d = {}\nfor i in range(30):\n    d[str(i)] = i\nlen(d.keys()) - d".to_string(), "... (Dict, 30 keys)".to_string()),
+        ("range(100).to_list().wings".to_string(), format!("{}... (List, 100 items) has no attribute 'wings'", start(&long_list))),
+        ("builtins.builtins".to_string(), "(Dict, ".to_string()),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(&input) {
+            Err(e) => {
+                assert!(e.contains(&expected), "Expected '{}' in '{}'", expected, e);
+                assert!(e.len() < 200, "Expected a short message, got '{}'", e);
             }
-            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
         }
     }
+
+    error::set_verbose(true);
+    let result = run_eval("range(100).to_list() + 1");
+    error::set_verbose(false);
+    match result {
+        Err(e) => assert!(e.contains(&format!("to {} and 1", long_list)), "Expected the whole list in '{}'", e),
+        other => panic!("Expected a type mismatch, got {:?}", other),
+    }
 }
 
 #[test]
-fn test_dictionary_expressions() {
+fn test_parser_diagnostics() {
+    use the_carrion_language::token::TokenType;
+
     let tests = vec![
-        ("{}", std::collections::HashMap::<String, Object>::new()),
-        ("{\"name\": \"John\"}", {
-            let mut map = std::collections::HashMap::new();
-            map.insert("name".to_string(), Object::String("John".to_string()));
-            map
-        }),
-        ("{\"age\": 30, \"name\": \"John\"}", {
-            let mut map = std::collections::HashMap::new();
-            map.insert("age".to_string(), Object::Integer(30));
-            map.insert("name".to_string(), Object::String("John".to_string()));
-            map
-        }),
+        ("if x\n    y = 1", (1, 5), TokenType::Newline, vec![TokenType::Colon], "Expected ':' after if condition."),
+        ("xs = [1, 2 3]", (1, 12), TokenType::Integer, vec![TokenType::Comma, TokenType::RightBracket], "Expected ',' or ']' in list literal."),
+        ("d = {\"a\": 1 \"b\": 2}", (1, 13), TokenType::StringLit, vec![TokenType::Comma, TokenType::RightBrace], "Expected ',' or '}' in dictionary literal."),
+        ("import 3", (1, 8), TokenType::Integer, vec![TokenType::StringLit, TokenType::Identifier], "found Integer(3)"),
+        ("x = 1\ny = * 2", (2, 5), TokenType::Asterisk, vec![], "No prefix parsing function found for token: Asterisk(*)"),
+        ("n = 99999999999999999999", (1, 5), TokenType::Integer, vec![], "Integer literal 99999999999999999999 is too large"),
     ];
+    for (input, (line, column), token, expected, message) in tests {
+        let mut lexer = lexer::Lexer::new(input.to_owned(), "diag.crl".into());
+        let mut parser = parser::Parser::new(lexer.scan_tokens());
+        parser.parse_program();
+        let diagnostic = parser.errors().first().unwrap_or_else(|| panic!("Expected a diagnostic for {:?}", input));
+        assert_eq!((diagnostic.line, diagnostic.column), (line, column), "location for {:?}", input);
+        assert_eq!(diagnostic.token.token_type, token, "token for {:?}", input);
+        assert_eq!(diagnostic.expected, expected, "expected tokens for {:?}", input);
+        assert!(diagnostic.message.contains(message), "Expected '{}' in '{}'", message, diagnostic.message);
+        assert_eq!(diagnostic.to_string(), format!("diag.crl:{}:{}: {}", line, column, diagnostic.message));
+        assert_eq!(diagnostic.span().to_string(), format!("diag.crl:{}:{}", line, column));
+    }
+}
 
-    for (input, expected) in tests {
-        match run_eval(input) {
-            Ok(Object::Dict(dict)) => {
-                assert_eq!(dict, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Dict, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+#[test]
+fn test_script_cache_round_trip() {
+    use the_carrion_language::cache::{self, Entry};
+    use the_carrion_language::strict;
+
+    let source = indoc::indoc! {r#"
+        import "lib/util" as util
+        grim Shape:
+            sides = 0
+            init(name):
+                self.name = name
+            spell area():
+                return 0
+        grim Square(Shape):
+            static spell unit():
+                return Square("unit")
+        spell total(first, *rest, **options):
+            sum = first
+            for x in rest:
+                sum += x
+            return sum
+        pure spell square(x):
+            return x * x
+        values = [1, 2.5, "three", True, None, (4, 5), {6, 7}, {"k": -8}]
+        count = 0
+        while count < 3:
+            count++
+        match values[0]:
+            case 0..1:
+                kind = "small"
+            case n:
+                kind = "other"
+            case _:
+                kind = "none"
+        label = "odd" if count % 2 == 1 else "even"
+        a, *b = values
+        If count > 2:
+            found = maybe label.missing
+        else:
+            found = none
+        double = spell(x): x * 2
+        result = [total(1, 2, 3, scale=2, **{"z": 1}), double(count), kind, label, not True, ~1, Square.unit().name]
+    "#};
+    let mut lexer = lexer::Lexer::new(source.to_string(), "first.crl".into());
+    let tokens = lexer.scan_tokens();
+    let mut warnings = strict::check_tokens(&tokens);
+    let mut parser = parser::Parser::new(tokens);
+    let program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+    warnings.extend(strict::check_program(&program));
+    assert!(!warnings.is_empty());
+    let entry = Entry { program, warnings };
+
+    let bytes = cache::encode(&entry);
+    assert_eq!(cache::decode(&bytes, "first.crl".as_ref()).unwrap(), entry);
+
+    // The same text read as another file points into that file.
+    let decoded = cache::decode(&bytes, "second.crl".as_ref()).unwrap();
+    for warning in &decoded.warnings {
+        assert!(warning.to_string().starts_with("second.crl:"), "{}", warning);
+    }
+    let spans: Vec<String> = decoded.program.statements.iter().map(|s| s.span.to_string()).collect();
+    let expected: Vec<String> = entry.program.statements.iter().map(|s| s.span.to_string().replace("first", "second")).collect();
+    assert_eq!(spans, expected);
+    match &decoded.program.statements[0].statement {
+        ast::Statement::Import(import) => assert_eq!(import.origin, std::path::Path::new("second.crl")),
+        other => panic!("Expected an import, got {:?}", other),
+    }
+
+    let extended = [&bytes[..], &[0]].concat();
+    let error_tests = vec![
+        (&b"not an entry"[..], "not a Carrion cache entry"),
+        (&bytes[..bytes.len() / 2], "truncated"),
+        (&extended[..], "trailing bytes"),
+    ];
+    for (input, expected) in error_tests {
+        match cache::decode(input, "x.crl".as_ref()) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}', got '{}'", expected, e),
+            Ok(_) => panic!("Expected '{}' decoding {} bytes", expected, input.len()),
         }
     }
+
+    // Different text, or text read as another language version, is a
+    // different entry.
+    assert_eq!(cache::key("x = 1"), cache::key("x = 1"));
+    assert_ne!(cache::key("x = 1"), cache::key("x = 2"));
+    assert_ne!(cache::key("x = 1"), cache::key("# carrion: 0.2\nx = 1"));
+
+    // Pruning removes the least recently used entries, and nothing else.
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => std::path::PathBuf::from(path.as_str()),
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let now = std::time::SystemTime::now();
+    let names: Vec<String> = (0..4).map(|n| cache::key(&format!("x = {}", n))).collect();
+    for (age, name) in names.iter().enumerate() {
+        let file = std::fs::File::create(dir.join(name)).unwrap();
+        file.set_len(100).unwrap();
+        file.set_modified(now - std::time::Duration::from_secs(60 * age as u64)).unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), vec![0; 1000]).unwrap();
+    cache::prune(&dir, 250);
+    let exists: Vec<bool> = names.iter().map(|name| dir.join(name).exists()).collect();
+    assert_eq!(exists, vec![true, true, false, false]);
+    assert!(dir.join("notes.txt").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
 }
 
 #[test]
-fn test_dictionary_indexing() {
+fn test_increment_and_decrement() {
     let tests = vec![
-        ("{\"name\": \"John\"}[\"name\"]", "John"),
-        ("{\"city\": \"New York\", \"country\": \"USA\"}[\"city\"]", "New York"),
-        ("{\"city\": \"New York\", \"country\": \"USA\"}[\"country\"]", "USA"),
+        ("x = 1\nx++\nx", "2"),
+        ("x = 1\n(x++, x)", "(1, 2)"),
+        ("x = 1\n(++x, x)", "(2, 2)"),
+        ("x = 5\n(x--, --x, x)", "(5, 3, 3)"),
+        ("x = 1.5\n++x", "2.5"),
+        ("xs = [1, 2]\nxs[1]++\nxs", "[1, 3]"),
+        ("grim Calls:\n    init():\n        self.n = 0\n    spell idx():\n        self.n += 1\n        return 0\ncalls = Calls()\nxs = [7]\nxs[calls.idx()]--\n(xs, calls.n)", "([6], 1)"),
+        ("d = {\"n\": 1}\n++d[\"n\"]\nd[\"n\"]", "2"),
+        ("grim Counter:\n    init():\n        self.n = 0\nc = Counter()\nc.n++\n++c.n\nc.n", "2"),
+        ("i = 0\nwhile i < 3:\n    i++\ni", "3"),
     ];
-
     for (input, expected) in tests {
         match run_eval(input) {
-            Ok(Object::String(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
         }
     }
 
-    // Test integer values
-    let int_tests = vec![
-        ("{\"age\": 30}[\"age\"]", 30),
-        ("{\"score\": 95, \"rank\": 1}[\"score\"]", 95),
-        ("{\"score\": 95, \"rank\": 1}[\"rank\"]", 1),
+    let error_tests = vec![
+        ("s = \"a\"\ns++", "'++' needs a number, got String"),
+        ("missing--", "Undefined variable: missing"),
+        ("++5", "'++' target must be an identifier, index or attribute"),
     ];
-
-    for (input, expected) in int_tests {
+    for (input, expected) in error_tests {
         match run_eval(input) {
-            Ok(Object::Integer(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
         }
     }
 }
 
 #[test]
-fn test_builtin_functions() {
-    // Test len function
-    let len_tests = vec![
-        ("len([1, 2, 3])", 3),
-        ("len([])", 0),
-        ("len([\"hello\", \"world\"])", 2),
-        ("len({\"a\": 1, \"b\": 2})", 2),
-        ("len({})", 0),
-        ("len(\"hello\")", 5),
+fn test_semicolon_separated_statements() {
+    let tests = vec![
+        ("x = 1; y = 2; x + y", "3"),
+        ("a, b = 1, 2; a += b; a", "3"),
+        ("x = 1;\nx", "1"),
+        ("items = []; items = items.push(1);; items = items.push(2); items", "[1, 2]"),
+        ("x = 5\nif x > 2: x = x * 2; x = x + 1\nx", "11"),
+        ("x = 1\nif x > 2: x = x * 2; x = x + 1\nx", "1"),
+        ("spell f(n): a = n * 2; return a + 1\nf(3)", "7"),
+        ("spell f(): return; \nf()", "None"),
+        ("for i in [1, 2]:\n    a = i; b = a * 10\nb", "20"),
     ];
-
-    for (input, expected) in len_tests {
+    for (input, expected) in tests {
         match run_eval(input) {
-            Ok(Object::Integer(val)) => {
-                assert_eq!(val, expected, "Failed for input: {}", input);
-            }
-            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
-            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
         }
     }
+    assert!(run_eval("x = (1; 2)").is_err());
+}
 
-    // Test push function
-    let push_tests = vec![
-        ("push([1, 2], 3)", vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)]),
-        ("push([], 42)", vec![Object::Integer(42)]),
-        ("push([\"hello\"], \"world\")", vec![Object::String("hello".to_string()), Object::String("world".to_string())]),
+#[test]
+fn test_main_entry_point() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use the_carrion_language::evaluator::environment::Environment;
+
+    let run = |input: &str, args: &[&str]| -> Result<Option<Object>, String> {
+        let program = parser::Parser::new(lexer::Lexer::new(input.to_owned(), "<test>".into()).scan_tokens()).parse_program();
+        let env = Rc::new(RefCell::new(Environment::new()));
+        evaluator::eval_with_env(&program, &env)?;
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        evaluator::call_main(&env, &args)
+    };
+    let tests = vec![
+        ("spell main(args):\n    return args", vec!["a", "--b"], Ok(Some(Object::list(vec![Object::String("a".into()), Object::String("--b".into())])))),
+        ("spell main(*args):\n    return len(args)", vec!["x"], Ok(Some(Object::Integer(1)))),
+        ("spell main():\n    return 7", vec!["ignored"], Ok(Some(Object::Integer(7)))),
+        ("count = 1\nspell main():\n    return count + 1", vec![], Ok(Some(Object::Integer(2)))),
+        ("spell helper():\n    return 1", vec![], Ok(None)),
+        ("main = 5", vec![], Ok(None)),
+        ("spell main(args):\n    return missing", vec![], Err("Identifier not found: missing".to_string())),
     ];
+    for (input, args, expected) in tests {
+        assert_eq!(run(input, &args), expected, "Failed for input: {}", input);
+    }
+}
 
-    for (input, expected) in push_tests {
+#[test]
+fn test_backslash_line_continuation() {
+    let tests = vec![
+        ("total = 1 + \\\n        2 + \\\n    3\ntotal", "6"),
+        ("x = 7\nif x > 5 and \\\n   x < 10:\n    \"mid\"", "mid"),
+        ("names = [\"a\", \\  \r\n\"b\"]\nnames", "[a, b]"),
+        ("spell add(a, b):\n    return a + \\\n        b\nadd(2, \\\n3)", "5"),
+        ("\"back\\slash\"", "back\\slash"),
+    ];
+    for (input, expected) in tests {
         match run_eval(input) {
-            Ok(Object::List(elements)) => {
-                assert_eq!(elements, expected, "Failed for input: {}", input);
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
+        }
+    }
+
+    // The joined lines are one line to the parser, but tokens keep their own line.
+    let tokens = lexer::Lexer::new("x = 1 + \\\n  2\n".to_string(), "<test>".into()).scan_tokens();
+    let summary: Vec<(String, usize)> = tokens.iter().map(|t| (format!("{:?}", t.token_type), t.line)).collect();
+    let newlines = summary.iter().filter(|(kind, _)| kind == "Newline").count();
+    assert_eq!(newlines, 1, "{:?}", summary);
+    assert_eq!(tokens.iter().find(|t| t.literal == "2").map(|t| (t.line, t.column)), Some((2, 3)));
+}
+
+#[test]
+fn test_render_diagnostic() {
+    use the_carrion_language::ast::Span;
+    use the_carrion_language::error::{self, render_diagnostic, CarrionError, Frame, SourceMap};
+
+    let span = |line, column| Span {
+        file: "calc.crl".into(),
+        line,
+        column,
+    };
+    let mut sources = SourceMap::new();
+    sources.add("calc.crl", "spell half(n):\n\treturn n / zero\nhalf(4)\n");
+
+    let tests = vec![
+        (CarrionError::evaluation("Identifier not found: missing"), "Evaluation Error: Identifier not found: missing"),
+        (CarrionError::parse("Expected ':'"), "Parse Error: Expected ':'"),
+        (
+            CarrionError::evaluation("Identifier not found: zero").at(span(2, 13)),
+            "Evaluation Error: Identifier not found: zero\n  --> calc.crl:2:13\n  |\n2 | \treturn n / zero\n  | \t           ^",
+        ),
+        (
+            CarrionError::evaluation("Identifier not found: zero").at(span(2, 13)).with_frames(vec![Frame {
+                spell: "half".to_string(),
+                span: span(3, 1),
+            }]),
+            "Evaluation Error: Identifier not found: zero\n  --> calc.crl:2:13\n  |\n2 | \treturn n / zero\n  | \t           ^\n  in spell 'half' called at calc.crl:3:1",
+        ),
+        // A location in a file the map doesn't hold is shown without its line.
+        (CarrionError::evaluation("boom").at(Span { file: "other.crl".into(), line: 1, column: 1 }), "Evaluation Error: boom\n  --> other.crl:1:1"),
+    ];
+    for (diagnostic, expected) in tests {
+        assert_eq!(render_diagnostic(&diagnostic, &sources), expected);
+    }
+
+    error::set_color(true);
+    let colored = render_diagnostic(&CarrionError::evaluation("boom"), &sources);
+    error::set_color(false);
+    assert_eq!(colored, "\x1b[1;31mEvaluation Error:\x1b[0m boom");
+}
+
+#[test]
+fn test_language_versions() {
+    use the_carrion_language::version::{self, Version};
+
+    assert_eq!(version::pragma("# carrion: 0.2\nx = 1"), Ok(Some(Version::V0_2)));
+    assert_eq!(version::pragma("# carrion: strict\n# carrion: 0.1\nx = 1"), Ok(Some(Version::V0_1)));
+    assert_eq!(version::pragma("x = 1\n# carrion: 0.2"), Ok(None));
+    assert_eq!(
+        version::pragma("# carrion: 0.9"),
+        Err("Unknown Carrion version '0.9'; this interpreter runs 0.1, 0.2".to_string())
+    );
+    assert_eq!(version::from_manifest("carrion = \"0.2\"\n[plugins]\n"), Ok(Some(Version::V0_2)));
+    assert_eq!(version::from_manifest("[plugins]\ncarrion = \"0.2\"\n"), Ok(None));
+    assert!(version::from_manifest("carrion = \"two\"").is_err());
+
+    // 0.1 reads capitalised keywords as keywords; 0.2 reads them as names.
+    let tests = vec![
+        ("If True:\n    x = 1\nx", Ok(Object::Integer(1))),
+        ("# carrion: 0.1\nIf True:\n    x = 1\nx", Ok(Object::Integer(1))),
+        ("# carrion: 0.2\nIf = 2\nSpell = 3\nIf + Spell", Ok(Object::Integer(5))),
+        ("# carrion: 0.2\nx = None\ny = True\nif y and not False:\n    x = 1\nx", Ok(Object::Integer(1))),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), expected, "Failed for input: {}", input);
+    }
+    assert!(run_eval("# carrion: 0.2\nIf True:\n    x = 1").is_err());
+
+    version::set_default(Version::V0_2);
+    let defaulted = run_eval("If = 4\nIf");
+    let pinned = run_eval("# carrion: 0.1\nIf True:\n    x = 1\nx");
+    version::set_default(Version::V0_1);
+    assert_eq!(defaulted, Ok(Object::Integer(4)));
+    assert_eq!(pinned, Ok(Object::Integer(1)));
+}
+
+#[test]
+fn test_strict_mode() {
+    use the_carrion_language::strict;
+
+    let check = |input: &str| -> Vec<String> {
+        let mut lexer = lexer::Lexer::new(input.to_owned(), "<test>".into());
+        let tokens = lexer.scan_tokens();
+        let mut warnings = strict::check_tokens(&tokens);
+        let program = parser::Parser::new(tokens).parse_program();
+        warnings.extend(strict::check_program(&program));
+        warnings.iter().map(|warning| warning.message.clone()).collect()
+    };
+    let tests = vec![
+        ("if x > 0:\n    y = 1\nprint(y)", vec!["'y' may be used before it is assigned: it is only assigned conditionally (line 1)"]),
+        ("if x > 0:\n    y = 1\nelse:\n    y = 2\nprint(y)", vec![]),
+        ("spell f(x):\n    if x:\n        return 1\n    else:\n        y = 2\n    return y", vec![]),
+        ("for i in range(3):\n    last = i\nprint(last)", vec!["'last' may be used before it is assigned: it is only assigned conditionally (line 1)"]),
+        ("match x:\n    case 1:\n        y = 1\n    case _:\n        y = 2\nprint(y)", vec![]),
+        ("spell f():\n    return later\nlater = 1", vec![]),
+        ("len = 3", vec!["Assigning to 'len' hides the builtin of that name; 'builtins.len' still reaches it"]),
+        ("spell print(x):\n    return x", vec!["Assigning to 'print' hides the builtin of that name; 'builtins.print' still reaches it"]),
+        ("builtins = {}", vec!["'builtins' can't be assigned to; it always holds the builtins"]),
+        ("If True:\n    x = 1", vec!["Keyword 'If' is written with capitals, which version 0.2 doesn't allow; write 'if'"]),
+        ("x = None\ny = True", vec![]),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(check(input), expected, "Failed for input: {}", input);
+    }
+
+    assert!(strict::has_pragma("# carrion: strict\nx = 1"));
+    assert!(strict::has_pragma("#!/usr/bin/env carrion\n\n#  Carrion:   STRICT\nx = 1"));
+    assert!(!strict::has_pragma("x = 1\n# carrion: strict"));
+
+    // Implicit coercions only fail while strict mode is on.
+    let coercions = vec![
+        ("if 0:\n    1", "Strict mode: condition is Integer 0, not a boolean; compare it explicitly"),
+        ("x = \"go\"\nwhile x:\n    x = None", "Strict mode: condition is String go, not a boolean; compare it explicitly"),
+        ("\"yes\" if [] else \"no\"", "Strict mode: condition is List [], not a boolean; compare it explicitly"),
+    ];
+    for (input, _) in &coercions {
+        assert!(run_eval(input).is_ok(), "Expected '{}' to run outside strict mode", input);
+    }
+    strict::set_enabled(true);
+    for (input, expected) in &coercions {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+    let allowed = run_eval("d = {\"a\": 1}\nif d[\"a\"] == 1 and not None:\n    \"fine\"");
+    strict::set_enabled(false);
+    assert_eq!(allowed, Ok(Object::String("fine".into())));
+}
+
+#[test]
+fn test_result_values() {
+    let setup = "spell parse_age(text):\n    if text == \"\":\n        return err(\"empty\")\n    return ok(int(text))\n\nspell check_adult(age):\n    if age < 18:\n        return err(\"too young: \" + str(age))\n    return ok(age)\n\nspell admit(text):\n    age = parse_age(text)?\n    adult = check_adult(age)?\n    return ok(\"admitted at \" + str(adult))\n\n";
+    let tests = vec![
+        ("ok(3)", "ok(3)"),
+        ("err(\"boom\")", "err(boom)"),
+        ("ok()", "ok(None)"),
+        ("ok(3).is_ok()", "True"),
+        ("err(\"x\").is_ok()", "False"),
+        ("err(\"x\").is_err()", "True"),
+        ("ok(3).unwrap_or(0)", "3"),
+        ("err(\"x\").unwrap_or(0)", "0"),
+        ("ok(3).map(spell(x): x * 2)", "ok(6)"),
+        ("err(\"x\").map(spell(x): x * 2)", "err(x)"),
+        ("err(\"x\").map_err(spell(e): e + \"!\")", "err(x!)"),
+        ("ok(20).and_then(check_adult)", "ok(20)"),
+        ("ok(5).and_then(check_adult)", "err(too young: 5)"),
+        ("ok(2).unwrap()", "2"),
+        ("ok(1) == ok(1)", "True"),
+        ("ok(1) == err(1)", "False"),
+        ("ok(1) != ok(2)", "True"),
+        ("str((ok(4).value, ok(4).error, err(\"e\").error))", "(4, None, e)"),
+        // `?` unwraps an ok and returns an err from the spell early.
+        ("admit(\"30\")", "ok(admitted at 30)"),
+        ("admit(\"\")", "err(empty)"),
+        ("admit(\"12\")", "err(too young: 12)"),
+        ("spell double(r):\n    return ok(r? * 2)\ndouble(ok(4))", "ok(8)"),
+        ("spell double(r):\n    return ok(r? * 2)\ndouble(err(\"no\"))", "err(no)"),
+        // Only the innermost spell returns; its caller sees the err as a value.
+        ("spell outer():\n    r = admit(\"\")\n    return r.is_err()\nouter()", "True"),
+        ("f = spell(r): r? + 1\nstr((f(ok(1)), f(err(\"e\"))))", "(2, err(e))"),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("err(\"bad\").unwrap()", "Called unwrap on err(bad)"),
+        ("err(\"bad\")?", "'?' returned err(bad) outside of a spell"),
+        ("spell f():\n    return 5?\nf()", "'?' expects ok(...) or err(...), got 5"),
+        ("ok(1).and_then(spell(x): x)", "and_then expects its spell to return ok(...) or err(...), got 1"),
+        ("ok(1).unwrap_or()", "Wrong number of arguments. got=0, want=1"),
+        ("err()", "Wrong number of arguments. got=0, want=1"),
+    ];
+    for (input, expected) in error_tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_null_safe_member_access() {
+    let setup = "grim User:\n    init(name, manager):\n        self.name = name\n        self.manager = manager\n    spell greet(greeting):\n        return greeting + \", \" + self.name\n\nboss = User(\"Odin\", none)\nworker = User(\"Loki\", boss)\n";
+    let tests = vec![
+        ("worker.manager?.name", Object::String("Odin".into())),
+        ("boss.manager?.name", Object::None),
+        ("worker?.manager?.manager?.name", Object::None),
+        ("boss.manager?.greet(\"Hail\")", Object::None),
+        ("worker.manager?.greet(\"Hail\")", Object::String("Hail, Odin".into())),
+        // The arguments of a skipped call aren't evaluated.
+        ("boss.manager?.greet(undefined_name)", Object::None),
+        ("{\"a\": none}[\"a\"]?.name", Object::None),
+        ("(boss.manager?.name) or \"nobody\"", Object::String("nobody".into())),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(expected), "Failed for input: {}", input);
+    }
+
+    let error_tests = vec![
+        // Only a `none` receiver is skipped; a missing attribute still fails.
+        ("worker?.age", "has no attribute 'age'"),
+        // `?.` guards one step: `.name` on the `none` it produced still fails.
+        ("boss.manager?.manager.name", "None has no attribute 'name'"),
+        ("worker?.name = \"Thor\"", "Cannot assign through '?.'; use '.name' instead"),
+        ("worker?.name += \"!\"", "Cannot assign through '?.'; use '.name' instead"),
+    ];
+    for (input, expected) in error_tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_maybe_chains() {
+    let setup = "grim Node:\n    init(value, next):\n        self.value = value\n        self.next = next\n    spell label():\n        return \"node\"\n    spell fail():\n        return missing\n\nchain = Node(1, Node(2, none))\nconfig = {\"db\": {\"hosts\": [\"a\", \"b\"]}}\n";
+    let tests = vec![
+        ("maybe chain.next.value", Object::Integer(2)),
+        ("maybe chain.next.label()", Object::String("node".into())),
+        ("maybe config.db.hosts[1]", Object::String("b".into())),
+        // A missing attribute, index or key, or a link on None, ends the chain.
+        ("maybe chain.next.next.value", Object::None),
+        ("maybe chain.next.next.label()", Object::None),
+        ("maybe chain.missing.deeper", Object::None),
+        ("maybe config.db.hosts[5].upper()", Object::None),
+        ("maybe config[\"cache\"][\"size\"]", Object::None),
+        ("maybe none.anything", Object::None),
+        // `maybe` binds tighter than operators.
+        ("(maybe config.cache.size) or 10", Object::Integer(10)),
+        ("maybe config.cache.size or 10", Object::Integer(10)),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(expected), "Failed for input: {}", input);
+    }
+
+    // Only missing links are forgiven: unknown names and errors inside the
+    // spells a chain calls still fail.
+    let error_tests = vec![
+        ("maybe nothing.value", "Identifier not found: nothing"),
+        ("maybe chain.fail()", "Identifier not found: missing"),
+        ("maybe chain.next.value(1)", "Not a function: 2"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(&format!("{}{}", setup, input)) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_conditional_expressions() {
+    let tests = vec![
+        ("1 if true else 2", Object::Integer(1)),
+        ("1 if none else 2", Object::Integer(2)),
+        ("1 if 0 else 2", Object::Integer(1)),
+        ("x = 5\n\"big\" if x > 3 else \"small\"", Object::String("big".into())),
+        ("y = 10 if false else 20\ny", Object::Integer(20)),
+        // The else branch takes the rest of the expression...
+        ("1 if false else 2 + 10", Object::Integer(12)),
+        ("1 if true else 2 + 10", Object::Integer(1)),
+        ("(1 if true else 2) + 10", Object::Integer(11)),
+        ("1 + 1 if true else 0", Object::Integer(2)),
+        // ...chains nest to the right...
+        ("1 if false else 2 if false else 3", Object::Integer(3)),
+        ("1 if false else 2 if true else 3", Object::Integer(2)),
+        // ...and `or`/`and` bind tighter than the conditional.
+        ("none or 5 if false else 7", Object::Integer(7)),
+        ("7 if none or 1 else 9", Object::Integer(7)),
+        ("abs = spell(n): n if n >= 0 else -n\nabs(-4)", Object::Integer(4)),
+        // Only the chosen branch is evaluated.
+        ("1 if true else missing()", Object::Integer(1)),
+        ("m = mock(3)\nm() if false else 0\nm.call_count", Object::Integer(0)),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    assert!(run_eval("missing() if false else 1").is_ok());
+    assert!(run_eval("1 if true").is_err());
+    assert!(run_eval("1 if false else missing()").is_err());
+}
+
+#[test]
+fn test_modulo_and_exponent_edge_cases() {
+    let float_tests = vec![
+        ("2 ** -1", 0.5),
+        ("10 ** -2", 0.01),
+        ("7.5 % 2.0", 1.5),
+        ("-7.5 % 2.0", 0.5),
+        ("2.0 ** 0.5", 2.0f64.sqrt()),
+        ("4.0 ** -1.0", 0.25),
+    ];
+    for (input, expected) in float_tests {
+        assert_eq!(run_eval(input), Ok(Object::Float(expected)), "Failed for input: {}", input);
+    }
+
+    let err_tests = vec!["5 % 0", "5.0 % 0.0", "0 ** -1", "0.0 ** -2.0", "2 ** 64", "2 % 1.5"];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+
+    // Exponents past u32::MAX overflow rather than falling back to floats.
+    for input in ["2 ** 5000000000", "1 ** 5000000000"] {
+        let result = run_eval(input);
+        assert!(matches!(&result, Err(e) if e.contains("Integer overflow")), "{}: {:?}", input, result);
+    }
+}
+
+#[test]
+fn test_number_display_round_trips() {
+    use the_carrion_language::object::format_float;
+
+    let tests = vec![
+        (0.1, "0.1"),
+        (2.0, "2.0"),
+        (-0.0, "-0.0"),
+        (1.5e-7, "1.5e-7"),
+        (1e300, "1e300"),
+        (123456.789, "123456.789"),
+        (1e16, "1e16"),
+        (0.0001, "0.0001"),
+        (f64::MAX, "1.7976931348623157e308"),
+        (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+        (f64::INFINITY, "inf"),
+        (f64::NEG_INFINITY, "-inf"),
+        (f64::NAN, "NaN"),
+    ];
+    for (value, expected) in tests {
+        assert_eq!(format_float(value), expected, "Failed for {:?}", value);
+    }
+
+    // str() of any number reads back as exactly the same number.
+    let floats = [0.1 + 0.2, 1.0 / 3.0, -2.5e-310, 6.02214076e23, 9007199254740993.0, -0.0, 1e-5, 5e-324];
+    for value in floats {
+        let program = format!("float(str({}))", format_float(value));
+        match run_eval(&program) {
+            Ok(Object::Float(back)) => assert_eq!(back.to_bits(), value.to_bits(), "Failed for {}", program),
+            other => panic!("Expected a float for {}, got {:?}", program, other),
+        }
+    }
+    for value in [0, -1, 42, i64::MAX, i64::MIN + 1] {
+        let program = format!("int(str({}))", value);
+        assert_eq!(run_eval(&program), Ok(Object::Integer(value)), "Failed for {}", program);
+    }
+    assert_eq!(run_eval("x = float(\"nan\")\nstr(float(str(x)))"), Ok(Object::String("NaN".into())));
+
+    let tests = vec![
+        ("str(1.0 / 4.0)", "0.25"),
+        ("str(4.0 / 2.0)", "2.0"),
+        ("str(\"text\")", "text"),
+        ("str([1, 2.0])", "[1, 2.0]"),
+        ("str(int(\" 17 \"))", "17"),
+        ("str(int(-2.9))", "-2"),
+        ("str(int(True))", "1"),
+        ("str(float(3))", "3.0"),
+        ("str(float(\"1e300\"))", "1e300"),
+        ("str(float(\"-inf\"))", "-inf"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(Object::String(expected.into())), "Failed for input: {}", input);
+    }
+
+    let error_tests = vec![
+        ("int(\"12.5\")", "int cannot parse '12.5' as an integer"),
+        ("int(float(\"inf\"))", "int cannot convert inf to an integer"),
+        ("int(1e19)", "int cannot convert 1e19 to an integer"),
+        ("float(\"lots\")", "float cannot parse 'lots' as a number"),
+        ("float([1])", "float expects a number or string, got List"),
+        ("str()", "Wrong number of arguments. got=0, want=1"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_integer_literal_overflow() {
+    use the_carrion_language::parser::{overflow, IntegerOverflow};
+
+    assert_eq!(run_eval("9223372036854775807"), Ok(Object::Integer(i64::MAX)));
+    assert_eq!(run_eval("-9223372036854775808"), Ok(Object::Integer(i64::MIN)));
+    assert_eq!(run_eval("-9_223_372_036_854_775_808 + 1"), Ok(Object::Integer(i64::MIN + 1)));
+
+    match run_eval("x = 1\ny = 9999999999999999999999") {
+        Err(e) => {
+            assert!(e.contains("<test>:2:5"), "Expected the literal's location in '{}'", e);
+            assert!(e.contains("too large"), "Expected an overflow message in '{}'", e);
+            assert!(e.contains("--int-overflow float"), "Expected the float hint in '{}'", e);
+        }
+        other => panic!("Expected overflow error, got {:?}", other),
+    }
+    assert!(run_eval("9223372036854775808").is_err());
+    assert!(run_eval("-9223372036854775808 ** 2").is_err());
+
+    overflow::set_mode(IntegerOverflow::Float);
+    let promoted = run_eval("9999999999999999999999");
+    overflow::set_mode(IntegerOverflow::Error);
+    assert_eq!(promoted, Ok(Object::Float(1e22)));
+}
+
+#[test]
+fn test_boolean_expressions() {
+    let tests = vec![
+        ("true", true),
+        ("false", false),
+        ("1 < 2", true),
+        ("1 > 2", false),
+        ("1 < 1", false),
+        ("1 > 1", false),
+        ("1 == 1", true),
+        ("1 != 1", false),
+        ("1 == 2", false),
+        ("1 != 2", true),
+        ("true and true", true),
+        ("true and false", false),
+        ("false or true", true),
+        ("false or false", false),
+        ("1 < 2 and 3 > 2", true),
+        ("1 > 2 or 2 > 3", false),
+        ("not false and true", true),
+        ("false and true or true", true),
+    ];
+
+    for (input, expected_val) in tests {
+        match run_eval(input) {
+            Ok(Object::Boolean(actual)) => {
+                assert_eq!(actual, expected_val, "Input: '{}'", input);
             }
-            Ok(other) => panic!("Expected List, got {:?} for input '{}'", other, input),
+            Ok(other) => panic!("Expected Boolean, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", e, input),
+        }
+    }
+}
+
+#[test]
+fn test_assignment_statements() {
+    // Test integer assignments
+    let int_tests = vec![
+        ("x = 5\nx", 5),
+        ("y = 10\ny", 10),
+        ("a = 5 * 5\na", 25),
+        ("b = 10 + 5 * 2\nb", 20),
+        ("x = 5\nx = 10\nx", 10),
+        ("x = 5\nx = x + 5\nx", 10),
+    ];
+
+    for (input, expected) in int_tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
             Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
         }
     }
 
-    // Test pop function
-    let pop_tests = vec![
-        ("pop([1, 2, 3])", vec![Object::Integer(1), Object::Integer(2)]),
-        ("pop([42])", Vec::<Object>::new()),
-        ("pop([\"hello\", \"world\"])", vec![Object::String("hello".to_string())]),
+    // Test string assignments
+    let string_tests = vec![
+        ("s = \"hello\"\ns", "hello"),
+        ("msg = \"world\"\nmsg", "world"),
     ];
 
-    for (input, expected) in pop_tests {
+    for (input, expected) in string_tests {
         match run_eval(input) {
-            Ok(Object::List(elements)) => {
-                assert_eq!(elements, expected, "Failed for input: {}", input);
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
             }
-            Ok(other) => panic!("Expected List, got {:?} for input '{}'", other, input),
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    // Test boolean assignments
+    let bool_tests = vec![
+        ("t = True\nt", true),
+        ("f = False\nf", false),
+    ];
+
+    for (input, expected) in bool_tests {
+        match run_eval(input) {
+            Ok(Object::Boolean(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Boolean, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_compound_assignment_statements() {
+    let tests = vec![
+        ("x = 10\nx += 5\nx", 15),
+        ("x = 20\nx -= 5\nx", 15),
+        ("x = 5\nx *= 3\nx", 15),
+        ("x = 20\nx /= 4\nx", 5),
+        ("x = 17\nx %= 5\nx", 2),
+        ("x = -7\nx %= 3\nx", 2),
+        ("x = 7\nx %= -3\nx", -2),
+        ("x = 3\nx **= 4\nx", 81),
+        ("x = 5\nx **= 0\nx", 1),
+        ("xs = [2, 10]\nxs[1] %= 4\nxs[0] **= 3\nxs[0] + xs[1]", 10),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("x = 7.5\nx %= 2.0\nx"), Ok(Object::Float(1.5)));
+    assert_eq!(run_eval("x = 2\nx **= -1\nx"), Ok(Object::Float(0.5)));
+    assert_eq!(run_eval("x = 2.0\nx **= 0.5\nx"), Ok(Object::Float(2.0f64.sqrt())));
+
+    let err_tests = vec!["x = 1\nx %= 0", "x = 1.0\nx %= 0.0", "x = 10\nx **= 40"];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+}
+
+#[test]
+fn test_multiple_assignment() {
+    // Test multiple assignment with different values
+    let tests = vec![
+        ("x, y = 10, 20\nx", 10),
+        ("x, y = 10, 20\ny", 20),
+        ("a, b, c = 1, 2, 3\na", 1),
+        ("a, b, c = 1, 2, 3\nb", 2),
+        ("a, b, c = 1, 2, 3\nc", 3),
+        ("x, y = 5 * 2, 3 + 4\nx", 10),
+        ("x, y = 5 * 2, 3 + 4\ny", 7),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
             Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
         }
     }
+
+    // Test multiple assignment with strings
+    let string_tests = vec![
+        ("s1, s2 = \"hello\", \"world\"\ns1", "hello"),
+        ("s1, s2 = \"hello\", \"world\"\ns2", "world"),
+    ];
+
+    for (input, expected) in string_tests {
+        match run_eval(input) {
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_star_unpacking() {
+    let tests = vec![
+        ("first, *rest = [1, 2, 3, 4]\n[first, rest]", "[1, [2, 3, 4]]"),
+        ("*head, last = (1, 2, 3)\n[head, last]", "[[1, 2], 3]"),
+        ("a, *mid, b = [1, 2]\n[a, mid, b]", "[1, [], 2]"),
+        ("a, *mid, b = 1, 2, 3, 4\n[a, mid, b]", "[1, [2, 3], 4]"),
+        ("*all = [5, 6]\nall", "[5, 6]"),
+        ("d = {}\nd[\"x\"], *d[\"rest\"] = [1, 2]\nd[\"rest\"]", "[2]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("a, *b, c = [1]", "at least 2 values"),
+        ("a, *b = 5", "Cannot unpack Integer"),
+        ("*a, *b = [1, 2]", "Only one '*' target"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_for_loop_multiple_targets() {
+    let tests = vec![
+        ("total = 0\nfor k, v in [(\"a\", 1), (\"b\", 2)]:\n    total = total + v\ntotal", "3"),
+        ("out = []\nfor i, x in enumerate([\"p\", \"q\"]):\n    out = out.push((i, x))\nout", "[(0, p), (1, q)]"),
+        ("out = []\nfor a, b, c in [[1, 2, 3], [4, 5, 6]]:\n    out = out.push(a + b + c)\nout", "[6, 15]"),
+        ("enumerate(range(3, 5))", "[(0, 3), (1, 4)]"),
+        ("enumerate(\"ab\")", "[(0, a), (1, b)]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("for a, b in [1, 2]:\n    a", "Cannot unpack Integer into 2 loop targets"),
+        ("for a, b in [(1, 2, 3)]:\n    a", "Loop target count mismatch: 2 targets but 3 values"),
+        ("for a, in [(1,)]:\n    a", "Expected variable name in for loop"),
+        ("enumerate(5)", "Object is not iterable: 5"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_for_loop_over_dicts_and_ranges() {
+    let tests = vec![
+        ("seen = set()\nfor key in {\"a\": 1, \"b\": 2}:\n    seen = seen.add(key)\nseen == {\"a\", \"b\"}", "True"),
+        ("total = 0\nfor name, count in {\"a\": 1, \"b\": 2, \"c\": 3}:\n    total = total + count\ntotal", "6"),
+        ("pairs = set()\nfor k, v in {\"x\": 10}:\n    pairs = pairs.add((k, v))\npairs", "{(x, 10)}"),
+        ("total = 0\nfor i in range(1, 10, 3):\n    total = total + i\ntotal", "12"),
+        ("out = []\nfor i in range(3, 0, -1):\n    out = out.push(i)\nout", "[3, 2, 1]"),
+        ("n = 0\nfor key in {}:\n    n = n + 1\nn", "0"),
+        ("len(enumerate({\"a\": 1, \"b\": 2}))", "2"),
+        ("xs = [1, 2, 3]\nfor x in xs:\n    xs = xs.push(x)\nxs", "[1, 2, 3, 1, 2, 3]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("for a, b, c in {\"k\": 1}:\n    a", "Cannot unpack String into 3 loop targets"),
+        ("for x in 5:\n    x", "Object is not iterable: 5"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_string_units() {
+    // "ñ" spelled as "n" plus a combining tilde: two code points, three bytes, one grapheme.
+    let tests = vec![
+        ("out = []\nfor ch in \"héllo\":\n    out = out.push(ch)\nout", "[h, é, l, l, o]"),
+        ("chars(\"héllo\")", "[h, é, l, l, o]"),
+        ("bytes(\"hé\")", "[104, 195, 169]"),
+        ("total = 0\nfor b in bytes(\"ab\"):\n    total += b\ntotal", "195"),
+        ("graphemes(\"an\u{303}o\")", "[a, n\u{303}, o]"),
+        ("[len(graphemes(\"n\u{303}\")), len(chars(\"n\u{303}\")), len(bytes(\"n\u{303}\"))]", "[1, 2, 3]"),
+        ("graphemes(\"\u{1F1F3}\u{1F1F4}!\")", "[\u{1F1F3}\u{1F1F4}, !]"),
+        ("graphemes(\"\")", "[]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("bytes(5)", "bytes expects a string, got Integer"),
+        ("graphemes()", "Wrong number of arguments. got=0, want=1"),
+        ("chars([\"a\"])", "chars expects a string, got List"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_iterator_protocol() {
+    let countdown = "grim Countdown:\n    init(start):\n        self.n = start\n    spell __next__():\n        if self.n == 0:\n            return None\n        self.n = self.n - 1\n        return self.n + 1\n";
+    let deck = "grim Deck:\n    init():\n        self.cards = [\"A\", \"K\"]\n    spell __iter__():\n        return self.cards\n";
+    let looped = "grim Looped:\n    init():\n        self.left = 2\n    spell __iter__():\n        return self\n    spell __next__():\n        self.left = self.left - 1\n        return self.left if self.left >= 0 else None\n";
+    let tests = vec![
+        (format!("{}out = []\nfor n in Countdown(3):\n    out = out.push(n)\nout", countdown), "[3, 2, 1]"),
+        (format!("{}sum(Countdown(4))", countdown), "10"),
+        (format!("{}enumerate(Countdown(2))", countdown), "[(0, 2), (1, 1)]"),
+        (format!("{}out = []\nfor card in Deck():\n    out = out.push(card)\nout", deck), "[A, K]"),
+        (format!("{}sum(Deck(), \"\")", deck), "AK"),
+        (format!("{}set(Looped())", looped), "{1, 0}"),
+        ("sum([1, 2, 3])".to_string(), "6"),
+        ("sum(range(5), 10)".to_string(), "20"),
+        ("sum([0.5, 0.25])".to_string(), "0.75"),
+        ("sum([])".to_string(), "0"),
+        ("sum((\"a\", \"b\"), \"\")".to_string(), "ab"),
+        ("set(range(3))".to_string(), "{0, 1, 2}"),
+        ("_hidden = 2\n__dunder__ = 3\n_hidden + __dunder__".to_string(), "5"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(&input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("grim Plain:\n    init():\n        self.x = 1\nfor p in Plain():\n    p", "its grimoire defines no __next__ spell"),
+        ("grim Odd:\n    spell __iter__():\n        return 5\nsum(Odd())", "Object is not iterable: 5"),
+        ("sum([1, \"a\"])", "Type mismatch"),
+        ("sum(3)", "Object is not iterable: 3"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_nested_destructuring() {
+    let tests = vec![
+        ("pair = (1, 2)\n(a, b), c = pair, 3\n[a, b, c]", "[1, 2, 3]"),
+        ("[x, y], z = [[1, 2], 3]\n[x, y, z]", "[1, 2, 3]"),
+        ("(a, (b, c)), d = ((1, (2, 3)), 4)\n[a, b, c, d]", "[1, 2, 3, 4]"),
+        ("(a, b) = [5, 6]\n[a, b]", "[5, 6]"),
+        ("first, *rest, (p, q) = [1, 2, 3, (4, 5)]\n[first, rest, p, q]", "[1, [2, 3], 4, 5]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("(a, b), c = (1, 2, 3), 4", "Assignment count mismatch at nesting level 2: 2 targets but 3 values"),
+        ("(a, (b, c)), d = ((1, (2,)), 4)", "at nesting level 3: 2 targets but 1 values"),
+        ("(a, b), c = 1, 2", "Cannot unpack Integer at nesting level 2"),
+        ("a, b = 1, 2, 3", "Assignment count mismatch: 2 targets but 3 values"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_tuples() {
+    let ints = |values: &[i64]| Object::tuple(values.iter().map(|v| Object::Integer(*v)).collect());
+    let tests = vec![
+        ("(1, 2)", ints(&[1, 2])),
+        ("(1,)", ints(&[1])),
+        ("()", ints(&[])),
+        ("(1, 2,)", ints(&[1, 2])),
+        ("(1 + 2)", Object::Integer(3)),
+        // A bare `a, b` on the right of `=` is a tuple too.
+        ("t = 1, 2 * 3\nt", ints(&[1, 6])),
+        ("a, b = 1, 2\na, b = b, a\n(a, b)", ints(&[2, 1])),
+        ("x, y = (3, 4)\ny", Object::Integer(4)),
+        ("(4, 5, 6)[2]", Object::Integer(6)),
+        ("len((1, 2, 3))", Object::Integer(3)),
+        ("(1, 2).contains(2)", Object::Boolean(true)),
+        ("(1, 2) == (1, 2)", Object::Boolean(true)),
+        ("(1, 2) != (2, 1)", Object::Boolean(true)),
+        ("total = 0\nfor n in (1, 2, 3):\n    total += n\ntotal", Object::Integer(6)),
+        // Tuples can be dict keys.
+        ("grid = {(0, 1): \"x\"}\ngrid[(0, 1)]", Object::String("x".into())),
+        ("grid = {}\ngrid[(2, 3)] = 5\ngrid[(2, 3)]", Object::Integer(5)),
+        ("grid = {(0, 1): \"t\", \"(0, 1)\": \"s\"}\n[len(grid), grid[(0, 1)], grid[\"(0, 1)\"]]", Object::list(vec![
+            Object::Integer(2),
+            Object::String("t".into()),
+            Object::String("s".into()),
+        ])),
+        ("{(1, 2): 0}.keys()[0][1]", Object::Integer(2)),
+        ("{(1, 2): 0, (2, 1): 1}.contains((2, 1))", Object::Boolean(true)),
+        ("repr((\"a\",))", Object::String("(\"a\",)".into())),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    assert_eq!(run_eval("(1, (2, 3))").unwrap().to_string(), "(1, (2, 3))");
+    assert_eq!(
+        run_eval("t = (1, 2)\nt[0] = 5"),
+        Err("Tuples are immutable; build a new tuple instead".to_string())
+    );
+    assert!(run_eval("(1, 2)[2]").is_err());
+    assert_eq!(run_eval("(1, 2)[-1]"), Err("Index out of bounds: -1 (tuple length: 2)".to_string()));
+    assert!(run_eval("(1, 2).push(3)").is_err());
+}
+
+#[test]
+fn test_sets() {
+    use std::rc::Rc;
+
+    let ints = |values: &[i64]| Object::Set(Rc::new(values.iter().map(|v| Object::Integer(*v)).collect()));
+    let tests = vec![
+        ("{1, 2, 3}", ints(&[1, 2, 3])),
+        ("{3, 1, 3, 2, 1}", ints(&[3, 1, 2])),
+        ("{1,}", ints(&[1])),
+        ("set()", ints(&[])),
+        ("set([2, 2, 5])", ints(&[2, 5])),
+        ("{1, 2} | {2, 3}", ints(&[1, 2, 3])),
+        ("{1, 2, 3} & {3, 2, 9}", ints(&[2, 3])),
+        ("{1, 2, 3} - {2}", ints(&[1, 3])),
+        ("{1, 2} ^ {2, 3}", ints(&[1, 3])),
+        ("{1, 2}.add(3).add(1)", ints(&[1, 2, 3])),
+        ("{1, 2}.remove(1)", ints(&[2])),
+        ("len({1, 1, 2})", Object::Integer(2)),
+        ("{1, 2} == {2, 1}", Object::Boolean(true)),
+        ("{1, 2} != {1}", Object::Boolean(true)),
+        ("{1, \"1\"}.len()", Object::Integer(2)),
+        ("{(0, 1), (0, 1)}.len()", Object::Integer(1)),
+        // Floats hash by value, with `0.0 == -0.0`.
+        ("{1.5, 1.5, 2.5}.len()", Object::Integer(2)),
+        ("{0.0, -0.0}.len()", Object::Integer(1)),
+        ("2.5 in {1.5, 2.5}", Object::Boolean(true)),
+        ("{1, 1.0}.len()", Object::Integer(2)),
+        // `{}` stays an empty dict.
+        ("{}", Object::Dict(Default::default())),
+        // Membership with `in` works on every container.
+        ("2 in {1, 2}", Object::Boolean(true)),
+        ("5 in {1, 2}", Object::Boolean(false)),
+        ("3 in [1, 2, 3]", Object::Boolean(true)),
+        ("(1, 2) in [(1, 2)]", Object::Boolean(true)),
+        ("\"ell\" in \"hello\"", Object::Boolean(true)),
+        ("\"a\" in {\"a\": 1}", Object::Boolean(true)),
+        ("1 + 1 in {2} and true", Object::Boolean(true)),
+        ("not (4 in {1})", Object::Boolean(true)),
+        ("total = 0\nfor n in {5, 5, 6}:\n    total += n\ntotal", Object::Integer(11)),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    assert_eq!(run_eval("{1, 2}").unwrap().to_string(), "{1, 2}");
+    // Elements stay in the order they were first added.
+    assert_eq!(run_eval("{3, 1, 3, 2, 1}").unwrap().to_string(), "{3, 1, 2}");
+    assert_eq!(run_eval("{1, 2, 3, 4}.remove(2).add(2).remove(1)").unwrap().to_string(), "{3, 4, 2}");
+    assert_eq!(run_eval("{4, 3} | {1, 3}").unwrap().to_string(), "{4, 3, 1}");
+    assert_eq!(run_eval("set()").unwrap().to_string(), "set()");
+    assert_eq!(
+        run_eval("{[1], 2}"),
+        Err("Unhashable type List cannot be a set element".to_string())
+    );
+    assert!(run_eval("{1}.remove(2)").is_err());
+    assert!(run_eval("{1} + {2}").is_err());
+    assert!(run_eval("1 in 5").is_err());
+}
+
+#[test]
+fn test_ranges() {
+    let ints = |values: &[i64]| Object::list(values.iter().map(|v| Object::Integer(*v)).collect());
+    let tests = vec![
+        ("range(4).to_list()", ints(&[0, 1, 2, 3])),
+        ("range(2, 5).to_list()", ints(&[2, 3, 4])),
+        ("range(0, 10, 3).to_list()", ints(&[0, 3, 6, 9])),
+        ("range(5, 0, -2).to_list()", ints(&[5, 3, 1])),
+        ("range(3, 3).to_list()", ints(&[])),
+        ("(1..4).to_list()", ints(&[1, 2, 3])),
+        ("n = 3\n(0..n + 1).to_list()", ints(&[0, 1, 2, 3])),
+        ("len(0..10)", Object::Integer(10)),
+        ("range(0, 10, 3).len()", Object::Integer(4)),
+        ("range(10, 0, -3).len()", Object::Integer(4)),
+        ("(0..10)[7]", Object::Integer(7)),
+        ("range(1, 20, 4)[2]", Object::Integer(9)),
+        ("5 in 0..10", Object::Boolean(true)),
+        ("10 in 0..10", Object::Boolean(false)),
+        ("4 in range(0, 10, 3)", Object::Boolean(false)),
+        ("\"a\" in 0..10", Object::Boolean(false)),
+        ("range(0, 10, 2).contains(8)", Object::Boolean(true)),
+        ("0..3 == range(3)", Object::Boolean(true)),
+        ("total = 0\nfor i in 1..101:\n    total += i\ntotal", Object::Integer(5050)),
+        ("total = 0\nfor i in range(10, 0, -1):\n    total += i\ntotal", Object::Integer(55)),
+        // Iterating a huge range never builds a list.
+        ("for i in 0..1000000000000:\n    if i == 3:\n        return i", Object::Integer(3)),
+        ("len(range(-9223372036854775807, 9223372036854775807, 4611686018427387904))", Object::Integer(4)),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    assert_eq!(run_eval("0..5").unwrap().to_string(), "range(0, 5)");
+    assert_eq!(run_eval("range(1, 9, 2)").unwrap().to_string(), "range(1, 9, 2)");
+    assert_eq!(run_eval("range(1, 2, 0)"), Err("range step cannot be zero".to_string()));
+    assert!(run_eval("(0..3)[3]").is_err());
+    assert!(run_eval("(0..3)[-1]").is_err());
+    assert!(run_eval("range(1.5)").is_err());
+    assert!(run_eval("\"a\"..\"z\"").is_err());
+}
+
+#[test]
+fn test_index_assignment() {
+    let tests = vec![
+        ("xs = [1, 2, 3]\nxs[1] = 20\nxs", Object::list(vec![Object::Integer(1), Object::Integer(20), Object::Integer(3)])),
+        ("d = {\"a\": 1}\nd[\"a\"] = 2\nd[\"b\"] = 3\nd[\"a\"] + d[\"b\"]", Object::Integer(5)),
+        ("grid = [[0, 0], [0, 0]]\ngrid[1][0] = 7\ngrid[1]", Object::list(vec![Object::Integer(7), Object::Integer(0)])),
+        ("d = {\"xs\": [1, 2]}\nd[\"xs\"][0] = 9\nd[\"xs\"][0]", Object::Integer(9)),
+        ("xs = [0, 0]\ni = 1\nxs[i] = 4\nxs[1]", Object::Integer(4)),
+        ("xs = [1, 2]\nxs[0], xs[1] = xs[1], xs[0]\nxs", Object::list(vec![Object::Integer(2), Object::Integer(1)])),
+        ("xs = [1]\nys = xs\nxs[0] = 5\nys[0]", Object::Integer(1)),
+        ("grid = [[0, 0], [0, 0]]\nrow = grid[0]\ngrid[0][1] = 3\n[row[1], grid[0][1]]", Object::list(vec![Object::Integer(0), Object::Integer(3)])),
+        ("xs = [0, 0, 0]\nxs[len(xs) - 1] = 2\nxs[2]", Object::Integer(2)),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    let compound_tests = vec![
+        ("xs = [1, 2, 3]\nxs[0] += 10\nxs[0]", 11),
+        ("d = {\"k\": 4}\nd[\"k\"] *= 2\nd[\"k\"]", 8),
+        ("grid = [[1, 2], [3, 4]]\ngrid[1][1] -= 5\ngrid[1][1]", -1),
+        ("calls = mock(0)\nxs = [5, 6]\nxs[calls()] += 1\nxs[0] * 10 + calls.call_count", 61),
+        ("grim Bag:\n    init():\n        self.items = [1, 2]\nb = Bag()\nb.items[1] += 5\nb.items[1]", 7),
+    ];
+    for (input, expected) in compound_tests {
+        assert_eq!(run_eval(input), Ok(Object::Integer(expected)), "Failed for input: {}", input);
+    }
+    assert!(run_eval("d = {}\nd[\"missing\"] += 1").is_err());
+
+    assert_eq!(
+        run_eval("xs = [1, 2]\nxs[2] = 3"),
+        Err("Index out of bounds: 2 (list length: 2)".to_string())
+    );
+    assert_eq!(
+        run_eval("xs = [1, 2]\nxs[-1] = 3"),
+        Err("Index out of bounds: -1 (list length: 2)".to_string())
+    );
+    assert_eq!(
+        run_eval("d = {}\nd[\"a\"][\"b\"] = 1"),
+        Err("Index assignment not supported for None with index b".to_string())
+    );
+    assert!(run_eval("s = \"abc\"\ns[0] = \"x\"").is_err());
+    assert!(run_eval("missing[0] = 1").is_err());
+}
+
+#[test]
+fn test_list_expressions() {
+    let tests = vec![
+        ("[]", Vec::<Object>::new()),
+        ("[1]", vec![Object::Integer(1)]),
+        ("[1, 2, 3]", vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)]),
+        ("[1, \"hello\", True]", vec![Object::Integer(1), Object::String("hello".into()), Object::Boolean(true)]),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::List(elements)) => {
+                assert_eq!(*elements, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected List, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_list_indexing() {
+    let tests = vec![
+        ("[1, 2, 3][0]", 1),
+        ("[1, 2, 3][1]", 2),
+        ("[1, 2, 3][2]", 3),
+        ("[10, 20, 30][1]", 20),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    // Test string elements
+    let string_tests = vec![
+        ("[\"hello\", \"world\"][0]", "hello"),
+        ("[\"hello\", \"world\"][1]", "world"),
+    ];
+
+    for (input, expected) in string_tests {
+        match run_eval(input) {
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_dictionary_expressions() {
+    let key = |text: &str| Object::String(text.into());
+    let tests = vec![
+        ("{}", vec![]),
+        ("{\"name\": \"John\"}", vec![(key("name"), Object::String("John".into()))]),
+        (
+            "{\"age\": 30, \"name\": \"John\"}",
+            vec![(key("age"), Object::Integer(30)), (key("name"), Object::String("John".into()))],
+        ),
+        // Keys keep their type.
+        ("{1: \"int\", \"1\": \"str\"}", vec![(Object::Integer(1), key("int")), (key("1"), key("str"))]),
+        (
+            "{(0, 1): \"t\", \"(0, 1)\": \"s\"}",
+            vec![(Object::tuple(vec![Object::Integer(0), Object::Integer(1)]), key("t")), (key("(0, 1)"), key("s"))],
+        ),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Dict(dict)) => {
+                assert_eq!(dict, expected.into_iter().collect(), "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Dict, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_dictionary_indexing() {
+    let tests = vec![
+        ("{\"name\": \"John\"}[\"name\"]", "John"),
+        ("{\"city\": \"New York\", \"country\": \"USA\"}[\"city\"]", "New York"),
+        ("{\"city\": \"New York\", \"country\": \"USA\"}[\"country\"]", "USA"),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    // Test integer values
+    let int_tests = vec![
+        ("{\"age\": 30}[\"age\"]", 30),
+        ("{\"score\": 95, \"rank\": 1}[\"score\"]", 95),
+        ("{\"score\": 95, \"rank\": 1}[\"rank\"]", 1),
+        // Keys keep their type, so `1` and `"1"` are different keys.
+        ("{1: 10, \"1\": 20}[1]", 10),
+        ("{1: 10, \"1\": 20}[\"1\"]", 20),
+        ("d = {}\nd[True] = 1\nd[\"True\"] = 2\nlen(d)", 2),
+        ("total = 0\nfor k, v in {1: 2, 3: 4}:\n    total += k * v\ntotal", 14),
+    ];
+
+    for (input, expected) in int_tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("repr({2: \"b\", \"1\": \"a\"})"), Ok(Object::String("{\"1\": \"a\", 2: \"b\"}".into())));
+    let error_tests = vec![
+        ("{[1]: 2}", "Unhashable type List cannot be a dict key"),
+        ("d = {}\nd[{}] = 1", "Unhashable type Dict cannot be a dict key"),
+        ("{(1, [2]): 3}", "Unhashable type Tuple cannot be a dict key"),
+        ("spell f(**kw):\n    return kw\nf(**{1: 2})", "Keyword argument names must be strings, got 1"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_builtin_functions() {
+    // Test len function
+    let len_tests = vec![
+        ("len([1, 2, 3])", 3),
+        ("len([])", 0),
+        ("len([\"hello\", \"world\"])", 2),
+        ("len({\"a\": 1, \"b\": 2})", 2),
+        ("len({})", 0),
+        ("len(\"hello\")", 5),
+    ];
+
+    for (input, expected) in len_tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    // Test push function
+    let push_tests = vec![
+        ("push([1, 2], 3)", vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)]),
+        ("push([], 42)", vec![Object::Integer(42)]),
+        ("push([\"hello\"], \"world\")", vec![Object::String("hello".into()), Object::String("world".into())]),
+    ];
+
+    for (input, expected) in push_tests {
+        match run_eval(input) {
+            Ok(Object::List(elements)) => {
+                assert_eq!(*elements, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected List, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    // Test pop function
+    let pop_tests = vec![
+        ("pop([1, 2, 3])", vec![Object::Integer(1), Object::Integer(2)]),
+        ("pop([42])", Vec::<Object>::new()),
+        ("pop([\"hello\", \"world\"])", vec![Object::String("hello".into())]),
+    ];
+
+    for (input, expected) in pop_tests {
+        match run_eval(input) {
+            Ok(Object::List(elements)) => {
+                assert_eq!(*elements, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected List, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_string_slices_share_their_buffer() {
+    let text = Str::from("héllo wörld");
+    let word = text.slice(7..13).unwrap();
+    assert_eq!(word, "wörld");
+    assert_eq!(word.slice(1..3).unwrap(), "ö");
+    assert_eq!(text.char_at(1).unwrap(), "é");
+    assert_eq!(text.slice_of(text.trim_start_matches('h')).unwrap(), "éllo wörld");
+    // Ranges that split a character or run past the end, and parts of other
+    // strings, have no slice.
+    assert_eq!(word.slice(1..2), None);
+    assert_eq!(word.slice(3..9), None);
+    assert_eq!(word.slice_of("wörld"), None);
+    assert_eq!(word.slice_of(&text[..7]), None);
+    // Slices point into the original text instead of copying it.
+    let start = text.as_ptr() as usize;
+    assert_eq!(word.as_ptr() as usize, start + 7);
+    assert!(text.chars_shared().all(|ch| (start..start + text.len()).contains(&(ch.as_ptr() as usize))));
+
+    let tests = vec![
+        ("\"héllo\"[1]", "é"),
+        ("\"héllo\"[4]", "o"),
+        ("\"  padded \t\".strip()", "padded"),
+        ("s = \"abc\"\nt = s[1]\nt + s", "babc"),
+        ("\"a,b,,c\".split(\",\")[3]", "c"),
+        ("\"one  two\".split()[1].upper()", "TWO"),
+        ("out = \"\"\nfor ch in \"wörd\":\n    out = ch + out\nout", "dröw"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(Object::String(expected.into())), "Failed for input: {}", input);
+    }
+    assert_eq!(
+        run_eval("\"héllo\"[5]"),
+        Err("Index out of bounds: 5 (string length: 5)".to_string())
+    );
+    assert_eq!(run_eval("{\"a\": 1}[\"xa\"[1]]"), Ok(Object::Integer(1)));
+}
+
+#[test]
+fn test_list_updates_keep_other_copies() {
+    let ints = |values: &[i64]| Object::list(values.iter().map(|v| Object::Integer(*v)).collect());
+    let tests = vec![
+        ("xs = [1, 2]\nys = xs\nxs = xs.push(3)\nys", ints(&[1, 2])),
+        ("xs = [1, 2]\nys = xs\nxs = push(xs, 3)\nxs", ints(&[1, 2, 3])),
+        ("xs = [1, 2, 3]\nys = xs\nxs = xs.pop()\n(xs, ys)", Object::tuple(vec![ints(&[1, 2]), ints(&[1, 2, 3])])),
+        ("xs = [1]\nys = xs.push(2)\nzs = xs.push(3)\n(xs, ys, zs)", Object::tuple(vec![ints(&[1]), ints(&[1, 2]), ints(&[1, 3])])),
+        ("xs = []\nsaved = []\nfor i in 0..3:\n    xs = xs.push(i)\n    saved = saved.push(xs)\nsaved", Object::list(vec![ints(&[0]), ints(&[0, 1]), ints(&[0, 1, 2])])),
+        ("xs = [1]\nxs = xs.push(xs.len())\nxs", ints(&[1, 1])),
+        ("xs = [[0]]\nrow = xs[0]\nxs[0] = row.push(1)\n(xs, row)", Object::tuple(vec![Object::list(vec![ints(&[0, 1])]), ints(&[0])])),
+        ("spell push(xs, item):\n    return item\nxs = [1]\nxs = push(xs, 5)\nxs", Object::Integer(5)),
+        ("spell grow(xs):\n    xs = xs.push(9)\n    return xs\nxs = [1]\n(grow(xs), xs)", Object::tuple(vec![ints(&[1, 9]), ints(&[1])])),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    assert_eq!(run_eval("xs = []\nxs = xs.pop()"), Err("Cannot pop from empty list".to_string()));
+    assert!(run_eval("xs = [1]\nxs = xs.push()").is_err());
+}
+
+#[test]
+fn test_spell_definitions_and_calls() {
+    let tests = vec![
+        ("spell add(a, b):\n    return a + b\nadd(2, 3)", 5),
+        ("spell five():\n    return 5\nfive()", 5),
+        (
+            "spell pick(x):\n    if x > 0:\n        return 1\n    else:\n        return 2\npick(-1)",
+            2,
+        ),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_closures_capture_environment() {
+    let tests = vec![
+        (
+            "spell make_adder(n):\n    spell add(x):\n        return x + n\n    return add\nadd_two = make_adder(2)\nadd_two(40)",
+            42,
+        ),
+        (
+            "base = 10\nspell scaled(x):\n    return x * base\nscaled(3)",
+            30,
+        ),
+        (
+            "spell outer():\n    secret = 7\n    spell inner():\n        return secret\n    return inner\nf = outer()\nsecret = 1\nf()",
+            7,
+        ),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_log_builtins() {
+    let ok_tests = vec![
+        "log[\"info\"](\"starting\")",
+        "log[\"warn\"](\"disk low\", {\"free_mb\": 12})",
+        "log[\"debug\"](\"filtered out at the default level\")",
+    ];
+
+    for input in ok_tests {
+        match run_eval(input) {
+            Ok(Object::None) => {}
+            Ok(other) => panic!("Expected None, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let err_tests = vec!["log[\"error\"]()", "log[\"info\"](\"msg\", 5)"];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+}
+
+#[test]
+fn test_grimoire_instances() {
+    let tests = vec![
+        ("grim Raven:\n    init(name):\n        return name\nRaven(\"Huginn\")", "Raven"),
+        ("grim Empty:\n    spell noop():\n        return 1\nEmpty()", "Empty"),
+        ("grim Crow:\n    spell init(self, age):\n        return age\nCrow(3)", "Crow"),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Instance(instance)) => {
+                assert_eq!(instance.borrow().grimoire.name, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Instance, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let err_tests = vec![
+        "grim Empty:\n    spell noop():\n        return 1\nEmpty(1)",
+        "grim Raven:\n    init(name):\n        return name\nRaven()",
+        "grim Broken:\n    init():\n        return missing\nBroken()",
+    ];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+}
+
+#[test]
+fn test_grimoire_inheritance() {
+    let setup = "grim Creature:\n    init(name):\n        self.name = name\n        self.legs = 4\n    spell describe():\n        return self.name + \" has \" + repr(self.legs) + \" legs\"\n    spell speak():\n        return \"...\"\n\ngrim Bird(Creature):\n    init(name):\n        super.init(name)\n        self.legs = 2\n    spell speak():\n        return \"caw\"\n\ngrim Raven(Bird):\n    spell speak():\n        return super.speak() + \"!\"\n\n";
+    let tests = vec![
+        // Inherited spells, including init, run with the child's `self`.
+        ("Raven(\"Huginn\").describe()", "Huginn has 2 legs"),
+        ("Creature(\"Cat\").describe()", "Cat has 4 legs"),
+        // `super` starts from the parent of the grimoire defining the spell.
+        ("Raven(\"Muninn\").speak()", "caw!"),
+        ("Bird(\"Tweety\").speak()", "caw"),
+        ("Creature(\"Cat\").speak()", "..."),
+        ("f = Raven(\"Huginn\").describe\nf()", "Huginn has 2 legs"),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(Object::String(expected.into())), "Failed for input: {}", input);
+    }
+
+    let error_tests = vec![
+        ("grim A:\n    spell f():\n        return super.f()\nA().f()", "'super' can only be used in the spells of a grimoire"),
+        ("x = 1\ngrim A(x):\n    spell f():\n        return 1\n", "Grimoire A can only inherit from a grimoire, got 1"),
+        ("grim A:\n    spell f():\n        return 1\ngrim B(A):\n    spell g():\n        return super.nope()\nB().g()", "Parent grimoire A has no spell 'nope'"),
+        ("grim B(Missing):\n    spell g():\n        return 1\n", "Identifier not found: Missing"),
+        ("grim A:\n    spell f():\n        return 1\ngrim B(A):\n    spell g():\n        return super\nB().g()", "'super' must name a spell"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_ordering_and_sorting() {
+    let tests = vec![
+        ("(1, 2) < (1, 3)", "True"),
+        ("(1, \"b\") < (2, \"a\")", "True"),
+        ("(1, 2) < (1, 2, 0)", "True"),
+        ("(2,) >= (1, 9)", "True"),
+        ("[1, [2, 3]] > [1, [2]]", "True"),
+        ("(1, 2) <= (1, 2)", "True"),
+        ("[1, 2] == [1, 2]", "True"),
+        ("[1, 2] != [2, 1]", "True"),
+        ("\"apple\" < \"banana\"", "True"),
+        ("\"b\" >= \"ba\"", "False"),
+        ("\"rune\" == \"rune\"", "True"),
+        ("sorted([(2, \"b\"), (1, \"z\"), (2, \"a\")])", "[(1, z), (2, a), (2, b)]"),
+        ("sorted([3, 1, 2])", "[1, 2, 3]"),
+        ("sorted((\"b\", \"a\"))", "[a, b]"),
+        ("sorted([])", "[]"),
+        ("sorted(3..0)", "[]"),
+        ("sorted([[2], [1, 5], [1]])", "[[1], [1, 5], [2]]"),
+        // Sorting by a key is stable.
+        ("sorted([\"bb\", \"a\", \"cc\", \"d\"], spell(w): len(w))", "[a, d, bb, cc]"),
+        ("grim V:\n    init(n):\n        self.n = n\n    spell __lt__(o):\n        return self.n < o.n\n    spell __gt__(o):\n        return self.n > o.n\n    spell __str__():\n        return str(self.n)\nsorted([V(2), V(1)])", "[1, 2]"),
+        ("match (1, 5):\n    case (0, 0)..(1, 0):\n        x = \"low\"\n    case (1, 0)..(2, 0):\n        x = \"one\"\n    case _:\n        x = \"high\"\nx", "one"),
+        ("match 7:\n    case -10..0:\n        x = \"negative\"\n    case 0..10:\n        x = \"digit\"\nx", "digit"),
+        ("match 10:\n    case 0..10:\n        x = \"digit\"\n    case _:\n        x = \"other\"\nx", "other"),
+        ("match \"m\":\n    case 0..10:\n        x = \"digit\"\n    case \"a\"..\"n\":\n        x = \"first half\"\nx", "first half"),
+        ("match (0, 0):\n    case (0, 0):\n        x = \"origin\"\nx", "origin"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("(1, 2) < (1, \"a\")", "Cannot compare Integer with String: values of different types have no order"),
+        ("[1] < (1,)", "Type mismatch: cannot apply operator LessThan"),
+        ("sorted([(1, 2), (1, \"a\")])", "values of different types have no order"),
+        ("sorted([1, 2.5])", "sorted: Cannot compare"),
+        ("sorted(5)", "Object is not iterable: 5"),
+        ("sorted([1], spell(x): x, 3)", "Wrong number of arguments. got=3, want=1 or 2"),
+        ("match 1:\n    case x..2:\n        y = 1\n", "Range patterns must be between two literals"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_operator_overloading() {
+    let setup = "grim Vec:\n    init(x, y):\n        self.x = x\n        self.y = y\n    spell __add__(other):\n        return Vec(self.x + other.x, self.y + other.y)\n    spell __mul__(k):\n        return Vec(self.x * k, self.y * k)\n    spell __rmul__(k):\n        return Vec(self.x * k, self.y * k)\n    spell __neg__():\n        return Vec(-self.x, -self.y)\n    spell __eq__(other):\n        return self.x == other.x and self.y == other.y\n    spell __lt__(other):\n        return self.x < other\n    spell __gt__(other):\n        return self.x > other\n    spell __index__(i):\n        if i == 0:\n            return self.x\n        return self.y\n    spell __contains__(n):\n        return n == self.x or n == self.y\n    spell __str__():\n        return \"Vec(\" + str(self.x) + \", \" + str(self.y) + \")\"\n\n";
+    let tests = vec![
+        ("str(Vec(1, 2) + Vec(3, 4))", "Vec(4, 6)"),
+        ("str(Vec(1, 2) * 3)", "Vec(3, 6)"),
+        // Only the right operand is an instance, so its reflected spell runs.
+        ("str(2 * Vec(1, 2))", "Vec(2, 4)"),
+        ("str(-Vec(1, -2))", "Vec(-1, 2)"),
+        ("str(Vec(1, 2) == Vec(1, 2))", "True"),
+        ("str(Vec(1, 2) != Vec(1, 2))", "False"),
+        ("str(Vec(1, 2) != Vec(2, 1))", "True"),
+        ("str(Vec(1, 2) < 5)", "True"),
+        // A comparison with the instance on the right mirrors the spell.
+        ("str(5 < Vec(1, 2))", "False"),
+        ("str(Vec(7, 8)[1])", "8"),
+        ("str(8 in Vec(7, 8))", "True"),
+        ("str(9 in Vec(7, 8))", "False"),
+        ("v = Vec(1, 1)\nv += Vec(1, 2)\nstr(v)", "Vec(2, 3)"),
+        ("str(sum([Vec(1, 2), Vec(3, 4)], Vec(0, 0)))", "Vec(4, 6)"),
+        ("str([Vec(1, 2)])", "[Vec(1, 2)]"),
+        ("grim Plain:\n    spell f():\n        return 1\nstr(Plain())", "<Plain instance>"),
+        ("grim Bad:\n    spell __str__():\n        return 1\nstr([Bad()])", "[<Bad instance>]"),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(Object::String(expected.into())), "Failed for input: {}", input);
+    }
+
+    let error_tests = vec![
+        ("Vec(1, 2) - Vec(1, 1)", "Type mismatch: cannot apply operator Minus to Vec(1, 2) and Vec(1, 1)"),
+        ("Vec(1, 2) / 2", "Type mismatch: cannot apply operator Divide to Vec(1, 2) and 2"),
+        ("grim Bad:\n    spell __str__():\n        return 1\nstr(Bad())", "__str__ must return a string, got Integer"),
+        ("grim Plain:\n    spell f():\n        return 1\nPlain()[0]", "Index operation not supported for <Plain instance>"),
+    ];
+    for (input, expected) in error_tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_static_spells_and_grimoire_attributes() {
+    let setup = "grim Counter:\n    created = 0\n    unit = \"ticks\"\n    init():\n        Counter.created += 1\n        self.count = 0\n    static spell describe(n):\n        return str(n) + \" \" + Counter.unit\n    static spell make_many(n):\n        made = []\n        for i in range(n):\n            made = push(made, Counter())\n        return made\n    spell label():\n        return Counter.describe(self.count)\n\ngrim Timer(Counter):\n    unit = \"seconds\"\n\n";
+    let tests = vec![
+        ("Counter.describe(3)", "3 ticks"),
+        ("Counter.created", "0"),
+        ("Counter()\nCounter()\nCounter.created", "2"),
+        ("many = Counter.make_many(3)\nstr((len(many), Counter.created))", "(3, 3)"),
+        // Instances read the grimoire's attributes and share its changes.
+        ("c = Counter()\nCounter.unit = \"tocks\"\nc.unit", "tocks"),
+        ("Counter().describe(1)", "1 ticks"),
+        ("Counter().label()", "0 ticks"),
+        // Assigning through an instance gives that instance its own field.
+        ("a = Counter()\nb = Counter()\na.unit = \"mine\"\nstr((a.unit, b.unit, Counter.unit))", "(mine, ticks, ticks)"),
+        // Children inherit static spells and attributes, and can shadow them.
+        ("Timer.unit", "seconds"),
+        ("Timer()\nstr((Timer.created, Counter.created))", "(1, 1)"),
+        ("Timer.describe(2)", "2 ticks"),
+        ("grim Empty:\n    limit = 10 * 2\nstr(Empty.limit)", "20"),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("grim A:\n    static spell f():\n        return self\nA.f()", "Identifier not found: self"),
+        ("grim A:\n    static f():\n        return 1\n", "Expected 'spell' after 'static'."),
+        ("grim A:\n    x = 1\nA.y", "<grimoire A> has no attribute 'y'"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_grimoire_properties() {
+    let setup = "grim Rect:\n    init(w, h):\n        self.w = w\n        self.h = h\n    property spell area():\n        return self.w * self.h\n    property spell width():\n        return self.w\n    property spell width(value):\n        if value < 0:\n            value = 0\n        self.w = value\n    property spell height(value):\n        self.h = int(value)\n\ngrim Square(Rect):\n    init(side):\n        super.init(side, side)\n\n";
+    let tests = vec![
+        ("Rect(2, 3).area", "6"),
+        ("r = Rect(2, 3)\nr.width = 5\nr.area", "15"),
+        // The setter validates what it stores.
+        ("r = Rect(2, 3)\nr.width = -4\nr.width", "0"),
+        ("r = Rect(2, 3)\nr.width += 1\nr.w", "3"),
+        ("r = Rect(2, 3)\nr.height = \"7\"\nr.h", "7"),
+        ("Square(4).area", "16"),
+        ("s = Square(4)\ns.width = 1\ns.area", "4"),
+        ("str(maybe Rect(1, 1).area)", "1"),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Unexpected error for '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("Rect(2, 3).area = 1", "Property 'area' of Rect is read-only; it has no setter"),
+        ("Square(2).area = 1", "Property 'area' of Square is read-only; it has no setter"),
+        ("r = Rect(2, 3)\nr.height = \"tall\"", "int cannot parse 'tall' as an integer"),
+        ("Rect(2, 3).height", "has no attribute 'height'"),
+        ("grim A:\n    property spell p(a, b):\n        return 1\n", "Property 'p' must take no parameters (to read it) or one (to assign it)"),
+    ];
+    for (input, expected) in error_tests {
+        let program = format!("{}{}", setup, input);
+        match run_eval(&program) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_arcane_grimoires() {
+    let setup = "arcane grim Shape:\n    init(name):\n        self.name = name\n    arcanespell area()\n    arcanespell perimeter():\n        \"The distance around the shape.\"\n    spell describe():\n        return self.name + \" with area \" + repr(self.area())\n\ngrim Square(Shape):\n    init(side):\n        super.init(\"square\")\n        self.side = side\n    spell area():\n        return self.side * self.side\n    spell perimeter():\n        return 4 * self.side\n\n";
+    let tests = vec![
+        ("Square(3).describe()", Object::String("square with area 9".into())),
+        ("Square(3).perimeter()", Object::Integer(12)),
+        // An arcane child of an arcane grimoire can leave spells to its own children.
+        ("arcane grim Quad(Shape):\n    spell perimeter():\n        return 4\ngrim Unit(Quad):\n    spell area():\n        return 1\nUnit(\"unit\").perimeter() + Unit(\"unit\").area()", Object::Integer(5)),
+    ];
+    for (input, expected) in tests {
+        let program = format!("{}{}", setup, input);
+        assert_eq!(run_eval(&program), Ok(expected), "Failed for input: {}", input);
+    }
+
+    let error_tests = vec![
+        ("Shape(\"any\")", "Cannot create an instance of arcane grimoire Shape"),
+        (
+            "grim Blob(Shape):\n    spell area():\n        return 0\nBlob(\"b\")",
+            "Cannot create an instance of grimoire Blob; it must override the arcane spells: perimeter",
+        ),
+        ("grim Nothing(Shape):\n    spell other():\n        return 0\nNothing(\"n\")", "must override the arcane spells: area, perimeter"),
+        (
+            "grim Lazy(Square):\n    spell area():\n        return super.perimeter() + super.missing()\nLazy(1).area()",
+            "Parent grimoire Square has no spell 'missing'",
+        ),
+        (
+            "grim Cheat(Shape):\n    spell area():\n        return super.area()\n    spell perimeter():\n        return 0\nCheat(\"c\").area()",
+            "Spell 'area' of Shape is arcane and has no body to call",
+        ),
+        ("grim Plain:\n    arcanespell f()\n", "arcanespell is only allowed in an arcane grimoire"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(&format!("{}{}", setup, input)) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_member_access() {
+    let int_tests = vec![
+        ("d = {\"x\": 5}\nd.x", 5),
+        ("[1, 2, 3].len()", 3),
+        ("\"raven\".len()", 5),
+        ("[1, 2].push(3).len()", 3),
+        ("{\"a\": 1}.get(\"b\", 7)", 7),
+        (
+            "grim Counter:\n    spell double(n):\n        return n * 2\nc = Counter()\nc.double(21)",
+            42,
+        ),
+        (
+            "grim Box:\n    spell get():\n        return self\n    spell twice(n):\n        return n + n\nBox().get().twice(4)",
+            8,
+        ),
+        (
+            "grim Util:\n    spell three():\n        return 3\nf = Util().three\nf()",
+            3,
+        ),
+    ];
+
+    for (input, expected) in int_tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let string_tests = vec![
+        ("\"Odin\".upper()", "ODIN"),
+        ("\"  padded \".strip()", "padded"),
+        ("\"a,b,c\".split(\",\")[1]", "b"),
+    ];
+
+    for (input, expected) in string_tests {
+        match run_eval(input) {
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("log.info(\"via dot access\")"), Ok(Object::None));
+
+    let err_tests = vec!["5.missing", "[1].nope()", "grim Empty:\n    spell f():\n        return 1\nEmpty().g"];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+}
+
+#[test]
+fn test_attribute_assignment() {
+    let tests = vec![
+        (
+            "grim Counter:\n    init(start):\n        self.count = start\n    spell bump():\n        self.count += 1\n        return self.count\nc = Counter(5)\nc.bump()\nc.bump()",
+            Object::Integer(7),
+        ),
+        ("grim Box:\n    spell f():\n        return 1\nb = Box()\nb.value = 3\nb.value *= 4\nb.value", Object::Integer(12)),
+        ("grim Box:\n    spell f():\n        return 1\nb = Box()\nalias = b\nb.tag = \"x\"\nalias.tag", Object::String("x".into())),
+        ("d = {\"n\": 1}\nd.n += 2\nd.m = 4\nd.n + d.m", Object::Integer(7)),
+        ("o = {\"inner\": {\"v\": 1}}\no.inner.v = 9\no[\"inner\"][\"v\"]", Object::Integer(9)),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    let err_tests = vec![
+        "x = 5\nx.y = 1",
+        "grim Box:\n    spell f():\n        return 1\nb = Box()\nb.missing += 1",
+        "\"text\".len = 3",
+    ];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_builtins() {
+    let input = "db = sqlite_open(\":memory:\")\n\
+                 db.execute(\"CREATE TABLE ravens (name TEXT, age INTEGER)\")\n\
+                 db.execute(\"INSERT INTO ravens VALUES (?, ?)\", [\"Huginn\", 7])\n\
+                 db.execute(\"INSERT INTO ravens VALUES (?, ?)\", [\"Muninn\", 9])\n\
+                 rows = db.query(\"SELECT name, age FROM ravens WHERE age > ? ORDER BY age\", [8])\n\
+                 rows[0][\"name\"]";
+    assert_eq!(run_eval(input), Ok(Object::String("Muninn".into())));
+
+    let count = "db = sqlite_open(\":memory:\")\n\
+                 db.execute(\"CREATE TABLE t (x INTEGER)\")\n\
+                 db.execute(\"INSERT INTO t VALUES (1), (2), (3)\")";
+    assert_eq!(run_eval(count), Ok(Object::Integer(3)));
+
+    assert!(run_eval("db = sqlite_open(\":memory:\")\ndb.query(\"SELEC nonsense\")").is_err());
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_serve_builtin() {
+    use std::io::{Read, Write};
+
+    // Reserve a free port, then release it for the interpreter to bind.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let requests: [&[u8]; 3] = [
+        b"GET /greet?name=Thor HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        b"POST /upload HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n",
+        b"POST /upload HTTP/1.1\r\nContent-Length: lots\r\n\r\n",
+    ];
+    let client = std::thread::spawn(move || {
+        let mut responses = Vec::new();
+        for request in requests {
+            for attempt in 0..50 {
+                if let Ok(mut stream) = std::net::TcpStream::connect(("127.0.0.1", port)) {
+                    stream.write_all(request).unwrap();
+                    let mut response = String::new();
+                    stream.read_to_string(&mut response).unwrap();
+                    responses.push(response);
+                    break;
+                }
+                assert!(attempt < 49, "server never came up");
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+        }
+        responses
+    });
+
+    let input = format!(
+        "spell handle(req):\n    return {{\"status\": 201, \"body\": req[\"path\"] + \" \" + req[\"query\"][\"name\"]}}\nserve({}, handle, 3)",
+        port
+    );
+    assert_eq!(run_eval(&input), Ok(Object::None));
+
+    let responses = client.join().unwrap();
+    assert!(responses[0].starts_with("HTTP/1.1 201 Created"), "{}", responses[0]);
+    assert!(responses[0].ends_with("/greet Thor"), "{}", responses[0]);
+    // Oversized and malformed bodies are refused before the handler runs.
+    assert!(responses[1].starts_with("HTTP/1.1 413 Content Too Large"), "{}", responses[1]);
+    assert!(responses[2].starts_with("HTTP/1.1 400 Bad Request"), "{}", responses[2]);
+}
+
+#[test]
+fn test_path_builtins() {
+    let string_tests = vec![
+        ("basename(\"src/lexer/mod.rs\")", "mod.rs"),
+        ("dirname(\"src/lexer/mod.rs\")", "src/lexer"),
+        ("extension(\"archive.tar.gz\")", "gz"),
+        ("extension(\"Makefile\")", ""),
+        ("path_join(\"src\", \"parser\", \"mod.rs\")", "src/parser/mod.rs"),
+        ("path_join(\"src\", \"/etc\")", "/etc"),
+    ];
+
+    for (input, expected) in string_tests {
+        match run_eval(input) {
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    match run_eval("absolute(\"notes.crl\")") {
+        Ok(Object::String(val)) => assert!(val.starts_with('/') && val.ends_with("notes.crl"), "{}", val),
+        other => panic!("Expected absolute path, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_glob_builtin() {
+    let root = std::env::temp_dir().join(format!("carrion_glob_{}", std::process::id()));
+    std::fs::create_dir_all(root.join("src/nested/deep")).unwrap();
+    for file in ["src/a.crl", "src/b.txt", "src/nested/c.crl", "src/nested/deep/d.crl", "src/.hidden.crl"] {
+        std::fs::write(root.join(file), "").unwrap();
+    }
+    let base = root.to_string_lossy().into_owned();
+
+    let glob = |pattern: &str| -> Vec<String> {
+        match run_eval(&format!("glob(\"{}/{}\")", base, pattern)) {
+            Ok(Object::List(items)) => items
+                .iter()
+                .map(|item| item.to_string().trim_start_matches(&base).to_string())
+                .collect(),
+            other => panic!("Expected List, got {:?}", other),
+        }
+    };
+
+    assert_eq!(glob("src/*.crl"), vec!["/src/a.crl"]);
+    assert_eq!(glob("src/?.txt"), vec!["/src/b.txt"]);
+    assert_eq!(glob("src/[ab].*"), vec!["/src/a.crl", "/src/b.txt"]);
+    assert_eq!(
+        glob("src/**/*.crl"),
+        vec!["/src/a.crl", "/src/nested/c.crl", "/src/nested/deep/d.crl"]
+    );
+    assert!(glob("missing/*.crl").is_empty());
+    assert_eq!(glob("src/[!b]*.c?l"), vec!["/src/a.crl"]);
+
+    // Many stars against a long name that almost matches stay fast.
+    let long = format!("src/{}.txt", "a".repeat(40));
+    std::fs::write(root.join(&long), "").unwrap();
+    assert_eq!(glob("src/*a*a*a*a*a*a*a*a*a*a*a*a.txt"), vec![format!("/{}", long)]);
+    assert!(glob("src/*a*a*a*a*a*a*a*a*a*a*a*a*b.txt").is_empty());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_match_statement() {
+    let classify = "spell classify(x):\n    match x:\n        case 0:\n            return \"zero\"\n        case -1:\n            return \"minus one\"\n        case \"raven\":\n            return \"bird\"\n        case none:\n            return \"nothing\"\n        case _:\n            return \"other\"\n";
+    let string_tests = vec![
+        (format!("{}classify(0)", classify), "zero"),
+        (format!("{}classify(-1)", classify), "minus one"),
+        (format!("{}classify(\"raven\")", classify), "bird"),
+        (format!("{}classify(none)", classify), "nothing"),
+        (format!("{}classify(42)", classify), "other"),
+    ];
+
+    for (input, expected) in string_tests {
+        match run_eval(&input) {
+            Ok(Object::String(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected String, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let int_tests = vec![
+        ("x = 5\nmatch x:\n    case 1:\n        y = 10\n    case n:\n        y = n * 2\ny", 10),
+        ("r = 0\nmatch 3:\n    case 3:\n        r = 1\n    case 3:\n        r = 2\nr", 1),
+    ];
+
+    for (input, expected) in int_tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => {
+                assert_eq!(val, expected, "Failed for input: {}", input);
+            }
+            Ok(other) => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("match 9:\n    case 1:\n        2"), Ok(Object::None));
+    assert!(run_eval("match 1:\n    case [1]:\n        2").is_err());
+}
+
+#[test]
+fn test_temp_and_atomic_write_builtins() {
+    let file = match run_eval("temp_file()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    assert!(std::path::Path::new(&file).is_file());
+    std::fs::remove_file(&file).unwrap();
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    assert!(std::path::Path::new(&dir).is_dir());
+
+    let target = format!("{}/config.txt", dir);
+    let input = format!(
+        "write_file_atomic(\"{0}\", \"first\")\nwrite_file_atomic(\"{0}\", \"second\")",
+        target
+    );
+    assert_eq!(run_eval(&input), Ok(Object::None));
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "second");
+    // Only the target remains; the temporary sibling was renamed into place.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    let missing = format!("write_file_atomic(\"{}/no/such/dir/x.txt\", \"data\")", dir);
+    assert!(run_eval(&missing).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_on_interrupt_runs_cleanup_handler() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use the_carrion_language::evaluator::{environment::Environment, interrupt};
+
+    let parse = |input: &str| {
+        let mut lexer = lexer::Lexer::new(input.to_owned(), "<test>".into());
+        let mut parser = parser::Parser::new(lexer.scan_tokens());
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "Parser errors: {:?}", parser.errors());
+        program
+    };
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let marker = format!("{}/cleaned.txt", dir);
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let setup = format!(
+        "spell cleanup():\n    write_file_atomic(\"{}\", \"done\")\non_interrupt(cleanup)",
+        marker
+    );
+    assert_eq!(evaluator::eval_with_env(&parse(&setup), &env), Ok(Object::None));
+
+    interrupt::request();
+    let result = evaluator::eval_with_env(&parse("x = 1\nx = 2"), &env);
+    assert_eq!(result, Err(interrupt::INTERRUPTED.to_string()));
+    assert_eq!(std::fs::read_to_string(&marker).unwrap(), "done");
+
+    // The flag is consumed, so evaluation continues normally afterwards.
+    assert_eq!(evaluator::eval_with_env(&parse("x = 3"), &env), Ok(Object::Integer(3)));
+
+    assert!(run_eval("on_interrupt(5)").is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_stopwatch_and_time_it() {
+    match run_eval("sw = stopwatch()\nsw.elapsed_ms()") {
+        Ok(Object::Float(ms)) => assert!(ms >= 0.0),
+        other => panic!("Expected Float, got {:?}", other),
+    }
+    match run_eval("sw = stopwatch()\nfirst = sw.restart()\nsw.elapsed_ms()") {
+        Ok(Object::Float(ms)) => assert!(ms >= 0.0),
+        other => panic!("Expected Float, got {:?}", other),
+    }
+
+    let input = "spell work():\n    total = 0\n    for i in [1, 2, 3]:\n        total += i\nstats = time_it(work, 5)";
+    match run_eval(input) {
+        Ok(Object::Dict(stats)) => {
+            let field = |key: &str| stats.get(&Object::String(key.into()));
+            assert_eq!(field("iterations"), Some(&Object::Integer(5)));
+            let get = |key: &str| match field(key) {
+                Some(Object::Float(ms)) => *ms,
+                other => panic!("Expected Float for {}, got {:?}", key, other),
+            };
+            assert!(get("min_ms") <= get("mean_ms") && get("mean_ms") <= get("max_ms"));
+            assert!(get("total_ms") >= get("max_ms"));
+        }
+        other => panic!("Expected Dict, got {:?}", other),
+    }
+
+    assert!(run_eval("spell f():\n    return 1\ntime_it(f, 0)").is_err());
+    assert!(run_eval("stopwatch(1)").is_err());
+}
+
+#[test]
+fn test_import_statement() {
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let write = |name: &str, source: &str| std::fs::write(format!("{}/{}", dir, name), source).unwrap();
+    write("helpers.crl", "base = 40\nspell add(n):\n    return base + n\n");
+    // `import helpers` inside a module resolves next to that module.
+    write("shapes.crl", "import helpers\nspell answer():\n    return helpers.add(2)\n");
+    write("cycle_a.crl", "import cycle_b\n");
+    write("cycle_b.crl", "import cycle_a\n");
+    write("broken.crl", "x = 1 +\n");
+
+    let int_tests = vec![
+        (format!("import \"{}/helpers.crl\"\nhelpers.add(1)", dir), 41),
+        (format!("import \"{}/helpers\" as h\nh.base", dir), 40),
+        (format!("import \"{}/shapes\"\nshapes.answer()", dir), 42),
+    ];
+    for (input, expected) in int_tests {
+        match run_eval(&input) {
+            Ok(Object::Integer(val)) => assert_eq!(val, expected, "Failed for input: {}", input),
+            other => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+        }
+    }
+
+    // Repeated imports share the cached module.
+    let input = format!("import \"{0}/helpers\" as a\nimport \"{0}/helpers\" as b\n[a, b]", dir);
+    match run_eval(&input) {
+        Ok(Object::List(modules)) => assert_eq!(modules[0], modules[1]),
+        other => panic!("Expected List, got {:?}", other),
+    }
+
+    let error_tests = vec![
+        (format!("import \"{}/missing\"", dir), "Module not found"),
+        (format!("import \"{}/cycle_a\"", dir), "Circular import"),
+        (format!("import \"{}/broken\"", dir), "Could not parse module"),
+        (format!("import \"{}/helpers\"\nhelpers.print", dir), "has no attribute"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(&input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_module_reload() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use the_carrion_language::evaluator::{environment::Environment, modules};
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let file = format!("{}/live.crl", dir);
+    std::fs::write(&file, "spell greet():\n    return \"v1\"\nhits = 3\n").unwrap();
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    let eval = |input: &str| {
+        let mut lexer = lexer::Lexer::new(input.to_owned(), "<test>".into());
+        let mut parser = parser::Parser::new(lexer.scan_tokens());
+        evaluator::eval_with_env(&parser.parse_program(), &env)
+    };
+    eval(&format!("import \"{}\"\nalias = live", file)).unwrap();
+    assert_eq!(eval("live.greet()"), Ok(Object::String("v1".into())));
+
+    // Reloading rebinds definitions in place; values the file no longer sets survive.
+    std::fs::write(&file, "spell greet():\n    return \"v2\"\n").unwrap();
+    let module = modules::reload(std::path::Path::new(&file)).unwrap();
+    assert_eq!(module.name, "live");
+    assert_eq!(eval("live.greet()"), Ok(Object::String("v2".into())));
+    assert_eq!(eval("alias.greet()"), Ok(Object::String("v2".into())));
+    assert_eq!(eval("live.hits"), Ok(Object::Integer(3)));
+
+    std::fs::write(&file, "spell greet(:\n").unwrap();
+    assert!(modules::reload(std::path::Path::new(&file)).is_err());
+    assert!(modules::reload(std::path::Path::new(&format!("{}/missing.crl", dir))).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_repl_config() {
+    use the_carrion_language::repl::config::ReplConfig;
+
+    assert_eq!(ReplConfig::parse(""), Ok(ReplConfig::default()));
+    assert_eq!(
+        ReplConfig::parse("# settings\n\nhistory_size = 250\n"),
+        Ok(ReplConfig { history_size: 250 })
+    );
+
+    let error_tests = vec![
+        ("history_size = lots", "line 1: history_size must be a whole number"),
+        ("\ncolour = red", "line 2: unknown setting 'colour'"),
+        ("history_size", "line 1: expected 'key = value'"),
+    ];
+    for (input, expected) in error_tests {
+        match ReplConfig::parse(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_repl_session() {
+    use the_carrion_language::repl::session::{ReplResponse, ReplSession};
+
+    let mut session = ReplSession::new();
+    let tests = vec![
+        ("x = 2", ReplResponse::Value(Object::Integer(2))),
+        ("spell double(n):", ReplResponse::Continue),
+        ("    return n * x", ReplResponse::Continue),
+        ("", ReplResponse::Value(Object::None)),
+        ("double(21)", ReplResponse::Value(Object::Integer(42))),
+        ("   ", ReplResponse::Empty),
+        ("scry", ReplResponse::Help),
+        (":inspect x", ReplResponse::Output("x: Integer 2".to_string())),
+        (":frobnicate", ReplResponse::Error("Unknown command ':frobnicate'. Available: :load path, :reload name, :inspect name, :partial on|off, :checkpoint name, :diff-env [name]".to_string())),
+        ("y +", ReplResponse::Error("Parse Error: No prefix parsing function found for token: Eof()\n  --> <repl:4>:1:4\n  |\n1 | y +\n  |    ^".to_string())),
+        ("missing", ReplResponse::Error("Evaluation Error: Identifier not found: missing\n  --> <repl:5>:1:1\n  |\n1 | missing\n  | ^".to_string())),
+        // Errors in a spell show its line from the entry that defined it.
+        ("spell fail():\n    return nope", ReplResponse::Value(Object::None)),
+        ("y = 1\nfail()", ReplResponse::Error("Evaluation Error: Identifier not found: nope\n  --> <repl:6>:2:5\n  |\n2 |     return nope\n  |     ^\n  in spell 'fail' called at <repl:7>:2:1".to_string())),
+        // A block recalled from history arrives whole and runs at once.
+        ("if x > 1:\n    x = x + 1", ReplResponse::Value(Object::Integer(3))),
+        ("x", ReplResponse::Value(Object::Integer(3))),
+    ];
+    for (line, expected) in tests {
+        assert_eq!(session.feed_line(line), expected, "feeding {:?}", line);
+    }
+
+    // Cancelling drops the unfinished block.
+    assert_eq!(session.feed_line("if True:"), ReplResponse::Continue);
+    assert!(session.in_block());
+    session.cancel();
+    assert!(!session.in_block());
+    assert_eq!(session.feed_line("x"), ReplResponse::Value(Object::Integer(3)));
+
+    assert!(matches!(session.feed_line("quit"), ReplResponse::Exit(_)));
+}
+
+#[test]
+fn test_repl_diff_env() {
+    use the_carrion_language::repl::session::{ReplResponse, ReplSession};
+
+    let long = format!("~ c: gone -> {}...", "x".repeat(60));
+    let changes = format!("a = 2\nb = b.push(3)\nc = \"{}\"", "x".repeat(100));
+    // Entries without an expected output only set things up.
+    let tests = vec![
+        ("a = 1\nb = [1, 2]\nc = \"gone\"", None),
+        (":diff-env", Some(Ok("+ a = 1\n+ b = [1, 2]\n+ c = gone".to_string()))),
+        (":checkpoint start", Some(Ok("Checkpoint 'start' saved with 3 bindings".to_string()))),
+        ("grim Point:\n    init(x):\n        self.x = x\n", None),
+        ("p = Point(1)", None),
+        (changes.as_str(), None),
+        (":diff-env", Some(Ok(format!("~ a: 1 -> 2\n~ b: [1, 2] -> [1, 2, 3]\n{}", long)))),
+        // Reading values changes nothing; the diff is the last entry's.
+        ("a + 1", None),
+        (":diff-env", Some(Ok("No bindings changed since the last entry".to_string()))),
+        ("p.x = 5\np.y = 6", None),
+        (":diff-env", Some(Ok("~ p.x: 1 -> 5\n+ p.y = 6".to_string()))),
+        ("del_me = 1", None),
+        (":checkpoint", Some(Err("Usage: :checkpoint name".to_string()))),
+        (":diff-env later", Some(Err("No checkpoint named 'later'; save one with :checkpoint later".to_string()))),
+    ];
+    let mut session = ReplSession::new();
+    for (line, expected) in tests {
+        let response = session.feed_line(line);
+        match expected {
+            None => assert!(!matches!(response, ReplResponse::Error(_)), "feeding {:?}: {:?}", line, response),
+            Some(Ok(text)) => assert_eq!(response, ReplResponse::Output(text), "feeding {:?}", line),
+            Some(Err(text)) => assert_eq!(response, ReplResponse::Error(text), "feeding {:?}", line),
+        }
+    }
+    match session.feed_line(":diff-env start") {
+        ReplResponse::Output(text) => {
+            let names: Vec<&str> = text.lines().map(|line| line.split([' ', ':']).nth(1).unwrap()).collect();
+            assert_eq!(names, ["Point", "a", "b", "c", "del_me", "p"], "{}", text);
+            assert!(text.contains(&long) && text.contains("+ p = "), "{}", text);
+        }
+        other => panic!("Expected a diff, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_repl_partial_evaluation() {
+    use the_carrion_language::repl::session::{ReplResponse, ReplSession};
+
+    let pasted = "a = 1\nif a > 0:\n    a = a + 1\nelse:\n    a = 0\nb = (a +\nc = 3";
+    let mut session = ReplSession::new();
+    // Off by default: nothing in a bad entry runs.
+    assert!(matches!(session.feed_line(pasted), ReplResponse::Error(_)));
+    assert!(matches!(session.feed_line("a"), ReplResponse::Error(_)));
+
+    let tests = vec![
+        (":partial", ReplResponse::Error("Usage: :partial on|off".to_string())),
+        (":partial on", ReplResponse::Output("Partial evaluation on".to_string())),
+        ("a", ReplResponse::Error("Evaluation Error: Identifier not found: a\n  --> <repl:3>:1:1\n  |\n1 | a\n  | ^".to_string())),
+    ];
+    for (line, expected) in tests {
+        assert_eq!(session.feed_line(line), expected, "feeding {:?}", line);
+    }
+    match session.feed_line(pasted) {
+        ReplResponse::Error(e) => {
+            assert!(e.starts_with("Ran lines 1-5 of the entry; the rest did not run.\nParse Error:"), "{}", e)
+        }
+        other => panic!("Expected a partial run, got {:?}", other),
+    }
+    // The if/else ran whole; nothing after the bad statement did.
+    assert_eq!(session.feed_line("a"), ReplResponse::Value(Object::Integer(2)));
+    assert!(matches!(session.feed_line("c"), ReplResponse::Error(_)));
+
+    // A bad first statement runs nothing, and the good prefix can still fail.
+    assert!(matches!(session.feed_line("d = (\ne = 1"), ReplResponse::Error(e) if e.starts_with("Parse Error:")));
+    assert_eq!(
+        session.feed_line("f = missing\ng = ("),
+        ReplResponse::Error("Evaluation Error: Identifier not found: missing\n  --> <repl:8>:1:1\n  |\n1 | f = missing\n  | ^".to_string())
+    );
+    // Lines joined with `\\` are one statement.
+    assert!(matches!(session.feed_line("h = 1 + \\\n2\ni = ("), ReplResponse::Error(e) if e.starts_with("Ran lines 1-2")));
+    assert_eq!(session.feed_line("h"), ReplResponse::Value(Object::Integer(3)));
+
+    assert_eq!(session.feed_line(":partial off"), ReplResponse::Output("Partial evaluation off".to_string()));
+    assert!(matches!(session.feed_line("j = 1\nk = ("), ReplResponse::Error(e) if e.starts_with("Parse Error:")));
+    assert!(matches!(session.feed_line("j"), ReplResponse::Error(_)));
+}
+
+#[test]
+fn test_snippet_source_names() {
+    use evaluator::environment::Environment;
+    use evaluator::snippets;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    assert_eq!(snippets::source_name("-e"), std::path::PathBuf::from("<-e>"));
+    assert_eq!(snippets::source_name("<rule>"), std::path::PathBuf::from("<rule>"));
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+    assert_eq!(evaluator::eval_string("x = 20\nx + 1", "setup", &env), Ok(Object::Integer(21)));
+    // Snippets share the scope they run in.
+    assert_eq!(evaluator::eval_string("x * 2", "double", &env), Ok(Object::Integer(40)));
+
+    let error_tests = vec![
+        // Positions count from the snippet's own first line.
+        ("a = 1\nb = (a +", "checkout-rule", "<checkout-rule>:2:9"),
+        // A newline is reported on the line it ends.
+        ("\n\nc = 1 +\n", "rule", "<rule>:3:8: No prefix parsing function found for token: Newline"),
+        ("x = 1\nmissing", "rule", "Evaluation Error: Identifier not found: missing\n  --> <rule>:2:1\n  |\n2 | missing\n  | ^"),
+    ];
+    for (source, name, expected) in error_tests {
+        match evaluator::eval_string(source, name, &env) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn test_builtins_sit_behind_global_scopes() {
+    use the_carrion_language::evaluator::environment::Environment;
+
+    // A new scope holds none of the builtins but still finds them.
+    let env = Environment::new();
+    assert!(env.local_names().is_empty());
+    assert!(matches!(env.get("len"), Some(Object::Builtin(_))));
+    assert!(matches!(env.get("log"), Some(Object::Dict(_))));
+    assert_eq!(env.get("no_such_name"), None);
+
+    let tests = vec![
+        ("len = 5\nlen", "5"),
+        ("spell f(xs):\n    return len(xs)\nf([1, 2])", "2"),
+        ("spell f():\n    len = 1\n    return len\n[f(), len([0])]", "[1, 1]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+}
+
+#[test]
+fn test_module_prefetch() {
+    use the_carrion_language::evaluator::modules;
+    use the_carrion_language::strict;
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let write = |name: &str, source: &str| std::fs::write(format!("{}/{}", dir, name), source).unwrap();
+    for i in 0..6 {
+        write(&format!("part{}.crl", i), &format!("import shared\nvalue = shared.base + {}\n", i));
+    }
+    write("shared.crl", "base = 100\n");
+    write("broken.crl", "x = 1 +\n");
+
+    let imports: String = (0..6).map(|i| format!("import \"{}/part{}\"\n", dir, i)).collect();
+    let input = format!("{}import \"{}/broken\"\nimport \"{}/missing\"\n", imports, dir, dir);
+    let mut parser = parser::Parser::new(lexer::Lexer::new(input, "<test>".into()).scan_tokens());
+    let program = parser.parse_program();
+    // Six parts plus the module they share; broken and missing files are left to the import.
+    assert_eq!(modules::prefetch(&program), 7);
+
+    // Imports use the prefetched programs rather than reading the files again.
+    write("part5.crl", "value = 0\n");
+    let sum: String = (0..6).map(|i| format!("part{}.value", i)).collect::<Vec<_>>().join(" + ");
+    let input = format!("import \"{}/shared\"\n{}[{}, shared.base]", dir, imports, sum);
+    match run_eval(&input) {
+        Ok(Object::List(items)) => assert_eq!(*items, vec![Object::Integer(615), Object::Integer(100)]),
+        other => panic!("Expected List, got {:?}", other),
+    }
+
+    // Strict mode checks prefetched modules like the ones an import reads itself.
+    write("shadow.crl", "len = 3\n");
+    let input = format!("import \"{}/shadow\"\nlen", dir);
+    let program = parser::Parser::new(lexer::Lexer::new(input, "<test>".into()).scan_tokens()).parse_program();
+    strict::set_enabled(true);
+    let prefetched = modules::prefetch(&program);
+    let result = evaluator::eval(&program);
+    strict::set_enabled(false);
+    assert_eq!(prefetched, 0);
+    match result {
+        Err(e) => assert!(e.contains("Strict mode: module"), "got '{}'", e),
+        other => panic!("Expected a strict mode error, got {:?}", other),
+    }
+
+    // So is the version a prefetched module asks for.
+    write("future.crl", "# carrion: 9.9\nx = 1\n");
+    let input = format!("import \"{}/future\"", dir);
+    let program = parser::Parser::new(lexer::Lexer::new(input, "<test>".into()).scan_tokens()).parse_program();
+    assert_eq!(modules::prefetch(&program), 0);
+    match evaluator::eval(&program) {
+        Err(e) => assert!(e.contains("Unknown Carrion version '9.9'"), "got '{}'", e),
+        other => panic!("Expected a version error, got {:?}", other),
+    }
+
+    // Modules are parsed with this thread's --int-overflow mode, whichever thread parses them.
+    for i in 0..2 {
+        write(&format!("huge{}.crl", i), "x = 99999999999999999999\n");
+    }
+    let input = format!("import \"{}/huge0\"\nimport \"{}/huge1\"", dir, dir);
+    let program = parser::Parser::new(lexer::Lexer::new(input, "<test>".into()).scan_tokens()).parse_program();
+    assert_eq!(modules::prefetch(&program), 0);
+    parser::overflow::set_mode(parser::IntegerOverflow::Float);
+    let prefetched = modules::prefetch(&program);
+    parser::overflow::set_mode(parser::IntegerOverflow::Error);
+    assert_eq!(prefetched, 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_module_loaders() {
+    use the_carrion_language::evaluator::modules;
+
+    modules::add_loader(|request| match request {
+        "virtual_helpers" => Some("import \"shapes/square\"\nspell twice(x):\n    return square.area(x) * 2\n".into()),
+        "shapes/square" => Some("spell area(x):\n    return x * x\n".into()),
+        _ => None,
+    });
+    modules::add_loader(|request| (request == "fallback").then(|| "answer = 42\n".to_string()));
+
+    let tests = vec![
+        ("import virtual_helpers\nvirtual_helpers.twice(3)", "18"),
+        ("import \"shapes/square\" as sq\nsq.area(5)", "25"),
+        ("import fallback\nfallback.answer", "42"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "input: {}", input),
+            Err(e) => panic!("input: {}\nerror: {}", input, e),
+        }
+    }
+
+    // Modules the loaders don't know are still looked up on disk.
+    match run_eval("import no_such_virtual_module") {
+        Err(e) => assert!(e.contains("Module not found: 'no_such_virtual_module'"), "got: {}", e),
+        Ok(value) => panic!("Expected error, got {:?}", value),
+    }
+
+    // A loaded module's errors name it by its virtual path.
+    modules::add_loader(|request| (request == "faulty").then(|| "x = 1 +\n".to_string()));
+    match run_eval("import faulty") {
+        Err(e) => assert!(e.contains("<loader>/faulty.crl"), "got: {}", e),
+        Ok(value) => panic!("Expected error, got {:?}", value),
+    }
+
+    modules::add_loader(|request| (request == "withdrawn").then(String::new));
+    modules::clear_loaders();
+    match run_eval("import withdrawn") {
+        Err(e) => assert!(e.contains("Module not found"), "got: {}", e),
+        Ok(value) => panic!("Expected error, got {:?}", value),
+    }
+}
+
+#[test]
+fn test_plugin_modules() {
+    use the_carrion_language::evaluator::modules::plugins::{self, PluginDescriptor, PluginFunction, PluginStr, PluginValue};
+
+    extern "C" fn add(args: *const PluginValue, count: usize, result: *mut PluginValue) -> bool {
+        let args = unsafe { std::slice::from_raw_parts(args, count) };
+        let value = match args {
+            [PluginValue::Integer(a), PluginValue::Integer(b)] => PluginValue::Integer(a + b),
+            _ => PluginValue::String(PluginStr::new("add takes two integers")),
+        };
+        unsafe { *result = value };
+        matches!(value, PluginValue::Integer(_))
+    }
+    extern "C" fn length(args: *const PluginValue, count: usize, result: *mut PluginValue) -> bool {
+        let args = unsafe { std::slice::from_raw_parts(args, count) };
+        let [PluginValue::String(text)] = args else { return false };
+        unsafe { *result = PluginValue::Integer(text.len as i64) };
+        true
+    }
+    // The strings above are static, so there is nothing to release.
+    extern "C" fn free_string(_: PluginStr) {}
+
+    let functions = Box::leak(Box::new([
+        PluginFunction { name: PluginStr::new("add"), call: add },
+        PluginFunction { name: PluginStr::new("byte_length"), call: length },
+    ]));
+    let descriptor = |abi_version| PluginDescriptor {
+        abi_version,
+        functions: functions.as_ptr(),
+        function_count: functions.len(),
+        free_string,
+    };
+    let current = Box::leak(Box::new(descriptor(plugins::ABI_VERSION)));
+    assert_eq!(unsafe { plugins::register("native_math", current) }, Ok(2));
+
+    let tests = vec![
+        ("import native_math\nnative_math.add(2, 3)", "5"),
+        ("import native_math as m\nm.byte_length(\"héllo\")", "6"),
+        ("import native_math\nnative_math.add", "<plugin spell native_math.add>"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "input: {}", input),
+            Err(e) => panic!("input: {}\nerror: {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("import native_math\nnative_math.add(1, \"x\")", "native_math.add: add takes two integers"),
+        ("import native_math\nnative_math.byte_length(1)", "native_math.byte_length failed"),
+        ("import native_math\nnative_math.add([1], 2)", "Plugin spells take None, booleans, integers, floats and strings, got List"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+
+    let future = Box::leak(Box::new(descriptor(plugins::ABI_VERSION + 1)));
+    match unsafe { plugins::register("native_future", future) } {
+        Err(e) => assert!(e.contains("built for plugin ABI version 2"), "got: {}", e),
+        Ok(count) => panic!("Expected error, got {}", count),
+    }
+
+    let manifest = "[package]\nname = \"demo\"\n\n[plugins]\n# image tools\nimage = \"plugins/libimage.so\"  # local build\nzip = \"/opt/zip.so\"\n";
+    assert_eq!(
+        plugins::parse_manifest(manifest),
+        Ok(vec![
+            ("image".to_string(), std::path::PathBuf::from("plugins/libimage.so")),
+            ("zip".to_string(), std::path::PathBuf::from("/opt/zip.so")),
+        ])
+    );
+    match plugins::parse_manifest("[plugins]\nimage = plugins/libimage.so\n") {
+        Err(e) => assert!(e.contains("line 2: the path of plugin 'image' must be a quoted string"), "got: {}", e),
+        Ok(plugins) => panic!("Expected error, got {:?}", plugins),
+    }
+}
+
+#[test]
+fn test_project_manifest() {
+    use std::process::Command;
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => std::path::PathBuf::from(path.as_str()),
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let carrion = env!("CARGO_BIN_EXE_carrion");
+    std::fs::write(dir.join("carrion.toml"), "carrion = \"0.2\"\n\n[plugins]\nimage = \"nope.so\"\n").unwrap();
+    std::fs::write(dir.join("script.crl"), "If = 3\nprint(If)\n").unwrap();
+    let script = dir.join("script.crl");
+
+    // Only file runs read the manifest, and only the one next to the script.
+    let output = Command::new(carrion).args(["-e", "print(1)"]).current_dir(&dir).output().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Its version applies, and its plugins stay unloaded without --plugins.
+    let output = Command::new(carrion).arg(&script).output().unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let output = Command::new(carrion).arg("--plugins").arg(&script).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("plugin 'image'"), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(carrion).args(["--plugins", "-e", "1"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_shipped_examples() {
+    use the_carrion_language::examples::{self, Mode};
+
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(examples::DEFAULT_DIRECTORY);
+    let carrion = std::path::Path::new(env!("CARGO_BIN_EXE_carrion"));
+    let results = examples::run_examples(&dir, carrion, Mode::Verify);
+    assert!(!results.is_empty(), "no examples found in {}", dir.display());
+    for result in results {
+        assert_eq!(result.outcome, Ok(()), "example {}", result.file.display());
+    }
+}
+
+#[test]
+fn test_example_verification() {
+    use the_carrion_language::examples::{self, Mode};
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => std::path::PathBuf::from(path.as_str()),
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let carrion = std::path::Path::new(env!("CARGO_BIN_EXE_carrion"));
+    std::fs::write(dir.join("greet.crl"), "print(\"hi\")\nprint(1 + 1)\n").unwrap();
+    std::fs::write(dir.join("broken.crl"), "print(\"before\")\nmissing_spell()\n").unwrap();
+    std::fs::write(dir.join("notes.txt"), "not an example").unwrap();
+
+    let results = examples::run_examples(&dir, carrion, Mode::Verify);
+    let outcomes: Vec<_> = results.iter().map(|r| (r.file.file_name().unwrap().to_owned(), r.outcome.clone())).collect();
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes[0].1.as_ref().unwrap_err().contains("Exited with"), "{:?}", outcomes[0]);
+    assert!(outcomes[1].1.as_ref().unwrap_err().contains("No recorded output"), "{:?}", outcomes[1]);
+
+    let results = examples::run_examples(&dir, carrion, Mode::Update);
+    assert_eq!(results[1].outcome, Ok(()));
+    assert_eq!(std::fs::read_to_string(dir.join("greet.out")).unwrap(), "hi\n2\n");
+    assert_eq!(examples::run_examples(&dir, carrion, Mode::Verify)[1].outcome, Ok(()));
+
+    std::fs::write(dir.join("greet.crl"), "print(\"hello\")\nprint(1 + 1)\n").unwrap();
+    match &examples::run_examples(&dir, carrion, Mode::Verify)[1].outcome {
+        Err(e) => assert!(e.contains("- hi\n+ hello\n  2"), "got: {}", e),
+        Ok(()) => panic!("Expected a mismatch"),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_module_search_path() {
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let lib = format!("{}/lib", dir);
+    std::fs::create_dir(&lib).unwrap();
+    std::fs::write(format!("{}/ravens.crl", lib), "count = 7\n").unwrap();
+
+    // No other test reads or writes CARRION_PATH, and std's env access is synchronised.
+    unsafe { std::env::set_var("CARRION_PATH", &lib) };
+    let found = run_eval("import ravens\nravens.count");
+    let missing = run_eval("import crows");
+    unsafe { std::env::remove_var("CARRION_PATH") };
+
+    assert_eq!(found, Ok(Object::Integer(7)));
+    match missing {
+        Err(e) => {
+            assert!(e.contains("Module not found: 'crows'"), "Unexpected error: {}", e);
+            assert!(e.contains(&format!("{}/crows.crl", lib)), "Search path not listed: {}", e);
+        }
+        other => panic!("Expected error, got {:?}", other),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_uuid_and_random_id() {
+    let uuid = match run_eval("uuid4()") {
+        Ok(Object::String(s)) => s,
+        other => panic!("Expected String, got {:?}", other),
+    };
+    let groups: Vec<&str> = uuid.split('-').collect();
+    assert_eq!(groups.iter().map(|g| g.len()).collect::<Vec<_>>(), vec![8, 4, 4, 4, 12]);
+    assert!(uuid.chars().all(|c| c == '-' || c.is_ascii_hexdigit()));
+    assert!(groups[2].starts_with('4'), "Not a version 4 UUID: {}", uuid);
+    assert!("89ab".contains(&groups[3][..1]), "Wrong variant: {}", uuid);
+    assert_ne!(run_eval("uuid4()"), Ok(Object::String(uuid)));
+
+    for (input, expected_len) in [("random_id()", 21), ("random_id(8)", 8)] {
+        match run_eval(input) {
+            Ok(Object::String(id)) => {
+                assert_eq!(id.len(), expected_len, "Failed for input: {}", input);
+                assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+            }
+            other => panic!("Expected String, got {:?} for input '{}'", other, input),
+        }
+    }
+
+    assert!(run_eval("random_id(0)").is_err());
+    assert!(run_eval("uuid4(1)").is_err());
+}
+
+#[test]
+fn test_validate() {
+    let schema = "schema = {\"name\": \"String\", \"port\": \"Integer\", \"ratio?\": \"Number\", \"tags\": [\"String\"], \"db\": {\"url\": \"String\", \"pool?\": \"Integer|None\"}}\n";
+    let tests = vec![
+        ("validate({\"name\": \"api\", \"port\": 80, \"tags\": [], \"db\": {\"url\": \"x\"}, \"extra\": 1}, schema)", "[]"),
+        ("validate({\"name\": \"api\", \"port\": 80, \"ratio\": 0.5, \"tags\": [\"a\"], \"db\": {\"url\": \"x\", \"pool\": None}}, schema)", "[]"),
+        (
+            "validate({\"name\": 1, \"tags\": [\"a\", 2], \"db\": {\"pool\": \"big\"}}, schema)",
+            "['db.pool' should be Integer|None, got String, missing required key 'db.url', 'name' should be String, got Integer, missing required key 'port', 'tags[1]' should be String, got Integer]",
+        ),
+        ("validate({\"port\": 1, \"name\": \"a\", \"tags\": \"a\", \"db\": 3}, schema)", "['db' should be Dict, got Integer, 'tags' should be List, got String]"),
+        ("validate([1], {\"a\": \"Any\"})", "[the data should be Dict, got List]"),
+        ("validate({\"a\": [1, 2]}, {\"a\": \"Any\"})", "[]"),
+    ];
+    for (input, expected) in tests {
+        let input = format!("{}{}", schema, input);
+        match run_eval(&input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("validate({}, {\"a\": \"Intger\"})", "validate: unknown type 'Intger' in the rule for 'a'"),
+        ("validate({\"a\": 1}, {\"a\": 5})", "the rule for 'a' must be a type name, a dict or a one-item list, got 5"),
+        ("validate({}, [\"String\"])", "validate expects a schema dict"),
+        ("validate({})", "want=2"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_crash_bundle() {
+    use the_carrion_language::crash;
+
+    assert_eq!(crash::catch(|| 7), Ok(7));
+
+    crash::set_script(std::path::Path::new("boom.crl"), "x = 1 + 2\n");
+    crash::reached(crash::Stage::Parsed);
+    let report = crash::catch(|| -> () { panic!("evaluator invariant broken") }).unwrap_err();
+    assert!(report.starts_with("Internal error: evaluator invariant broken"), "got: {}", report);
+    let path = report
+        .lines()
+        .find_map(|line| line.strip_prefix("A crash report was written to "))
+        .expect("report names the bundle");
+    let bundle = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    for section in [
+        "version: ",
+        "== panic ==\nevaluator invariant broken",
+        "== backtrace ==",
+        "== source: boom.crl ==\nx = 1 + 2",
+        "== tokens ==\nToken {",
+        "== syntax tree ==\nProgram {",
+    ] {
+        assert!(bundle.contains(section), "missing '{}' in:\n{}", section, bundle);
+    }
+
+    // The tree is only included once the script was parsed.
+    crash::set_script(std::path::Path::new("early.crl"), "y = 2\n");
+    let bundle = crash::bundle("boom", "(none)");
+    assert!(bundle.contains("== source: early.crl =="));
+    assert!(!bundle.contains("== tokens =="), "{}", bundle);
+    assert!(!bundle.contains("== syntax tree =="), "{}", bundle);
+}
+
+#[test]
+fn test_carrion_info() {
+    let version = Object::String(env!("CARGO_PKG_VERSION").into());
+    assert_eq!(run_eval("VERSION"), Ok(version.clone()));
+    assert_eq!(run_eval("carrion_info()[\"version\"]"), Ok(version));
+    assert_eq!(
+        run_eval("carrion_info()[\"platform\"]"),
+        Ok(Object::String(std::env::consts::OS.into()))
+    );
+    assert_eq!(
+        run_eval("carrion_info()[\"features\"].contains(\"fast-hash\")"),
+        Ok(Object::Boolean(cfg!(feature = "fast-hash")))
+    );
+    assert_eq!(
+        run_eval("carrion_info()[\"features\"].contains(\"sqlite\")"),
+        Ok(Object::Boolean(cfg!(feature = "sqlite")))
+    );
+    assert!(run_eval("carrion_info(1)").is_err());
+}
+
+#[test]
+fn test_anonymous_spells() {
+    let tests = vec![
+        ("double = spell(x): x * 2\ndouble(21)", 42),
+        ("spell apply(f, v):\n    return f(v)\napply(spell(x): x + 1, 9)", 10),
+        ("spell adder(n):\n    return spell(x): x + n\nadd5 = adder(5)\nadd5(10)", 15),
+        ("(spell(a, b): a * b)(6, 7)", 42),
+        ("ops = {\"neg\": spell(x): -x}\nops[\"neg\"](3) + 10", 7),
+        ("spell(): 5\nf = spell(): 8\nf()", 8),
+    ];
+
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(Object::Integer(val)) => assert_eq!(val, expected, "Failed for input: {}", input),
+            other => panic!("Expected Integer, got {:?} for input '{}'", other, input),
+        }
+    }
+
+    assert!(run_eval("f = spell(x): x\nf(1, 2)").is_err());
+}
+
+#[test]
+fn test_variadic_spells() {
+    let tests = vec![
+        ("spell f(*rest):\n    return rest\nf()", "[]"),
+        ("spell f(first, *rest):\n    return [first, rest]\nf(1, 2, 3)", "[1, [2, 3]]"),
+        ("spell f(a, b, c):\n    return a + b + c\nf(*[1, 2, 3])", "6"),
+        ("spell f(a, b, c):\n    return [a, b, c]\nxs = (2, 3)\nf(1, *xs)", "[1, 2, 3]"),
+        ("spell f(*xs):\n    return xs\nf(*[1], 2, *(3, 4))", "[1, 2, 3, 4]"),
+        ("total = spell(*xs): len(xs)\ntotal(*[], 1, 2)", "2"),
+        ("len(*[[1, 2, 3]])", "3"),
+        (
+            "grim Bag:\n    init(*items):\n        self.items = items\nBag(1, 2).items",
+            "[1, 2]",
+        ),
+        ("xs = [1]\nxs = xs.push(*[2])\nxs", "[1, 2]"),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("spell f(a, *rest):\n    return a\nf()", "want at least 1"),
+        ("spell f(a):\n    return a\nf(*[1, 2])", "Wrong number of arguments"),
+        ("spell f(a):\n    return a\nf(*5)", "Only a list or tuple can be spread"),
+        ("spell f(*rest, a):\n    return a", "Only a '**' parameter can follow '*rest'"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_keyword_arguments() {
+    let tests = vec![
+        ("spell f(a, b):\n    return [a, b]\nf(b=2, a=1)", "[1, 2]"),
+        ("spell f(a, b):\n    return [a, b]\nf(1, b=2)", "[1, 2]"),
+        ("spell f(**opts):\n    return opts\nf(color=\"red\")", "{color: red}"),
+        ("spell f(a, **opts):\n    return [a, len(opts)]\nf(a=1, x=2, y=3)", "[1, 2]"),
+        ("spell f(a, *rest, **opts):\n    return [a, rest, opts]\nf(1, 2, k=3)", "[1, [2], {k: 3}]"),
+        ("spell f(a, b):\n    return a - b\nargs = {\"b\": 1, \"a\": 10}\nf(**args)", "9"),
+        ("spell f(a, **opts):\n    return opts\nf(a=1, **{\"b\": 2})", "{b: 2}"),
+        ("spell f(**opts):\n    return opts\nf()", "{}"),
+        ("g = spell(x, **kw): [x, kw]\ng(x=1)", "[1, {}]"),
+        (
+            "grim Point:\n    init(x, y):\n        self.x = x\n        self.y = y\nPoint(y=2, x=1).x",
+            "1",
+        ),
+        (
+            "grim Box:\n    spell scaled(by):\n        return by * 2\nBox().scaled(by=5)",
+            "10",
+        ),
+    ];
+    for (input, expected) in tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let error_tests = vec![
+        ("spell f(a):\n    return a\nf(b=1)", "Missing argument 'a'"),
+        ("spell f(a):\n    return a\nf(a=1, c=2)", "Unexpected keyword argument 'c'"),
+        ("spell f(a):\n    return a\nf(1, a=2)", "given both by position and by name"),
+        ("spell f(**o):\n    return o\nf(a=1, **{\"a\": 2})", "given more than once"),
+        ("spell f(**o):\n    return o\nf(**[1])", "Only a dict can be spread"),
+        ("len(x=[1])", "takes no keyword arguments"),
+        ("spell f(a, b):\n    return a\nf(a=1, 2)", "Positional arguments must come before keyword arguments"),
+        ("spell f(**o, a):\n    return a", "must come last"),
+    ];
+    for (input, expected) in error_tests {
+        match run_eval(input) {
+            Err(e) => assert!(e.contains(expected), "Expected '{}' in error '{}'", expected, e),
+            other => panic!("Expected error for '{}', got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_decimal_arithmetic() {
+    let display_tests = vec![
+        ("decimal(\"3.50\")", "3.50"),
+        ("decimal(\"0.1\") + decimal(\"0.2\")", "0.3"),
+        ("decimal(\"19.99\") * 3", "59.97"),
+        ("10 - decimal(\"0.01\")", "9.99"),
+        ("decimal(\"-1.5\") * decimal(\"2.25\")", "-3.375"),
+        ("decimal(\"10.00\") / 2", "5.00"),
+        ("decimal(1) / 3", "0.333333333333333333"),
+        ("decimal(\"2.675\").round(2)", "2.68"),
+        ("decimal(\"2.665\").round(2)", "2.66"),
+        ("decimal(\"2.665\").round(2, \"half_up\")", "2.67"),
+        ("decimal(\"-2.61\").round(1, \"floor\")", "-2.7"),
+        ("decimal(\"-2.69\").round(1, \"down\")", "-2.6"),
+        ("decimal(\"2.61\").round(1, \"ceiling\")", "2.7"),
+        ("decimal(\"5\").round(2)", "5.00"),
+        ("decimal(0.1)", "0.1"),
+        ("decimal(\".5\")", "0.5"),
+    ];
+    for (input, expected) in display_tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let bool_tests = vec![
+        ("decimal(\"1.10\") == decimal(\"1.1\")", true),
+        ("decimal(\"0.1\") + decimal(\"0.2\") == decimal(\"0.3\")", true),
+        ("decimal(\"2.5\") > 2", true),
+        ("3 <= decimal(\"2.99\")", false),
+    ];
+    for (input, expected) in bool_tests {
+        assert_eq!(run_eval(input), Ok(Object::Boolean(expected)), "Failed for input: {}", input);
+    }
+
+    assert_eq!(run_eval("decimal(\"1.25\").to_float()"), Ok(Object::Float(1.25)));
+
+    for input in [
+        "decimal(\"1.2.3\")",
+        "decimal(\"abc\")",
+        "decimal(\"1\") / 0",
+        "decimal(\"1\") + 0.5",
+        "decimal(\"1\").round(2, \"sideways\")",
+    ] {
+        assert!(run_eval(input).is_err(), "Expected error for input: {}", input);
+    }
+}
+
+#[test]
+fn test_rational_arithmetic() {
+    let display_tests = vec![
+        ("frac(1, 3) + frac(1, 6)", "1/2"),
+        ("frac(6, -4)", "-3/2"),
+        ("frac(2, 4) * 4", "2"),
+        ("1 - frac(1, 3)", "2/3"),
+        ("frac(3, 4) / frac(3, 8)", "2"),
+        ("1 / frac(2, 5)", "5/2"),
+        ("frac(5)", "5"),
+        ("frac(0, -7)", "0"),
+    ];
+    for (input, expected) in display_tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("frac(1, 4) + 0.5"), Ok(Object::Float(0.75)));
+    assert_eq!(run_eval("frac(3, 4).to_float()"), Ok(Object::Float(0.75)));
+    assert_eq!(run_eval("frac(10, 4).numerator()"), Ok(Object::Integer(5)));
+    assert_eq!(run_eval("frac(10, 4).denominator()"), Ok(Object::Integer(2)));
+    assert_eq!(run_eval("frac(1, 2) == frac(2, 4)"), Ok(Object::Boolean(true)));
+    assert_eq!(run_eval("frac(1, 3) < frac(1, 2)"), Ok(Object::Boolean(true)));
+    assert_eq!(run_eval("frac(3, 3) == 1"), Ok(Object::Boolean(true)));
+
+    assert!(run_eval("frac(1, 0)").is_err());
+    assert!(run_eval("frac(1, 2) / frac(0, 1)").is_err());
+    assert!(run_eval("frac(\"1\", 2)").is_err());
+}
+
+#[test]
+fn test_complex_numbers() {
+    let display_tests = vec![
+        ("complex(1, 2)", "1+2i"),
+        ("complex(1, -2)", "1-2i"),
+        ("complex(3, 4) * complex(0, 1)", "-4+3i"),
+        ("complex(1, 1) + 2", "3+1i"),
+        ("1.5 - complex(0, 2)", "1.5-2i"),
+        ("complex(5, 5) / complex(1, 1)", "5+0i"),
+        ("complex(3, 4).conjugate()", "3-4i"),
+        ("complex(2)", "2+0i"),
+    ];
+    for (input, expected) in display_tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("abs(complex(3, 4))"), Ok(Object::Float(5.0)));
+    assert_eq!(run_eval("abs(-7)"), Ok(Object::Integer(7)));
+    assert_eq!(run_eval("abs(-2.5)"), Ok(Object::Float(2.5)));
+    assert_eq!(run_eval("complex(3, 4).real()"), Ok(Object::Float(3.0)));
+    assert_eq!(run_eval("complex(3, 4).imag()"), Ok(Object::Float(4.0)));
+    assert_eq!(run_eval("complex(1, 0) == 1"), Ok(Object::Boolean(true)));
+
+    assert!(run_eval("complex(1, 1) / complex(0, 0)").is_err());
+    assert!(run_eval("complex(1, 1) < complex(2, 2)").is_err());
+    assert!(run_eval("abs(\"x\")").is_err());
+}
+
+#[test]
+fn test_matrix_builtins() {
+    let display_tests = vec![
+        ("matrix([[1, 2], [3, 4]])", "matrix([[1, 2], [3, 4]])"),
+        ("matrix([[1, 2], [3, 4]]).transpose()", "matrix([[1, 3], [2, 4]])"),
+        ("matrix([[1, 2], [3, 4]]).matmul(matrix([[1], [1]]))", "matrix([[3], [7]])"),
+        ("m = matrix([[1, 2], [3, 4]])\nm * 2 + m", "matrix([[3, 6], [9, 12]])"),
+        ("matrix([[4, 6]]) / 2 - matrix([[1, 1]])", "matrix([[1, 2]])"),
+        ("matrix([[1, 2], [3, 4]]).map(spell(x): x * x)", "matrix([[1, 4], [9, 16]])"),
+        ("matrix([[1, 2, 3]]).shape()", "[1, 3]"),
+        ("matrix([[1.5]]).to_list()", "[[1.5]]"),
+    ];
+    for (input, expected) in display_tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    assert_eq!(run_eval("matrix([[1, 2], [3, 4]]).get(1, 0)"), Ok(Object::Float(3.0)));
+    assert_eq!(
+        run_eval("matrix([[1, 2]]) == matrix([[1, 2]])"),
+        Ok(Object::Boolean(true))
+    );
+
+    for input in [
+        "matrix([[1, 2], [3]])",
+        "matrix([[1, \"a\"]])",
+        "matrix([[1, 2]]).get(1, 0)",
+        "matrix([[1, 2]]).matmul(matrix([[1, 2]]))",
+        "matrix([[1, 2]]) + matrix([[1], [2]])",
+        "matrix([[1]]).map(spell(x): \"no\")",
+    ] {
+        assert!(run_eval(input).is_err(), "Expected error for input: {}", input);
+    }
+}
+
+#[test]
+fn test_dates_and_durations() {
+    let display_tests = vec![
+        ("date(2024, 3, 10)", "2024-03-10T00:00:00Z"),
+        ("date(2024, 2, 28, 23, 30) + hours(1)", "2024-02-29T00:30:00Z"),
+        ("date(2024, 3, 1) - days(1)", "2024-02-29T00:00:00Z"),
+        ("parse_date(\"2024-03-10T12:00:00+02:00\")", "2024-03-10T10:00:00Z"),
+        ("parse_date(\"2024-03-10 08:15:30.250Z\")", "2024-03-10T08:15:30.250Z"),
+        ("parse_date(\"1999-12-31\").iso()", "1999-12-31T00:00:00Z"),
+        ("date(2024, 3, 10) - date(2024, 3, 8, 21, 30)", "P1DT2H30M"),
+        ("days(2) + minutes(5)", "P2DT5M"),
+        ("minutes(1.5)", "PT1M30S"),
+        ("seconds(0)", "PT0S"),
+        ("milliseconds(1500)", "PT1.5S"),
+        ("minutes(5) - hours(1)", "-PT55M"),
+        ("weeks(1) * 2", "P14D"),
+        ("hours(1) / 4", "PT15M"),
+        ("days(1) + date(2000, 1, 1)", "2000-01-02T00:00:00Z"),
+    ];
+    for (input, expected) in display_tests {
+        match run_eval(input) {
+            Ok(value) => assert_eq!(value.to_string(), expected, "Failed for input: {}", input),
+            Err(e) => panic!("Evaluation failed for input '{}': {}", input, e),
+        }
+    }
+
+    let int_tests = vec![
+        ("date(2024, 3, 10).weekday()", 7),
+        ("date(1970, 1, 1).weekday()", 4),
+        ("parse_date(\"2023-07-04T05:06:07Z\").year()", 2023),
+        ("parse_date(\"2023-07-04T05:06:07Z\").month()", 7),
+        ("parse_date(\"2023-07-04T05:06:07Z\").second()", 7),
+        ("minutes(2).total_ms()", 120_000),
+    ];
+    for (input, expected) in int_tests {
+        assert_eq!(run_eval(input), Ok(Object::Integer(expected)), "Failed for input: {}", input);
+    }
+
+    let bool_tests = vec![
+        ("date(2024, 1, 1) < date(2024, 1, 2)", true),
+        ("date(2024, 1, 1) + days(1) == date(2024, 1, 2)", true),
+        ("minutes(60) == hours(1)", true),
+        ("seconds(59) > minutes(1)", false),
+    ];
+    for (input, expected) in bool_tests {
+        assert_eq!(run_eval(input), Ok(Object::Boolean(expected)), "Failed for input: {}", input);
+    }
+
+    assert_eq!(run_eval("hours(3) / hours(2)"), Ok(Object::Float(1.5)));
+    assert_eq!(run_eval("date(1970, 1, 2).timestamp()"), Ok(Object::Float(86400.0)));
+    assert!(matches!(run_eval("now() > date(2020, 1, 1)"), Ok(Object::Boolean(true))));
+
+    for input in [
+        "date(2023, 2, 29)",
+        "date(2024, 13, 1)",
+        "date(9223372036854775807, 1, 1)",
+        "date(-9223372036854775807, 1, 1)",
+        "date(10000, 1, 1)",
+        "parse_date(\"2024-3-10\")",
+        "parse_date(\"yesterday\")",
+        "parse_date(\"2024-03-10T10:00+99:99\")",
+        "parse_date(\"2024-03-10T10:00-24:00\")",
+        "parse_date(\"2024-03-10T10:00+05:60\")",
+        "days(1) - date(2024, 1, 1)",
+        "hours(1) / 0",
+    ] {
+        assert!(run_eval(input).is_err(), "Expected error for input: {}", input);
+    }
+}
+
+#[test]
+fn test_numeric_literal_forms() {
+    let int_tests = vec![("1_000_000", 1_000_000), ("2_5 + 1", 26), ("e1 = 4\n2 * e1", 8)];
+    for (input, expected) in int_tests {
+        assert_eq!(run_eval(input), Ok(Object::Integer(expected)), "Failed for input: {}", input);
+    }
+
+    let float_tests = vec![
+        ("1e9", 1e9),
+        ("2.5e-3", 2.5e-3),
+        ("1E+2", 100.0),
+        ("6.02e2_3", 6.02e23),
+        ("1_000.5", 1000.5),
+    ];
+    for (input, expected) in float_tests {
+        assert_eq!(run_eval(input), Ok(Object::Float(expected)), "Failed for input: {}", input);
+    }
+
+    // A trailing separator is not part of the number.
+    assert!(run_eval("1_").is_err());
+}
+
+#[test]
+fn test_template_builtin() {
+    let tests = vec![
+        ("template(\"Hello {name}\", {\"name\": \"Thor\"})", "Hello Thor"),
+        ("template(\"{a} + {a} = {b}\", {\"a\": 2, \"b\": 4})", "2 + 2 = 4"),
+        ("template(\"{{literal}} {x}\", {\"x\": True})", "{literal} True"),
+        ("template(\"{ spaced }\", {\"spaced\": 1.5})", "1.5"),
+        ("template(\"no placeholders\", {})", "no placeholders"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(
+            run_eval(input),
+            Ok(Object::String(expected.into())),
+            "Failed for input: {}",
+            input
+        );
+    }
+
+    assert_eq!(
+        run_eval("template(\"{a} {b} {a} {c}\", {\"b\": 1})"),
+        Err("Missing template values: a, c".to_string())
+    );
+    for input in [
+        "template(\"{open\", {})",
+        "template(\"close}\", {})",
+        "template(\"{}\", {})",
+        "template(\"x\", [1])",
+    ] {
+        assert!(run_eval(input).is_err(), "Expected error for input: {}", input);
+    }
+}
+
+#[test]
+fn test_diff_builtin() {
+    let ops = |input: &str| -> Vec<String> {
+        match run_eval(input) {
+            Ok(Object::List(edits)) => edits
+                .iter()
+                .map(|edit| match edit {
+                    Object::Dict(entry) => {
+                        format!("{} {}", entry[&Object::String("op".into())], entry[&Object::String("value".into())])
+                    }
+                    other => panic!("Expected Dict, got {:?}", other),
+                })
+                .collect(),
+            other => panic!("Expected List, got {:?} for input '{}'", other, input),
+        }
+    };
+
+    assert_eq!(
+        ops("diff([1, 2, 3, 4], [1, 3, 4, 5])"),
+        vec!["keep 1", "delete 2", "keep 3", "keep 4", "insert 5"]
+    );
+    assert_eq!(ops("diff(\"same\", \"changed\")"), vec!["delete same", "insert changed"]);
+    assert_eq!(
+        the_carrion_language::evaluator::builtins::diff::text_diff("a\nb\nc", "a\nx\nc"),
+        vec!["  a", "- b", "+ x", "  c"]
+    );
+    assert_eq!(ops("diff([], [\"new\"])"), vec!["insert new"]);
+    assert_eq!(ops("diff([1, 2], [1, 2])"), vec!["keep 1", "keep 2"]);
+
+    assert_eq!(run_eval("print_diff([1], [1])"), Ok(Object::Boolean(false)));
+    assert_eq!(run_eval("print_diff(\"a\", \"b\")"), Ok(Object::Boolean(true)));
+    assert!(run_eval("diff(\"a\", [1])").is_err());
+}
+
+#[test]
+fn test_repr_and_snapshots() {
+    use the_carrion_language::evaluator::builtins::snapshot;
+
+    assert_eq!(
+        run_eval("repr({\"b\": [1, \"two\"], \"a\": none})"),
+        Ok(Object::String("{\"a\": None, \"b\": [1, \"two\"]}".into()))
+    );
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    snapshot::set_directory(Some(std::path::PathBuf::from(&dir)));
+
+    // The first run records the snapshot, later runs compare against it.
+    assert_eq!(run_eval("check_snapshot(\"greeting\", [\"hi\", 1])"), Ok(Object::Boolean(true)));
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/greeting.snap", dir)).unwrap(),
+        "[\"hi\", 1]\n"
+    );
+    assert_eq!(run_eval("check_snapshot(\"greeting\", [\"hi\", 1])"), Ok(Object::Boolean(true)));
+    match run_eval("check_snapshot(\"greeting\", [\"hi\", 2])") {
+        Err(e) => {
+            assert!(e.contains("Snapshot 'greeting' does not match"), "Unexpected error: {}", e);
+            assert!(e.contains("- [\"hi\", 1]") && e.contains("+ [\"hi\", 2]"), "Missing diff: {}", e);
+        }
+        other => panic!("Expected mismatch, got {:?}", other),
+    }
+    assert!(run_eval("check_snapshot(\"../escape\", 1)").is_err());
+
+    snapshot::set_directory(None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mock_and_spy() {
+    let tests = vec![
+        ("m = mock()\nm(1)", Object::None),
+        ("m = mock(3)\nm(1, 2)\nm.call_count", Object::Integer(1)),
+        ("m = mock(3)\nm(1, 2)\nm.called_with(1, 2)", Object::Boolean(true)),
+        ("m = mock(3)\nm(1, 2)\nm.called_with(1)", Object::Boolean(false)),
+        ("m = mock()\nm.returns(\"hi\")\nm()", Object::String("hi".into())),
+        ("m = mock(0)\nm.returns_each([1, 2])\nm() + m() * 10 + m() * 100", Object::Integer(21)),
+        ("m = mock()\nm(1)\nm.reset()\nm.call_count", Object::Integer(0)),
+        (
+            "m = mock()\nspell notify(send, who):\n    send(who, \"ready\")\nnotify(m, \"Odin\")\nm.calls",
+            Object::list(vec![Object::list(vec![
+                Object::String("Odin".into()),
+                Object::String("ready".into()),
+            ])]),
+        ),
+        ("spell double(n):\n    return n * 2\ns = spy(double)\ns(4) + s.call_count", Object::Integer(9)),
+        ("s = spy(len)\ns.returns_each([0])\ns(\"abc\") + s(\"abc\")", Object::Integer(3)),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(run_eval(input), Ok(expected), "Failed for input: {}", input);
+    }
+
+    let err_tests = vec!["mock(1, 2)", "spy(5)", "m = mock()\nm.returns_each(1)", "m = mock()\nm.missing"];
+    for input in err_tests {
+        assert!(run_eval(input).is_err(), "Expected error for input '{}'", input);
+    }
+}
+
+#[test]
+fn test_runner_discovers_test_spells() {
+    use the_carrion_language::test_runner;
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => std::path::PathBuf::from(path.as_str()),
+        other => panic!("Expected path, got {:?}", other),
+    };
+    std::fs::write(
+        dir.join("test_sample.crl"),
+        "spell test_ok():\n    return 1\nspell test_broken():\n    return missing\nspell helper():\n    return missing\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("not_a_test.crl"), "this is not valid carrion (").unwrap();
+
+    let results = test_runner::run_tests(std::slice::from_ref(&dir), false);
+    let summary: Vec<(String, bool)> = results
+        .iter()
+        .map(|r| (r.name.clone(), r.outcome.is_ok()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("test_broken".to_string(), false), ("test_ok".to_string(), true)]
+    );
+
+    // With --doc, docstring examples in every source file run as well.
+    std::fs::remove_file(dir.join("not_a_test.crl")).unwrap();
+    std::fs::write(
+        dir.join("lib.crl"),
+        "spell double(n):\n    \"Doubles n.\n\n    >>> double(2)\n    4\n    >>> x = double(5)\n    >>> x + 1\n    11\n    \"\n    return n * 2\n\
+         spell half(n):\n    \">>> half(4)\n    3\n    \"\n    return n / 2\n\
+         spell plain():\n    \"No examples here.\"\n    return 1\n",
+    )
+    .unwrap();
+    let results = test_runner::run_tests(std::slice::from_ref(&dir), true);
+    let summary: Vec<(String, bool)> = results
+        .iter()
+        .map(|r| (r.name.clone(), r.outcome.is_ok()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("doc: double".to_string(), true),
+            ("doc: half".to_string(), false),
+            ("test_broken".to_string(), false),
+            ("test_ok".to_string(), true),
+        ]
+    );
+    assert_eq!(
+        results[1].outcome,
+        Err(">>> half(4)\nexpected:\n3\ngot:\n2".to_string())
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_watch_classifies_edits() {
+    use the_carrion_language::watch::{classify, Change};
+
+    let parse = |source: &str| {
+        let mut lexer = lexer::Lexer::new(source.to_owned(), "<test>".into());
+        let mut parser = parser::Parser::new(lexer.scan_tokens());
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "Parser errors: {:?}", parser.errors());
+        program
+    };
+    let original = parse("count = 1\nspell show():\n    return count\nspell other():\n    return 2\nshow()\n");
+
+    let tests = vec![
+        ("count = 1\nspell show():\n    return count\nspell other():\n    return 2\nshow()\n", Change::Unchanged),
+        (
+            "count = 1\nspell show(n):\n    return count + n\nspell other():\n    return 2\nshow()\n",
+            Change::Spells(vec!["show".to_string()]),
+        ),
+        (
+            "count = 1\nspell show():\n    return 0\nspell other():\n    return 3\nshow()\n",
+            Change::Spells(vec!["show".to_string(), "other".to_string()]),
+        ),
+        // A longer spell moves the ones after it, which are defined again.
+        (
+            "count = 1\nspell show():\n    x = 1\n    return count\nspell other():\n    return 2\nshow()\n",
+            Change::Spells(vec!["show".to_string(), "other".to_string()]),
+        ),
+        ("count = 2\nspell show():\n    return count\nspell other():\n    return 2\nshow()\n", Change::Restart),
+        ("count = 1\nspell shown():\n    return count\nspell other():\n    return 2\nshow()\n", Change::Restart),
+        ("count = 1\nspell show():\n    return count\nshow()\n", Change::Restart),
+    ];
+    for (source, expected) in tests {
+        assert_eq!(classify(&original, &parse(source)), expected, "Failed for source: {}", source);
+    }
+
+    // The same statement on another line compares unequal.
+    assert_ne!(parse("x = 1").statements[0], parse("\nx = 1").statements[0]);
+    assert_eq!(parse("x = 1").statements[0].statement, parse("\nx = 1").statements[0].statement);
+}
+
+#[test]
+fn test_inspect_renders_value_tree() {
+    use the_carrion_language::repl::inspect::{estimate_size, inspect};
+
+    let value = run_eval(
+        "grim Raven:\n    init(name):\n        self.name = name\n        self.me = self\n    spell fly():\n        return 1\n{\"b\": [1, \"x\", [2.5, none]], \"a\": Raven(\"Huginn\")}",
+    )
+    .unwrap();
+    let tree = inspect("d", &value);
+    // Sizes depend on the platform, so only the structure is compared.
+    let without_sizes: Vec<String> = tree
+        .lines()
+        .map(|line| match line.find(", ~") {
+            Some(at) => line[..at].to_string(),
+            None => line.to_string(),
+        })
+        .collect();
+    assert_eq!(
+        without_sizes,
+        vec![
+            "d: Dict, len 2",
+            "  element types: Instance, List",
+            "  \"a\": Instance of Raven, 2 fields",
+            "    spells: fly, init",
+            "    .me: Instance of Raven (shown above)",
+            "    .name: String, len 6: \"Huginn\"",
+            "  \"b\": List, len 3",
+            "    element types: Integer, List, String",
+            "    [0]: Integer 1",
+            "    [1]: String, len 1: \"x\"",
+            "    [2]: List, len 2",
+            "      element types: Float, None",
+            "      [0]: Float 2.5",
+            "      [1]: None",
+        ]
+    );
+
+    let long = run_eval("xs = []\ni = 0\nwhile i < 25:\n    xs = xs.push(i)\n    i += 1\nxs").unwrap();
+    let tree = inspect("xs", &long);
+    assert!(tree.ends_with("  ... 5 more"), "Unexpected tree: {}", tree);
+    assert!(estimate_size(&long) > estimate_size(&Object::Integer(1)) * 25);
+}
+
+#[test]
+fn test_breakpoint_session() {
+    use std::collections::VecDeque;
+    use the_carrion_language::evaluator::debugger;
+
+    let script = "spell f(n):\n    y = n * 2\n    breakpoint()\n    return y\nf(5)";
+    // Without a reader, as when embedded, breakpoint() does nothing.
+    assert_eq!(run_eval(script), Ok(Object::Integer(10)));
+
+    let scripted = |lines: &[&str]| {
+        let mut lines: VecDeque<String> = lines.iter().map(|l| l.to_string()).collect();
+        debugger::set_reader(Box::new(move |_| lines.pop_front()));
+    };
+
+    // The session runs in the spell's scope and can change its variables.
+    scripted(&["y", "y = n + 95", ":continue", "y = 0"]);
+    assert_eq!(run_eval(script), Ok(Object::Integer(100)));
+
+    // The end of input resumes; breakpoints hit inside the session are ignored.
+    scripted(&["breakpoint()"]);
+    assert_eq!(run_eval(script), Ok(Object::Integer(10)));
+
+    scripted(&[":abort"]);
+    assert_eq!(run_eval(script), Err("Aborted at breakpoint".to_string()));
+    assert!(run_eval("breakpoint(1)").is_err());
+}
+
+#[test]
+fn test_trace_record_and_replay() {
+    use evaluator::trace;
+
+    let dir = match run_eval("temp_dir()") {
+        Ok(Object::String(path)) => path,
+        other => panic!("Expected path, got {:?}", other),
+    };
+    let path = std::path::PathBuf::from(format!("{}/run.trace", dir));
+    let script = "sw = stopwatch()\n[uuid4(), random_id(8), now().iso(), sw.elapsed_ms()]";
+
+    trace::start_recording(&path);
+    let recorded = run_eval(script);
+    trace::finish().unwrap();
+    assert!(recorded.is_ok(), "Unexpected result: {:?}", recorded);
+
+    // Replaying gives back the same ids, clock reads and durations.
+    trace::start_replay(&path).unwrap();
+    assert_eq!(run_eval(script), recorded);
+    trace::finish().unwrap();
+
+    // Reads that don't match the trace stop the script.
+    trace::start_replay(&path).unwrap();
+    let diverged = run_eval("now()");
+    trace::finish().unwrap();
+    assert_eq!(
+        diverged,
+        Err("Replay diverged: the script read time but the trace has random".to_string())
+    );
+
+    trace::start_replay(&path).unwrap();
+    let exhausted = run_eval(&format!("{}\nuuid4()", script));
+    trace::finish().unwrap();
+    assert_eq!(
+        exhausted,
+        Err("Replay diverged: the script read random after the trace ended".to_string())
+    );
+
+    std::fs::write(&path, "not a trace").unwrap();
+    assert!(trace::start_replay(&path).is_err());
+    assert_ne!(run_eval("uuid4()"), run_eval("uuid4()"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_interpreter_stats() {
+    use evaluator::stats;
+
+    assert_eq!(stats::take(), None);
+    run_eval("len([1])").unwrap();
+    assert_eq!(stats::snapshot(), None);
+
+    stats::enable();
+    let program = "spell outer(n):\n    spell inner(m):\n        return len(repr(m))\n    return inner(n)\n\ntotal = 0\nfor i in range(3):\n    total += outer(i)\nname = \"ab\".upper()";
+    run_eval(program).unwrap();
+    let counted = stats::take().unwrap();
+    assert_eq!(counted.statements, 16);
+    // Global scope, outer's call scope, then inner's (closed over outer's).
+    assert_eq!(counted.peak_env_depth, 3);
+    let calls: Vec<(&str, u64)> = counted.builtin_calls.iter().map(|(name, n)| (name.as_str(), *n)).collect();
+    assert_eq!(calls, vec![("len", 3), ("range", 1), ("repr", 3), ("upper", 1)]);
+
+    // Taking the counts resets them.
+    run_eval("x = 1").unwrap();
+    let counted = stats::take().unwrap();
+    assert_eq!(counted.statements, 1);
+    assert!(counted.builtin_calls.is_empty());
+    stats::disable();
+    assert_eq!(stats::take(), None);
+}
+
+#[test]
+fn test_slow_statement_reports() {
+    use evaluator::slow;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let reports = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&reports);
+    slow::enable(
+        Duration::ZERO,
+        Box::new(move |span, _| sink.borrow_mut().push((span.line, span.column))),
+    );
+    let result = run_eval("spell twice(n):\n    m = n * 2\n    return m\n\nx = 1\ntwice(x)");
+    slow::disable();
+    assert_eq!(result, Ok(Object::Integer(2)));
+
+    // Inner statements are reported before the statement that ran them.
+    assert_eq!(*reports.borrow(), vec![(1, 1), (5, 1), (2, 5), (3, 5), (6, 1)]);
+
+    reports.borrow_mut().clear();
+    let sink = Rc::clone(&reports);
+    slow::enable(
+        Duration::from_secs(60),
+        Box::new(move |span, _| sink.borrow_mut().push((span.line, span.column))),
+    );
+    run_eval("x = 1\ny = x + 1").unwrap();
+    slow::disable();
+    assert!(reports.borrow().is_empty());
+
+    let span = ast::Span {
+        file: "main.crl".into(),
+        line: 3,
+        column: 5,
+    };
+    assert_eq!(
+        slow::describe(&span, Duration::from_micros(12_340)),
+        "Slow statement at main.crl:3:5 took 12.3 ms"
+    );
+
+    // Thresholds too large for a duration are refused rather than crashing.
+    let carrion = std::path::Path::new(env!("CARGO_BIN_EXE_carrion"));
+    for threshold in ["inf", "1e300", "-1"] {
+        let output = std::process::Command::new(carrion).args(["--slow-statements", threshold, "-e", "1"]).output().unwrap();
+        assert_eq!(output.status.code(), Some(1), "{:?}", output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.starts_with("Error: --slow-statements requires a threshold"), "{}", stderr);
+    }
 }